@@ -0,0 +1,33 @@
+use solana_sdk::hash::{Hash, Hasher};
+
+/// Fanout of the merkle tree the validator builds over account hashes.
+pub const MERKLE_FANOUT: usize = 16;
+
+/// Computes the root of the validator's accounts merkle tree.
+///
+/// Each level hashes the concatenation of up to `fanout` hashes of the level below,
+/// until a single hash remains. `hashes` must be sorted by pubkey and exclude
+/// zero-lamport accounts.
+pub fn compute_merkle_root(hashes: &[Hash], fanout: usize) -> Hash {
+    if hashes.is_empty() {
+        return Hasher::default().result();
+    }
+    let mut level = hash_level(hashes, fanout);
+    while level.len() > 1 {
+        level = hash_level(&level, fanout);
+    }
+    level[0]
+}
+
+fn hash_level(hashes: &[Hash], fanout: usize) -> Vec<Hash> {
+    hashes
+        .chunks(fanout)
+        .map(|chunk| {
+            let mut hasher = Hasher::default();
+            for hash in chunk {
+                hasher.hash(hash.as_ref());
+            }
+            hasher.result()
+        })
+        .collect()
+}
@@ -16,6 +16,7 @@
 // Source: solana/runtime/src/append_vec.rs
 
 use {
+    crate::tiered::{self, HotStorageReader},
     log::*,
     memmap2::{Mmap, MmapMut},
     serde::{Deserialize, Serialize},
@@ -24,13 +25,16 @@ use {
         clock::Epoch,
         hash::Hash,
         pubkey::Pubkey,
+        system_instruction::MAX_PERMITTED_DATA_LENGTH,
     },
     std::{
+        collections::HashSet,
         convert::TryFrom,
         fs::OpenOptions,
         io::{self, Read},
         mem,
         path::Path,
+        sync::{Arc, Mutex},
     },
 };
 
@@ -47,6 +51,37 @@ pub const MAXIMUM_APPEND_VEC_FILE_SIZE: u64 = 16 * 1024 * 1024 * 1024; // 16 GiB
 
 pub type StoredMetaWriteVersion = u64;
 
+/// Default cap on an account's data length: the runtime's `MAX_PERMITTED_DATA_LENGTH` of
+/// 10 MiB. No account can be larger, so larger lengths indicate a corrupt entry.
+pub const DEFAULT_MAX_DATA_LEN: u64 = MAX_PERMITTED_DATA_LENGTH;
+
+/// An account entry rejected because its data length exceeds the cap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OversizedAccount {
+    pub slot: u64,
+    pub offset: usize,
+    pub pubkey: Pubkey,
+    pub data_len: u64,
+    /// Whether iteration of the append vec could continue past this entry
+    pub skipped: bool,
+}
+
+/// Collects oversized account entries across append vecs.
+#[derive(Default)]
+pub struct OversizedAccounts {
+    entries: Mutex<Vec<OversizedAccount>>,
+}
+
+impl OversizedAccounts {
+    pub fn record(&self, entry: OversizedAccount) {
+        self.entries.lock().unwrap().push(entry);
+    }
+
+    pub fn entries(&self) -> Vec<OversizedAccount> {
+        self.entries.lock().unwrap().clone()
+    }
+}
+
 /// Meta contains enough context to recover the index from storage itself
 /// This struct will be backed by mmaped and snapshotted data files.
 /// So the data layout must be stable and consistent across the entire cluster!
@@ -119,6 +154,20 @@ impl<'a> StoredAccountMeta<'a> {
     }
 }
 
+/// Decides which accounts of an append vec are superseded by other versions.
+///
+/// `slot` and `id` identify the append vec the account is stored in.
+pub trait AccountMask: Send + Sync {
+    fn is_hidden(&self, slot: u64, id: u64, account: &StoredAccountMeta) -> bool;
+}
+
+/// Hides every account with one of the given pubkeys, regardless of slot.
+impl AccountMask for HashSet<Pubkey> {
+    fn is_hidden(&self, _slot: u64, _id: u64, account: &StoredAccountMeta) -> bool {
+        self.contains(&account.meta.pubkey)
+    }
+}
+
 /// A thread-safe, file-backed block of memory used to store `Account` instances. Append operations
 /// are serialized such that only one thread updates the internal `append_lock` at a time. No
 /// restrictions are placed on reading. That is, one may read items from one thread while another
@@ -134,6 +183,17 @@ pub struct AppendVec {
     file_size: u64,
 
     slot: u64,
+
+    /// Storage ID, unique within the slot
+    id: u64,
+
+    /// Masks hiding superseded accounts from iterators.
+    masks: Vec<Arc<dyn AccountMask>>,
+
+    /// Entries claiming more data than this are treated as corrupt.
+    max_data_len: u64,
+
+    oversized: Option<Arc<OversizedAccounts>>,
 }
 
 impl AppendVec {
@@ -182,6 +242,7 @@ impl AppendVec {
         path: P,
         current_len: usize,
         slot: u64,
+        id: u64,
     ) -> io::Result<Self> {
         let data = OpenOptions::new()
             .read(true)
@@ -201,11 +262,19 @@ impl AppendVec {
             result?
         };
 
+        if tiered::is_tiered_storage(&map[..current_len]) {
+            return Self::new_from_tiered(&map[..current_len], slot, id);
+        }
+
         let new = AppendVec {
             map,
             current_len,
             file_size,
             slot,
+            id,
+            masks: Vec::new(),
+            max_data_len: DEFAULT_MAX_DATA_LEN,
+            oversized: None,
         };
 
         Ok(new)
@@ -215,14 +284,41 @@ impl AppendVec {
         reader: &mut R,
         current_len: usize,
         slot: u64,
+        id: u64,
     ) -> io::Result<Self> {
         let mut map = MmapMut::map_anon(current_len)?;
         io::copy(&mut reader.take(current_len as u64), &mut map.as_mut())?;
+        if tiered::is_tiered_storage(&map) {
+            return Self::new_from_tiered(&map, slot, id);
+        }
         Ok(AppendVec {
             map: map.make_read_only()?,
             current_len,
             file_size: current_len as u64,
             slot,
+            id,
+            masks: Vec::new(),
+            max_data_len: DEFAULT_MAX_DATA_LEN,
+            oversized: None,
+        })
+    }
+
+    /// Converts a hot storage file into the append vec layout, so that accounts
+    /// written by validators using tiered storage are read like any other.
+    fn new_from_tiered(bytes: &[u8], slot: u64, id: u64) -> io::Result<Self> {
+        let converted = HotStorageReader::new(bytes)?.to_append_vec_bytes()?;
+        // Anonymous maps can't be empty
+        let mut map = MmapMut::map_anon(converted.len().max(1))?;
+        map[..converted.len()].copy_from_slice(&converted);
+        Ok(AppendVec {
+            map: map.make_read_only()?,
+            current_len: converted.len(),
+            file_size: converted.len() as u64,
+            slot,
+            id,
+            masks: Vec::new(),
+            max_data_len: DEFAULT_MAX_DATA_LEN,
+            oversized: None,
         })
     }
 
@@ -260,11 +356,15 @@ impl AppendVec {
     /// Return account metadata for the account at `offset` if its data doesn't overrun
     /// the internal buffer. Otherwise return None. Also return the offset of the first byte
     /// after the requested data that falls on a 64-byte boundary.
+    /// Entries whose data length exceeds the cap are rejected.
     pub fn get_account<'a>(&'a self, offset: usize) -> Option<(StoredAccountMeta<'a>, usize)> {
         let (meta, next): (&'a StoredMeta, _) = self.get_type(offset)?;
+        if meta.data_len > self.max_data_len {
+            return None;
+        }
         let (account_meta, next): (&'a AccountMeta, _) = self.get_type(next)?;
         let (hash, next): (&'a Hash, _) = self.get_type(next)?;
-        let (data, next) = self.get_slice(next, meta.data_len as usize)?;
+        let (data, next) = self.get_slice(next, usize::try_from(meta.data_len).ok()?)?;
         let stored_size = next - offset;
         Some((
             StoredAccountMeta {
@@ -279,7 +379,99 @@ impl AppendVec {
         ))
     }
 
+    /// Reads the account at `offset` during sequential iteration. Returns the account,
+    /// or None if it was skipped for exceeding the data length cap, along with the offset
+    /// of the next entry. Returns None at the end of the append vec, or if an oversized
+    /// entry can't be skipped because it runs past the end.
+    pub fn next_account(&self, offset: usize) -> Option<(Option<StoredAccountMeta<'_>>, usize)> {
+        let (meta, _): (&StoredMeta, _) = self.get_type(offset)?;
+        if meta.data_len <= self.max_data_len {
+            return self
+                .get_account(offset)
+                .map(|(account, next)| (Some(account), next));
+        }
+
+        let next = self.entry_end(offset);
+
+        warn!(
+            "Account {} in slot {} at offset {} claims {} bytes of data (cap {}), {}",
+            meta.pubkey,
+            self.slot,
+            offset,
+            meta.data_len,
+            self.max_data_len,
+            if next.is_some() {
+                "skipping"
+            } else {
+                "abandoning rest of append vec"
+            }
+        );
+        if let Some(oversized) = &self.oversized {
+            oversized.record(OversizedAccount {
+                slot: self.slot,
+                offset,
+                pubkey: meta.pubkey,
+                data_len: meta.data_len,
+                skipped: next.is_some(),
+            });
+        }
+        next.map(|next| (None, next))
+    }
+
+    /// Returns the offset of the entry following the one at `offset`, without applying
+    /// the data length cap. Returns None if the entry runs past the end.
+    fn entry_end(&self, offset: usize) -> Option<usize> {
+        let (meta, next): (&StoredMeta, _) = self.get_type(offset)?;
+        let (_, next): (&AccountMeta, _) = self.get_type(next)?;
+        let (_, next): (&Hash, _) = self.get_type(next)?;
+        usize::try_from(meta.data_len)
+            .ok()
+            .and_then(|data_len| next.checked_add(data_len))
+            .filter(|end| *end <= self.len())
+            .map(|end| u64_align!(end))
+    }
+
+    /// Walks the entries of the append vec, returning the offset of the first entry
+    /// that can't be parsed, if any. Iterators stop at that offset.
+    pub fn find_corruption(&self) -> Option<usize> {
+        let mut offset = 0;
+        while offset < self.len() {
+            offset = match self.entry_end(offset) {
+                Some(next) => next,
+                None => return Some(offset),
+            };
+        }
+        None
+    }
+
     pub fn get_slot(&self) -> u64 {
         self.slot
     }
+
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    /// Sets the data length cap, reporting rejected entries to `oversized`.
+    pub fn with_max_data_len(
+        mut self,
+        max_data_len: u64,
+        oversized: Option<Arc<OversizedAccounts>>,
+    ) -> Self {
+        self.max_data_len = max_data_len;
+        self.oversized = oversized;
+        self
+    }
+
+    /// Adds a mask, hiding the accounts it matches from account iterators.
+    pub fn with_mask(mut self, mask: Arc<dyn AccountMask>) -> Self {
+        self.masks.push(mask);
+        self
+    }
+
+    pub fn is_hidden(&self, account: &StoredAccountMeta) -> bool {
+        self.masks
+            .iter()
+            .any(|mask| mask.is_hidden(self.slot, self.id, account))
+    }
 }
@@ -1,24 +1,84 @@
 use crate::{
-    deserialize_from, parse_append_vec_name, AccountsDbFields, AppendVec, AppendVecIterator,
-    DeserializableVersionedBank, Result, SerializableAccountStorageEntry, SnapshotError,
-    SnapshotExtractor,
+    parse_append_vec_name, AppendVec, AppendVecIterator, Result, SnapshotError, SnapshotExtractor,
+    SnapshotInfo, SnapshotManifest,
 };
 use log::info;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{self, BufRead, BufReader, Read};
 use std::path::{Component, Path};
 use std::pin::Pin;
-use std::time::Instant;
 use tar::{Archive, Entries, Entry};
 
-/// Extracts account data from a .tar.zst stream.
+/// Compression of a snapshot tarball, detected from its leading magic bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveCompression {
+    Zstd,
+    Gzip,
+    Bzip2,
+    /// LZ4 frame format
+    Lz4,
+}
+
+impl ArchiveCompression {
+    const MAGIC: [(&'static [u8], ArchiveCompression); 4] = [
+        (&[0x28, 0xb5, 0x2f, 0xfd], ArchiveCompression::Zstd),
+        (&[0x1f, 0x8b], ArchiveCompression::Gzip),
+        (b"BZh", ArchiveCompression::Bzip2),
+        (&[0x04, 0x22, 0x4d, 0x18], ArchiveCompression::Lz4),
+    ];
+
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        Self::MAGIC
+            .iter()
+            .find(|(magic, _)| header.starts_with(magic))
+            .map(|(_, compression)| *compression)
+    }
+}
+
+/// Decompressing reader over a snapshot tarball.
+pub enum ArchiveDecoder<Source: Read> {
+    Zstd(zstd::Decoder<'static, BufReader<Source>>),
+    Gzip(flate2::bufread::MultiGzDecoder<BufReader<Source>>),
+    Bzip2(bzip2::bufread::MultiBzDecoder<BufReader<Source>>),
+    Lz4(lz4::Decoder<BufReader<Source>>),
+}
+
+impl<Source: Read> ArchiveDecoder<Source> {
+    pub fn new(source: Source) -> Result<Self> {
+        let mut source = BufReader::new(source);
+        let compression = ArchiveCompression::detect(source.fill_buf()?)
+            .ok_or(SnapshotError::UnknownArchiveCompression)?;
+        info!("Detected {:?} snapshot archive", compression);
+        Ok(match compression {
+            ArchiveCompression::Zstd => Self::Zstd(zstd::Decoder::with_buffer(source)?),
+            ArchiveCompression::Gzip => Self::Gzip(flate2::bufread::MultiGzDecoder::new(source)),
+            ArchiveCompression::Bzip2 => Self::Bzip2(bzip2::bufread::MultiBzDecoder::new(source)),
+            ArchiveCompression::Lz4 => Self::Lz4(lz4::Decoder::new(source)?),
+        })
+    }
+}
+
+impl<Source: Read> Read for ArchiveDecoder<Source> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Zstd(decoder) => decoder.read(buf),
+            Self::Gzip(decoder) => decoder.read(buf),
+            Self::Bzip2(decoder) => decoder.read(buf),
+            Self::Lz4(decoder) => decoder.read(buf),
+        }
+    }
+}
+
+/// Extracts account data from a .tar.zst, .tar.gz, .tar.bz2 or .tar.lz4 stream.
 pub struct ArchiveSnapshotExtractor<Source>
 where
     Source: Read + Unpin + 'static,
 {
-    accounts_db_fields: AccountsDbFields<SerializableAccountStorageEntry>,
-    _archive: Pin<Box<Archive<zstd::Decoder<'static, BufReader<Source>>>>>,
-    entries: Option<Entries<'static, zstd::Decoder<'static, BufReader<Source>>>>,
+    manifest: SnapshotManifest,
+    info: SnapshotInfo,
+    // Declared before `archive`, so it is dropped before the archive it borrows
+    entries: Option<Entries<'static, ArchiveDecoder<Source>>>,
+    archive: Option<Pin<Box<Archive<ArchiveDecoder<Source>>>>>,
 }
 
 impl<Source> SnapshotExtractor for ArchiveSnapshotExtractor<Source>
@@ -28,6 +88,14 @@ where
     fn iter(&mut self) -> AppendVecIterator<'_> {
         Box::new(self.unboxed_iter())
     }
+
+    fn manifest(&self) -> &SnapshotManifest {
+        &self.manifest
+    }
+
+    fn info(&self) -> &SnapshotInfo {
+        &self.info
+    }
 }
 
 impl<Source> ArchiveSnapshotExtractor<Source>
@@ -35,7 +103,7 @@ where
     Source: Read + Unpin + 'static,
 {
     pub fn from_reader(source: Source) -> Result<Self> {
-        let tar_stream = zstd::stream::read::Decoder::new(source)?;
+        let tar_stream = ArchiveDecoder::new(source)?;
         let mut archive = Box::pin(Archive::new(tar_stream));
 
         // This is safe as long as we guarantee that entries never gets accessed past drop.
@@ -62,58 +130,64 @@ where
         info!("Opening snapshot manifest: {:?}", &snapshot_file_path);
         let mut snapshot_file = BufReader::new(snapshot_file);
 
-        let pre_unpack = Instant::now();
-        let versioned_bank: DeserializableVersionedBank = deserialize_from(&mut snapshot_file)?;
-        drop(versioned_bank);
-        let versioned_bank_post_time = Instant::now();
-
-        let accounts_db_fields: AccountsDbFields<SerializableAccountStorageEntry> =
-            deserialize_from(&mut snapshot_file)?;
-        let accounts_db_fields_post_time = Instant::now();
+        let manifest = SnapshotManifest::read_from(&mut snapshot_file)?;
+        let info = manifest.info();
         drop(snapshot_file);
 
-        info!(
-            "Read bank fields in {:?}",
-            versioned_bank_post_time - pre_unpack
-        );
-        info!(
-            "Read accounts DB fields in {:?}",
-            accounts_db_fields_post_time - versioned_bank_post_time
-        );
-
         Ok(ArchiveSnapshotExtractor {
-            _archive: archive,
-            accounts_db_fields,
             entries: Some(entries),
+            archive: Some(archive),
+            manifest,
+            info,
         })
     }
 
     fn unboxed_iter(&mut self) -> impl Iterator<Item = Result<AppendVec>> + '_ {
-        self.entries
-            .take()
-            .into_iter()
-            .flatten()
-            .filter_map(|entry| {
-                let mut entry = match entry {
-                    Ok(x) => x,
-                    Err(e) => return Some(Err(e.into())),
-                };
-                let path = match entry.path() {
-                    Ok(x) => x,
-                    Err(e) => return Some(Err(e.into())),
-                };
-                let (slot, id) = path.file_name().and_then(parse_append_vec_name)?;
-                Some(self.process_entry(&mut entry, slot, id))
-            })
+        let manifest = &self.manifest;
+        let archive = &mut self.archive;
+        let mut entries = self.entries.take();
+        std::iter::from_fn(move || loop {
+            let Some(iter) = entries.as_mut() else {
+                return Self::drain(archive.take()?).err().map(Err);
+            };
+            let mut entry = match iter.next() {
+                Some(Ok(x)) => x,
+                Some(Err(e)) => return Some(Err(e.into())),
+                None => {
+                    // Entries borrow the archive, so they must be dropped before draining it
+                    entries = None;
+                    continue;
+                }
+            };
+            let path = match entry.path() {
+                Ok(x) => x,
+                Err(e) => return Some(Err(e.into())),
+            };
+            let Some((slot, id)) = path.file_name().and_then(parse_append_vec_name) else {
+                continue;
+            };
+            return Some(
+                Self::process_entry(manifest, &mut entry, slot, id)
+                    .map_err(|e| SnapshotError::storage(slot, id, e)),
+            );
+        })
+    }
+
+    /// Reads the stream past the end of the tarball, so that wrapping readers
+    /// (e.g. digest checks) see all of it.
+    fn drain(archive: Pin<Box<Archive<ArchiveDecoder<Source>>>>) -> Result<()> {
+        let mut decoder = Pin::into_inner(archive).into_inner();
+        io::copy(&mut decoder, &mut io::sink())?;
+        Ok(())
     }
 
     fn process_entry(
-        &self,
-        entry: &mut Entry<'static, zstd::Decoder<'static, BufReader<Source>>>,
+        manifest: &SnapshotManifest,
+        entry: &mut Entry<'static, ArchiveDecoder<Source>>,
         slot: u64,
         id: u64,
     ) -> Result<AppendVec> {
-        let known_vecs = self
+        let known_vecs = manifest
             .accounts_db_fields
             .0
             .get(&slot)
@@ -128,6 +202,7 @@ where
             entry,
             known_vec.accounts_current_len,
             slot,
+            id,
         )?)
     }
 
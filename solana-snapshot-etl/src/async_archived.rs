@@ -0,0 +1,88 @@
+use crate::archived::ArchiveSnapshotExtractor;
+use crate::{AppendVec, Result, SnapshotExtractor, SnapshotInfo};
+use futures::stream::{Stream, TryStreamExt};
+use solana_sdk::clock::Slot;
+use solana_sdk::hash::Hash;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::AsyncRead;
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::io::{StreamReader, SyncIoBridge};
+
+/// Number of decoded append vecs buffered ahead of the consumer.
+const CHANNEL_CAPACITY: usize = 4;
+
+/// Extracts account data from a snapshot archive stream without blocking the async runtime.
+///
+/// Decompression and unpacking run on a blocking task; append vecs are handed back
+/// through a bounded channel and consumed as a [`Stream`].
+pub struct AsyncArchiveSnapshotExtractor {
+    info: SnapshotInfo,
+    receiver: mpsc::Receiver<Result<AppendVec>>,
+}
+
+impl AsyncArchiveSnapshotExtractor {
+    /// Streams a snapshot archive from an HTTP(S) URL.
+    pub async fn from_url(url: &str) -> Result<Self> {
+        let response = reqwest::get(url)
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let stream = response
+            .bytes_stream()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e));
+        Self::from_async_reader(StreamReader::new(stream)).await
+    }
+
+    pub async fn from_async_reader<R>(reader: R) -> Result<Self>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let source = SyncIoBridge::new(reader);
+        let (manifest_tx, manifest_rx) = oneshot::channel();
+        let (tx, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+
+        tokio::task::spawn_blocking(move || {
+            let mut extractor = match ArchiveSnapshotExtractor::from_reader(source) {
+                Ok(extractor) => extractor,
+                Err(e) => {
+                    let _ = manifest_tx.send(Err(e));
+                    return;
+                }
+            };
+            let _ = manifest_tx.send(Ok(extractor.info().clone()));
+            for append_vec in extractor.iter() {
+                if tx.blocking_send(append_vec).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let info = manifest_rx.await.map_err(|_| {
+            io::Error::new(io::ErrorKind::Other, "snapshot extractor task terminated")
+        })??;
+
+        Ok(Self { info, receiver })
+    }
+
+    pub fn info(&self) -> &SnapshotInfo {
+        &self.info
+    }
+
+    pub fn bank_hash(&self) -> Hash {
+        self.info.bank_hash
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.info.slot
+    }
+}
+
+impl Stream for AsyncArchiveSnapshotExtractor {
+    type Item = Result<AppendVec>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
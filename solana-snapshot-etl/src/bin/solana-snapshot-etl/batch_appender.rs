@@ -0,0 +1,166 @@
+use crate::schema::TableSchema;
+use duckdb::arrow::array::{
+    ArrayBuilder, ArrayRef, BooleanBuilder, StringBuilder, UInt64Builder, UInt8Builder,
+};
+use duckdb::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use duckdb::arrow::record_batch::RecordBatch;
+use duckdb::types::Value;
+use duckdb::{Appender, Connection};
+use std::sync::Arc;
+
+/// Rows buffered before they are handed to DuckDB as one record batch, by default.
+const BATCH_ROWS: usize = 50_000;
+
+/// Arrow builder for one column, chosen by the column's SQL type.
+enum ColumnBuilder {
+    Text(StringBuilder),
+    UBigInt(UInt64Builder),
+    UTinyInt(UInt8Builder),
+    Boolean(BooleanBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(sql_type: &str) -> Result<Self, String> {
+        match sql_type.split_whitespace().next().unwrap_or_default() {
+            "VARCHAR" => Ok(Self::Text(StringBuilder::new())),
+            "UBIGINT" => Ok(Self::UBigInt(UInt64Builder::new())),
+            "UTINYINT" => Ok(Self::UTinyInt(UInt8Builder::new())),
+            "BOOLEAN" => Ok(Self::Boolean(BooleanBuilder::new())),
+            other => Err(format!("Unsupported column type '{}'", other)),
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            Self::Text(_) => DataType::Utf8,
+            Self::UBigInt(_) => DataType::UInt64,
+            Self::UTinyInt(_) => DataType::UInt8,
+            Self::Boolean(_) => DataType::Boolean,
+        }
+    }
+
+    fn append(&mut self, value: Value) -> Result<(), String> {
+        match (self, value) {
+            (Self::Text(builder), Value::Text(value)) => builder.append_value(value),
+            (Self::Text(builder), Value::Null) => builder.append_null(),
+            (Self::UBigInt(builder), Value::UBigInt(value)) => builder.append_value(value),
+            (Self::UBigInt(builder), Value::Null) => builder.append_null(),
+            (Self::UTinyInt(builder), Value::UTinyInt(value)) => builder.append_value(value),
+            (Self::UTinyInt(builder), Value::Null) => builder.append_null(),
+            (Self::Boolean(builder), Value::Boolean(value)) => builder.append_value(value),
+            (Self::Boolean(builder), Value::Null) => builder.append_null(),
+            (builder, value) => {
+                return Err(format!(
+                    "Value {:?} doesn't match column type {}",
+                    value,
+                    builder.data_type()
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            Self::Text(builder) => Arc::new(builder.finish()),
+            Self::UBigInt(builder) => Arc::new(builder.finish()),
+            Self::UTinyInt(builder) => Arc::new(builder.finish()),
+            Self::Boolean(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+/// Buffers the rows of a table and appends them to DuckDB as Arrow record batches,
+/// which is much faster than appending row by row.
+pub struct BatchAppender<'conn> {
+    appender: Appender<'conn>,
+    schema: SchemaRef,
+    builders: Vec<ColumnBuilder>,
+    batch_rows: usize,
+}
+
+impl<'conn> BatchAppender<'conn> {
+    pub fn new(
+        conn: &'conn Connection,
+        table: &TableSchema,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_batch_rows(conn, table, BATCH_ROWS)
+    }
+
+    /// Like `new`, handing rows to DuckDB every `batch_rows` rows. Smaller batches use
+    /// less memory, which matters for wide rows.
+    pub fn with_batch_rows(
+        conn: &'conn Connection,
+        table: &TableSchema,
+        batch_rows: usize,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut fields = Vec::new();
+        let mut builders = Vec::new();
+        for (name, sql_type) in table.columns() {
+            let builder = ColumnBuilder::new(sql_type)?;
+            fields.push(Field::new(
+                name,
+                builder.data_type(),
+                !sql_type.contains("NOT NULL"),
+            ));
+            builders.push(builder);
+        }
+        Ok(Self {
+            appender: conn.appender(&table.name)?,
+            schema: Arc::new(Schema::new(fields)),
+            builders,
+            batch_rows,
+        })
+    }
+
+    /// Buffers a row holding the table's selected columns.
+    pub fn append_row(&mut self, row: Vec<Value>) -> Result<(), Box<dyn std::error::Error>> {
+        // A short row would leave the column builders out of step with each other
+        if row.len() != self.builders.len() {
+            return Err(format!(
+                "Row of {} values appended to a table of {} columns",
+                row.len(),
+                self.builders.len()
+            )
+            .into());
+        }
+        for (builder, value) in self.builders.iter_mut().zip(row) {
+            builder.append(value)?;
+        }
+        if self.buffered_rows() >= self.batch_rows {
+            self.append_batch()?;
+        }
+        Ok(())
+    }
+
+    /// Appends buffered rows and flushes them to the database.
+    pub fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.append_batch()?;
+        self.appender.flush()?;
+        Ok(())
+    }
+
+    fn buffered_rows(&self) -> usize {
+        match self.builders.first() {
+            Some(ColumnBuilder::Text(builder)) => builder.len(),
+            Some(ColumnBuilder::UBigInt(builder)) => builder.len(),
+            Some(ColumnBuilder::UTinyInt(builder)) => builder.len(),
+            Some(ColumnBuilder::Boolean(builder)) => builder.len(),
+            None => 0,
+        }
+    }
+
+    fn append_batch(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.buffered_rows() == 0 {
+            return Ok(());
+        }
+        let columns = self
+            .builders
+            .iter_mut()
+            .map(ColumnBuilder::finish)
+            .collect();
+        let batch = RecordBatch::try_new(Arc::clone(&self.schema), columns)?;
+        self.appender.append_record_batch(batch)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use crate::format;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{
+    bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable, native_loader, nonce, system_program,
+};
+use solana_snapshot_etl::append_vec::StoredAccountMeta;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+
+/// Chain invariants an account can violate.
+#[derive(Clone, Copy)]
+enum Violation {
+    /// System-owned account with data that isn't a nonce account
+    SystemOwnedWithData,
+    ExecutableNotLoaderOwned,
+    ExecutableWithoutData,
+    ZeroLamportsWithData,
+}
+
+impl Violation {
+    const ALL: [Violation; 4] = [
+        Violation::SystemOwnedWithData,
+        Violation::ExecutableNotLoaderOwned,
+        Violation::ExecutableWithoutData,
+        Violation::ZeroLamportsWithData,
+    ];
+
+    fn description(self) -> &'static str {
+        match self {
+            Violation::SystemOwnedWithData => "System-owned accounts with non-nonce data",
+            Violation::ExecutableNotLoaderOwned => "Executable accounts not owned by a loader",
+            Violation::ExecutableWithoutData => "Executable accounts without data",
+            Violation::ZeroLamportsWithData => "Zero-lamport accounts with data",
+        }
+    }
+
+    fn check(self, account: &StoredAccountMeta) -> bool {
+        let meta = account.account_meta;
+        match self {
+            Violation::SystemOwnedWithData => {
+                meta.owner == system_program::id()
+                    && !account.data.is_empty()
+                    && account.data.len() != nonce::State::size()
+            }
+            Violation::ExecutableNotLoaderOwned => meta.executable && !is_loader(&meta.owner),
+            Violation::ExecutableWithoutData => meta.executable && account.data.is_empty(),
+            Violation::ZeroLamportsWithData => meta.lamports == 0 && !account.data.is_empty(),
+        }
+    }
+}
+
+fn is_loader(owner: &Pubkey) -> bool {
+    *owner == bpf_loader::id()
+        || *owner == bpf_loader_deprecated::id()
+        || *owner == bpf_loader_upgradeable::id()
+        || *owner == native_loader::id()
+}
+
+struct Example {
+    pubkey: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    data_len: usize,
+}
+
+#[derive(Default)]
+struct Findings {
+    count: u64,
+    examples: Vec<Example>,
+}
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    max_examples: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut findings: Vec<Findings> = Violation::ALL.iter().map(|_| Findings::default()).collect();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            for (violation, found) in Violation::ALL.iter().zip(findings.iter_mut()) {
+                if !violation.check(&account) {
+                    continue;
+                }
+                found.count += 1;
+                if found.examples.len() < max_examples {
+                    found.examples.push(Example {
+                        pubkey: account.meta.pubkey,
+                        owner: account.account_meta.owner,
+                        lamports: account.account_meta.lamports,
+                        data_len: account.data.len(),
+                    });
+                }
+            }
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let total_violations: u64 = findings.iter().map(|f| f.count).sum();
+    info!(
+        "Audited {} accounts, found {} invariant violations",
+        format::count(total_accounts),
+        format::count(total_violations)
+    );
+
+    println!("\n--- Chain Invariant Audit ---\n");
+    println!(
+        "{:<45} {:>15}",
+        "Accounts audited",
+        format::count(total_accounts)
+    );
+    for (violation, found) in Violation::ALL.iter().zip(&findings) {
+        println!(
+            "{:<45} {:>15}",
+            violation.description(),
+            format::count(found.count)
+        );
+    }
+
+    for (violation, found) in Violation::ALL.iter().zip(findings) {
+        if found.examples.is_empty() {
+            continue;
+        }
+        println!("\n{}:\n", violation.description());
+        println!(
+            "{:<45} {:<45} {:>20} {:>12}",
+            "Pubkey", "Owner", "Lamports", "Data Len"
+        );
+        println!("{}", "-".repeat(125));
+        for example in found.examples {
+            println!(
+                "{:<45} {:<45} {:>20} {:>12}",
+                example.pubkey.to_string(),
+                example.owner.to_string(),
+                format::lamports(example.lamports),
+                format::bytes(example.data_len as u64)
+            );
+        }
+    }
+
+    Ok(())
+}
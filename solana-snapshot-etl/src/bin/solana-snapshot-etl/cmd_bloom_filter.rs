@@ -0,0 +1,104 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::hash::Hash;
+use solana_snapshot_etl::append_vec::{AccountMeta, StoredMeta};
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::bloom::PubkeyBloomFilter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::mem;
+
+/// Smallest possible append vec entry: an account without data.
+const MIN_STORED_ACCOUNT_LEN: usize =
+    mem::size_of::<StoredMeta>() + mem::size_of::<AccountMeta>() + mem::size_of::<Hash>();
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    output: &str,
+    fp_rate: f64,
+    expected_accounts: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !(fp_rate > 0.0 && fp_rate < 1.0) {
+        return Err(format!("--fp-rate {} must be between 0 and 1", fp_rate).into());
+    }
+    // Without a count, size the filter for as many accounts as the append vecs can hold
+    let expected_accounts = expected_accounts.unwrap_or_else(|| {
+        let stored_bytes: usize = loader
+            .manifest()
+            .accounts_db_fields
+            .0
+            .values()
+            .flatten()
+            .map(|storage| storage.accounts_current_len)
+            .sum();
+        (stored_bytes / MIN_STORED_ACCOUNT_LEN) as u64
+    });
+    let mut filter = PubkeyBloomFilter::new(
+        expected_accounts,
+        fp_rate,
+        loader.slot(),
+        loader.bank_hash(),
+    );
+    info!(
+        "Sized Bloom filter for {} accounts: {} with {} hash functions",
+        format::count(expected_accounts),
+        format::bytes(filter.num_bits().div_ceil(8)),
+        filter.num_hashes()
+    );
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    // Closed accounts, and older versions of live ones, must not answer for the snapshot slot
+    let mut total_accounts: u64 = 0;
+    let mut live: LatestAccounts<()> = LatestAccounts::new();
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+            live.insert(slot, &account, || Some(()));
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let live_accounts = live.len() as u64;
+    for (pubkey, _) in &live {
+        filter.insert(pubkey);
+    }
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    filter.write_to(&mut writer)?;
+    writer.flush()?;
+
+    println!("\n--- Pubkey Bloom Filter ---\n");
+    println!("Slot:                {}", filter.slot());
+    println!("Accounts inserted:   {}", format::count(live_accounts));
+    println!(
+        "Filter size:         {}",
+        format::bytes(filter.num_bits().div_ceil(8))
+    );
+    println!("Hash functions:      {}", filter.num_hashes());
+    println!(
+        "False positive rate: {:.6}",
+        filter.false_positive_rate(live_accounts)
+    );
+    println!("Written to:          {}", output);
+    Ok(())
+}
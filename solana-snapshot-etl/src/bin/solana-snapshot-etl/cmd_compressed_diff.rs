@@ -0,0 +1,145 @@
+use crate::compressor::{Compressor, TokenAccountCompressor, TokenAccountPubkey};
+use crate::format;
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Identifies a token account across compressed states. Canonical associated token
+/// accounts are stored without their address, which is unique per owner and mint.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum TokenAccountKey {
+    Associated { owner: Pubkey, mint: Pubkey },
+    Address(Pubkey),
+}
+
+/// Changes to the token accounts of one mint.
+#[derive(Default)]
+struct MintChanges {
+    new_accounts: u64,
+    closed_accounts: u64,
+    balance_changes: u64,
+    /// Net change of the mint's balance held in token accounts, in base units
+    amount_delta: i128,
+}
+
+impl MintChanges {
+    fn total(&self) -> u64 {
+        self.new_accounts + self.closed_accounts + self.balance_changes
+    }
+}
+
+/// Mint and amount of every token account in a compressed state.
+///
+/// States hold every stored version of an account, so the last one in the file wins.
+fn balances(
+    path: &str,
+) -> Result<HashMap<TokenAccountKey, (Pubkey, u64)>, Box<dyn std::error::Error>> {
+    info!("Loading compressed token state: {}", path);
+    let compressor = TokenAccountCompressor::load(path)
+        .map_err(|e| format!("Failed to load compressed token state {}: {}", path, e))?;
+    let mut balances = HashMap::with_capacity(compressor.len());
+    for account in compressor.iter() {
+        let mint = compressor.pubkey(account.mint);
+        let key = match account.pubkey {
+            TokenAccountPubkey::Pda => TokenAccountKey::Associated {
+                owner: compressor.pubkey(account.owner),
+                mint,
+            },
+            TokenAccountPubkey::Custom(position) => {
+                TokenAccountKey::Address(compressor.pubkey(position))
+            }
+        };
+        balances.insert(key, (mint, account.amount));
+    }
+    Ok(balances)
+}
+
+/// Compares two states written by `custom-compress`, `before_path` taken from the earlier
+/// snapshot, and reports new accounts, closed accounts and balance changes per mint.
+pub fn run(
+    before_path: &str,
+    after_path: &str,
+    top: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut before = balances(before_path)?;
+    let after = balances(after_path)?;
+    let (accounts_before, accounts_after) = (before.len() as u64, after.len() as u64);
+
+    // Amounts of different mints don't add up, so only the counts are totalled
+    let mut overall = MintChanges::default();
+    let mut by_mint: HashMap<Pubkey, MintChanges> = HashMap::new();
+    for (key, (mint, amount)) in &after {
+        let changes = by_mint.entry(*mint).or_default();
+        match before.remove(key) {
+            None => {
+                changes.new_accounts += 1;
+                overall.new_accounts += 1;
+            }
+            Some((_, previous)) if previous == *amount => continue,
+            Some((_, previous)) => {
+                changes.balance_changes += 1;
+                overall.balance_changes += 1;
+                changes.amount_delta -= previous as i128;
+            }
+        }
+        changes.amount_delta += *amount as i128;
+    }
+    // Accounts left over were closed since the earlier state
+    for (mint, amount) in before.values() {
+        let changes = by_mint.entry(*mint).or_default();
+        changes.closed_accounts += 1;
+        overall.closed_accounts += 1;
+        changes.amount_delta -= *amount as i128;
+    }
+
+    let mut mints: Vec<_> = by_mint
+        .into_iter()
+        .filter(|(_, changes)| changes.total() > 0)
+        .collect();
+    mints.sort_by_key(|(mint, changes)| (Reverse(changes.total()), *mint));
+
+    println!("\n--- Token State Diff ---\n");
+    println!(
+        "Token accounts before:   {:>24}",
+        format::count(accounts_before)
+    );
+    println!(
+        "Token accounts after:    {:>24}",
+        format::count(accounts_after)
+    );
+    println!(
+        "New accounts:            {:>24}",
+        format::count(overall.new_accounts)
+    );
+    println!(
+        "Closed accounts:         {:>24}",
+        format::count(overall.closed_accounts)
+    );
+    println!(
+        "Balance changes:         {:>24}",
+        format::count(overall.balance_changes)
+    );
+    println!(
+        "Mints with changes:      {:>24}",
+        format::count(mints.len() as u64)
+    );
+
+    println!("\nTop {} mints by changed accounts:\n", top);
+    println!(
+        "{:<44} {:>12} {:>12} {:>12} {:>26}",
+        "Mint", "New", "Closed", "Changed", "Net Amount"
+    );
+    println!("{}", "-".repeat(110));
+    for (mint, changes) in mints.iter().take(top) {
+        println!(
+            "{:<44} {:>12} {:>12} {:>12} {:>26}",
+            mint.to_string(),
+            format::count(changes.new_accounts),
+            format::count(changes.closed_accounts),
+            format::count(changes.balance_changes),
+            format::signed(changes.amount_delta, format::count_wide)
+        );
+    }
+    Ok(())
+}
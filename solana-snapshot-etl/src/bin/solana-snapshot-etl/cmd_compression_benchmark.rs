@@ -1,17 +1,22 @@
 use crate::compression_benchmark::CompressionBenchmarkConsumer;
 use crate::loader::SupportedLoader;
+use itertools::Itertools;
 use log::{error, info};
 use solana_sdk::pubkey::Pubkey;
 use solana_snapshot_etl::parallel::AppendVecConsumer;
 use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::HashSet;
 
 pub fn run(
     loader: &mut SupportedLoader,
-    owner_filter: Option<Pubkey>,
+    owner_filter: Option<HashSet<Pubkey>>,
     compression_level: i32,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    match owner_filter {
-        Some(owner) => info!("Filtering accounts by owner: {}", owner),
+    match &owner_filter {
+        Some(owners) => info!(
+            "Filtering accounts by owner: {}",
+            owners.iter().map(Pubkey::to_string).join(", ")
+        ),
         None => info!("Processing all accounts (no owner filter)"),
     }
     info!("Compression level: {}", compression_level);
@@ -21,7 +26,7 @@ pub fn run(
     for append_vec in loader.iter() {
         match append_vec {
             Ok(v) => {
-                consumer.on_append_vec(v).unwrap_or_else(|err| {
+                consumer.on_append_vec(&v).unwrap_or_else(|err| {
                     error!("on_append_vec: {:?}", err);
                 });
             }
@@ -1,12 +1,13 @@
 use crate::compressor::{Compressor, TokenAccountCompressor};
+use crate::format;
 use crate::loader::SupportedLoader;
+use crate::progress;
 use crate::token::TOKEN_PROGRAM_ID;
 use indicatif::{ProgressBar, ProgressStyle};
 use log::info;
 use solana_sdk::pubkey::Pubkey;
 use solana_snapshot_etl::append_vec_iter;
 use solana_snapshot_etl::SnapshotExtractor;
-use std::rc::Rc;
 use std::str::FromStr;
 
 pub fn run(
@@ -22,17 +23,18 @@ pub fn run(
         "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
     )
     .unwrap();
-    let spinner = ProgressBar::new_spinner()
-        .with_style(spinner_style)
-        .with_prefix("compress");
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("compress"),
+    );
 
     let mut total_accounts: u64 = 0;
     let mut accepted_accounts: usize = 0;
 
     'outer: for append_vec in loader.iter() {
         let append_vec = append_vec?;
-        for account in append_vec_iter(Rc::new(append_vec)) {
-            let account = account.access().unwrap();
+        for account in append_vec_iter(&append_vec) {
             total_accounts += 1;
 
             if total_accounts % 10000 == 0 {
@@ -58,17 +60,22 @@ pub fn run(
         }
     }
 
+    spinner.set_position(accepted_accounts as u64);
     spinner.finish();
 
     info!(
         "Processed {} accounts from {} total accounts",
-        accepted_accounts, total_accounts
+        format::count(accepted_accounts as u64),
+        format::count(total_accounts)
     );
 
     info!("Persisting to: {}", output_path);
     compressor.persist(output_path)?;
 
-    info!("Done! Saved {} accounts", compressor.len());
+    info!(
+        "Done! Saved {} accounts",
+        format::count(compressor.len() as u64)
+    );
 
     Ok(())
 }
@@ -0,0 +1,144 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::token::{MINT_ACCOUNT_LEN, TOKEN_ACCOUNT_LEN, TOKEN_PROGRAM_ID};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// Latest version of an account of the token program.
+enum TokenProgramAccount {
+    Mint,
+    TokenAccount { mint: Pubkey, amount: u64 },
+}
+
+struct MintReferences {
+    token_accounts: u64,
+    total_amount: u64,
+    example: Pubkey,
+}
+
+pub fn run(loader: &mut SupportedLoader, top_n: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    // Closed mints and token accounts keep their older versions in earlier slots, so
+    // only the latest version of each account is kept
+    let mut total_accounts: u64 = 0;
+    let mut accounts: LatestAccounts<TokenProgramAccount> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            accounts.insert(slot, &account, || {
+                if account.account_meta.owner != token_program {
+                    return None;
+                }
+                match account.data.len() {
+                    MINT_ACCOUNT_LEN => Some(TokenProgramAccount::Mint),
+                    TOKEN_ACCOUNT_LEN => Some(TokenProgramAccount::TokenAccount {
+                        mint: Pubkey::try_from(&account.data[0..32]).unwrap(),
+                        amount: u64::from_le_bytes(account.data[64..72].try_into().unwrap()),
+                    }),
+                    _ => None,
+                }
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut token_accounts: u64 = 0;
+    let mut mints: HashSet<Pubkey> = HashSet::new();
+    let mut references: HashMap<Pubkey, MintReferences> = HashMap::new();
+    for (pubkey, account) in &accounts {
+        match account {
+            TokenProgramAccount::Mint => {
+                mints.insert(*pubkey);
+            }
+            TokenProgramAccount::TokenAccount { mint, amount } => {
+                token_accounts += 1;
+                let entry = references.entry(*mint).or_insert(MintReferences {
+                    token_accounts: 0,
+                    total_amount: 0,
+                    example: *pubkey,
+                });
+                entry.token_accounts += 1;
+                entry.total_amount = entry.total_amount.saturating_add(*amount);
+                entry.example = entry.example.min(*pubkey);
+            }
+        }
+    }
+
+    let mut dangling: Vec<_> = references
+        .into_iter()
+        .filter(|(mint, _)| !mints.contains(mint))
+        .collect();
+    dangling.sort_by(|a, b| b.1.token_accounts.cmp(&a.1.token_accounts));
+    let dangling_accounts: u64 = dangling.iter().map(|(_, r)| r.token_accounts).sum();
+
+    info!(
+        "Checked {} token accounts against {} mints",
+        format::count(token_accounts),
+        format::count(mints.len() as u64)
+    );
+
+    println!("\n--- Token Accounts Referencing Missing Mints ---\n");
+    println!(
+        "Token accounts checked:   {:>15}",
+        format::count(token_accounts)
+    );
+    println!(
+        "Mints found:              {:>15}",
+        format::count(mints.len() as u64)
+    );
+    println!(
+        "Missing mints:            {:>15}",
+        format::count(dangling.len() as u64)
+    );
+    println!(
+        "Dangling token accounts:  {:>15}",
+        format::count(dangling_accounts)
+    );
+
+    if dangling.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "\n{:<45} {:>15} {:>20} {:<45}",
+        "Missing Mint", "Accounts", "Total Amount", "Example Token Account"
+    );
+    println!("{}", "-".repeat(128));
+    for (mint, refs) in dangling.into_iter().take(top_n) {
+        println!(
+            "{:<45} {:>15} {:>20} {:<45}",
+            mint.to_string(),
+            format::count(refs.token_accounts),
+            refs.total_amount,
+            refs.example.to_string()
+        );
+    }
+
+    Ok(())
+}
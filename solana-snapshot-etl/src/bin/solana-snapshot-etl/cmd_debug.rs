@@ -1,28 +1,31 @@
+use crate::format;
 use crate::loader::SupportedLoader;
-use crate::token::{TOKEN_ACCOUNT_LEN, TOKEN_PROGRAM_ID};
+use crate::token::{MULTISIG_ACCOUNT_LEN, TOKEN_ACCOUNT_LEN, TOKEN_PROGRAM_ID};
+use itertools::Itertools;
 use log::info;
 use solana_sdk::pubkey::Pubkey;
 use solana_snapshot_etl::append_vec_iter;
 use solana_snapshot_etl::SnapshotExtractor;
-use std::rc::Rc;
+use std::collections::HashSet;
 use std::str::FromStr;
 
 pub fn run(
     loader: &mut SupportedLoader,
-    owner_filter: Pubkey,
+    owners: &HashSet<Pubkey>,
     max_count: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    info!("Looking for accounts owned by: {}", owner_filter);
+    info!(
+        "Looking for accounts owned by: {}",
+        owners.iter().map(Pubkey::to_string).join(", ")
+    );
 
     let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
     let mut found = 0;
 
     'outer: for append_vec in loader.iter() {
         let append_vec = append_vec?;
-        for account in append_vec_iter(Rc::new(append_vec)) {
-            let account = account.access().unwrap();
-
-            if account.account_meta.owner != owner_filter {
+        for account in append_vec_iter(&append_vec) {
+            if !owners.contains(&account.account_meta.owner) {
                 continue;
             }
 
@@ -30,16 +33,20 @@ pub fn run(
             println!("\n--- Account {} ---", found);
             println!("Pubkey:      {}", account.meta.pubkey);
             println!("Owner:       {}", account.account_meta.owner);
-            println!("Lamports:    {}", account.account_meta.lamports);
-            println!("Data len:    {}", account.data.len());
+            println!(
+                "Lamports:    {}",
+                format::lamports(account.account_meta.lamports)
+            );
+            println!("Data len:    {}", format::bytes(account.data.len() as u64));
             println!("Executable:  {}", account.account_meta.executable);
             println!("Rent epoch:  {}", account.account_meta.rent_epoch);
 
-            // Try to parse as SPL Token Account
-            if account.account_meta.owner == token_program
-                && account.data.len() == TOKEN_ACCOUNT_LEN
-            {
+            // Try to parse as SPL Token Account or Multisig
+            let is_token_program = account.account_meta.owner == token_program;
+            if is_token_program && account.data.len() == TOKEN_ACCOUNT_LEN {
                 print_token_account(account.data);
+            } else if is_token_program && account.data.len() == MULTISIG_ACCOUNT_LEN {
+                print_multisig(account.data);
             } else {
                 // Print first 64 bytes of data as hex
                 let preview_len = account.data.len().min(64);
@@ -118,3 +125,24 @@ fn print_token_account(data: &[u8]) {
     println!("  Delegated Amount: {}", delegated_amount);
     println!("  Close Authority:  {:?}", close_authority);
 }
+
+fn print_multisig(data: &[u8]) {
+    // Multisig layout (355 bytes):
+    // - m: u8 (1)
+    // - n: u8 (1)
+    // - is_initialized: bool (1)
+    // - signers: [Pubkey; 11] (11 * 32 = 352)
+
+    let m = data[0];
+    let n = data[1];
+    let is_initialized = data[2] != 0;
+
+    println!("Multisig:");
+    println!("  Required (m):     {}", m);
+    println!("  Signers (n):      {}", n);
+    println!("  Is Initialized:   {}", is_initialized);
+    for (i, signer) in data[3..].chunks_exact(32).take(n as usize).enumerate() {
+        let label = format!("Signer {}:", i + 1);
+        println!("  {:<18}{}", label, Pubkey::try_from(signer).unwrap());
+    }
+}
@@ -0,0 +1,316 @@
+use crate::batch_appender::BatchAppender;
+use crate::format;
+use crate::loader::SupportedLoader;
+use crate::programs;
+use crate::progress;
+use crate::schema::{Column, SchemaMapping};
+use duckdb::types::Value;
+use duckdb::Connection;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::hash::{hash, Hash};
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+const ACCOUNT_CHANGE_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "owner",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "change",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "lamports_before",
+        sql_type: "UBIGINT",
+    },
+    Column {
+        name: "lamports_after",
+        sql_type: "UBIGINT",
+    },
+    Column {
+        name: "data_len_before",
+        sql_type: "UBIGINT",
+    },
+    Column {
+        name: "data_len_after",
+        sql_type: "UBIGINT",
+    },
+];
+
+/// Latest (slot, write version) of an account, with the fields compared at that version.
+struct LatestState {
+    version: (u64, u64),
+    lamports: u64,
+    owner: Pubkey,
+    data_len: u64,
+    data_hash: Hash,
+}
+
+/// Changes to the accounts of one owner.
+#[derive(Default)]
+struct OwnerChanges {
+    created: u64,
+    deleted: u64,
+    changed: u64,
+    lamports_delta: i128,
+    data_delta: i128,
+}
+
+impl OwnerChanges {
+    fn total(&self) -> u64 {
+        self.created + self.deleted + self.changed
+    }
+}
+
+/// Scans a snapshot for the latest state of every live account.
+fn latest_states(
+    loader: &mut SupportedLoader,
+    prefix: &'static str,
+) -> Result<HashMap<Pubkey, LatestState>, Box<dyn std::error::Error>> {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix(prefix),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut latest: HashMap<Pubkey, LatestState> = HashMap::new();
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            let version = (slot, account.meta.write_version);
+            if latest
+                .get(&account.meta.pubkey)
+                .is_some_and(|existing| existing.version >= version)
+            {
+                continue;
+            }
+            latest.insert(
+                account.meta.pubkey,
+                LatestState {
+                    version,
+                    lamports: account.account_meta.lamports,
+                    owner: account.account_meta.owner,
+                    data_len: account.meta.data_len,
+                    data_hash: hash(account.data),
+                },
+            );
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    // Zero-lamport accounts are deleted
+    latest.retain(|_, state| state.lamports > 0);
+    Ok(latest)
+}
+
+/// Row of the `account_changes` table.
+fn change_row(
+    pubkey: &Pubkey,
+    change: &str,
+    before: Option<&LatestState>,
+    after: Option<&LatestState>,
+) -> Vec<Value> {
+    let owner = after
+        .or(before)
+        .map(|state| state.owner)
+        .unwrap_or_default();
+    let ubigint = |value: Option<u64>| value.map(Value::UBigInt).unwrap_or(Value::Null);
+    vec![
+        Value::Text(pubkey.to_string()),
+        Value::Text(owner.to_string()),
+        Value::Text(change.to_string()),
+        ubigint(before.map(|state| state.lamports)),
+        ubigint(after.map(|state| state.lamports)),
+        ubigint(before.map(|state| state.data_len)),
+        ubigint(after.map(|state| state.data_len)),
+    ]
+}
+
+/// Compares the latest state of every account in `loader` with `other`, a later snapshot.
+pub fn run(
+    loader: &mut SupportedLoader,
+    other: &mut SupportedLoader,
+    top: usize,
+    db_path: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (slot_before, slot_after) = (loader.slot(), other.slot());
+    let mut before = latest_states(loader, "before")?;
+    let after = latest_states(other, "after")?;
+
+    let conn = match db_path {
+        Some(path) => {
+            info!("Opening DuckDB database: {}", path);
+            Some(Connection::open(path)?)
+        }
+        None => None,
+    };
+    let change_schema =
+        SchemaMapping::default().table("account_changes", ACCOUNT_CHANGE_COLUMNS)?;
+    if let Some(conn) = &conn {
+        conn.execute_batch(&format!(
+            "DROP TABLE IF EXISTS account_changes;
+             {}",
+            change_schema.create_table_sql()
+        ))?;
+    }
+    let mut appender = conn
+        .as_ref()
+        .map(|conn| BatchAppender::new(conn, &change_schema))
+        .transpose()?;
+
+    let mut overall = OwnerChanges::default();
+    let mut lamports_changed: u64 = 0;
+    let mut data_changed: u64 = 0;
+    let mut owner_changed: u64 = 0;
+    let mut unchanged: u64 = 0;
+    // Accounts are attributed to their latest owner
+    let mut by_owner: HashMap<Pubkey, OwnerChanges> = HashMap::new();
+
+    for (pubkey, after_state) in &after {
+        let before_state = before.remove(pubkey);
+        let change = match &before_state {
+            None => "created",
+            Some(before_state) => {
+                let lamports = before_state.lamports != after_state.lamports;
+                let data = before_state.data_hash != after_state.data_hash;
+                let owner = before_state.owner != after_state.owner;
+                if !(lamports || data || owner) {
+                    unchanged += 1;
+                    continue;
+                }
+                lamports_changed += lamports as u64;
+                data_changed += data as u64;
+                owner_changed += owner as u64;
+                "changed"
+            }
+        };
+
+        let lamports_delta = after_state.lamports as i128
+            - before_state.as_ref().map_or(0, |state| state.lamports) as i128;
+        let data_delta = after_state.data_len as i128
+            - before_state.as_ref().map_or(0, |state| state.data_len) as i128;
+        for changes in [&mut overall, by_owner.entry(after_state.owner).or_default()] {
+            if before_state.is_some() {
+                changes.changed += 1;
+            } else {
+                changes.created += 1;
+            }
+            changes.lamports_delta += lamports_delta;
+            changes.data_delta += data_delta;
+        }
+        if let Some(appender) = &mut appender {
+            appender.append_row(change_schema.select(change_row(
+                pubkey,
+                change,
+                before_state.as_ref(),
+                Some(after_state),
+            )))?;
+        }
+    }
+    // Accounts left over only exist in the earlier snapshot
+    for (pubkey, before_state) in &before {
+        for changes in [
+            &mut overall,
+            by_owner.entry(before_state.owner).or_default(),
+        ] {
+            changes.deleted += 1;
+            changes.lamports_delta -= before_state.lamports as i128;
+            changes.data_delta -= before_state.data_len as i128;
+        }
+        if let Some(appender) = &mut appender {
+            appender.append_row(change_schema.select(change_row(
+                pubkey,
+                "deleted",
+                Some(before_state),
+                None,
+            )))?;
+        }
+    }
+    if let Some(appender) = &mut appender {
+        appender.flush()?;
+        info!(
+            "Wrote {} account changes to {}",
+            format::count(overall.total()),
+            db_path.unwrap_or_default()
+        );
+    }
+
+    let mut owners: Vec<_> = by_owner.into_iter().collect();
+    owners.sort_by_key(|(owner, changes)| (Reverse(changes.total()), *owner));
+
+    println!("\n--- Snapshot Diff ---\n");
+    println!("Slots:                   {} -> {}", slot_before, slot_after);
+    println!(
+        "Created accounts:        {:>24}",
+        format::count(overall.created)
+    );
+    println!(
+        "Deleted accounts:        {:>24}",
+        format::count(overall.deleted)
+    );
+    println!(
+        "Changed accounts:        {:>24}",
+        format::count(overall.changed)
+    );
+    println!(
+        "  Lamports changed:      {:>24}",
+        format::count(lamports_changed)
+    );
+    println!(
+        "  Data changed:          {:>24}",
+        format::count(data_changed)
+    );
+    println!(
+        "  Owner changed:         {:>24}",
+        format::count(owner_changed)
+    );
+    println!("Unchanged accounts:      {:>24}", format::count(unchanged));
+    println!(
+        "Lamports change:         {:>24}",
+        format::signed(overall.lamports_delta, format::lamports_wide)
+    );
+    println!(
+        "Data size change:        {:>24}",
+        format::signed(overall.data_delta, format::bytes_wide)
+    );
+
+    println!("\nTop {} owners by changed accounts:\n", top);
+    println!(
+        "{:<44} {:>12} {:>12} {:>12} {:>25} {:>16}",
+        "Owner", "Created", "Deleted", "Changed", "Lamports", "Data Size"
+    );
+    println!("{}", "-".repeat(126));
+    for (owner, changes) in owners.iter().take(top) {
+        println!(
+            "{:<44} {:>12} {:>12} {:>12} {:>25} {:>16}",
+            programs::label(owner),
+            format::count(changes.created),
+            format::count(changes.deleted),
+            format::count(changes.changed),
+            format::signed(changes.lamports_delta, format::lamports_wide),
+            format::signed(changes.data_delta, format::bytes_wide)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,308 @@
+use crate::batch_appender::BatchAppender;
+use crate::format;
+use crate::governance::{self, GovernanceAccount};
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::schema::{Column, SchemaMapping};
+use duckdb::types::Value;
+use duckdb::Connection;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::HashSet;
+
+const REALM_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "program_id",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "version",
+        sql_type: "UTINYINT NOT NULL",
+    },
+    Column {
+        name: "community_mint",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "council_mint",
+        sql_type: "VARCHAR",
+    },
+    Column {
+        name: "authority",
+        sql_type: "VARCHAR",
+    },
+    Column {
+        name: "name",
+        sql_type: "VARCHAR NOT NULL",
+    },
+];
+
+const TOKEN_OWNER_RECORD_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "program_id",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "version",
+        sql_type: "UTINYINT NOT NULL",
+    },
+    Column {
+        name: "realm",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "governing_token_mint",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "governing_token_owner",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "deposit_amount",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "outstanding_proposal_count",
+        sql_type: "UTINYINT NOT NULL",
+    },
+    Column {
+        name: "governance_delegate",
+        sql_type: "VARCHAR",
+    },
+];
+
+const PROPOSAL_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "program_id",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "version",
+        sql_type: "UTINYINT NOT NULL",
+    },
+    Column {
+        name: "governance",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "governing_token_mint",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "state",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "token_owner_record",
+        sql_type: "VARCHAR NOT NULL",
+    },
+];
+
+const VOTE_RECORD_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "program_id",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "version",
+        sql_type: "UTINYINT NOT NULL",
+    },
+    Column {
+        name: "proposal",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "governing_token_owner",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "is_relinquished",
+        sql_type: "BOOLEAN NOT NULL",
+    },
+    Column {
+        name: "vote",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "voter_weight",
+        sql_type: "UBIGINT NOT NULL",
+    },
+];
+
+fn optional_pubkey(pubkey: Option<Pubkey>) -> Value {
+    pubkey
+        .map(|pubkey| Value::Text(pubkey.to_string()))
+        .unwrap_or(Value::Null)
+}
+
+/// Writes the latest state of the realms, token owner records, proposals and vote records of
+/// the given SPL Governance program instances to DuckDB tables.
+pub fn run(
+    loader: &mut SupportedLoader,
+    db_path: &str,
+    programs: &HashSet<Pubkey>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("governance"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    // Governance account -> program instance and the account, if it decoded. Deposits and
+    // proposal states change over time, so only the latest version of each account is kept
+    let mut governance_accounts: LatestAccounts<(Pubkey, Option<GovernanceAccount>)> =
+        LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(governance_accounts.len() as u64);
+            }
+
+            governance_accounts.insert(slot, &account, || {
+                let program_id = account.account_meta.owner;
+                // Governances, signatory records and proposal transactions aren't decoded
+                programs
+                    .contains(&program_id)
+                    .then(|| (program_id, governance::parse(account.data)))
+            });
+        }
+    }
+    spinner.set_position(governance_accounts.len() as u64);
+    spinner.finish();
+
+    let schema = SchemaMapping::default();
+    let realm_schema = schema.table("realms", REALM_COLUMNS)?;
+    let record_schema = schema.table("token_owner_records", TOKEN_OWNER_RECORD_COLUMNS)?;
+    let proposal_schema = schema.table("proposals", PROPOSAL_COLUMNS)?;
+    let vote_schema = schema.table("vote_records", VOTE_RECORD_COLUMNS)?;
+
+    info!("Opening DuckDB database: {}", db_path);
+    let conn = Connection::open(db_path)?;
+    conn.execute_batch(&format!(
+        "DROP TABLE IF EXISTS realms;
+         DROP TABLE IF EXISTS token_owner_records;
+         DROP TABLE IF EXISTS proposals;
+         DROP TABLE IF EXISTS vote_records;
+         {}
+         {}
+         {}
+         {}",
+        realm_schema.create_table_sql(),
+        record_schema.create_table_sql(),
+        proposal_schema.create_table_sql(),
+        vote_schema.create_table_sql()
+    ))?;
+    let mut realm_appender = BatchAppender::new(&conn, &realm_schema)?;
+    let mut record_appender = BatchAppender::new(&conn, &record_schema)?;
+    let mut proposal_appender = BatchAppender::new(&conn, &proposal_schema)?;
+    let mut vote_appender = BatchAppender::new(&conn, &vote_schema)?;
+
+    let mut realms: u64 = 0;
+    let mut records: u64 = 0;
+    let mut proposals: u64 = 0;
+    let mut votes: u64 = 0;
+    let total_governance_accounts = governance_accounts.len() as u64;
+    for (pubkey, (program_id, account)) in governance_accounts {
+        let pubkey = Value::Text(pubkey.to_string());
+        let program_id = Value::Text(program_id.to_string());
+        match account {
+            Some(GovernanceAccount::Realm(realm)) => {
+                realm_appender.append_row(realm_schema.select(vec![
+                    pubkey,
+                    program_id,
+                    Value::UTinyInt(realm.version),
+                    Value::Text(realm.community_mint.to_string()),
+                    optional_pubkey(realm.council_mint),
+                    optional_pubkey(realm.authority),
+                    Value::Text(realm.name),
+                ]))?;
+                realms += 1;
+            }
+            Some(GovernanceAccount::TokenOwnerRecord(record)) => {
+                record_appender.append_row(record_schema.select(vec![
+                    pubkey,
+                    program_id,
+                    Value::UTinyInt(record.version),
+                    Value::Text(record.realm.to_string()),
+                    Value::Text(record.governing_token_mint.to_string()),
+                    Value::Text(record.governing_token_owner.to_string()),
+                    Value::UBigInt(record.governing_token_deposit_amount),
+                    Value::UTinyInt(record.outstanding_proposal_count),
+                    optional_pubkey(record.governance_delegate),
+                ]))?;
+                records += 1;
+            }
+            Some(GovernanceAccount::Proposal(proposal)) => {
+                proposal_appender.append_row(proposal_schema.select(vec![
+                    pubkey,
+                    program_id,
+                    Value::UTinyInt(proposal.version),
+                    Value::Text(proposal.governance.to_string()),
+                    Value::Text(proposal.governing_token_mint.to_string()),
+                    Value::Text(governance::proposal_state_name(proposal.state).to_string()),
+                    Value::Text(proposal.token_owner_record.to_string()),
+                ]))?;
+                proposals += 1;
+            }
+            Some(GovernanceAccount::VoteRecord(vote)) => {
+                vote_appender.append_row(vote_schema.select(vec![
+                    pubkey,
+                    program_id,
+                    Value::UTinyInt(vote.version),
+                    Value::Text(vote.proposal.to_string()),
+                    Value::Text(vote.governing_token_owner.to_string()),
+                    Value::Boolean(vote.is_relinquished),
+                    Value::Text(vote.vote.to_string()),
+                    Value::UBigInt(vote.voter_weight),
+                ]))?;
+                votes += 1;
+            }
+            None => {}
+        }
+    }
+
+    realm_appender.flush()?;
+    record_appender.flush()?;
+    proposal_appender.flush()?;
+    vote_appender.flush()?;
+
+    info!(
+        "Dumped {} realms, {} token owner records, {} proposals and {} vote records from {} governance accounts",
+        format::count(realms),
+        format::count(records),
+        format::count(proposals),
+        format::count(votes),
+        format::count(total_governance_accounts)
+    );
+    Ok(())
+}
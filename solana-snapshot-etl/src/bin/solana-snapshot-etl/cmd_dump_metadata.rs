@@ -0,0 +1,231 @@
+use crate::batch_appender::BatchAppender;
+use crate::format;
+use crate::loader::SupportedLoader;
+use crate::mpl_metadata::{self, ParsedEdition};
+use crate::progress;
+use crate::schema::{Column, SchemaMapping};
+use duckdb::types::Value;
+use duckdb::Connection;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+
+const METADATA_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "mint",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "update_authority",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "name",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "symbol",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "uri",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "seller_fee_basis_points",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "primary_sale_happened",
+        sql_type: "BOOLEAN NOT NULL",
+    },
+    Column {
+        name: "is_mutable",
+        sql_type: "BOOLEAN NOT NULL",
+    },
+    Column {
+        name: "token_standard",
+        sql_type: "UTINYINT",
+    },
+    Column {
+        name: "collection",
+        sql_type: "VARCHAR",
+    },
+    Column {
+        name: "collection_verified",
+        sql_type: "BOOLEAN",
+    },
+    Column {
+        name: "data_len",
+        sql_type: "UBIGINT NOT NULL",
+    },
+];
+
+/// Master editions fill `supply` and `max_supply`, print editions `parent` and `edition`.
+const EDITION_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "kind",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "supply",
+        sql_type: "UBIGINT",
+    },
+    Column {
+        name: "max_supply",
+        sql_type: "UBIGINT",
+    },
+    Column {
+        name: "parent",
+        sql_type: "VARCHAR",
+    },
+    Column {
+        name: "edition",
+        sql_type: "UBIGINT",
+    },
+];
+
+/// Strings in metadata are padded with NUL bytes to their maximum length.
+fn trim_padding(value: &str) -> String {
+    value.trim_end_matches('\0').to_string()
+}
+
+pub fn run(loader: &mut SupportedLoader, db_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let metadata_program = mpl_metadata::id();
+
+    let schema = SchemaMapping::default();
+    let metadata_schema = schema.table("metadata", METADATA_COLUMNS)?;
+    let edition_schema = schema.table("editions", EDITION_COLUMNS)?;
+
+    info!("Opening DuckDB database: {}", db_path);
+    let conn = Connection::open(db_path)?;
+    conn.execute_batch(&format!(
+        "DROP TABLE IF EXISTS metadata;
+         DROP TABLE IF EXISTS editions;
+         {}
+         {}",
+        metadata_schema.create_table_sql(),
+        edition_schema.create_table_sql()
+    ))?;
+    let mut metadata_appender = BatchAppender::new(&conn, &metadata_schema)?;
+    let mut edition_appender = BatchAppender::new(&conn, &edition_schema)?;
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("metadata"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut metadata_accounts: u64 = 0;
+    let mut master_editions: u64 = 0;
+    let mut print_editions: u64 = 0;
+    let mut skipped: u64 = 0;
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(metadata_accounts);
+            }
+
+            if account.account_meta.owner != metadata_program {
+                continue;
+            }
+
+            let pubkey = Value::Text(account.meta.pubkey.to_string());
+            if let Some(parsed) = mpl_metadata::parse_metadata(account.data) {
+                let metadata = parsed.metadata;
+                let (token_standard, collection) = match parsed.ext {
+                    Some(ext) => (ext.token_standard, ext.collection),
+                    None => (None, None),
+                };
+                metadata_appender.append_row(metadata_schema.select(vec![
+                    pubkey,
+                    Value::Text(metadata.mint.to_string()),
+                    Value::Text(metadata.update_authority.to_string()),
+                    Value::Text(trim_padding(&metadata.data.name)),
+                    Value::Text(trim_padding(&metadata.data.symbol)),
+                    Value::Text(trim_padding(&metadata.data.uri)),
+                    Value::UBigInt(metadata.data.seller_fee_basis_points as u64),
+                    Value::Boolean(metadata.primary_sale_happened),
+                    Value::Boolean(metadata.is_mutable),
+                    token_standard.map(Value::UTinyInt).unwrap_or(Value::Null),
+                    collection
+                        .as_ref()
+                        .map(|collection| Value::Text(collection.key.to_string()))
+                        .unwrap_or(Value::Null),
+                    collection
+                        .map(|collection| Value::Boolean(collection.verified))
+                        .unwrap_or(Value::Null),
+                    Value::UBigInt(account.data.len() as u64),
+                ]))?;
+                metadata_accounts += 1;
+                continue;
+            }
+
+            let row = match mpl_metadata::parse_edition(account.data) {
+                Some(ParsedEdition::Master(master)) => {
+                    master_editions += 1;
+                    vec![
+                        pubkey,
+                        Value::Text("master".to_string()),
+                        Value::UBigInt(master.supply),
+                        master.max_supply.map(Value::UBigInt).unwrap_or(Value::Null),
+                        Value::Null,
+                        Value::Null,
+                    ]
+                }
+                Some(ParsedEdition::Print(print)) => {
+                    print_editions += 1;
+                    vec![
+                        pubkey,
+                        Value::Text("print".to_string()),
+                        Value::Null,
+                        Value::Null,
+                        Value::Text(print.parent.to_string()),
+                        Value::UBigInt(print.edition),
+                    ]
+                }
+                // Edition markers, use and collection authority records
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            edition_appender.append_row(edition_schema.select(row))?;
+        }
+    }
+
+    metadata_appender.flush()?;
+    edition_appender.flush()?;
+    spinner.set_position(metadata_accounts);
+    spinner.finish();
+
+    info!(
+        "Dumped {} metadata accounts, {} master editions and {} print editions from {} total accounts",
+        format::count(metadata_accounts),
+        format::count(master_editions),
+        format::count(print_editions),
+        format::count(total_accounts)
+    );
+    info!(
+        "Skipped {} other Metaplex metadata program accounts",
+        format::count(skipped)
+    );
+    Ok(())
+}
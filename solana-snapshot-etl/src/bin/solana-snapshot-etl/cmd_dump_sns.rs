@@ -0,0 +1,152 @@
+use crate::batch_appender::BatchAppender;
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::schema::{Column, SchemaMapping};
+use crate::sns::{self, NAME_SERVICE_PROGRAM_ID, REVERSE_LOOKUP_CLASS, SOL_TLD};
+use duckdb::types::Value;
+use duckdb::Connection;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::str::FromStr;
+
+const DOMAIN_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "name",
+        sql_type: "VARCHAR",
+    },
+    Column {
+        name: "owner",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "class",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "data_len",
+        sql_type: "UBIGINT NOT NULL",
+    },
+];
+
+enum NameRecord {
+    /// Owner, class and data length of a `.sol` domain
+    Domain {
+        owner: Pubkey,
+        class: Pubkey,
+        data_len: u64,
+    },
+    /// Name stored in a reverse lookup record
+    ReverseLookup(String),
+}
+
+/// Writes every `.sol` domain with its owner to the `sol_domains` table, named from the
+/// domain's reverse lookup record where one exists.
+pub fn run(loader: &mut SupportedLoader, db_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let name_program = Pubkey::from_str(NAME_SERVICE_PROGRAM_ID).unwrap();
+    let sol_tld = Pubkey::from_str(SOL_TLD).unwrap();
+    let reverse_lookup_class = Pubkey::from_str(REVERSE_LOOKUP_CLASS).unwrap();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    // Owners change with every transfer, so only the latest version is kept
+    let mut total_accounts: u64 = 0;
+    let mut latest: LatestAccounts<NameRecord> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            latest.insert(slot, &account, || {
+                if account.account_meta.owner != name_program {
+                    return None;
+                }
+                let header = sns::parse_header(account.data)?;
+                // Subdomains and records of other top-level domains aren't dumped
+                if header.parent_name == sol_tld {
+                    Some(NameRecord::Domain {
+                        owner: header.owner,
+                        class: header.class,
+                        data_len: account.data.len() as u64,
+                    })
+                } else if header.class == reverse_lookup_class {
+                    sns::parse_reverse_lookup(account.data).map(NameRecord::ReverseLookup)
+                } else {
+                    None
+                }
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let domain_schema = SchemaMapping::default().table("sol_domains", DOMAIN_COLUMNS)?;
+    info!("Opening DuckDB database: {}", db_path);
+    let conn = Connection::open(db_path)?;
+    conn.execute_batch(&format!(
+        "DROP TABLE IF EXISTS sol_domains;
+         {}",
+        domain_schema.create_table_sql()
+    ))?;
+    let mut appender = BatchAppender::new(&conn, &domain_schema)?;
+
+    let mut domains: u64 = 0;
+    let mut named: u64 = 0;
+    for (pubkey, record) in &latest {
+        let NameRecord::Domain {
+            owner,
+            class,
+            data_len,
+        } = record
+        else {
+            continue;
+        };
+        // Names are only stored in the reverse lookup record derived from the domain
+        let reverse_key = sns::reverse_lookup_key(pubkey, &name_program, &reverse_lookup_class);
+        let name = match latest.get(&reverse_key) {
+            Some(NameRecord::ReverseLookup(name)) => {
+                named += 1;
+                Value::Text(name.clone())
+            }
+            _ => Value::Null,
+        };
+        appender.append_row(domain_schema.select(vec![
+            Value::Text(pubkey.to_string()),
+            name,
+            Value::Text(owner.to_string()),
+            Value::Text(class.to_string()),
+            Value::UBigInt(*data_len),
+        ]))?;
+        domains += 1;
+    }
+    appender.flush()?;
+
+    info!(
+        "Dumped {} .sol domains, {} of them named, to {}",
+        format::count(domains),
+        format::count(named),
+        db_path
+    );
+    Ok(())
+}
@@ -0,0 +1,238 @@
+use crate::batch_appender::BatchAppender;
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::schema::{Column, SchemaMapping};
+use crate::stake::{parse_stake, StakeRecord};
+use duckdb::types::Value;
+use duckdb::Connection;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::stake;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+const STAKE_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "lamports",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "rent_exempt_reserve",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "staker",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "withdrawer",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "voter",
+        sql_type: "VARCHAR",
+    },
+    Column {
+        name: "stake",
+        sql_type: "UBIGINT",
+    },
+    Column {
+        name: "activation_epoch",
+        sql_type: "UBIGINT",
+    },
+    // NULL while the stake isn't deactivating
+    Column {
+        name: "deactivation_epoch",
+        sql_type: "UBIGINT",
+    },
+];
+
+const DELEGATION_COLUMNS: &[Column] = &[
+    Column {
+        name: "voter",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "stake_accounts",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "delegated_stake",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "deactivating_accounts",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "deactivating_stake",
+        sql_type: "UBIGINT NOT NULL",
+    },
+];
+
+/// Stake delegated to one vote account.
+#[derive(Default)]
+struct ValidatorDelegations {
+    stake_accounts: u64,
+    delegated_stake: u64,
+    deactivating_accounts: u64,
+    deactivating_stake: u64,
+}
+
+/// Writes every stake account to the `stake_accounts` table and the stake delegated to
+/// every vote account to `stake_delegations`, and prints the validators with the most stake.
+pub fn run(
+    loader: &mut SupportedLoader,
+    db_path: &str,
+    top: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut stakes: LatestAccounts<StakeRecord> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            stakes.insert(slot, &account, || {
+                (account.account_meta.owner == stake::program::id())
+                    .then(|| parse_stake(&account))
+                    .flatten()
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let schema = SchemaMapping::default();
+    let stake_schema = schema.table("stake_accounts", STAKE_COLUMNS)?;
+    let delegation_schema = schema.table("stake_delegations", DELEGATION_COLUMNS)?;
+    info!("Opening DuckDB database: {}", db_path);
+    let conn = Connection::open(db_path)?;
+    conn.execute_batch(&format!(
+        "DROP TABLE IF EXISTS stake_accounts;
+         DROP TABLE IF EXISTS stake_delegations;
+         {}
+         {}",
+        stake_schema.create_table_sql(),
+        delegation_schema.create_table_sql()
+    ))?;
+    let mut stake_appender = BatchAppender::new(&conn, &stake_schema)?;
+
+    let mut stake_accounts: u64 = 0;
+    let mut by_voter: HashMap<Pubkey, ValidatorDelegations> = HashMap::new();
+    for stake in stakes.into_values() {
+        let delegation = stake.delegation;
+        let deactivation_epoch = delegation
+            .map(|delegation| delegation.deactivation_epoch)
+            .filter(|epoch| *epoch != u64::MAX);
+        stake_appender.append_row(stake_schema.select(vec![
+            Value::Text(stake.pubkey.to_string()),
+            Value::UBigInt(stake.lamports),
+            Value::UBigInt(stake.rent_exempt_reserve),
+            Value::Text(stake.staker.to_string()),
+            Value::Text(stake.withdrawer.to_string()),
+            delegation
+                .map(|delegation| Value::Text(delegation.voter_pubkey.to_string()))
+                .unwrap_or(Value::Null),
+            delegation
+                .map(|delegation| Value::UBigInt(delegation.stake))
+                .unwrap_or(Value::Null),
+            delegation
+                .map(|delegation| Value::UBigInt(delegation.activation_epoch))
+                .unwrap_or(Value::Null),
+            deactivation_epoch.map(Value::UBigInt).unwrap_or(Value::Null),
+        ]))?;
+        stake_accounts += 1;
+
+        if let Some(delegation) = delegation {
+            let validator = by_voter.entry(delegation.voter_pubkey).or_default();
+            validator.stake_accounts += 1;
+            validator.delegated_stake += delegation.stake;
+            if deactivation_epoch.is_some() {
+                validator.deactivating_accounts += 1;
+                validator.deactivating_stake += delegation.stake;
+            }
+        }
+    }
+    stake_appender.flush()?;
+
+    let mut validators: Vec<(Pubkey, ValidatorDelegations)> = by_voter.into_iter().collect();
+    validators.sort_by_key(|(voter, validator)| (Reverse(validator.delegated_stake), *voter));
+    let mut delegation_appender = BatchAppender::new(&conn, &delegation_schema)?;
+    for (voter, validator) in &validators {
+        delegation_appender.append_row(delegation_schema.select(vec![
+            Value::Text(voter.to_string()),
+            Value::UBigInt(validator.stake_accounts),
+            Value::UBigInt(validator.delegated_stake),
+            Value::UBigInt(validator.deactivating_accounts),
+            Value::UBigInt(validator.deactivating_stake),
+        ]))?;
+    }
+    delegation_appender.flush()?;
+
+    info!(
+        "Dumped {} stake accounts delegated to {} vote accounts to {}",
+        format::count(stake_accounts),
+        format::count(validators.len() as u64),
+        db_path
+    );
+
+    let delegated: u64 = validators
+        .iter()
+        .map(|(_, validator)| validator.delegated_stake)
+        .sum();
+    println!("\n--- Stake Delegations ---\n");
+    println!(
+        "Stake accounts:          {:>24}",
+        format::count(stake_accounts)
+    );
+    println!(
+        "Vote accounts:           {:>24}",
+        format::count(validators.len() as u64)
+    );
+    println!(
+        "Delegated stake:         {:>24}",
+        format::lamports(delegated)
+    );
+
+    println!("\nTop {} vote accounts by delegated stake:\n", top);
+    println!(
+        "{:<44} {:>12} {:>24} {:>8}",
+        "Vote Account", "Accounts", "Delegated Stake", "%"
+    );
+    println!("{}", "-".repeat(91));
+    for (voter, validator) in validators.iter().take(top) {
+        println!(
+            "{:<44} {:>12} {:>24} {:>7.2}%",
+            voter.to_string(),
+            format::count(validator.stake_accounts),
+            format::lamports(validator.delegated_stake),
+            validator.delegated_stake as f64 / delegated.max(1) as f64 * 100.0
+        );
+    }
+    Ok(())
+}
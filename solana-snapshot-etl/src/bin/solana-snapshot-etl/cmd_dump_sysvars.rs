@@ -0,0 +1,141 @@
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use solana_sdk::clock::Clock;
+use solana_sdk::epoch_schedule::EpochSchedule;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::slot_hashes::SlotHashes;
+use solana_sdk::stake_history::StakeHistory;
+use solana_sdk::sysvar;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+
+/// Decodes the latest version of a sysvar account, warning when it is missing or
+/// doesn't decode.
+fn decode<T: DeserializeOwned>(
+    sysvars: &LatestAccounts<Vec<u8>>,
+    id: &Pubkey,
+    name: &str,
+) -> Option<T> {
+    let Some(data) = sysvars.get(id) else {
+        warn!("{} sysvar not found", name);
+        return None;
+    };
+    let decoded = bincode::deserialize(data).ok();
+    if decoded.is_none() {
+        warn!("{} sysvar didn't decode", name);
+    }
+    decoded
+}
+
+/// Prints the Clock, Rent, EpochSchedule, SlotHashes and StakeHistory sysvars stored in
+/// the snapshot as a JSON object, with `null` for any that are missing.
+pub fn run(loader: &mut SupportedLoader) -> Result<(), Box<dyn std::error::Error>> {
+    let ids = [
+        sysvar::clock::id(),
+        sysvar::rent::id(),
+        sysvar::epoch_schedule::id(),
+        sysvar::slot_hashes::id(),
+        sysvar::stake_history::id(),
+    ];
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    // Sysvars are rewritten every slot, so only the latest version is kept
+    let mut total_accounts: u64 = 0;
+    let mut sysvars: LatestAccounts<Vec<u8>> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            sysvars.insert(slot, &account, || {
+                (account.account_meta.owner == sysvar::id() && ids.contains(&account.meta.pubkey))
+                    .then(|| account.data.to_vec())
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let clock = decode::<Clock>(&sysvars, &sysvar::clock::id(), "Clock").map(|clock| {
+        json!({
+            "slot": clock.slot,
+            "epoch_start_timestamp": clock.epoch_start_timestamp,
+            "epoch": clock.epoch,
+            "leader_schedule_epoch": clock.leader_schedule_epoch,
+            "unix_timestamp": clock.unix_timestamp,
+        })
+    });
+    let rent = decode::<Rent>(&sysvars, &sysvar::rent::id(), "Rent").map(|rent| {
+        json!({
+            "lamports_per_byte_year": rent.lamports_per_byte_year,
+            "exemption_threshold": rent.exemption_threshold,
+            "burn_percent": rent.burn_percent,
+        })
+    });
+    let epoch_schedule =
+        decode::<EpochSchedule>(&sysvars, &sysvar::epoch_schedule::id(), "EpochSchedule").map(
+            |schedule| {
+                json!({
+                    "slots_per_epoch": schedule.slots_per_epoch,
+                    "leader_schedule_slot_offset": schedule.leader_schedule_slot_offset,
+                    "warmup": schedule.warmup,
+                    "first_normal_epoch": schedule.first_normal_epoch,
+                    "first_normal_slot": schedule.first_normal_slot,
+                })
+            },
+        );
+    let slot_hashes =
+        decode::<SlotHashes>(&sysvars, &sysvar::slot_hashes::id(), "SlotHashes").map(|hashes| {
+            hashes
+                .iter()
+                .map(|(slot, hash)| json!({ "slot": slot, "hash": hash.to_string() }))
+                .collect::<Vec<_>>()
+        });
+    let stake_history =
+        decode::<StakeHistory>(&sysvars, &sysvar::stake_history::id(), "StakeHistory").map(
+            |history| {
+                history
+                    .iter()
+                    .map(|(epoch, entry)| {
+                        json!({
+                            "epoch": epoch,
+                            "effective": entry.effective,
+                            "activating": entry.activating,
+                            "deactivating": entry.deactivating,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            },
+        );
+
+    let report = json!({
+        "snapshot_slot": loader.slot(),
+        "clock": clock,
+        "rent": rent,
+        "epoch_schedule": epoch_schedule,
+        "slot_hashes": slot_hashes,
+        "stake_history": stake_history,
+    });
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
@@ -1,59 +1,329 @@
+use crate::batch_appender::BatchAppender;
+use crate::format;
 use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::pseudonym::PubkeyEncoder;
+use crate::schema::{quote_identifier, Column, SchemaMapping, TableSchema};
 use crate::token::{
-    ASSOCIATED_TOKEN_PROGRAM_ID, MINT_ACCOUNT_LEN, TOKEN_ACCOUNT_LEN, TOKEN_PROGRAM_ID,
+    ACCOUNT_STATE_FROZEN, ASSOCIATED_TOKEN_PROGRAM_ID, MINT_ACCOUNT_LEN, MULTISIG_ACCOUNT_LEN,
+    TOKEN_ACCOUNT_LEN, TOKEN_PROGRAM_ID,
 };
+use clap::ValueEnum;
+use duckdb::types::Value;
 use duckdb::{params, Connection};
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use log::info;
+use indicatif::{ProgressBar, ProgressStyle};
+use itertools::Itertools;
+use log::{info, warn};
 use solana_sdk::pubkey::Pubkey;
 use solana_snapshot_etl::append_vec_iter;
 use solana_snapshot_etl::SnapshotExtractor;
-use std::rc::Rc;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-pub fn run(loader: &mut SupportedLoader, db_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+const TOKEN_ACCOUNT_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "owner",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "mint",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "amount",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "is_pda",
+        sql_type: "BOOLEAN NOT NULL",
+    },
+    Column {
+        name: "delegate",
+        sql_type: "VARCHAR",
+    },
+    Column {
+        name: "delegated_amount",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "state",
+        sql_type: "UTINYINT NOT NULL",
+    },
+    Column {
+        name: "frozen",
+        sql_type: "BOOLEAN NOT NULL",
+    },
+    Column {
+        name: "is_native",
+        sql_type: "UBIGINT",
+    },
+    Column {
+        name: "close_authority",
+        sql_type: "VARCHAR",
+    },
+    Column {
+        name: "lamports",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "rent_epoch",
+        sql_type: "UBIGINT NOT NULL",
+    },
+];
+
+const MINT_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "mint_authority",
+        sql_type: "VARCHAR",
+    },
+    Column {
+        name: "supply",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "decimals",
+        sql_type: "UTINYINT NOT NULL",
+    },
+    Column {
+        name: "is_initialized",
+        sql_type: "BOOLEAN NOT NULL",
+    },
+    Column {
+        name: "freeze_authority",
+        sql_type: "VARCHAR",
+    },
+];
+
+const MULTISIG_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "m",
+        sql_type: "UTINYINT NOT NULL",
+    },
+    Column {
+        name: "n",
+        sql_type: "UTINYINT NOT NULL",
+    },
+    Column {
+        name: "is_initialized",
+        sql_type: "BOOLEAN NOT NULL",
+    },
+    Column {
+        name: "signers",
+        sql_type: "VARCHAR NOT NULL",
+    },
+];
+
+/// Column added to every table by `--append`, telling the snapshots of the rows apart.
+const SNAPSHOT_SLOT_COLUMN: Column = Column {
+    name: "snapshot_slot",
+    sql_type: "UBIGINT NOT NULL",
+};
+
+/// token_accounts columns indexed by `--create-indexes`.
+const INDEXED_TOKEN_COLUMNS: [&str; 3] = ["mint", "owner", "pubkey"];
+
+/// Name of the database staging the tables of a Parquet export, in the output directory.
+const STAGING_DB: &str = "dump-tokens.staging.duckdb";
+
+/// Output format of `dump-tokens`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DumpTokensFormat {
+    /// A DuckDB database file
+    Duckdb,
+    /// A directory of Parquet files per table
+    Parquet,
+}
+
+/// Where `dump-tokens` writes its tables.
+#[derive(Clone, Copy)]
+pub enum DumpTokensOutput<'a> {
+    /// DuckDB database file
+    Database(&'a str),
+    /// Output directory holding a directory of Parquet files per table
+    Parquet(&'a Path),
+}
+
+/// Where and how `dump-tokens` writes its tables.
+pub struct DumpTokensOptions<'a> {
+    pub output: DumpTokensOutput<'a>,
+    /// JSON5 file selecting and renaming output columns per table
+    pub schema_path: Option<&'a str>,
+    /// Key for replacing pubkeys with keyed-hash tokens
+    pub hash_key: Option<&'a [u8]>,
+    /// Rows written before the open transaction is committed
+    pub flush_every: u64,
+    /// Rows buffered per table before they are handed to DuckDB as one batch
+    pub batch_rows: usize,
+    /// Index the token_accounts table once it's loaded
+    pub create_indexes: bool,
+    /// Append rows tagged with the snapshot slot instead of replacing the tables
+    pub append: bool,
+    /// Token accounts holding less than this amount, in base units, are skipped
+    pub min_amount: u64,
+}
+
+/// A table's columns, followed by `snapshot_slot` when appending.
+fn table_columns(columns: &[Column], append: bool) -> Vec<Column> {
+    let mut columns = columns.to_vec();
+    if append {
+        columns.push(SNAPSHOT_SLOT_COLUMN);
+    }
+    columns
+}
+
+/// Checks that a table kept by `--append` has the columns that are about to be appended,
+/// e.g. that it wasn't created by an older version or with another column mapping.
+fn check_existing_columns(
+    conn: &Connection,
+    table: &TableSchema,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT column_name FROM information_schema.columns
+         WHERE table_schema = current_schema() AND table_name = ?
+         ORDER BY ordinal_position",
+    )?;
+    let existing = stmt
+        .query_map(params![table.name], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let expected: Vec<&str> = table.columns().map(|(name, _)| name).collect();
+    if existing != expected {
+        return Err(format!(
+            "Can't append to table {}: it has the columns ({}), not ({})",
+            table.name,
+            existing.join(", "),
+            expected.join(", ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    options: &DumpTokensOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let DumpTokensOptions {
+        output,
+        schema_path,
+        hash_key,
+        flush_every,
+        batch_rows,
+        create_indexes,
+        append,
+        min_amount,
+    } = *options;
+    if append && matches!(output, DumpTokensOutput::Parquet(_)) {
+        return Err("--append only applies to --format duckdb".into());
+    }
     let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
     let ata_program = Pubkey::from_str(ASSOCIATED_TOKEN_PROGRAM_ID).unwrap();
 
-    info!("Opening DuckDB database: {}", db_path);
-    let conn = Connection::open(db_path)?;
-
-    // Create tables
-    conn.execute_batch(
-        "DROP TABLE IF EXISTS token_accounts;
-         DROP TABLE IF EXISTS mints;
-         CREATE TABLE token_accounts (
-             pubkey VARCHAR NOT NULL,
-             owner VARCHAR NOT NULL,
-             mint VARCHAR NOT NULL,
-             amount UBIGINT NOT NULL,
-             is_pda BOOLEAN NOT NULL
-         );
-         CREATE TABLE mints (
-             pubkey VARCHAR NOT NULL,
-             mint_authority VARCHAR,
-             supply UBIGINT NOT NULL,
-             decimals UTINYINT NOT NULL,
-             is_initialized BOOLEAN NOT NULL,
-             freeze_authority VARCHAR
-         );",
+    let schema = match schema_path {
+        Some(path) => {
+            info!("Loading column mapping: {}", path);
+            SchemaMapping::load(path, &["token_accounts", "mints", "multisigs"])?
+        }
+        None => SchemaMapping::default(),
+    };
+    let encoder = PubkeyEncoder::new(hash_key);
+    if hash_key.is_some() {
+        info!("Replacing pubkeys with keyed-hash tokens");
+    }
+    let token_schema = schema.table(
+        "token_accounts",
+        &table_columns(TOKEN_ACCOUNT_COLUMNS, append),
     )?;
+    let mint_schema = schema.table("mints", &table_columns(MINT_COLUMNS, append))?;
+    let multisig_schema = schema.table("multisigs", &table_columns(MULTISIG_COLUMNS, append))?;
+    let slot = loader.slot();
+    // Appended rows end with the snapshot slot
+    let row = |mut values: Vec<Value>| {
+        if append {
+            values.push(Value::UBigInt(slot));
+        }
+        values
+    };
+
+    // Parquet files are exported from a staging database once it's loaded
+    let db_path = match output {
+        DumpTokensOutput::Database(path) => PathBuf::from(path),
+        DumpTokensOutput::Parquet(dir) => {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+            dir.join(STAGING_DB)
+        }
+    };
+    info!("Opening DuckDB database: {}", db_path.display());
+    let conn = Connection::open(&db_path)?;
 
-    let mut token_appender = conn.appender("token_accounts")?;
-    let mut mint_appender = conn.appender("mints")?;
+    // Rows are committed in explicit transactions of `flush_every` rows, rather than one
+    // per appended batch. The first one also drops the tables or deletes the rows of a
+    // rerun snapshot, which are kept if loading fails before anything is committed
+    conn.execute_batch("BEGIN TRANSACTION")?;
+    let mut uncommitted_rows: u64 = 0;
+
+    if append {
+        for table in [&token_schema, &mint_schema, &multisig_schema] {
+            let slot_column = table
+                .output_name(SNAPSHOT_SLOT_COLUMN.name)
+                .ok_or_else(|| {
+                    format!("--append needs the snapshot_slot column of {}", table.name)
+                })?;
+            conn.execute_batch(&table.create_table_if_not_exists_sql())?;
+            check_existing_columns(&conn, table)?;
+            // Rerunning a snapshot replaces its rows
+            conn.execute(
+                &format!(
+                    "DELETE FROM {} WHERE {} = ?",
+                    table.quoted_name(),
+                    quote_identifier(slot_column)
+                ),
+                params![slot],
+            )?;
+        }
+    } else {
+        // Create tables
+        conn.execute_batch(&format!(
+            "DROP TABLE IF EXISTS token_accounts;
+             DROP TABLE IF EXISTS mints;
+             DROP TABLE IF EXISTS multisigs;
+             {}
+             {}
+             {}",
+            token_schema.create_table_sql(),
+            mint_schema.create_table_sql(),
+            multisig_schema.create_table_sql()
+        ))?;
+    }
+
+    let mut token_appender = BatchAppender::with_batch_rows(&conn, &token_schema, batch_rows)?;
+    let mut mint_appender = BatchAppender::with_batch_rows(&conn, &mint_schema, batch_rows)?;
+    let mut multisig_appender =
+        BatchAppender::with_batch_rows(&conn, &multisig_schema, batch_rows)?;
 
     let spinner_style = ProgressStyle::with_template(
         "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
     )
     .unwrap();
 
-    let multi = MultiProgress::new();
-    let token_spinner = multi.add(
+    let token_spinner = progress::add(
         ProgressBar::new_spinner()
             .with_style(spinner_style.clone())
             .with_prefix("tokens"),
     );
-    let mint_spinner = multi.add(
+    let mint_spinner = progress::add(
         ProgressBar::new_spinner()
             .with_style(spinner_style)
             .with_prefix("mints"),
@@ -62,11 +332,12 @@ pub fn run(loader: &mut SupportedLoader, db_path: &str) -> Result<(), Box<dyn st
     let mut total_accounts: u64 = 0;
     let mut token_accounts: u64 = 0;
     let mut mint_accounts: u64 = 0;
+    let mut multisig_accounts: u64 = 0;
+    let mut dust_accounts: u64 = 0;
 
     for append_vec in loader.iter() {
         let append_vec = append_vec?;
-        for account in append_vec_iter(Rc::new(append_vec)) {
-            let account = account.access().unwrap();
+        for account in append_vec_iter(&append_vec) {
             total_accounts += 1;
 
             if total_accounts % 10000 == 0 {
@@ -81,39 +352,76 @@ pub fn run(loader: &mut SupportedLoader, db_path: &str) -> Result<(), Box<dyn st
 
             if account.data.len() == TOKEN_ACCOUNT_LEN {
                 // Parse token account
+                // Layout (165 bytes):
+                // - mint: Pubkey (32)
+                // - owner: Pubkey (32)
+                // - amount: u64 (8)
+                // - delegate: COption<Pubkey> (4 + 32 = 36)
+                // - state: u8 (1)
+                // - is_native: COption<u64> (4 + 8 = 12), the rent-exempt reserve
+                // - delegated_amount: u64 (8)
+                // - close_authority: COption<Pubkey> (4 + 32 = 36)
                 let mint = Pubkey::try_from(&account.data[0..32]).unwrap();
                 let token_owner = Pubkey::try_from(&account.data[32..64]).unwrap();
                 let amount = u64::from_le_bytes(account.data[64..72].try_into().unwrap());
+                if amount < min_amount {
+                    dust_accounts += 1;
+                    continue;
+                }
+
+                let delegate_tag = u32::from_le_bytes(account.data[72..76].try_into().unwrap());
+                let delegate = if delegate_tag == 1 {
+                    Some(encoder.encode(&Pubkey::try_from(&account.data[76..108]).unwrap()))
+                } else {
+                    None
+                };
+
+                let state = account.data[108];
+
+                let is_native_tag = u32::from_le_bytes(account.data[109..113].try_into().unwrap());
+                let is_native = if is_native_tag == 1 {
+                    Some(u64::from_le_bytes(
+                        account.data[113..121].try_into().unwrap(),
+                    ))
+                } else {
+                    None
+                };
+
+                let delegated_amount =
+                    u64::from_le_bytes(account.data[121..129].try_into().unwrap());
+
+                let close_authority_tag =
+                    u32::from_le_bytes(account.data[129..133].try_into().unwrap());
+                let close_authority = if close_authority_tag == 1 {
+                    Some(encoder.encode(&Pubkey::try_from(&account.data[133..165]).unwrap()))
+                } else {
+                    None
+                };
 
                 // Check if this is the canonical ATA PDA
                 let (expected_ata, _bump) = Pubkey::find_program_address(
-                    &[
-                        token_owner.as_ref(),
-                        token_program.as_ref(),
-                        mint.as_ref(),
-                    ],
+                    &[token_owner.as_ref(), token_program.as_ref(), mint.as_ref()],
                     &ata_program,
                 );
                 let is_pda = account.meta.pubkey == expected_ata;
 
-                token_appender.append_row(params![
-                    account.meta.pubkey.to_string(),
-                    token_owner.to_string(),
-                    mint.to_string(),
-                    amount,
-                    is_pda,
-                ])?;
+                token_appender.append_row(token_schema.select(row(vec![
+                    Value::Text(encoder.encode(&account.meta.pubkey)),
+                    Value::Text(encoder.encode(&token_owner)),
+                    Value::Text(encoder.encode(&mint)),
+                    Value::UBigInt(amount),
+                    Value::Boolean(is_pda),
+                    delegate.map(Value::Text).unwrap_or(Value::Null),
+                    Value::UBigInt(delegated_amount),
+                    Value::UTinyInt(state),
+                    Value::Boolean(state == ACCOUNT_STATE_FROZEN),
+                    is_native.map(Value::UBigInt).unwrap_or(Value::Null),
+                    close_authority.map(Value::Text).unwrap_or(Value::Null),
+                    Value::UBigInt(account.account_meta.lamports),
+                    Value::UBigInt(account.account_meta.rent_epoch),
+                ])))?;
 
                 token_accounts += 1;
-
-                // Flush every million records
-                if token_accounts % 1_000_000 == 0 {
-                    token_appender.flush()?;
-                    info!(
-                        "Flushed {} token accounts ({} total scanned)",
-                        token_accounts, total_accounts
-                    );
-                }
             } else if account.data.len() == MINT_ACCOUNT_LEN {
                 // Parse mint account
                 // Layout (82 bytes):
@@ -125,7 +433,7 @@ pub fn run(loader: &mut SupportedLoader, db_path: &str) -> Result<(), Box<dyn st
 
                 let mint_authority_tag = u32::from_le_bytes(account.data[0..4].try_into().unwrap());
                 let mint_authority = if mint_authority_tag == 1 {
-                    Some(Pubkey::try_from(&account.data[4..36]).unwrap().to_string())
+                    Some(encoder.encode(&Pubkey::try_from(&account.data[4..36]).unwrap()))
                 } else {
                     None
                 };
@@ -137,43 +445,147 @@ pub fn run(loader: &mut SupportedLoader, db_path: &str) -> Result<(), Box<dyn st
                 let freeze_authority_tag =
                     u32::from_le_bytes(account.data[46..50].try_into().unwrap());
                 let freeze_authority = if freeze_authority_tag == 1 {
-                    Some(Pubkey::try_from(&account.data[50..82]).unwrap().to_string())
+                    Some(encoder.encode(&Pubkey::try_from(&account.data[50..82]).unwrap()))
                 } else {
                     None
                 };
 
-                mint_appender.append_row(params![
-                    account.meta.pubkey.to_string(),
-                    mint_authority,
-                    supply,
-                    decimals,
-                    is_initialized,
-                    freeze_authority,
-                ])?;
+                mint_appender.append_row(mint_schema.select(row(vec![
+                    Value::Text(encoder.encode(&account.meta.pubkey)),
+                    mint_authority.map(Value::Text).unwrap_or(Value::Null),
+                    Value::UBigInt(supply),
+                    Value::UTinyInt(decimals),
+                    Value::Boolean(is_initialized),
+                    freeze_authority.map(Value::Text).unwrap_or(Value::Null),
+                ])))?;
 
                 mint_accounts += 1;
+            } else if account.data.len() == MULTISIG_ACCOUNT_LEN {
+                // Parse multisig account
+                // Layout (355 bytes):
+                // - m: u8 (1), signatures required
+                // - n: u8 (1), valid signers
+                // - is_initialized: bool (1)
+                // - signers: [Pubkey; 11] (11 * 32 = 352), only the first n are set
 
-                // Flush every million records
-                if mint_accounts % 1_000_000 == 0 {
-                    mint_appender.flush()?;
-                    info!(
-                        "Flushed {} mint accounts ({} total scanned)",
-                        mint_accounts, total_accounts
-                    );
-                }
+                let m = account.data[0];
+                let n = account.data[1];
+                let is_initialized = account.data[2] != 0;
+                let signers = account.data[3..]
+                    .chunks_exact(32)
+                    .take(n as usize)
+                    .map(|signer| encoder.encode(&Pubkey::try_from(signer).unwrap()))
+                    .join(",");
+
+                multisig_appender.append_row(multisig_schema.select(row(vec![
+                    Value::Text(encoder.encode(&account.meta.pubkey)),
+                    Value::UTinyInt(m),
+                    Value::UTinyInt(n),
+                    Value::Boolean(is_initialized),
+                    Value::Text(signers),
+                ])))?;
+
+                multisig_accounts += 1;
+            } else {
+                continue;
+            }
+
+            uncommitted_rows += 1;
+            if uncommitted_rows >= flush_every {
+                token_appender.flush()?;
+                mint_appender.flush()?;
+                multisig_appender.flush()?;
+                conn.execute_batch("COMMIT; BEGIN TRANSACTION")?;
+                uncommitted_rows = 0;
+                info!(
+                    "Committed {} token accounts and {} mints ({} total scanned)",
+                    format::count(token_accounts),
+                    format::count(mint_accounts),
+                    format::count(total_accounts)
+                );
             }
         }
     }
 
     token_appender.flush()?;
     mint_appender.flush()?;
+    multisig_appender.flush()?;
+    conn.execute_batch("COMMIT")?;
+    token_spinner.set_position(token_accounts);
     token_spinner.finish();
+    mint_spinner.set_position(mint_accounts);
     mint_spinner.finish();
 
+    // Building indexes after the bulk load is much faster than maintaining them during it
+    if create_indexes && matches!(output, DumpTokensOutput::Parquet(_)) {
+        warn!("Indexes don't apply to Parquet output, not creating them");
+    } else if create_indexes {
+        for source in INDEXED_TOKEN_COLUMNS {
+            let Some(column) = token_schema.output_name(source) else {
+                warn!("Not indexing {}: left out by the column mapping", source);
+                continue;
+            };
+            info!("Creating index on {}({})", token_schema.name, column);
+            conn.execute_batch(&format!(
+                "CREATE INDEX IF NOT EXISTS {}_{}_idx ON {} ({});",
+                token_schema.name,
+                source,
+                token_schema.quoted_name(),
+                quote_identifier(column)
+            ))?;
+        }
+    }
+
     info!(
-        "Dumped {} token accounts and {} mints from {} total accounts",
-        token_accounts, mint_accounts, total_accounts
+        "Dumped {} token accounts, {} mints and {} multisigs from {} total accounts",
+        format::count(token_accounts),
+        format::count(mint_accounts),
+        format::count(multisig_accounts),
+        format::count(total_accounts)
     );
+    if min_amount > 0 {
+        info!(
+            "Skipped {} token accounts holding less than {}",
+            format::count(dust_accounts),
+            min_amount
+        );
+    }
 
+    if let DumpTokensOutput::Parquet(dir) = output {
+        for table in [&token_schema, &mint_schema, &multisig_schema] {
+            export_parquet(&conn, table, dir)?;
+        }
+        drop((token_appender, mint_appender, multisig_appender));
+        drop(conn);
+        fs::remove_file(&db_path)?;
+        // A leftover write-ahead log would be replayed into a later staging database
+        let wal = db_path.with_extension("duckdb.wal");
+        if wal.exists() {
+            fs::remove_file(wal)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a table to `<dir>/<table>/` as Parquet files, one per export thread, replacing
+/// the files of an earlier export.
+fn export_parquet(
+    conn: &Connection,
+    table: &TableSchema,
+    dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let table_dir = dir.join(&table.name);
+    if table_dir.exists() {
+        fs::remove_dir_all(&table_dir)
+            .map_err(|e| format!("Failed to replace {:?}: {}", table_dir, e))?;
+    }
+    info!("Exporting {} to {}", table.name, table_dir.display());
+    let quoted_dir = table_dir.to_string_lossy().replace('\'', "''");
+    conn.execute_batch(&format!(
+        "COPY {} TO '{}' (FORMAT PARQUET, PER_THREAD_OUTPUT true);",
+        table.quoted_name(),
+        quoted_dir
+    ))?;
     Ok(())
 }
@@ -0,0 +1,176 @@
+use crate::batch_appender::BatchAppender;
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::schema::{Column, SchemaMapping};
+use duckdb::types::Value;
+use duckdb::Connection;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::vote;
+use solana_sdk::vote::state::VoteState;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+
+const VOTE_ACCOUNT_COLUMNS: &[Column] = &[
+    Column {
+        name: "pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "lamports",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "node_pubkey",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "authorized_withdrawer",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "commission",
+        sql_type: "UTINYINT NOT NULL",
+    },
+    Column {
+        name: "last_vote_slot",
+        sql_type: "UBIGINT",
+    },
+    Column {
+        name: "root_slot",
+        sql_type: "UBIGINT",
+    },
+    Column {
+        name: "credits",
+        sql_type: "UBIGINT NOT NULL",
+    },
+];
+
+/// Credits of a vote account at the end of each of its recent epochs, as kept in
+/// `VoteState::epoch_credits`.
+const EPOCH_CREDIT_COLUMNS: &[Column] = &[
+    Column {
+        name: "vote_account",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "epoch",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "credits",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "prev_credits",
+        sql_type: "UBIGINT NOT NULL",
+    },
+];
+
+/// Writes the latest state of every vote account to the `vote_accounts` table and its
+/// epoch credits history to `vote_epoch_credits`.
+pub fn run(loader: &mut SupportedLoader, db_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    // Vote account -> lamports and vote state, if it decoded
+    let mut votes: LatestAccounts<(u64, Option<VoteState>)> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            votes.insert(slot, &account, || {
+                (account.account_meta.owner == vote::program::id()).then(|| {
+                    (
+                        account.account_meta.lamports,
+                        VoteState::deserialize(account.data).ok(),
+                    )
+                })
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let schema = SchemaMapping::default();
+    let vote_schema = schema.table("vote_accounts", VOTE_ACCOUNT_COLUMNS)?;
+    let credit_schema = schema.table("vote_epoch_credits", EPOCH_CREDIT_COLUMNS)?;
+    info!("Opening DuckDB database: {}", db_path);
+    let conn = Connection::open(db_path)?;
+    conn.execute_batch(&format!(
+        "DROP TABLE IF EXISTS vote_accounts;
+         DROP TABLE IF EXISTS vote_epoch_credits;
+         {}
+         {}",
+        vote_schema.create_table_sql(),
+        credit_schema.create_table_sql()
+    ))?;
+    let mut vote_appender = BatchAppender::new(&conn, &vote_schema)?;
+    let mut credit_appender = BatchAppender::new(&conn, &credit_schema)?;
+
+    let mut vote_accounts: u64 = 0;
+    let mut credit_rows: u64 = 0;
+    let mut unparsed: u64 = 0;
+    for (pubkey, (lamports, vote_state)) in &votes {
+        let Some(vote_state) = vote_state else {
+            unparsed += 1;
+            continue;
+        };
+        vote_appender.append_row(vote_schema.select(vec![
+            Value::Text(pubkey.to_string()),
+            Value::UBigInt(*lamports),
+            Value::Text(vote_state.node_pubkey.to_string()),
+            Value::Text(vote_state.authorized_withdrawer.to_string()),
+            Value::UTinyInt(vote_state.commission),
+            vote_state
+                .last_voted_slot()
+                .map(Value::UBigInt)
+                .unwrap_or(Value::Null),
+            vote_state.root_slot.map(Value::UBigInt).unwrap_or(Value::Null),
+            Value::UBigInt(vote_state.credits()),
+        ]))?;
+        vote_accounts += 1;
+
+        for (epoch, credits, prev_credits) in &vote_state.epoch_credits {
+            credit_appender.append_row(credit_schema.select(vec![
+                Value::Text(pubkey.to_string()),
+                Value::UBigInt(*epoch),
+                Value::UBigInt(*credits),
+                Value::UBigInt(*prev_credits),
+            ]))?;
+            credit_rows += 1;
+        }
+    }
+    vote_appender.flush()?;
+    credit_appender.flush()?;
+
+    info!(
+        "Dumped {} vote accounts and {} epoch credit entries to {}",
+        format::count(vote_accounts),
+        format::count(credit_rows),
+        db_path
+    );
+    if unparsed > 0 {
+        info!(
+            "Skipped {} vote accounts that didn't decode",
+            format::count(unparsed)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use crate::format;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::HashMap;
+
+/// Versions of a pubkey seen across append vecs.
+struct Versions {
+    /// (slot, write version) of the latest version
+    latest: (u64, u64),
+    latest_size: u64,
+    total_size: u64,
+    /// Stored versions, including duplicates within one append vec
+    versions: u32,
+    append_vecs: u32,
+    /// (slot, id) of the last append vec holding a version
+    last_append_vec: (u64, u64),
+}
+
+impl Versions {
+    /// Bytes taken up by versions other than the latest.
+    fn wasted_bytes(&self) -> u64 {
+        self.total_size - self.latest_size
+    }
+}
+
+pub fn run(loader: &mut SupportedLoader, top_n: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut total_bytes: u64 = 0;
+    let mut versions: HashMap<Pubkey, Versions> = HashMap::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        let storage = (slot, append_vec.get_id());
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            let version = (slot, account.meta.write_version);
+            let size = account.stored_size as u64;
+            total_bytes += size;
+            versions
+                .entry(account.meta.pubkey)
+                .and_modify(|entry| {
+                    if version > entry.latest {
+                        entry.latest = version;
+                        entry.latest_size = size;
+                    }
+                    entry.total_size += size;
+                    entry.versions += 1;
+                    // The accounts of an append vec are read together
+                    if entry.last_append_vec != storage {
+                        entry.append_vecs += 1;
+                        entry.last_append_vec = storage;
+                    }
+                })
+                .or_insert(Versions {
+                    latest: version,
+                    latest_size: size,
+                    total_size: size,
+                    versions: 1,
+                    append_vecs: 1,
+                    last_append_vec: storage,
+                });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let unique_accounts = versions.len() as u64;
+    let mut duplicates: Vec<_> = versions
+        .into_iter()
+        .filter(|(_, v)| v.versions > 1)
+        .collect();
+    duplicates.sort_by(|a, b| b.1.wasted_bytes().cmp(&a.1.wasted_bytes()));
+    let wasted_bytes: u64 = duplicates.iter().map(|(_, v)| v.wasted_bytes()).sum();
+
+    info!(
+        "Found {} distinct pubkeys among {} stored accounts",
+        format::count(unique_accounts),
+        format::count(total_accounts)
+    );
+
+    println!("\n--- Duplicate Pubkeys ---\n");
+    println!(
+        "Stored accounts:          {:>15}",
+        format::count(total_accounts)
+    );
+    println!(
+        "Distinct pubkeys:         {:>15}",
+        format::count(unique_accounts)
+    );
+    println!(
+        "Pubkeys with >1 version:  {:>15}",
+        format::count(duplicates.len() as u64)
+    );
+    println!(
+        "Pubkeys in >1 append vec: {:>15}",
+        format::count(duplicates.iter().filter(|(_, v)| v.append_vecs > 1).count() as u64)
+    );
+    println!(
+        "Stored bytes:             {:>15}",
+        format::bytes(total_bytes)
+    );
+    println!(
+        "Wasted bytes:             {:>15} ({:.2}%)",
+        format::bytes(wasted_bytes),
+        if total_bytes > 0 {
+            wasted_bytes as f64 / total_bytes as f64 * 100.0
+        } else {
+            0.0
+        }
+    );
+
+    if duplicates.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "\n{:<45} {:>10} {:>12} {:>15} {:>15}",
+        "Pubkey", "Versions", "Append Vecs", "Latest Size", "Wasted"
+    );
+    println!("{}", "-".repeat(101));
+    for (pubkey, v) in duplicates.into_iter().take(top_n) {
+        println!(
+            "{:<45} {:>10} {:>12} {:>15} {:>15}",
+            pubkey.to_string(),
+            format::count(v.versions as u64),
+            format::count(v.append_vecs as u64),
+            format::bytes(v.latest_size),
+            format::bytes(v.wasted_bytes())
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,227 @@
+use crate::elf::{self, SbfVersion, Section};
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::bpf_loader_upgradeable::UpgradeableLoaderState;
+use solana_sdk::hash::{hash, Hash};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable};
+use solana_snapshot_etl::append_vec::StoredAccountMeta;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::HashMap;
+
+#[derive(Default)]
+struct SectionStats {
+    count: u64,
+    total_size: u64,
+}
+
+struct Deployment {
+    /// Account holding the ELF (program account or ProgramData account)
+    pubkey: Pubkey,
+    len: usize,
+}
+
+/// Latest state of an account owned by one of the BPF loaders.
+enum LoaderAccount {
+    /// Upgradeable program, whose ELF is held by its ProgramData account
+    Program {
+        programdata_address: Pubkey,
+    },
+    Binary {
+        sbf_version: SbfVersion,
+        sections: Vec<Section>,
+        elf_hash: Hash,
+        len: usize,
+    },
+    Unparseable,
+}
+
+/// Decodes an account owned by one of the BPF loaders, parsing the ELF it holds.
+fn loader_account(account: &StoredAccountMeta, programdata_offset: usize) -> Option<LoaderAccount> {
+    let owner = account.account_meta.owner;
+    let program_bytes = if owner == bpf_loader::id() || owner == bpf_loader_deprecated::id() {
+        if !account.account_meta.executable {
+            return None;
+        }
+        account.data
+    } else if owner == bpf_loader_upgradeable::id() {
+        match bincode::deserialize::<UpgradeableLoaderState>(account.data).ok()? {
+            UpgradeableLoaderState::Program {
+                programdata_address,
+            } => {
+                return Some(LoaderAccount::Program {
+                    programdata_address,
+                })
+            }
+            UpgradeableLoaderState::ProgramData { .. } => {
+                account.data.get(programdata_offset..).unwrap_or(&[])
+            }
+            _ => return None,
+        }
+    } else {
+        return None;
+    };
+
+    Some(match elf::parse(program_bytes) {
+        Some(elf_info) => LoaderAccount::Binary {
+            sbf_version: elf_info.sbf_version,
+            elf_hash: hash(&program_bytes[..elf_info.len]),
+            sections: elf_info.sections,
+            len: elf_info.len,
+        },
+        None => LoaderAccount::Unparseable,
+    })
+}
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    show_sections: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let programdata_offset = UpgradeableLoaderState::size_of_programdata_metadata();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    // Programs are redeployed in place, so only the latest version of each account is kept
+    let mut total_accounts: u64 = 0;
+    let mut accounts: LatestAccounts<LoaderAccount> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            accounts.insert(slot, &account, || {
+                loader_account(&account, programdata_offset)
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut unparseable: u64 = 0;
+    let mut versions: HashMap<SbfVersion, u64> = HashMap::new();
+    let mut sections: HashMap<String, SectionStats> = HashMap::new();
+    let mut deployments: HashMap<Hash, Vec<Deployment>> = HashMap::new();
+    // ProgramData address -> program ID, for upgradeable programs
+    let mut program_ids: HashMap<Pubkey, Pubkey> = HashMap::new();
+    for (pubkey, loader_account) in accounts {
+        match loader_account {
+            LoaderAccount::Program {
+                programdata_address,
+            } => {
+                program_ids.insert(programdata_address, pubkey);
+            }
+            LoaderAccount::Binary {
+                sbf_version,
+                sections: elf_sections,
+                elf_hash,
+                len,
+            } => {
+                *versions.entry(sbf_version).or_default() += 1;
+                for section in elf_sections {
+                    let entry = sections.entry(section.name).or_default();
+                    entry.count += 1;
+                    entry.total_size += section.size;
+                }
+                deployments
+                    .entry(elf_hash)
+                    .or_default()
+                    .push(Deployment { pubkey, len });
+            }
+            LoaderAccount::Unparseable => unparseable += 1,
+        }
+    }
+
+    let total_programs: u64 = versions.values().sum();
+    info!(
+        "Parsed {} program binaries ({} unparseable) from {} accounts",
+        total_programs, unparseable, total_accounts
+    );
+
+    println!("\n--- Program ELF Analysis ---\n");
+    println!(
+        "Programs parsed:      {:>15}",
+        format::count(total_programs)
+    );
+    println!(
+        "SBFv1:                {:>15}",
+        format::count(versions.get(&SbfVersion::V1).copied().unwrap_or(0))
+    );
+    println!(
+        "SBFv2:                {:>15}",
+        format::count(versions.get(&SbfVersion::V2).copied().unwrap_or(0))
+    );
+    println!("Unparseable:          {:>15}", format::count(unparseable));
+    println!(
+        "Unique binaries:      {:>15}",
+        format::count(deployments.len() as u64)
+    );
+
+    if show_sections {
+        let mut sections: Vec<_> = sections.into_iter().collect();
+        sections.sort_by(|a, b| b.1.total_size.cmp(&a.1.total_size));
+
+        println!(
+            "\n{:<30} {:>15} {:>20}",
+            "Section", "Count", "Total Size (bytes)"
+        );
+        println!("{}", "-".repeat(67));
+        for (name, stats) in sections {
+            println!(
+                "{:<30} {:>15} {:>20}",
+                name,
+                format::count(stats.count),
+                format::bytes(stats.total_size)
+            );
+        }
+    }
+
+    let mut duplicates: Vec<_> = deployments
+        .into_iter()
+        .filter(|(_, deployments)| deployments.len() > 1)
+        .collect();
+    let wasted =
+        |deployments: &[Deployment]| (deployments.len() as u64 - 1) * deployments[0].len as u64;
+    duplicates.sort_by_key(|(_, deployments)| std::cmp::Reverse(wasted(deployments)));
+    let total_wasted: u64 = duplicates.iter().map(|(_, d)| wasted(d)).sum();
+
+    println!(
+        "\n--- Duplicate Deployments ({} binaries, {} wasted bytes) ---\n",
+        format::count(duplicates.len() as u64),
+        format::bytes(total_wasted)
+    );
+    for (elf_hash, deployments) in &duplicates {
+        println!(
+            "{} copies={} size={} wasted={}",
+            elf_hash,
+            deployments.len(),
+            format::bytes(deployments[0].len as u64),
+            format::bytes(wasted(deployments))
+        );
+        for deployment in deployments {
+            let program_id = program_ids
+                .get(&deployment.pubkey)
+                .unwrap_or(&deployment.pubkey);
+            println!("    {}", program_id);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::clock::Slot;
+use solana_sdk::feature::{self, Feature};
+use solana_sdk::feature_set::FEATURE_NAMES;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::HashSet;
+
+/// Description of a feature known to this build of the Solana SDK.
+fn description(feature_id: &Pubkey) -> &'static str {
+    FEATURE_NAMES
+        .get(feature_id)
+        .copied()
+        .unwrap_or("(unknown)")
+}
+
+fn print_features(title: &str, features: &[(Pubkey, Option<Slot>)]) {
+    println!("\n{}:\n", title);
+    println!("{:<44} {:>12}  Description", "Feature", "Activated At");
+    println!("{}", "-".repeat(100));
+    for (feature_id, activated_at) in features {
+        println!(
+            "{:<44} {:>12}  {}",
+            feature_id.to_string(),
+            activated_at.map_or("-".to_string(), |slot| slot.to_string()),
+            description(feature_id)
+        );
+    }
+}
+
+/// Reports which runtime features were active at the snapshot slot, from the accounts owned
+/// by the feature program. With `expected`, also lists the expected features that aren't
+/// active and the active features that weren't expected.
+pub fn run(
+    loader: &mut SupportedLoader,
+    expected: &HashSet<Pubkey>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let snapshot_slot = loader.slot();
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    // Feature account -> activation slot, if activated
+    let mut features: LatestAccounts<Option<Slot>> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            features.insert(slot, &account, || {
+                if account.account_meta.owner != feature::id() {
+                    return None;
+                }
+                let feature: Feature = bincode::deserialize(account.data).ok()?;
+                Some(feature.activated_at)
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    // Features requested but not yet activated by the runtime have no activation slot
+    let (mut active, mut pending): (Vec<_>, Vec<_>) = features.into_iter().partition(
+        |(_, activated_at)| matches!(activated_at, Some(slot) if *slot <= snapshot_slot),
+    );
+    active.sort_by_key(|(feature_id, activated_at)| (*activated_at, *feature_id));
+    pending.sort_by_key(|(feature_id, _)| *feature_id);
+    let unknown = active
+        .iter()
+        .chain(&pending)
+        .filter(|(feature_id, _)| !FEATURE_NAMES.contains_key(feature_id))
+        .count() as u64;
+    info!(
+        "Found {} feature accounts at slot {}",
+        format::count((active.len() + pending.len()) as u64),
+        snapshot_slot
+    );
+
+    println!("\n--- Feature Gates (slot {}) ---\n", snapshot_slot);
+    println!(
+        "Feature accounts:        {:>24}",
+        format::count((active.len() + pending.len()) as u64)
+    );
+    println!(
+        "Active:                  {:>24}",
+        format::count(active.len() as u64)
+    );
+    println!(
+        "Pending activation:      {:>24}",
+        format::count(pending.len() as u64)
+    );
+    println!("Unknown to this build:   {:>24}", format::count(unknown));
+
+    print_features("Active features", &active);
+    if !pending.is_empty() {
+        print_features("Pending features", &pending);
+    }
+
+    if !expected.is_empty() {
+        let active_ids: HashSet<Pubkey> =
+            active.iter().map(|(feature_id, _)| *feature_id).collect();
+        let mut inactive: Vec<(Pubkey, Option<Slot>)> = expected
+            .iter()
+            .filter(|feature_id| !active_ids.contains(feature_id))
+            .map(|feature_id| (*feature_id, None))
+            .collect();
+        inactive.sort();
+        let unexpected: Vec<(Pubkey, Option<Slot>)> = active
+            .iter()
+            .filter(|(feature_id, _)| !expected.contains(feature_id))
+            .copied()
+            .collect();
+
+        println!();
+        println!(
+            "Expected but inactive:   {:>24}",
+            format::count(inactive.len() as u64)
+        );
+        println!(
+            "Active but not expected: {:>24}",
+            format::count(unexpected.len() as u64)
+        );
+        if !inactive.is_empty() {
+            print_features("Expected features that are inactive", &inactive);
+        }
+        if !unexpected.is_empty() {
+            print_features("Active features that weren't expected", &unexpected);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::token::{ACCOUNT_STATE_FROZEN, MINT_ACCOUNT_LEN, TOKEN_ACCOUNT_LEN, TOKEN_PROGRAM_ID};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Latest version of an account of the token program.
+enum TokenProgramAccount {
+    Mint {
+        freeze_authority: Option<Pubkey>,
+    },
+    TokenAccount {
+        mint: Pubkey,
+        amount: u64,
+        frozen: bool,
+    },
+}
+
+/// Token accounts of one mint.
+#[derive(Default)]
+struct MintFreezes {
+    token_accounts: u64,
+    frozen_accounts: u64,
+    frozen_amount: u64,
+    total_amount: u64,
+}
+
+pub fn run(loader: &mut SupportedLoader, top_n: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    // Accounts are thawed and mints change authorities, so only the latest version of each
+    // account is kept
+    let mut total_accounts: u64 = 0;
+    let mut accounts: LatestAccounts<TokenProgramAccount> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            accounts.insert(slot, &account, || {
+                if account.account_meta.owner != token_program {
+                    return None;
+                }
+                match account.data.len() {
+                    MINT_ACCOUNT_LEN => {
+                        let freeze_authority_tag =
+                            u32::from_le_bytes(account.data[46..50].try_into().unwrap());
+                        Some(TokenProgramAccount::Mint {
+                            freeze_authority: (freeze_authority_tag == 1)
+                                .then(|| Pubkey::try_from(&account.data[50..82]).unwrap()),
+                        })
+                    }
+                    TOKEN_ACCOUNT_LEN => Some(TokenProgramAccount::TokenAccount {
+                        mint: Pubkey::try_from(&account.data[0..32]).unwrap(),
+                        amount: u64::from_le_bytes(account.data[64..72].try_into().unwrap()),
+                        frozen: account.data[108] == ACCOUNT_STATE_FROZEN,
+                    }),
+                    _ => None,
+                }
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut token_accounts: u64 = 0;
+    let mut frozen_accounts: u64 = 0;
+    // Freeze authority of every mint, `None` if it has none
+    let mut freeze_authorities: HashMap<Pubkey, Option<Pubkey>> = HashMap::new();
+    let mut by_mint: HashMap<Pubkey, MintFreezes> = HashMap::new();
+    for (pubkey, account) in accounts {
+        match account {
+            TokenProgramAccount::Mint { freeze_authority } => {
+                freeze_authorities.insert(pubkey, freeze_authority);
+            }
+            TokenProgramAccount::TokenAccount {
+                mint,
+                amount,
+                frozen,
+            } => {
+                token_accounts += 1;
+                let entry = by_mint.entry(mint).or_default();
+                entry.token_accounts += 1;
+                entry.total_amount = entry.total_amount.saturating_add(amount);
+                if frozen {
+                    frozen_accounts += 1;
+                    entry.frozen_accounts += 1;
+                    entry.frozen_amount = entry.frozen_amount.saturating_add(amount);
+                }
+            }
+        }
+    }
+
+    let mints_with_authority = freeze_authorities
+        .values()
+        .filter(|authority| authority.is_some())
+        .count() as u64;
+    let mut frozen: Vec<_> = by_mint
+        .into_iter()
+        .filter(|(_, freezes)| freezes.frozen_accounts > 0)
+        .collect();
+    frozen.sort_by_key(|(mint, freezes)| (Reverse(freezes.frozen_accounts), *mint));
+
+    info!(
+        "Checked {} token accounts of {} mints",
+        format::count(token_accounts),
+        format::count(freeze_authorities.len() as u64)
+    );
+
+    println!("\n--- Frozen Token Accounts ---\n");
+    println!(
+        "Token accounts checked:   {:>15}",
+        format::count(token_accounts)
+    );
+    println!(
+        "Frozen accounts:          {:>15}",
+        format::count(frozen_accounts)
+    );
+    println!(
+        "Mints with frozen accs:   {:>15}",
+        format::count(frozen.len() as u64)
+    );
+    println!(
+        "Mints with freeze auth:   {:>15}",
+        format::count(mints_with_authority)
+    );
+
+    if frozen.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "\n{:<45} {:>12} {:>12} {:>8} {:>20} {:>20} {:<45}",
+        "Mint", "Frozen", "Accounts", "%", "Frozen Amount", "Total Amount", "Freeze Authority"
+    );
+    println!("{}", "-".repeat(168));
+    for (mint, freezes) in frozen.into_iter().take(top_n) {
+        let authority = match freeze_authorities.get(&mint) {
+            Some(Some(authority)) => authority.to_string(),
+            Some(None) => "(none)".to_string(),
+            None => "(mint missing)".to_string(),
+        };
+        println!(
+            "{:<45} {:>12} {:>12} {:>7.2}% {:>20} {:>20} {:<45}",
+            mint.to_string(),
+            format::count(freezes.frozen_accounts),
+            format::count(freezes.token_accounts),
+            freezes.frozen_accounts as f64 / freezes.token_accounts as f64 * 100.0,
+            format::count(freezes.frozen_amount),
+            format::count(freezes.total_amount),
+            authority
+        );
+    }
+
+    Ok(())
+}
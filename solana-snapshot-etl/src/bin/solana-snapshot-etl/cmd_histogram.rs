@@ -0,0 +1,175 @@
+use crate::format;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// Bucket 0 holds empty accounts, bucket `i` lengths in `2^(i-1)..2^i`.
+const BUCKETS: usize = 65;
+
+/// Account counts and data bytes per power-of-two data length bucket.
+struct Histogram {
+    counts: [u64; BUCKETS],
+    bytes: [u64; BUCKETS],
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; BUCKETS],
+            bytes: [0; BUCKETS],
+        }
+    }
+}
+
+impl Histogram {
+    fn add(&mut self, data_len: u64) {
+        let bucket = (u64::BITS - data_len.leading_zeros()) as usize;
+        self.counts[bucket] += 1;
+        self.bytes[bucket] += data_len;
+    }
+
+    fn total_count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.bytes.iter().sum()
+    }
+
+    /// Non-empty buckets with their data length ranges.
+    fn buckets(&self) -> impl Iterator<Item = (RangeInclusive<u64>, u64, u64)> + '_ {
+        (0..BUCKETS)
+            .filter(|&bucket| self.counts[bucket] > 0)
+            .map(|bucket| {
+                (
+                    bucket_range(bucket),
+                    self.counts[bucket],
+                    self.bytes[bucket],
+                )
+            })
+    }
+}
+
+fn bucket_range(bucket: usize) -> RangeInclusive<u64> {
+    match bucket {
+        0 => 0..=0,
+        64 => 1 << 63..=u64::MAX,
+        _ => 1 << (bucket - 1)..=(1 << bucket) - 1,
+    }
+}
+
+fn range_label(range: &RangeInclusive<u64>) -> String {
+    if range.start() == range.end() {
+        range.start().to_string()
+    } else {
+        format!("{}-{}", range.start(), range.end())
+    }
+}
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    top_owners: usize,
+    csv_path: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut overall = Histogram::default();
+    let mut by_owner: HashMap<Pubkey, Histogram> = HashMap::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+            let data_len = account.data.len() as u64;
+            overall.add(data_len);
+            by_owner
+                .entry(account.account_meta.owner)
+                .or_default()
+                .add(data_len);
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut owners: Vec<_> = by_owner.into_iter().collect();
+    owners.sort_by(|a, b| {
+        b.1.total_bytes()
+            .cmp(&a.1.total_bytes())
+            .then(b.1.total_count().cmp(&a.1.total_count()))
+    });
+
+    println!("\n--- Data Length Histogram ---");
+    print_histogram("All accounts", &overall);
+    for (owner, histogram) in owners.iter().take(top_owners) {
+        print_histogram(&owner.to_string(), histogram);
+    }
+
+    if let Some(csv_path) = csv_path {
+        let mut writer = csv::Writer::from_path(csv_path)?;
+        writer.write_record([
+            "owner",
+            "min_data_len",
+            "max_data_len",
+            "accounts",
+            "data_bytes",
+        ])?;
+        let all = std::iter::once(("all".to_string(), &overall));
+        let per_owner = owners.iter().map(|(owner, h)| (owner.to_string(), h));
+        for (owner, histogram) in all.chain(per_owner) {
+            for (range, count, bytes) in histogram.buckets() {
+                writer.write_record([
+                    owner.clone(),
+                    range.start().to_string(),
+                    range.end().to_string(),
+                    count.to_string(),
+                    bytes.to_string(),
+                ])?;
+            }
+        }
+        writer.flush()?;
+        info!("Wrote histogram of {} owners to {}", owners.len(), csv_path);
+    }
+    Ok(())
+}
+
+fn print_histogram(title: &str, histogram: &Histogram) {
+    let total = histogram.total_count();
+    println!(
+        "\n{} ({} accounts, {})",
+        title,
+        format::count(total),
+        format::bytes(histogram.total_bytes())
+    );
+    println!(
+        "{:>24} {:>15} {:>8} {:>15}",
+        "Data Len", "Accounts", "%", "Data Size"
+    );
+    println!("{}", "-".repeat(65));
+    for (range, count, bytes) in histogram.buckets() {
+        println!(
+            "{:>24} {:>15} {:>7.2}% {:>15}",
+            range_label(&range),
+            format::count(count),
+            count as f64 / total as f64 * 100.0,
+            format::bytes(bytes)
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::token::{self, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use indicatif::{ProgressBar, ProgressStyle};
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Owner and amount of the latest version of a token account.
+struct Balance {
+    owner: Pubkey,
+    amount: u64,
+}
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    mint: &Pubkey,
+    top: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
+    let token_2022_program = Pubkey::from_str(TOKEN_2022_PROGRAM_ID).unwrap();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    // Token accounts of the mint at their latest version
+    let mut latest: LatestAccounts<Balance> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            latest.insert(slot, &account, || {
+                let owner = account.account_meta.owner;
+                let is_token_2022 = owner == token_2022_program;
+                if !(owner == token_program || is_token_2022)
+                    || !token::is_token_account(account.data, is_token_2022)
+                    || account.data[0..32] != mint.to_bytes()
+                {
+                    return None;
+                }
+                Some(Balance {
+                    owner: Pubkey::try_from(&account.data[32..64]).unwrap(),
+                    amount: u64::from_le_bytes(account.data[64..72].try_into().unwrap()),
+                })
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut balances: Vec<(Pubkey, Balance)> = latest.into_iter().collect();
+    let token_accounts = balances.len() as u64;
+    let total_amount: u128 = balances.iter().map(|(_, entry)| entry.amount as u128).sum();
+    let holders: HashSet<Pubkey> = balances
+        .iter()
+        .filter(|(_, entry)| entry.amount > 0)
+        .map(|(_, entry)| entry.owner)
+        .collect();
+    balances.sort_unstable_by(|a, b| b.1.amount.cmp(&a.1.amount).then(a.0.cmp(&b.0)));
+    balances.truncate(top);
+
+    println!("\n--- Holders of {} ---\n", mint);
+    println!(
+        "Token accounts:          {:>24}",
+        format::count(token_accounts)
+    );
+    println!(
+        "Holders:                 {:>24}",
+        format::count(holders.len() as u64)
+    );
+    println!("Total amount:            {:>24}", total_amount);
+
+    println!(
+        "\n{:>5}  {:<44} {:<44} {:>20} {:>8}",
+        "Rank", "Token Account", "Owner", "Amount", "% Held"
+    );
+    println!("{}", "-".repeat(126));
+    for (rank, (pubkey, entry)) in balances.iter().enumerate() {
+        let share = if total_amount > 0 {
+            entry.amount as f64 / total_amount as f64 * 100.0
+        } else {
+            0.0
+        };
+        println!(
+            "{:>5}  {:<44} {:<44} {:>20} {:>7.2}%",
+            rank + 1,
+            pubkey.to_string(),
+            entry.owner.to_string(),
+            entry.amount,
+            share
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use crate::cmd_lookup::{self, Version};
+use crate::format;
+use crate::loader::{LoadProgressTracking, SupportedLoader};
+use itertools::Itertools;
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::index::{self, IndexedSnapshot};
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Instant;
+
+pub fn build(loader: &mut SupportedLoader, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    info!("Building pubkey index {:?}", path);
+    let bank_hash = loader.bank_hash();
+    let accounts = index::build_index(loader.iter(), &bank_hash, path)?;
+    println!("Indexed {} accounts in {:?}", format::count(accounts), path);
+    Ok(())
+}
+
+pub fn get(
+    snapshot_dir: &Path,
+    index_path: &Path,
+    pubkeys: &HashSet<Pubkey>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !snapshot_dir.is_dir() {
+        return Err("Index lookups require an unpacked snapshot directory as the source".into());
+    }
+    let snapshot =
+        IndexedSnapshot::open(snapshot_dir, index_path, Box::new(LoadProgressTracking {}))?;
+    info!(
+        "Opened index {:?} of {} accounts",
+        index_path,
+        format::count(snapshot.len() as u64)
+    );
+
+    for pubkey in pubkeys.iter().sorted() {
+        let start = Instant::now();
+        let account = snapshot.get(pubkey)?;
+        info!("Looked up {} in {:?}", pubkey, start.elapsed());
+
+        println!("\n--- Account {} ---", pubkey);
+        match account {
+            Some(account) => {
+                cmd_lookup::print_version(&Version::new(account.slot(), &account.access()))
+            }
+            None => println!("Not found in snapshot"),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use crate::format;
+use crate::loader::SupportedLoader;
+use solana_snapshot_etl::SnapshotExtractor;
+
+pub fn run(loader: &SupportedLoader) -> Result<(), Box<dyn std::error::Error>> {
+    let info = loader.info();
+
+    println!("\n--- Snapshot Manifest ---\n");
+    println!("Slot:            {:>20}", info.slot);
+    println!("Epoch:           {:>20}", info.epoch);
+    println!("Block height:    {:>20}", info.block_height);
+    println!(
+        "Capitalization:  {:>20}",
+        format::lamports(info.capitalization)
+    );
+    println!(
+        "Storages:        {:>20}",
+        format::count(info.storages as u64)
+    );
+    println!("Bank hash:       {}", info.bank_hash);
+    println!("Accounts hash:   {}", info.accounts_hash);
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::programs;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+
+/// Data length and owner of the latest version of an account.
+struct Size {
+    data_len: u64,
+    owner: Pubkey,
+}
+
+pub fn run(loader: &mut SupportedLoader, top: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut latest: LatestAccounts<Size> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            latest.insert(slot, &account, || {
+                Some(Size {
+                    data_len: account.meta.data_len,
+                    owner: account.account_meta.owner,
+                })
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut largest: Vec<(Pubkey, Size)> = latest.into_iter().collect();
+    let total_data: u64 = largest.iter().map(|(_, entry)| entry.data_len).sum();
+    largest.sort_unstable_by(|a, b| b.1.data_len.cmp(&a.1.data_len).then(a.0.cmp(&b.0)));
+    largest.truncate(top);
+
+    println!("\n--- Largest Accounts by Data ---\n");
+    println!(
+        "{:>5}  {:<44} {:>15} {:>8}  Owner",
+        "Rank", "Pubkey", "Data Size", "% Data"
+    );
+    println!("{}", "-".repeat(100));
+    for (rank, (pubkey, entry)) in largest.iter().enumerate() {
+        let share = if total_data > 0 {
+            entry.data_len as f64 / total_data as f64 * 100.0
+        } else {
+            0.0
+        };
+        println!(
+            "{:>5}  {:<44} {:>15} {:>7.2}%  {}",
+            rank + 1,
+            pubkey.to_string(),
+            format::bytes(entry.data_len),
+            share,
+            programs::label(&entry.owner)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use crate::format;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use itertools::Itertools;
+use log::info;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec::StoredAccountMeta;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::{HashMap, HashSet};
+
+/// Bytes per line of the data hex dump.
+const HEX_DUMP_WIDTH: usize = 16;
+
+/// A stored version of a looked up account.
+pub struct Version {
+    slot: u64,
+    write_version: u64,
+    lamports: u64,
+    owner: Pubkey,
+    executable: bool,
+    rent_epoch: u64,
+    hash: Hash,
+    data: Vec<u8>,
+}
+
+impl Version {
+    pub fn new(slot: u64, account: &StoredAccountMeta) -> Self {
+        Self {
+            slot,
+            write_version: account.meta.write_version,
+            lamports: account.account_meta.lamports,
+            owner: account.account_meta.owner,
+            executable: account.account_meta.executable,
+            rent_epoch: account.account_meta.rent_epoch,
+            hash: *account.hash,
+            data: account.data.to_vec(),
+        }
+    }
+}
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    pubkeys: &HashSet<Pubkey>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!(
+        "Looking up {}",
+        pubkeys.iter().map(Pubkey::to_string).join(", ")
+    );
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut versions: HashMap<Pubkey, Vec<Version>> = HashMap::new();
+
+    // Stale versions may be in any append vec, so the whole snapshot is scanned
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+            if !pubkeys.contains(&account.meta.pubkey) {
+                continue;
+            }
+            versions
+                .entry(account.meta.pubkey)
+                .or_default()
+                .push(Version::new(slot, &account));
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    for pubkey in pubkeys.iter().sorted() {
+        println!("\n--- Account {} ---", pubkey);
+        let Some(versions) = versions.get_mut(pubkey) else {
+            println!("Not found in snapshot");
+            continue;
+        };
+        // Latest first
+        versions.sort_by(|a, b| (b.slot, b.write_version).cmp(&(a.slot, a.write_version)));
+        println!("Versions found: {}", versions.len());
+        for (i, version) in versions.iter().enumerate() {
+            println!(
+                "\nVersion {} of {}{}",
+                i + 1,
+                versions.len(),
+                if i == 0 { " (latest)" } else { " (stale)" }
+            );
+            print_version(version);
+        }
+    }
+
+    let missing = pubkeys.len() - versions.len();
+    if missing > 0 {
+        println!("\n{} of {} pubkeys not found", missing, pubkeys.len());
+    }
+    Ok(())
+}
+
+pub fn print_version(version: &Version) {
+    println!("Slot:          {}", version.slot);
+    println!("Write version: {}", version.write_version);
+    println!("Lamports:      {}", format::lamports(version.lamports));
+    println!("Owner:         {}", version.owner);
+    println!("Executable:    {}", version.executable);
+    println!("Rent epoch:    {}", version.rent_epoch);
+    println!("Hash:          {}", version.hash);
+    println!(
+        "Data len:      {}",
+        format::bytes(version.data.len() as u64)
+    );
+    for (i, chunk) in version.data.chunks(HEX_DUMP_WIDTH).enumerate() {
+        let hex = chunk.iter().map(|b| format!("{:02x}", b)).join(" ");
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+            .collect();
+        println!(
+            "  {:08x}  {:<width$}  |{}|",
+            i * HEX_DUMP_WIDTH,
+            hex,
+            ascii,
+            width = HEX_DUMP_WIDTH * 3 - 1
+        );
+    }
+}
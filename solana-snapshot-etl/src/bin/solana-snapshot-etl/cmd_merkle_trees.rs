@@ -0,0 +1,184 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::merkle_tree::{
+    self, MerkleTree, TreeConfig, ACCOUNT_COMPRESSION_PROGRAM_ID, BUBBLEGUM_PROGRAM_ID,
+};
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+enum Parsed {
+    Tree(MerkleTree),
+    Config(TreeConfig),
+    Unparsed,
+}
+
+/// Data length and parsed data of the latest version of an account-compression or
+/// Bubblegum account.
+struct TreeAccount {
+    data_len: u64,
+    parsed: Parsed,
+}
+
+/// Trees sharing a max depth, max buffer size and canopy depth.
+#[derive(Default)]
+struct ShapeStats {
+    trees: u64,
+    leaves: u64,
+    bytes: u64,
+}
+
+/// Reports the concurrent merkle trees of the SPL account-compression program by shape,
+/// and the Bubblegum tree configs that own compressed-NFT trees.
+pub fn run(loader: &mut SupportedLoader, top: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let compression_program = Pubkey::from_str(ACCOUNT_COMPRESSION_PROGRAM_ID).unwrap();
+    let bubblegum_program = Pubkey::from_str(BUBBLEGUM_PROGRAM_ID).unwrap();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    // Trees are rewritten by every update, so only the latest version is kept
+    let mut total_accounts: u64 = 0;
+    let mut latest: LatestAccounts<TreeAccount> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            latest.insert(slot, &account, || {
+                let owner = account.account_meta.owner;
+                let parsed = if owner == compression_program {
+                    merkle_tree::parse_tree(account.data)
+                        .map(Parsed::Tree)
+                        .unwrap_or(Parsed::Unparsed)
+                } else if owner == bubblegum_program {
+                    // Other Bubblegum accounts, such as vouchers, aren't counted
+                    Parsed::Config(merkle_tree::parse_tree_config(account.data)?)
+                } else {
+                    return None;
+                };
+                Some(TreeAccount {
+                    data_len: account.data.len() as u64,
+                    parsed,
+                })
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    // Bubblegum trees have their tree config as authority
+    let configs: HashSet<Pubkey> = latest
+        .iter()
+        .filter(|(_, entry)| matches!(entry.parsed, Parsed::Config(_)))
+        .map(|(pubkey, _)| *pubkey)
+        .collect();
+
+    let mut trees: u64 = 0;
+    let mut bubblegum_trees: u64 = 0;
+    let mut unparsed: u64 = 0;
+    let mut leaves: u64 = 0;
+    let mut capacity: u64 = 0;
+    let mut tree_bytes: u64 = 0;
+    let mut canopy_bytes: u64 = 0;
+    let mut minted: u64 = 0;
+    let mut mint_capacity: u64 = 0;
+    let mut shapes: HashMap<(u32, u32, u32), ShapeStats> = HashMap::new();
+    for entry in latest.values() {
+        match &entry.parsed {
+            Parsed::Tree(tree) => {
+                trees += 1;
+                bubblegum_trees += configs.contains(&tree.authority) as u64;
+                leaves += tree.leaves;
+                capacity += 1 << tree.max_depth;
+                tree_bytes += entry.data_len;
+                canopy_bytes += tree.canopy_bytes;
+                let shape = shapes
+                    .entry((tree.max_depth, tree.max_buffer_size, tree.canopy_depth))
+                    .or_default();
+                shape.trees += 1;
+                shape.leaves += tree.leaves;
+                shape.bytes += entry.data_len;
+            }
+            Parsed::Config(config) => {
+                minted += config.num_minted;
+                mint_capacity = mint_capacity.saturating_add(config.total_mint_capacity);
+            }
+            Parsed::Unparsed => unparsed += 1,
+        }
+    }
+    let mut shapes: Vec<_> = shapes.into_iter().collect();
+    shapes.sort_by_key(|(shape, stats)| (Reverse(stats.trees), *shape));
+
+    info!(
+        "Found {} merkle trees and {} Bubblegum tree configs",
+        format::count(trees),
+        format::count(configs.len() as u64)
+    );
+
+    println!("\n--- Account Compression ---\n");
+    println!("Merkle trees:            {:>24}", format::count(trees));
+    println!(
+        "  Bubblegum trees:       {:>24}",
+        format::count(bubblegum_trees)
+    );
+    println!("  Unparsed:              {:>24}", format::count(unparsed));
+    println!("Leaves appended:         {:>24}", format::count(leaves));
+    println!("Leaf capacity:           {:>24}", format::count(capacity));
+    println!("Tree bytes:              {:>24}", format::bytes(tree_bytes));
+    println!(
+        "  Canopy bytes:          {:>24}",
+        format::bytes(canopy_bytes)
+    );
+    println!(
+        "Bubblegum tree configs:  {:>24}",
+        format::count(configs.len() as u64)
+    );
+    println!("  Minted:                {:>24}", format::count(minted));
+    println!(
+        "  Mint capacity:         {:>24}",
+        format::count(mint_capacity)
+    );
+
+    if shapes.is_empty() {
+        return Ok(());
+    }
+
+    println!("\nTop {} tree shapes:\n", top);
+    println!(
+        "{:>6} {:>8} {:>7} {:>12} {:>16} {:>16}",
+        "Depth", "Buffer", "Canopy", "Trees", "Leaves", "Bytes"
+    );
+    println!("{}", "-".repeat(70));
+    for ((depth, buffer, canopy), stats) in shapes.iter().take(top) {
+        println!(
+            "{:>6} {:>8} {:>7} {:>12} {:>16} {:>16}",
+            depth,
+            buffer,
+            canopy,
+            format::count(stats.trees),
+            format::count(stats.leaves),
+            format::bytes(stats.bytes)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::mpl_metadata;
+use crate::progress;
+use crate::token::{self, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// Latest version of a metadata account, or of a token account holding exactly one unit.
+enum NftAccount {
+    Metadata {
+        mint: Pubkey,
+        /// Collection the item claims, and whether it is verified
+        collection: Option<(Pubkey, bool)>,
+        len: usize,
+    },
+    UnparsedMetadata,
+    Holding {
+        mint: Pubkey,
+        owner: Pubkey,
+    },
+}
+
+/// Items of one verified collection.
+#[derive(Default)]
+struct CollectionStats {
+    items: u64,
+    holders: HashSet<Pubkey>,
+    metadata_bytes: u64,
+}
+
+/// Groups metadata accounts by their verified collection and reports the items, distinct
+/// holders and metadata storage of every collection.
+pub fn run(loader: &mut SupportedLoader, top: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let metadata_program = mpl_metadata::id();
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
+    let token_2022_program = Pubkey::from_str(TOKEN_2022_PROGRAM_ID).unwrap();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    // Items get verified and transferred, so only the latest version of each account is kept
+    let mut total_accounts: u64 = 0;
+    let mut accounts: LatestAccounts<NftAccount> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            accounts.insert(slot, &account, || {
+                let owner = account.account_meta.owner;
+                if owner == metadata_program {
+                    // Editions and other records share the program, only metadata is counted
+                    if account.data.first() != Some(&(mpl_metadata::AccountKey::MetadataV1 as u8)) {
+                        return None;
+                    }
+                    let Some(parsed) = mpl_metadata::parse_metadata(account.data) else {
+                        return Some(NftAccount::UnparsedMetadata);
+                    };
+                    return Some(NftAccount::Metadata {
+                        mint: parsed.metadata.mint,
+                        collection: parsed
+                            .ext
+                            .and_then(|ext| ext.collection)
+                            .map(|collection| (collection.key, collection.verified)),
+                        len: account.data.len(),
+                    });
+                }
+
+                let is_token_2022 = owner == token_2022_program;
+                ((owner == token_program || is_token_2022)
+                    && token::is_token_account(account.data, is_token_2022)
+                    && account.data[64..72] == 1u64.to_le_bytes())
+                .then(|| NftAccount::Holding {
+                    mint: Pubkey::try_from(&account.data[0..32]).unwrap(),
+                    owner: Pubkey::try_from(&account.data[32..64]).unwrap(),
+                })
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut metadata_accounts: u64 = 0;
+    let mut unparsed: u64 = 0;
+    let mut unverified: u64 = 0;
+    let mut collections: HashMap<Pubkey, CollectionStats> = HashMap::new();
+    // Verified collection of every item mint
+    let mut item_collections: HashMap<Pubkey, Pubkey> = HashMap::new();
+    // Mint and owner of every token account holding exactly one unit, matched against
+    // the item mints once all metadata has been read
+    let mut holdings: Vec<(Pubkey, Pubkey)> = Vec::new();
+    for account in accounts.into_values() {
+        match account {
+            NftAccount::Metadata {
+                mint,
+                collection,
+                len,
+            } => {
+                metadata_accounts += 1;
+                match collection {
+                    Some((collection, true)) => {
+                        let stats = collections.entry(collection).or_default();
+                        stats.items += 1;
+                        stats.metadata_bytes += len as u64;
+                        item_collections.insert(mint, collection);
+                    }
+                    Some(_) => unverified += 1,
+                    None => {}
+                }
+            }
+            NftAccount::UnparsedMetadata => {
+                metadata_accounts += 1;
+                unparsed += 1;
+            }
+            NftAccount::Holding { mint, owner } => holdings.push((mint, owner)),
+        }
+    }
+
+    for (mint, owner) in holdings {
+        if let Some(collection) = item_collections.get(&mint) {
+            if let Some(stats) = collections.get_mut(collection) {
+                stats.holders.insert(owner);
+            }
+        }
+    }
+    let items: u64 = collections.values().map(|stats| stats.items).sum();
+    let mut collections: Vec<(Pubkey, CollectionStats)> = collections.into_iter().collect();
+    collections.sort_by_key(|(collection, stats)| (Reverse(stats.items), *collection));
+
+    info!(
+        "Grouped {} items into {} collections",
+        format::count(items),
+        format::count(collections.len() as u64)
+    );
+
+    println!("\n--- NFT Collections ---\n");
+    println!(
+        "Metadata accounts:       {:>24}",
+        format::count(metadata_accounts)
+    );
+    println!("  Unparsed:              {:>24}", format::count(unparsed));
+    println!("  Verified items:        {:>24}", format::count(items));
+    println!("  Unverified items:      {:>24}", format::count(unverified));
+    println!(
+        "Collections:             {:>24}",
+        format::count(collections.len() as u64)
+    );
+
+    if collections.is_empty() {
+        return Ok(());
+    }
+
+    println!("\nTop {} collections by items:\n", top);
+    println!(
+        "{:<44} {:>12} {:>12} {:>16}",
+        "Collection", "Items", "Holders", "Metadata Bytes"
+    );
+    println!("{}", "-".repeat(87));
+    for (collection, stats) in collections.iter().take(top) {
+        println!(
+            "{:<44} {:>12} {:>12} {:>16}",
+            collection.to_string(),
+            format::count(stats.items),
+            format::count(stats.holders.len() as u64),
+            format::bytes(stats.metadata_bytes)
+        );
+    }
+    Ok(())
+}
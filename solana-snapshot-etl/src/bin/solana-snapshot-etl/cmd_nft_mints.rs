@@ -0,0 +1,209 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::token::{self, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Latest version of an account of Token or Token-2022 that can take part in an NFT.
+enum TokenProgramAccount {
+    NftMint(NftMint),
+    /// Token account holding exactly one unit of its mint
+    Holding {
+        mint: Pubkey,
+        owner: Pubkey,
+    },
+}
+
+/// Authorities of a mint with a supply of 1 and no decimals.
+struct NftMint {
+    has_mint_authority: bool,
+    has_freeze_authority: bool,
+}
+
+/// Token account holding one unit of a mint, with its owner.
+struct Holding {
+    token_account: Pubkey,
+    owner: Pubkey,
+}
+
+/// Identifies mints with a supply of 1 and 0 decimals across Token and Token-2022, and
+/// reports the token accounts currently holding them and the owners of those accounts.
+pub fn run(
+    loader: &mut SupportedLoader,
+    top: usize,
+    csv_path: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
+    let token_2022_program = Pubkey::from_str(TOKEN_2022_PROGRAM_ID).unwrap();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    // Holdings move and mints get burned, so only the latest version of each account is kept
+    let mut total_accounts: u64 = 0;
+    let mut accounts: LatestAccounts<TokenProgramAccount> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            accounts.insert(slot, &account, || {
+                let owner = account.account_meta.owner;
+                let is_token_2022 = owner == token_2022_program;
+                if !(owner == token_program || is_token_2022) {
+                    return None;
+                }
+
+                let data = account.data;
+                if token::is_mint(data, is_token_2022) {
+                    let supply = u64::from_le_bytes(data[36..44].try_into().unwrap());
+                    let decimals = data[44];
+                    (supply == 1 && decimals == 0).then(|| {
+                        TokenProgramAccount::NftMint(NftMint {
+                            has_mint_authority: data[0..4] == [1, 0, 0, 0],
+                            has_freeze_authority: data[46..50] == [1, 0, 0, 0],
+                        })
+                    })
+                } else if token::is_token_account(data, is_token_2022) {
+                    let amount = u64::from_le_bytes(data[64..72].try_into().unwrap());
+                    (amount == 1).then(|| TokenProgramAccount::Holding {
+                        mint: Pubkey::try_from(&data[0..32]).unwrap(),
+                        owner: Pubkey::try_from(&data[32..64]).unwrap(),
+                    })
+                } else {
+                    None
+                }
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut nft_mints: HashMap<Pubkey, NftMint> = HashMap::new();
+    // Every token account holding exactly one unit is kept, and matched against the NFT-like
+    // mints afterwards
+    let mut holdings: HashMap<Pubkey, Vec<Holding>> = HashMap::new();
+    for (pubkey, account) in accounts {
+        match account {
+            TokenProgramAccount::NftMint(nft_mint) => {
+                nft_mints.insert(pubkey, nft_mint);
+            }
+            TokenProgramAccount::Holding { mint, owner } => {
+                holdings.entry(mint).or_default().push(Holding {
+                    token_account: pubkey,
+                    owner,
+                });
+            }
+        }
+    }
+
+    holdings.retain(|mint, _| nft_mints.contains_key(mint));
+    let held_mints = holdings.len() as u64;
+    let holder_accounts: u64 = holdings.values().map(|held| held.len() as u64).sum();
+    let with_mint_authority = nft_mints
+        .values()
+        .filter(|nft| nft.has_mint_authority)
+        .count() as u64;
+    let with_freeze_authority = nft_mints
+        .values()
+        .filter(|nft| nft.has_freeze_authority)
+        .count() as u64;
+
+    let mut by_owner: HashMap<Pubkey, u64> = HashMap::new();
+    for holding in holdings.values().flatten() {
+        *by_owner.entry(holding.owner).or_default() += 1;
+    }
+    let mut owners: Vec<(Pubkey, u64)> = by_owner.into_iter().collect();
+    owners.sort_by_key(|(owner, nfts)| (Reverse(*nfts), *owner));
+
+    info!(
+        "Found {} NFT-like mints held by {} owners",
+        format::count(nft_mints.len() as u64),
+        format::count(owners.len() as u64)
+    );
+
+    if let Some(csv_path) = csv_path {
+        let mut writer = csv::Writer::from_path(csv_path)?;
+        writer.write_record(["mint", "token_account", "owner"])?;
+        for (mint, held) in &holdings {
+            for holding in held {
+                writer.write_record([
+                    mint.to_string(),
+                    holding.token_account.to_string(),
+                    holding.owner.to_string(),
+                ])?;
+            }
+        }
+        writer.flush()?;
+        info!(
+            "Wrote {} holder token accounts to {}",
+            format::count(holder_accounts),
+            csv_path
+        );
+    }
+
+    println!("\n--- NFT-like Mints ---\n");
+    println!(
+        "NFT-like mints:          {:>24}",
+        format::count(nft_mints.len() as u64)
+    );
+    println!("  Held:                  {:>24}", format::count(held_mints));
+    println!(
+        "  Without holder:        {:>24}",
+        format::count(nft_mints.len() as u64 - held_mints)
+    );
+    println!(
+        "  With mint authority:   {:>24}",
+        format::count(with_mint_authority)
+    );
+    println!(
+        "  With freeze authority: {:>24}",
+        format::count(with_freeze_authority)
+    );
+    println!(
+        "Holder token accounts:   {:>24}",
+        format::count(holder_accounts)
+    );
+    println!(
+        "Distinct holders:        {:>24}",
+        format::count(owners.len() as u64)
+    );
+
+    if owners.is_empty() {
+        return Ok(());
+    }
+
+    println!("\nTop {} holders by NFT-like mints:\n", top);
+    println!("{:>5}  {:<44} {:>12} {:>8}", "Rank", "Owner", "Mints", "%");
+    println!("{}", "-".repeat(72));
+    for (rank, (owner, nfts)) in owners.iter().take(top).enumerate() {
+        println!(
+            "{:>5}  {:<44} {:>12} {:>7.2}%",
+            rank + 1,
+            owner.to_string(),
+            format::count(*nfts),
+            *nfts as f64 / holder_accounts as f64 * 100.0
+        );
+    }
+    Ok(())
+}
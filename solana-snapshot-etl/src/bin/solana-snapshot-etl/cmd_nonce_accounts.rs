@@ -0,0 +1,156 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::nonce::state::{Data, State, Versions};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Lamports held by a nonce account and its decoded state.
+struct NonceAccount {
+    lamports: u64,
+    data: Option<Data>,
+}
+
+#[derive(Default)]
+struct AuthorityNonces {
+    accounts: u64,
+    lamports: u64,
+}
+
+/// Reports durable nonce accounts: system-owned accounts holding a nonce state, with the
+/// lamports they lock up and their authorities.
+pub fn run(
+    loader: &mut SupportedLoader,
+    top: usize,
+    csv_path: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    // The blockhash advances every time a nonce is used, so only the latest version is kept
+    let mut nonces: LatestAccounts<NonceAccount> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            nonces.insert(slot, &account, || {
+                if account.account_meta.owner != system_program::id()
+                    || account.data.len() != State::size()
+                {
+                    return None;
+                }
+                let versions: Versions = bincode::deserialize(account.data).ok()?;
+                let data = match versions.state() {
+                    State::Uninitialized => None,
+                    State::Initialized(data) => Some(data.clone()),
+                };
+                Some(NonceAccount {
+                    lamports: account.account_meta.lamports,
+                    data,
+                })
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut initialized: u64 = 0;
+    let mut uninitialized: u64 = 0;
+    let mut locked: u64 = 0;
+    let mut by_authority: HashMap<Pubkey, AuthorityNonces> = HashMap::new();
+    let mut accounts: Vec<(Pubkey, NonceAccount)> = nonces.into_iter().collect();
+    accounts.sort_by_key(|(pubkey, _)| *pubkey);
+    for (_, nonce) in &accounts {
+        locked += nonce.lamports;
+        match &nonce.data {
+            Some(data) => {
+                initialized += 1;
+                let authority = by_authority.entry(data.authority).or_default();
+                authority.accounts += 1;
+                authority.lamports += nonce.lamports;
+            }
+            None => uninitialized += 1,
+        }
+    }
+
+    if let Some(csv_path) = csv_path {
+        let mut writer = csv::Writer::from_path(csv_path)?;
+        writer.write_record([
+            "pubkey",
+            "lamports",
+            "authority",
+            "blockhash",
+            "lamports_per_signature",
+        ])?;
+        for (pubkey, nonce) in &accounts {
+            let Some(data) = &nonce.data else {
+                continue;
+            };
+            writer.write_record([
+                pubkey.to_string(),
+                nonce.lamports.to_string(),
+                data.authority.to_string(),
+                data.blockhash().to_string(),
+                data.get_lamports_per_signature().to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        info!(
+            "Wrote {} nonce accounts to {}",
+            format::count(initialized),
+            csv_path
+        );
+    }
+
+    let mut authorities: Vec<(Pubkey, AuthorityNonces)> = by_authority.into_iter().collect();
+    authorities.sort_by_key(|(authority, nonces)| (Reverse(nonces.accounts), *authority));
+
+    println!("\n--- Durable Nonce Accounts ---\n");
+    println!(
+        "Nonce accounts:          {:>24}",
+        format::count(initialized)
+    );
+    println!(
+        "Uninitialized:           {:>24}",
+        format::count(uninitialized)
+    );
+    println!(
+        "Distinct authorities:    {:>24}",
+        format::count(authorities.len() as u64)
+    );
+    println!("Locked lamports:         {:>24}", format::lamports(locked));
+
+    println!("\nTop {} nonce authorities by accounts:\n", top);
+    println!("{:<44} {:>12} {:>24}", "Authority", "Accounts", "Lamports");
+    println!("{}", "-".repeat(82));
+    for (authority, nonces) in authorities.iter().take(top) {
+        println!(
+            "{:<44} {:>12} {:>24}",
+            authority.to_string(),
+            format::count(nonces.accounts),
+            format::lamports(nonces.lamports)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! `plugin` command: runs a WebAssembly account consumer over every account.
+//!
+//! Plugins run in a sandbox without WASI, so they can't touch files, the network or the
+//! environment; their only way out is the `emit` import. Each call into the plugin may run
+//! [`FUEL_PER_CALL`] instructions, so a plugin stuck in a loop fails instead of hanging
+//! the scan. The ABI, in wasm32 terms:
+//!
+//! Exports required from the plugin:
+//! - `memory`: the plugin's linear memory.
+//! - `alloc(len: i32) -> i32`: returns a buffer of `len` bytes in `memory`. It is called
+//!   before each account and the buffer is not used after `on_account` returns, so the
+//!   plugin may hand out the same buffer every time.
+//! - `on_account(ptr: i32, len: i32)`: receives one account record, laid out as below.
+//!
+//! Optional exports:
+//! - `on_end()`: called after the last account.
+//!
+//! Imports provided by the host, in module `solana_snapshot_etl`:
+//! - `emit(ptr: i32, len: i32)`: writes the bytes as one line to the output. JSON is
+//!   recommended, but the host doesn't interpret the bytes.
+//!
+//! Account records, with integers in little-endian:
+//!
+//! | offset | size | field               |
+//! |--------|------|---------------------|
+//! | 0      | 32   | pubkey              |
+//! | 32     | 32   | owner               |
+//! | 64     | 8    | lamports            |
+//! | 72     | 8    | rent epoch          |
+//! | 80     | 8    | slot                |
+//! | 88     | 8    | write version       |
+//! | 96     | 1    | executable (0 or 1) |
+//! | 97     | rest | account data        |
+
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_snapshot_etl::append_vec::StoredAccountMeta;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use wasmtime::{Caller, Config, Engine, Linker, Module, Store};
+
+/// Size of an account record before the account data.
+const ACCOUNT_HEADER_LEN: usize = 97;
+
+const IMPORT_MODULE: &str = "solana_snapshot_etl";
+
+/// Instructions (roughly) a single call into the plugin may execute.
+const FUEL_PER_CALL: u64 = 100_000_000;
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    plugin: &Path,
+    output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config)?;
+    let module = Module::from_file(&engine, plugin)
+        .map_err(|e| format!("Failed to load plugin {:?}: {}", plugin, e))?;
+    let mut store = Store::new(&engine, writer);
+    let mut linker = Linker::new(&engine);
+    linker.func_wrap(
+        IMPORT_MODULE,
+        "emit",
+        |mut caller: Caller<'_, Box<dyn Write>>, ptr: u32, len: u32| -> wasmtime::Result<()> {
+            let memory = caller
+                .get_export("memory")
+                .and_then(|export| export.into_memory())
+                .ok_or_else(|| wasmtime::Error::msg("plugin does not export its memory"))?;
+            let (data, writer) = memory.data_and_store_mut(&mut caller);
+            let line = (ptr as usize)
+                .checked_add(len as usize)
+                .and_then(|end| data.get(ptr as usize..end))
+                .ok_or_else(|| {
+                    wasmtime::Error::msg(format!(
+                        "emit({}, {}) is outside of the plugin memory ({} bytes)",
+                        ptr,
+                        len,
+                        data.len()
+                    ))
+                })?;
+            writer.write_all(line)?;
+            writer.write_all(b"\n")?;
+            Ok(())
+        },
+    )?;
+    refuel(&mut store)?;
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .map_err(|e| format!("Failed to instantiate plugin {:?}: {}", plugin, e))?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| format!("Plugin {:?} does not export its memory", plugin))?;
+    let alloc = instance.get_typed_func::<u32, u32>(&mut store, "alloc")?;
+    let on_account = instance.get_typed_func::<(u32, u32), ()>(&mut store, "on_account")?;
+    let on_end = instance.get_typed_func::<(), ()>(&mut store, "on_end").ok();
+    info!("Running plugin {:?}", plugin);
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut record = Vec::new();
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+            encode_account(&mut record, slot, &account);
+            let len = u32::try_from(record.len())?;
+            refuel(&mut store)?;
+            let ptr = alloc
+                .call(&mut store, len)
+                .map_err(|e| format!("Plugin failed in alloc: {:#}", e))?;
+            memory
+                .write(&mut store, ptr as usize, &record)
+                .map_err(|e| format!("Plugin returned an invalid buffer: {}", e))?;
+            refuel(&mut store)?;
+            on_account.call(&mut store, (ptr, len)).map_err(|e| {
+                format!("Plugin failed on account {}: {:#}", account.meta.pubkey, e)
+            })?;
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    if let Some(on_end) = on_end {
+        refuel(&mut store)?;
+        on_end
+            .call(&mut store, ())
+            .map_err(|e| format!("Plugin failed in on_end: {:#}", e))?;
+    }
+    store.data_mut().flush()?;
+    Ok(())
+}
+
+/// Tops the fuel of `store` up to [`FUEL_PER_CALL`], so that every call gets the same budget.
+fn refuel<T>(store: &mut Store<T>) -> wasmtime::Result<()> {
+    let remaining = store.consume_fuel(0)?;
+    store.add_fuel(FUEL_PER_CALL.saturating_sub(remaining))
+}
+
+fn encode_account(record: &mut Vec<u8>, slot: u64, account: &StoredAccountMeta) {
+    record.clear();
+    record.extend_from_slice(account.meta.pubkey.as_ref());
+    record.extend_from_slice(account.account_meta.owner.as_ref());
+    record.extend_from_slice(&account.account_meta.lamports.to_le_bytes());
+    record.extend_from_slice(&account.account_meta.rent_epoch.to_le_bytes());
+    record.extend_from_slice(&slot.to_le_bytes());
+    record.extend_from_slice(&account.meta.write_version.to_le_bytes());
+    record.push(account.account_meta.executable as u8);
+    debug_assert_eq!(record.len(), ACCOUNT_HEADER_LEN);
+    record.extend_from_slice(account.data);
+}
@@ -0,0 +1,217 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::programs;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+/// Program and ProgramData accounts of the upgradeable loader; buffers aren't tracked.
+enum LoaderAccount {
+    Program {
+        programdata_address: Pubkey,
+    },
+    ProgramData {
+        slot: Slot,
+        upgrade_authority: Option<Pubkey>,
+        binary_len: u64,
+    },
+}
+
+/// A program paired with its ProgramData account.
+struct Deployment {
+    program_id: Pubkey,
+    programdata_address: Pubkey,
+    slot: Slot,
+    upgrade_authority: Option<Pubkey>,
+    binary_len: u64,
+}
+
+/// Pairs the Program accounts of the upgradeable loader with their ProgramData accounts and
+/// reports the upgrade authority, last deployed slot and binary size of every program.
+pub fn run(
+    loader: &mut SupportedLoader,
+    top: usize,
+    csv_path: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let programdata_offset = UpgradeableLoaderState::size_of_programdata_metadata();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    // ProgramData accounts are rewritten on every upgrade, so only the latest version is kept
+    let mut total_accounts: u64 = 0;
+    let mut accounts: LatestAccounts<LoaderAccount> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            accounts.insert(slot, &account, || {
+                if account.account_meta.owner != bpf_loader_upgradeable::id() {
+                    return None;
+                }
+                let state: UpgradeableLoaderState = bincode::deserialize(account.data).ok()?;
+                match state {
+                    UpgradeableLoaderState::Program {
+                        programdata_address,
+                    } => Some(LoaderAccount::Program {
+                        programdata_address,
+                    }),
+                    UpgradeableLoaderState::ProgramData {
+                        slot,
+                        upgrade_authority_address,
+                    } => Some(LoaderAccount::ProgramData {
+                        slot,
+                        upgrade_authority: upgrade_authority_address,
+                        binary_len: account.data.len().saturating_sub(programdata_offset) as u64,
+                    }),
+                    _ => None,
+                }
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut deployments: Vec<Deployment> = Vec::new();
+    let mut missing_programdata: u64 = 0;
+    let mut paired: HashSet<Pubkey> = HashSet::new();
+    for (program_id, loader_account) in &accounts {
+        let LoaderAccount::Program {
+            programdata_address,
+        } = loader_account
+        else {
+            continue;
+        };
+        // Closed programs keep their Program account but lose their ProgramData
+        match accounts.get(programdata_address) {
+            Some(LoaderAccount::ProgramData {
+                slot,
+                upgrade_authority,
+                binary_len,
+            }) => {
+                paired.insert(*programdata_address);
+                deployments.push(Deployment {
+                    program_id: *program_id,
+                    programdata_address: *programdata_address,
+                    slot: *slot,
+                    upgrade_authority: *upgrade_authority,
+                    binary_len: *binary_len,
+                });
+            }
+            _ => missing_programdata += 1,
+        }
+    }
+    let orphaned_programdata = accounts
+        .iter()
+        .filter(|(pubkey, loader_account)| {
+            matches!(loader_account, LoaderAccount::ProgramData { .. }) && !paired.contains(pubkey)
+        })
+        .count() as u64;
+    deployments.sort_by_key(|deployment| (Reverse(deployment.binary_len), deployment.program_id));
+
+    let immutable = deployments
+        .iter()
+        .filter(|deployment| deployment.upgrade_authority.is_none())
+        .count() as u64;
+    let binary_bytes: u64 = deployments
+        .iter()
+        .map(|deployment| deployment.binary_len)
+        .sum();
+    info!(
+        "Paired {} upgradeable programs with their ProgramData accounts",
+        format::count(deployments.len() as u64)
+    );
+
+    if let Some(csv_path) = csv_path {
+        let mut writer = csv::Writer::from_path(csv_path)?;
+        writer.write_record([
+            "program_id",
+            "programdata_address",
+            "upgrade_authority",
+            "last_deployed_slot",
+            "binary_len",
+        ])?;
+        for deployment in &deployments {
+            writer.write_record([
+                deployment.program_id.to_string(),
+                deployment.programdata_address.to_string(),
+                deployment
+                    .upgrade_authority
+                    .map(|authority| authority.to_string())
+                    .unwrap_or_default(),
+                deployment.slot.to_string(),
+                deployment.binary_len.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        info!(
+            "Wrote {} programs to {}",
+            format::count(deployments.len() as u64),
+            csv_path
+        );
+    }
+
+    println!("\n--- Upgradeable Programs ---\n");
+    println!(
+        "Deployed programs:       {:>24}",
+        format::count(deployments.len() as u64)
+    );
+    println!(
+        "  Upgradeable:           {:>24}",
+        format::count(deployments.len() as u64 - immutable)
+    );
+    println!("  Immutable:             {:>24}", format::count(immutable));
+    println!(
+        "Missing ProgramData:     {:>24}",
+        format::count(missing_programdata)
+    );
+    println!(
+        "Orphaned ProgramData:    {:>24}",
+        format::count(orphaned_programdata)
+    );
+    println!(
+        "Binary size:             {:>24}",
+        format::bytes(binary_bytes)
+    );
+
+    println!("\nTop {} programs by binary size:\n", top);
+    println!(
+        "{:<44} {:<44} {:>12} {:>15}  Name",
+        "Program", "Upgrade Authority", "Slot", "Binary Size"
+    );
+    println!("{}", "-".repeat(125));
+    for deployment in deployments.iter().take(top) {
+        println!(
+            "{:<44} {:<44} {:>12} {:>15}  {}",
+            deployment.program_id.to_string(),
+            deployment
+                .upgrade_authority
+                .map_or("(immutable)".to_string(), |authority| authority.to_string()),
+            deployment.slot,
+            format::bytes(deployment.binary_len),
+            programs::name(&deployment.program_id).unwrap_or_default()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::programs;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::HashMap;
+
+/// Fields rent depends on at the latest version of an account.
+struct RentFields {
+    lamports: u64,
+    data_len: u64,
+    owner: Pubkey,
+    empty: bool,
+}
+
+/// Rent figures of a set of accounts.
+#[derive(Default)]
+struct RentStats {
+    accounts: u64,
+    /// Lamports held towards the rent-exempt minimum
+    reserve: u64,
+    underfunded: u64,
+    /// Lamports missing to make under-funded accounts rent-exempt
+    shortfall: u64,
+    empty: u64,
+    /// Lamports held by empty accounts, released by closing them
+    reclaimable: u64,
+}
+
+impl RentStats {
+    fn add(&mut self, lamports: u64, minimum_balance: u64, empty: bool) {
+        self.accounts += 1;
+        self.reserve += lamports.min(minimum_balance);
+        if lamports < minimum_balance {
+            self.underfunded += 1;
+            self.shortfall += minimum_balance - lamports;
+        }
+        if empty {
+            self.empty += 1;
+            self.reclaimable += lamports;
+        }
+    }
+}
+
+pub fn run(loader: &mut SupportedLoader, top: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let rent = loader.manifest().rent().clone();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut latest: LatestAccounts<RentFields> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            latest.insert(slot, &account, || {
+                // Program accounts without any state only hold lamports for their reserve,
+                // while system accounts without data are wallets
+                let owner = account.account_meta.owner;
+                let empty = owner != system_program::id() && account.data.iter().all(|&b| b == 0);
+                Some(RentFields {
+                    lamports: account.account_meta.lamports,
+                    data_len: account.meta.data_len,
+                    owner,
+                    empty,
+                })
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut overall = RentStats::default();
+    let mut by_owner: HashMap<Pubkey, RentStats> = HashMap::new();
+    for entry in latest.into_values() {
+        let minimum_balance = rent.minimum_balance(entry.data_len as usize);
+        overall.add(entry.lamports, minimum_balance, entry.empty);
+        by_owner
+            .entry(entry.owner)
+            .or_default()
+            .add(entry.lamports, minimum_balance, entry.empty);
+    }
+    let mut owners: Vec<_> = by_owner.into_iter().collect();
+    owners.sort_by(|a, b| b.1.reserve.cmp(&a.1.reserve).then(a.0.cmp(&b.0)));
+
+    println!("\n--- Rent ---\n");
+    println!(
+        "Lamports per byte-year:  {:>24}",
+        rent.lamports_per_byte_year
+    );
+    println!(
+        "Exemption threshold:     {:>21} yr",
+        rent.exemption_threshold
+    );
+    println!("Burn percent:            {:>23}%", rent.burn_percent);
+    println!(
+        "Accounts:                {:>24}",
+        format::count(overall.accounts)
+    );
+    println!(
+        "Rent-exempt reserve:     {:>24}",
+        format::lamports(overall.reserve)
+    );
+    println!(
+        "Under-funded accounts:   {:>24}",
+        format::count(overall.underfunded)
+    );
+    println!(
+        "Under-funded shortfall:  {:>24}",
+        format::lamports(overall.shortfall)
+    );
+    println!(
+        "Empty accounts:          {:>24}",
+        format::count(overall.empty)
+    );
+    println!(
+        "Reclaimable:             {:>24}",
+        format::lamports(overall.reclaimable)
+    );
+
+    println!("\nTop {} owners by rent-exempt reserve:\n", top);
+    println!(
+        "{:<44} {:>12} {:>24} {:>12} {:>12} {:>24}",
+        "Owner", "Accounts", "Reserve", "Underfunded", "Empty", "Reclaimable"
+    );
+    println!("{}", "-".repeat(133));
+    for (owner, stats) in owners.iter().take(top) {
+        println!(
+            "{:<44} {:>12} {:>24} {:>12} {:>12} {:>24}",
+            programs::label(owner),
+            format::count(stats.accounts),
+            format::lamports(stats.reserve),
+            format::count(stats.underfunded),
+            format::count(stats.empty),
+            format::lamports(stats.reclaimable)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::programs;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+
+/// Lamports and owner of the latest version of an account.
+struct Balance {
+    lamports: u64,
+    owner: Pubkey,
+}
+
+pub fn run(loader: &mut SupportedLoader, top: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let capitalization = loader.info().capitalization;
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut latest: LatestAccounts<Balance> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            latest.insert(slot, &account, || {
+                Some(Balance {
+                    lamports: account.account_meta.lamports,
+                    owner: account.account_meta.owner,
+                })
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut richest: Vec<(Pubkey, Balance)> = latest.into_iter().collect();
+    richest.sort_unstable_by(|a, b| b.1.lamports.cmp(&a.1.lamports).then(a.0.cmp(&b.0)));
+    richest.truncate(top);
+
+    println!("\n--- Richest Accounts ---\n");
+    println!(
+        "{:>5}  {:<44} {:>24} {:>8}  Owner",
+        "Rank", "Pubkey", "Lamports", "% Cap"
+    );
+    println!("{}", "-".repeat(110));
+    for (rank, (pubkey, entry)) in richest.iter().enumerate() {
+        let share = if capitalization > 0 {
+            entry.lamports as f64 / capitalization as f64 * 100.0
+        } else {
+            0.0
+        };
+        println!(
+            "{:>5}  {:<44} {:>24} {:>7.2}%  {}",
+            rank + 1,
+            pubkey.to_string(),
+            format::lamports(entry.lamports),
+            share,
+            programs::label(&entry.owner)
+        );
+    }
+    Ok(())
+}
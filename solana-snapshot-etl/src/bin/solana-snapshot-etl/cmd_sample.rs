@@ -0,0 +1,112 @@
+use crate::decode::account_json;
+use crate::format;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::sampling::{Reservoir, SplitMix64};
+use indicatif::{ProgressBar, ProgressStyle};
+use itertools::Itertools;
+use log::info;
+use serde_json::Value;
+use solana_snapshot_etl::append_vec::StoredAccountMeta;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// How accounts are picked.
+pub enum SampleSize {
+    /// Each account independently with this probability
+    Rate(f64),
+    /// Exactly this many accounts, uniformly (or all if there are fewer)
+    Count(usize),
+}
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    size: SampleSize,
+    seed: Option<u64>,
+    output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let SampleSize::Rate(rate) = size {
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(format!("--rate {} must be between 0 and 1", rate).into());
+        }
+    }
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    let mut rng = seed
+        .map(SplitMix64::new)
+        .unwrap_or_else(SplitMix64::from_time);
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut sampled: u64 = 0;
+    let (rate, mut reservoir) = match size {
+        SampleSize::Rate(rate) => (rate, None),
+        SampleSize::Count(count) => (0.0, Some(Reservoir::new(count))),
+    };
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+            if let Some(reservoir) = &mut reservoir {
+                if let Some(index) = reservoir.offer(&mut rng) {
+                    reservoir.put(index, sample_json(&account, slot));
+                }
+            } else if unit_interval(&mut rng) < rate {
+                writeln!(writer, "{}", sample_json(&account, slot))?;
+                sampled += 1;
+            }
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    if let Some(reservoir) = reservoir {
+        for sample in reservoir.into_items() {
+            writeln!(writer, "{}", sample)?;
+            sampled += 1;
+        }
+    }
+    writer.flush()?;
+
+    info!(
+        "Sampled {} of {} accounts",
+        format::count(sampled),
+        format::count(total_accounts)
+    );
+    Ok(())
+}
+
+/// Returns a uniformly distributed value in `[0, 1)`.
+fn unit_interval(rng: &mut SplitMix64) -> f64 {
+    (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// The account's fields as in sample bundles, plus its data in hex.
+fn sample_json(account: &StoredAccountMeta, slot: u64) -> Value {
+    let mut json = account_json(account, slot);
+    json["data"] = account
+        .data
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .join("")
+        .into();
+    json
+}
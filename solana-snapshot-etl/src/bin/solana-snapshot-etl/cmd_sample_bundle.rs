@@ -0,0 +1,146 @@
+use crate::decode::account_json;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::sampling::{Reservoir, SplitMix64};
+use crate::stats::SharedStats;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::cache::{variant_name, ArtifactCache, STATS_ARTIFACT};
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+struct Sample {
+    json: Value,
+    data: Vec<u8>,
+}
+
+struct OwnerSamples {
+    total_size: u64,
+    reservoir: Reservoir<Sample>,
+}
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    cache: Option<&ArtifactCache>,
+    cache_variant: &str,
+    output_dir: &str,
+    per_owner: usize,
+    top_owners: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // With a cached stats report the top owners are known up front,
+    // otherwise every owner is sampled and the ranking is applied at the end.
+    let bank_hash = loader.bank_hash();
+    let known_top: Option<HashSet<Pubkey>> = match cache
+        .map(|c| c.read(&bank_hash, &variant_name(STATS_ARTIFACT, cache_variant)))
+        .transpose()?
+        .flatten()
+    {
+        Some(cached) => {
+            let stats = SharedStats::from_json(&cached)?;
+            info!("Selecting top owners from cached stats report");
+            Some(stats.top_owners(top_owners).into_iter().collect())
+        }
+        None => None,
+    };
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut rng = SplitMix64::from_time();
+    let mut owners: HashMap<Pubkey, OwnerSamples> = HashMap::new();
+    let mut total_accounts: u64 = 0;
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            let owner = account.account_meta.owner;
+            if let Some(known_top) = &known_top {
+                if !known_top.contains(&owner) {
+                    continue;
+                }
+            }
+
+            let entry = owners.entry(owner).or_insert_with(|| OwnerSamples {
+                total_size: 0,
+                reservoir: Reservoir::new(per_owner),
+            });
+            entry.total_size += account.data.len() as u64;
+            if let Some(index) = entry.reservoir.offer(&mut rng) {
+                entry.reservoir.put(
+                    index,
+                    Sample {
+                        json: account_json(&account, slot),
+                        data: account.data.to_vec(),
+                    },
+                );
+            }
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut owners: Vec<_> = owners.into_iter().collect();
+    owners.sort_by(|a, b| b.1.total_size.cmp(&a.1.total_size));
+
+    let output_dir = Path::new(output_dir);
+    fs::create_dir_all(output_dir)?;
+
+    let mut index = Vec::new();
+    for (rank, (owner, samples)) in owners.into_iter().take(top_owners).enumerate() {
+        let owner_dir = output_dir.join(format!("{:03}_{}", rank + 1, owner));
+        fs::create_dir_all(&owner_dir)?;
+
+        // Several versions of an account may be sampled, so files are named after the version
+        let mut names = Vec::new();
+        for sample in samples.reservoir.into_items() {
+            let name = format!(
+                "{}_{}_{}",
+                sample.json["pubkey"].as_str().unwrap_or_default(),
+                sample.json["slot"],
+                sample.json["write_version"]
+            );
+            fs::write(
+                owner_dir.join(format!("{}.json", name)),
+                serde_json::to_vec_pretty(&sample.json)?,
+            )?;
+            fs::write(owner_dir.join(format!("{}.bin", name)), &sample.data)?;
+            names.push(name);
+        }
+
+        index.push(json!({
+            "rank": rank + 1,
+            "owner": owner.to_string(),
+            "total_size": samples.total_size,
+            "samples": names,
+        }));
+    }
+    fs::write(
+        output_dir.join("index.json"),
+        serde_json::to_vec_pretty(&index)?,
+    )?;
+
+    info!(
+        "Wrote samples for {} owners to {:?}",
+        index.len(),
+        output_dir
+    );
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::programs;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use itertools::Itertools;
+use solana_sdk::hash::hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Length of an Anchor account discriminator.
+const DISCRIMINATOR_LEN: usize = 8;
+
+type Discriminator = [u8; DISCRIMINATOR_LEN];
+
+/// Discriminator (`None` if the data is too short to have one) and data length of the
+/// latest version of an account.
+struct Layout {
+    discriminator: Option<Discriminator>,
+    data_len: u64,
+}
+
+/// Accounts sharing a discriminator.
+struct AccountType {
+    count: u64,
+    total_size: u64,
+    min_len: u64,
+    max_len: u64,
+}
+
+/// Anchor discriminator of the account type `name`: the first 8 bytes of
+/// `sha256("account:<name>")`.
+fn anchor_discriminator(name: &str) -> Discriminator {
+    hash(format!("account:{}", name).as_bytes()).to_bytes()[..DISCRIMINATOR_LEN]
+        .try_into()
+        .unwrap()
+}
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    program: &Pubkey,
+    type_names: &[String],
+    top: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let names: HashMap<Discriminator, &str> = type_names
+        .iter()
+        .map(|name| (anchor_discriminator(name), name.as_str()))
+        .collect();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    // Accounts owned by the program at their latest version
+    let mut latest: LatestAccounts<Layout> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            latest.insert(slot, &account, || {
+                (account.account_meta.owner == *program).then(|| Layout {
+                    discriminator: account
+                        .data
+                        .get(..DISCRIMINATOR_LEN)
+                        .map(|bytes| bytes.try_into().unwrap()),
+                    data_len: account.meta.data_len,
+                })
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut types: HashMap<Option<Discriminator>, AccountType> = HashMap::new();
+    let mut program_accounts: u64 = 0;
+    for entry in latest.into_values() {
+        program_accounts += 1;
+        let account_type = types.entry(entry.discriminator).or_insert(AccountType {
+            count: 0,
+            total_size: 0,
+            min_len: u64::MAX,
+            max_len: 0,
+        });
+        account_type.count += 1;
+        account_type.total_size += entry.data_len;
+        account_type.min_len = account_type.min_len.min(entry.data_len);
+        account_type.max_len = account_type.max_len.max(entry.data_len);
+    }
+    let mut types: Vec<_> = types.into_iter().collect();
+    types
+        .sort_by_key(|(discriminator, account_type)| (Reverse(account_type.count), *discriminator));
+
+    println!("\n--- Account Types of {} ---\n", programs::label(program));
+    println!(
+        "{:<18} {:<24} {:>15} {:>8} {:>15} {:>10} {:>10}",
+        "Discriminator", "Type", "Accounts", "%", "Total Size", "Min Len", "Max Len"
+    );
+    println!("{}", "-".repeat(106));
+    for (discriminator, account_type) in types.iter().take(top) {
+        let (hex, name) = match discriminator {
+            Some(discriminator) => (
+                discriminator.iter().map(|b| format!("{:02x}", b)).join(""),
+                names.get(discriminator).copied().unwrap_or_default(),
+            ),
+            None => ("-".to_string(), "(shorter than 8 bytes)"),
+        };
+        println!(
+            "{:<18} {:<24} {:>15} {:>7.2}% {:>15} {:>10} {:>10}",
+            hex,
+            name,
+            format::count(account_type.count),
+            account_type.count as f64 / program_accounts as f64 * 100.0,
+            format::bytes(account_type.total_size),
+            account_type.min_len,
+            account_type.max_len
+        );
+    }
+    if types.len() > top {
+        println!(
+            "... and {} more account types",
+            format::count((types.len() - top) as u64)
+        );
+    }
+    println!(
+        "\nAccounts: {} in {} account types",
+        format::count(program_accounts),
+        format::count(types.len() as u64)
+    );
+    Ok(())
+}
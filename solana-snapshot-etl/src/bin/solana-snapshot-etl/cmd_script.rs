@@ -0,0 +1,192 @@
+//! `script` command: runs a Rhai script over every account.
+//!
+//! The script defines `fn process(account)`, called once per account with a map of
+//! `pubkey`, `owner`, `lamports`, `data_len`, `rent_epoch`, `rent_exempt`, `executable`,
+//! `write_version`, `slot` and `data` (a blob). It can call:
+//!
+//! - `emit(row)` to write `row` (usually a map) as a JSON line to the output,
+//! - `metric(name, value)` to add an integer or float to a named total, printed at the end.
+//!
+//! An optional `fn finish()` is called after the last account.
+
+use crate::format;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, INT};
+use solana_sdk::clock::Epoch;
+use solana_snapshot_etl::append_vec::StoredAccountMeta;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Clone, Copy)]
+enum Total {
+    Int(i128),
+    Float(f64),
+}
+
+impl Total {
+    fn add(self, other: Total) -> Total {
+        match (self, other) {
+            (Total::Int(a), Total::Int(b)) => Total::Int(a + b),
+            (a, b) => Total::Float(a.as_f64() + b.as_f64()),
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Total::Int(n) => n as f64,
+            Total::Float(n) => n,
+        }
+    }
+}
+
+type Metrics = Rc<RefCell<BTreeMap<String, Total>>>;
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    script: &Path,
+    output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    let writer = Rc::new(RefCell::new(writer));
+    let metrics = Metrics::default();
+
+    let mut engine = Engine::new();
+    let rows = Rc::clone(&writer);
+    engine.register_fn(
+        "emit",
+        move |row: Dynamic| -> Result<(), Box<EvalAltResult>> {
+            let row: serde_json::Value = rhai::serde::from_dynamic(&row)?;
+            writeln!(rows.borrow_mut(), "{}", row).map_err(|e| e.to_string().into())
+        },
+    );
+    let totals = Rc::clone(&metrics);
+    engine.register_fn("metric", move |name: &str, value: INT| {
+        add_metric(&totals, name, Total::Int(value as i128))
+    });
+    let totals = Rc::clone(&metrics);
+    engine.register_fn("metric", move |name: &str, value: f64| {
+        add_metric(&totals, name, Total::Float(value))
+    });
+
+    let ast = engine
+        .compile_file(script.to_path_buf())
+        .map_err(|e| format!("Failed to load script {:?}: {}", script, e))?;
+    if !defines(&ast, "process", 1) {
+        return Err(format!("Script {:?} does not define fn process(account)", script).into());
+    }
+    info!("Running script {:?}", script);
+
+    // Top-level statements run once, before the first account
+    let mut scope = Scope::new();
+    engine.run_ast_with_scope(&mut scope, &ast)?;
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+            let account = account_map(slot, &account);
+            engine
+                .call_fn_with_options::<Dynamic>(
+                    CallFnOptions::new().eval_ast(false),
+                    &mut scope,
+                    &ast,
+                    "process",
+                    (account,),
+                )
+                .map_err(|e| format!("Script failed: {}", e))?;
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    if defines(&ast, "finish", 0) {
+        engine
+            .call_fn_with_options::<Dynamic>(
+                CallFnOptions::new().eval_ast(false),
+                &mut scope,
+                &ast,
+                "finish",
+                (),
+            )
+            .map_err(|e| format!("Script failed in finish(): {}", e))?;
+    }
+    writer.borrow_mut().flush()?;
+
+    let metrics = metrics.borrow();
+    if !metrics.is_empty() {
+        println!();
+        println!("Script metrics");
+        let width = metrics.keys().map(String::len).max().unwrap_or(0);
+        for (name, total) in metrics.iter() {
+            let value = match *total {
+                Total::Int(n) => match u64::try_from(n) {
+                    Ok(n) => format::count(n),
+                    Err(_) => n.to_string(),
+                },
+                Total::Float(n) => n.to_string(),
+            };
+            println!("  {:<width$}  {}", name, value, width = width);
+        }
+    }
+    Ok(())
+}
+
+fn defines(ast: &AST, name: &str, params: usize) -> bool {
+    ast.iter_functions()
+        .any(|f| f.name == name && f.params.len() == params)
+}
+
+fn add_metric(metrics: &Metrics, name: &str, value: Total) {
+    let mut metrics = metrics.borrow_mut();
+    let total = metrics.entry(name.to_string()).or_insert(Total::Int(0));
+    *total = total.add(value);
+}
+
+fn account_map(slot: u64, account: &StoredAccountMeta) -> Map {
+    let int = |n: u64| Dynamic::from_int(INT::try_from(n).unwrap_or(INT::MAX));
+    let mut map = Map::new();
+    map.insert("pubkey".into(), account.meta.pubkey.to_string().into());
+    map.insert(
+        "owner".into(),
+        account.account_meta.owner.to_string().into(),
+    );
+    map.insert("lamports".into(), int(account.account_meta.lamports));
+    map.insert("data_len".into(), int(account.meta.data_len));
+    map.insert("rent_epoch".into(), int(account.account_meta.rent_epoch));
+    map.insert(
+        "rent_exempt".into(),
+        (account.account_meta.rent_epoch == Epoch::MAX).into(),
+    );
+    map.insert("executable".into(), account.account_meta.executable.into());
+    map.insert("write_version".into(), int(account.meta.write_version));
+    map.insert("slot".into(), int(slot));
+    map.insert("data".into(), Dynamic::from_blob(account.data.to_vec()));
+    map
+}
@@ -0,0 +1,176 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::stake::{parse_stake, StakeRecord};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{info, warn};
+use solana_sdk::clock::{Clock, Epoch};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::vote::state::VoteState;
+use solana_sdk::{stake, sysvar, vote};
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::HashMap;
+
+/// Latest versions of the accounts the report is built from.
+enum TrackedAccount {
+    Stake(StakeRecord),
+    /// Most recent epoch with earned credits of a vote account
+    Vote(Epoch),
+    Clock(Clock),
+}
+
+#[derive(Default)]
+struct AuthorityReport {
+    missing_vote: u64,
+    delinquent_vote: u64,
+    deactivated: u64,
+    lamports: u64,
+}
+
+pub fn run(loader: &mut SupportedLoader, top_n: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut latest: LatestAccounts<TrackedAccount> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            latest.insert(slot, &account, || {
+                let owner = account.account_meta.owner;
+                if owner == stake::program::id() {
+                    parse_stake(&account).map(TrackedAccount::Stake)
+                } else if owner == vote::program::id() {
+                    let vote_state = VoteState::deserialize(account.data).ok()?;
+                    let last_credited = vote_state
+                        .epoch_credits
+                        .last()
+                        .map(|(epoch, _, _)| *epoch)
+                        .unwrap_or(0);
+                    Some(TrackedAccount::Vote(last_credited))
+                } else if account.meta.pubkey == sysvar::clock::id() {
+                    bincode::deserialize(account.data)
+                        .ok()
+                        .map(TrackedAccount::Clock)
+                } else {
+                    None
+                }
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let current_epoch = match latest.get(&sysvar::clock::id()) {
+        Some(TrackedAccount::Clock(clock)) => clock.epoch,
+        _ => {
+            warn!("Clock sysvar not found, cannot determine current epoch");
+            return Ok(());
+        }
+    };
+    info!("Snapshot epoch: {}", current_epoch);
+
+    let mut by_authority: HashMap<Pubkey, AuthorityReport> = HashMap::new();
+    let mut totals = AuthorityReport::default();
+    let mut stake_count: u64 = 0;
+
+    for stake in latest.values() {
+        let TrackedAccount::Stake(stake) = stake else {
+            continue;
+        };
+        stake_count += 1;
+        let delegation = match stake.delegation {
+            Some(delegation) => delegation,
+            None => continue,
+        };
+
+        let report = by_authority.entry(stake.withdrawer).or_default();
+        let fully_deactivated = delegation.deactivation_epoch < current_epoch;
+        if fully_deactivated {
+            if stake.lamports > stake.rent_exempt_reserve {
+                report.deactivated += 1;
+                report.lamports += stake.lamports;
+                totals.deactivated += 1;
+                totals.lamports += stake.lamports;
+            }
+            continue;
+        }
+
+        match latest.get(&delegation.voter_pubkey) {
+            // No credits earned in the current or previous epoch
+            Some(TrackedAccount::Vote(last_credited)) if last_credited + 1 < current_epoch => {
+                report.delinquent_vote += 1;
+                totals.delinquent_vote += 1;
+            }
+            Some(TrackedAccount::Vote(_)) => continue,
+            _ => {
+                report.missing_vote += 1;
+                totals.missing_vote += 1;
+            }
+        }
+        report.lamports += stake.lamports;
+        totals.lamports += stake.lamports;
+    }
+
+    println!("\n--- Stake Hygiene Report (epoch {}) ---\n", current_epoch);
+    println!(
+        "Stake accounts:             {:>15}",
+        format::count(stake_count)
+    );
+    println!(
+        "Delegated to missing vote:  {:>15}",
+        format::count(totals.missing_vote)
+    );
+    println!(
+        "Delegated to delinquent:    {:>15}",
+        format::count(totals.delinquent_vote)
+    );
+    println!(
+        "Deactivated with balance:   {:>15}",
+        format::count(totals.deactivated)
+    );
+    println!(
+        "Lamports affected:          {:>15}",
+        format::lamports(totals.lamports)
+    );
+
+    let mut rows: Vec<_> = by_authority
+        .into_iter()
+        .filter(|(_, report)| report.lamports > 0)
+        .collect();
+    rows.sort_by(|a, b| b.1.lamports.cmp(&a.1.lamports));
+
+    println!(
+        "\n{:<45} {:>10} {:>12} {:>12} {:>20}",
+        "Withdraw Authority", "Missing", "Delinquent", "Deactivated", "Lamports"
+    );
+    println!("{}", "-".repeat(103));
+    for (withdrawer, report) in rows.into_iter().take(top_n) {
+        println!(
+            "{:<45} {:>10} {:>12} {:>12} {:>20}",
+            withdrawer.to_string(),
+            format::count(report.missing_vote),
+            format::count(report.delinquent_vote),
+            format::count(report.deactivated),
+            format::lamports(report.lamports)
+        );
+    }
+
+    Ok(())
+}
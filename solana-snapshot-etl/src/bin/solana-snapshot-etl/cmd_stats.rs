@@ -1,19 +1,135 @@
+use crate::format;
 use crate::loader::SupportedLoader;
-use crate::stats::{SharedStats, StatsConsumerFactory};
+use crate::stats::{
+    Checkpoints, SharedStats, StatsConsumerFactory, StatsFormat, StatsGrouping, StatsTableOptions,
+};
+use log::{info, warn};
+use solana_snapshot_etl::cache::{
+    variant_name, ArtifactCache, STATS_ARTIFACT, STATS_CHECKPOINT_ARTIFACT,
+};
 use solana_snapshot_etl::parallel::par_iter_append_vecs;
 use solana_snapshot_etl::SnapshotExtractor;
+use std::sync::Arc;
+
+/// Where `run` writes the stats of all owners, besides printing them.
+pub struct StatsOutputs<'a> {
+    /// File in the given format
+    pub file: Option<(&'a str, StatsFormat)>,
+    /// DuckDB database whose `owner_stats` table the stats are appended to
+    pub db: Option<&'a str>,
+}
 
 pub fn run(
     loader: &mut SupportedLoader,
     num_threads: usize,
+    cache: Option<&ArtifactCache>,
+    cache_variant: &str,
+    checkpoint_every: Option<u64>,
+    table: &StatsTableOptions,
+    outputs: &StatsOutputs,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let shared_stats = SharedStats::new();
-    let mut factory = StatsConsumerFactory::new(shared_stats.clone());
+    let shared_stats = collect(
+        loader,
+        num_threads,
+        cache,
+        cache_variant,
+        true,
+        table.data_len_percentiles,
+        checkpoint_every,
+    )?;
+    match table.group_by {
+        StatsGrouping::Owner => shared_stats.print_stats(table),
+        StatsGrouping::Category => shared_stats.print_category_stats(),
+    }
+    if table.data_len_percentiles {
+        shared_stats.print_data_len_percentiles(table);
+    }
+    shared_stats.print_executable_stats();
+    if let Some((path, format)) = outputs.file {
+        shared_stats.write_stats(path, format, table.sort)?;
+    }
+    if let Some(db_path) = outputs.db {
+        shared_stats.write_db(db_path, loader.slot())?;
+    }
+    Ok(())
+}
+
+/// Collects per-owner stats, reusing a cached report for the same bank hash if available.
+///
+/// With `progress_reports`, interim stats are printed every million accounts. With
+/// `data_lens`, the distribution of data lengths is tracked per owner. With
+/// `checkpoint_every`, the stats collected so far are checkpointed to the cache every
+/// that many accounts; an interrupted run is resumed from its last checkpoint.
+pub fn collect(
+    loader: &mut SupportedLoader,
+    num_threads: usize,
+    cache: Option<&ArtifactCache>,
+    cache_variant: &str,
+    progress_reports: bool,
+    data_lens: bool,
+    checkpoint_every: Option<u64>,
+) -> Result<Arc<SharedStats>, Box<dyn std::error::Error>> {
+    if checkpoint_every.is_some() && cache.is_none() {
+        return Err("Checkpoints are written to the artifact cache, which is disabled".into());
+    }
+    let bank_hash = loader.bank_hash();
+    // Reports with data length sketches are larger, so they are cached separately
+    let options = match (data_lens, cache_variant.is_empty()) {
+        (false, _) => cache_variant.to_string(),
+        (true, true) => "data-lens".to_string(),
+        (true, false) => format!("{},data-lens", cache_variant),
+    };
+    let artifact = variant_name(STATS_ARTIFACT, &options);
+    let checkpoint_artifact = variant_name(STATS_CHECKPOINT_ARTIFACT, &options);
+
+    if let Some(cached) = cache
+        .map(|c| c.read(&bank_hash, &artifact))
+        .transpose()?
+        .flatten()
+    {
+        let stats = SharedStats::from_json(&cached)?;
+        info!("Using cached stats report for bank hash {}", bank_hash);
+        return Ok(stats);
+    }
+
+    let checkpoint = cache
+        .map(|c| c.read(&bank_hash, &checkpoint_artifact))
+        .transpose()?
+        .flatten();
+    let shared_stats = match checkpoint {
+        Some(checkpoint) => {
+            let stats = SharedStats::from_checkpoint(&checkpoint, progress_reports)?;
+            info!(
+                "Resuming from stats checkpoint of {} accounts",
+                format::count(stats.accounts_count())
+            );
+            stats
+        }
+        None => SharedStats::with_progress_reports(progress_reports),
+    };
+    if let (Some(cache), Some(every)) = (cache, checkpoint_every) {
+        shared_stats.enable_checkpoints(Checkpoints::new(
+            cache.clone(),
+            bank_hash,
+            checkpoint_artifact.clone(),
+            every,
+        ));
+    }
+    let mut factory = StatsConsumerFactory::new(shared_stats.clone()).with_data_lens(data_lens);
 
     par_iter_append_vecs(loader.iter(), &mut factory, num_threads)?;
 
     shared_stats.finish();
-    shared_stats.print_stats(None);
 
-    Ok(())
+    if let Some(cache) = cache {
+        if let Err(e) = cache.write(&bank_hash, &artifact, &shared_stats.to_json()?) {
+            warn!("Failed to cache stats report: {}", e);
+        }
+        // The finished report supersedes the checkpoint
+        if let Err(e) = cache.remove(&bank_hash, &checkpoint_artifact) {
+            warn!("Failed to remove stats checkpoint: {}", e);
+        }
+    }
+
+    Ok(shared_stats)
 }
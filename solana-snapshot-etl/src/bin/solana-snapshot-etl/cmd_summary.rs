@@ -0,0 +1,82 @@
+use crate::cmd_stats;
+use crate::format;
+use crate::loader::SupportedLoader;
+use clap::ValueEnum;
+use serde_json::json;
+use solana_snapshot_etl::cache::ArtifactCache;
+use solana_snapshot_etl::SnapshotExtractor;
+
+const TOP_OWNERS: usize = 5;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SummaryFormat {
+    Text,
+    /// A single JSON object on one line
+    Json,
+}
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    num_threads: usize,
+    cache: Option<&ArtifactCache>,
+    cache_variant: &str,
+    summary_format: SummaryFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let info = loader.info().clone();
+    let stats = cmd_stats::collect(
+        loader,
+        num_threads,
+        cache,
+        cache_variant,
+        false,
+        false,
+        None,
+    )?;
+    let top_owners = stats.top_owner_stats(TOP_OWNERS);
+
+    match summary_format {
+        SummaryFormat::Json => {
+            let summary = json!({
+                "slot": info.slot,
+                "epoch": info.epoch,
+                "total_accounts": stats.accounts_count(),
+                "total_data_bytes": stats.total_size(),
+                "capitalization": info.capitalization,
+                "top_owners": top_owners
+                    .iter()
+                    .map(|(owner, owner_stats)| json!({
+                        "owner": owner.to_string(),
+                        "count": owner_stats.count,
+                        "total_size": owner_stats.total_size,
+                    }))
+                    .collect::<Vec<_>>(),
+            });
+            println!("{}", summary);
+        }
+        SummaryFormat::Text => {
+            println!("\n--- Snapshot Summary ---\n");
+            println!("Slot:            {:>20}", info.slot);
+            println!("Epoch:           {:>20}", info.epoch);
+            println!(
+                "Accounts:        {:>20}",
+                format::count(stats.accounts_count())
+            );
+            println!("Data:            {:>20}", format::bytes(stats.total_size()));
+            println!(
+                "Capitalization:  {:>20}",
+                format::lamports(info.capitalization)
+            );
+            println!("\nTop {} owners by data size:", TOP_OWNERS);
+            for (owner, owner_stats) in top_owners {
+                println!(
+                    "  {:<45} {:>15} {:>20}",
+                    owner.to_string(),
+                    format::count(owner_stats.count),
+                    format::bytes(owner_stats.total_size)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::token::{MINT_ACCOUNT_LEN, TOKEN_ACCOUNT_LEN, TOKEN_PROGRAM_ID};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Latest version of an account of the token program.
+enum TokenProgramAccount {
+    Mint { supply: u64 },
+    TokenAccount { mint: Pubkey, amount: u64 },
+}
+
+/// Recorded supply of a mint and the amounts its token accounts hold.
+#[derive(Default)]
+struct MintBalance {
+    /// `None` until the mint account is found
+    supply: Option<u64>,
+    held: u128,
+    token_accounts: u64,
+}
+
+impl MintBalance {
+    /// Amount held in token accounts minus the recorded supply.
+    fn difference(&self) -> i128 {
+        self.held as i128 - self.supply.unwrap_or_default() as i128
+    }
+}
+
+pub fn run(loader: &mut SupportedLoader, top_n: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    // Supplies and balances change with every transfer, so only the latest version of each
+    // account is kept
+    let mut total_accounts: u64 = 0;
+    let mut accounts: LatestAccounts<TokenProgramAccount> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            accounts.insert(slot, &account, || {
+                if account.account_meta.owner != token_program {
+                    return None;
+                }
+                match account.data.len() {
+                    MINT_ACCOUNT_LEN => Some(TokenProgramAccount::Mint {
+                        supply: u64::from_le_bytes(account.data[36..44].try_into().unwrap()),
+                    }),
+                    TOKEN_ACCOUNT_LEN => Some(TokenProgramAccount::TokenAccount {
+                        mint: Pubkey::try_from(&account.data[0..32]).unwrap(),
+                        amount: u64::from_le_bytes(account.data[64..72].try_into().unwrap()),
+                    }),
+                    _ => None,
+                }
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut token_accounts: u64 = 0;
+    let mut mints: HashMap<Pubkey, MintBalance> = HashMap::new();
+    for (pubkey, account) in accounts {
+        match account {
+            TokenProgramAccount::Mint { supply } => {
+                mints.entry(pubkey).or_default().supply = Some(supply);
+            }
+            TokenProgramAccount::TokenAccount { mint, amount } => {
+                token_accounts += 1;
+                let entry = mints.entry(mint).or_default();
+                entry.held += amount as u128;
+                entry.token_accounts += 1;
+            }
+        }
+    }
+
+    // Token accounts of missing mints are reported by dangling-mints
+    let mints: Vec<(Pubkey, MintBalance)> = mints
+        .into_iter()
+        .filter(|(_, balance)| balance.supply.is_some())
+        .collect();
+    let mints_checked = mints.len() as u64;
+    let mut diverging: Vec<_> = mints
+        .into_iter()
+        .filter(|(_, balance)| balance.difference() != 0)
+        .collect();
+    diverging.sort_by_key(|(mint, balance)| (Reverse(balance.difference().unsigned_abs()), *mint));
+
+    info!(
+        "Checked {} token accounts against {} mints",
+        format::count(token_accounts),
+        format::count(mints_checked)
+    );
+
+    println!("\n--- Token Supply Reconciliation ---\n");
+    println!(
+        "Mints checked:            {:>15}",
+        format::count(mints_checked)
+    );
+    println!(
+        "Token accounts:           {:>15}",
+        format::count(token_accounts)
+    );
+    println!(
+        "Mints matching supply:    {:>15}",
+        format::count(mints_checked - diverging.len() as u64)
+    );
+    println!(
+        "Mints diverging:          {:>15}",
+        format::count(diverging.len() as u64)
+    );
+
+    if diverging.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "\n{:<45} {:>22} {:>22} {:>23} {:>12}",
+        "Mint", "Supply", "Held", "Difference", "Accounts"
+    );
+    println!("{}", "-".repeat(128));
+    for (mint, balance) in diverging.into_iter().take(top_n) {
+        println!(
+            "{:<45} {:>22} {:>22} {:>23} {:>12}",
+            mint.to_string(),
+            balance.supply.unwrap_or_default(),
+            balance.held,
+            format::signed(balance.difference(), |amount| amount.to_string()),
+            format::count(balance.token_accounts)
+        );
+    }
+
+    Ok(())
+}
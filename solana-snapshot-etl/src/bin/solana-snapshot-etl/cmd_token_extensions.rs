@@ -0,0 +1,265 @@
+use crate::batch_appender::BatchAppender;
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use crate::schema::{Column, SchemaMapping};
+use crate::token::{
+    MINT_ACCOUNT_LEN, MULTISIG_ACCOUNT_LEN, TOKEN_2022_PROGRAM_ID, TOKEN_ACCOUNT_LEN,
+};
+use duckdb::types::Value;
+use duckdb::Connection;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+const MINT_EXTENSION_COLUMNS: &[Column] = &[
+    Column {
+        name: "mint",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "extension_type",
+        sql_type: "UBIGINT NOT NULL",
+    },
+    Column {
+        name: "extension",
+        sql_type: "VARCHAR NOT NULL",
+    },
+    Column {
+        name: "data_len",
+        sql_type: "UBIGINT NOT NULL",
+    },
+];
+
+/// Offset of the account type byte. Extended mints are padded to the length of a token
+/// account so both kinds carry it at the same position, followed by the TLV entries.
+const ACCOUNT_TYPE_OFFSET: usize = TOKEN_ACCOUNT_LEN;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+/// Length of a TLV entry header: a u16 extension type and a u16 value length.
+const TLV_HEADER_LEN: usize = 4;
+
+#[derive(Clone, Copy)]
+enum AccountKind {
+    Mint,
+    Account,
+}
+
+/// Mints and accounts using one extension type.
+#[derive(Default)]
+struct ExtensionUsage {
+    mints: u64,
+    accounts: u64,
+}
+
+/// Name of a Token-2022 extension type, as in `spl_token_2022::extension::ExtensionType`.
+fn extension_name(extension_type: u16) -> &'static str {
+    match extension_type {
+        1 => "TransferFeeConfig",
+        2 => "TransferFeeAmount",
+        3 => "MintCloseAuthority",
+        4 => "ConfidentialTransferMint",
+        5 => "ConfidentialTransferAccount",
+        6 => "DefaultAccountState",
+        7 => "ImmutableOwner",
+        8 => "MemoTransfer",
+        9 => "NonTransferable",
+        10 => "InterestBearingConfig",
+        11 => "CpiGuard",
+        12 => "PermanentDelegate",
+        13 => "NonTransferableAccount",
+        14 => "TransferHook",
+        15 => "TransferHookAccount",
+        16 => "ConfidentialTransferFeeConfig",
+        17 => "ConfidentialTransferFeeAmount",
+        18 => "MetadataPointer",
+        19 => "TokenMetadata",
+        20 => "GroupPointer",
+        21 => "TokenGroup",
+        22 => "GroupMemberPointer",
+        23 => "TokenGroupMember",
+        24 => "ConfidentialMintBurn",
+        25 => "ScaledUiAmount",
+        26 => "Pausable",
+        27 => "PausableAccount",
+        _ => "Unknown",
+    }
+}
+
+/// Kind and extensions of a Token-2022 account, or `None` for multisigs and data that
+/// isn't a valid mint or token account.
+fn parse_extensions(data: &[u8]) -> Option<(AccountKind, Vec<(u16, u16)>)> {
+    match data.len() {
+        MINT_ACCOUNT_LEN => return Some((AccountKind::Mint, Vec::new())),
+        TOKEN_ACCOUNT_LEN => return Some((AccountKind::Account, Vec::new())),
+        MULTISIG_ACCOUNT_LEN => return None,
+        len if len <= ACCOUNT_TYPE_OFFSET => return None,
+        _ => {}
+    }
+    let kind = match data[ACCOUNT_TYPE_OFFSET] {
+        ACCOUNT_TYPE_MINT => AccountKind::Mint,
+        ACCOUNT_TYPE_ACCOUNT => AccountKind::Account,
+        _ => return None,
+    };
+
+    let mut extensions = Vec::new();
+    let mut offset = ACCOUNT_TYPE_OFFSET + 1;
+    while let Some(header) = data.get(offset..offset + TLV_HEADER_LEN) {
+        let extension_type = u16::from_le_bytes([header[0], header[1]]);
+        let len = u16::from_le_bytes([header[2], header[3]]);
+        // An uninitialized entry marks the start of unused space
+        if extension_type == 0 {
+            break;
+        }
+        offset += TLV_HEADER_LEN + len as usize;
+        if offset > data.len() {
+            return None;
+        }
+        extensions.push((extension_type, len));
+    }
+    Some((kind, extensions))
+}
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    db_path: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let token_2022_program = Pubkey::from_str(TOKEN_2022_PROGRAM_ID).unwrap();
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    // Kind and extensions (type and value length) of the latest version of every
+    // Token-2022 account, if it parsed as a mint or token account
+    let mut latest: LatestAccounts<Option<(AccountKind, Vec<(u16, u16)>)>> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            latest.insert(slot, &account, || {
+                (account.account_meta.owner == token_2022_program)
+                    .then(|| parse_extensions(account.data))
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let conn = match db_path {
+        Some(path) => {
+            info!("Opening DuckDB database: {}", path);
+            Some(Connection::open(path)?)
+        }
+        None => None,
+    };
+    let extension_schema =
+        SchemaMapping::default().table("mint_extensions", MINT_EXTENSION_COLUMNS)?;
+    if let Some(conn) = &conn {
+        conn.execute_batch(&format!(
+            "DROP TABLE IF EXISTS mint_extensions;
+             {}",
+            extension_schema.create_table_sql()
+        ))?;
+    }
+    let mut appender = conn
+        .as_ref()
+        .map(|conn| BatchAppender::new(conn, &extension_schema))
+        .transpose()?;
+
+    let mut mints: u64 = 0;
+    let mut extended_mints: u64 = 0;
+    let mut accounts: u64 = 0;
+    let mut extended_accounts: u64 = 0;
+    let mut unparsed: u64 = 0;
+    let mut detail_rows: u64 = 0;
+    let mut usage: BTreeMap<u16, ExtensionUsage> = BTreeMap::new();
+    for (pubkey, parsed) in &latest {
+        let Some((kind, extensions)) = parsed else {
+            unparsed += 1;
+            continue;
+        };
+        let extended = !extensions.is_empty();
+        match kind {
+            AccountKind::Mint => {
+                mints += 1;
+                extended_mints += extended as u64;
+            }
+            AccountKind::Account => {
+                accounts += 1;
+                extended_accounts += extended as u64;
+            }
+        }
+        for &(extension_type, len) in extensions {
+            let extension_usage = usage.entry(extension_type).or_default();
+            match kind {
+                AccountKind::Mint => extension_usage.mints += 1,
+                AccountKind::Account => extension_usage.accounts += 1,
+            }
+            if let (AccountKind::Mint, Some(appender)) = (kind, &mut appender) {
+                appender.append_row(extension_schema.select(vec![
+                    Value::Text(pubkey.to_string()),
+                    Value::UBigInt(extension_type as u64),
+                    Value::Text(extension_name(extension_type).to_string()),
+                    Value::UBigInt(len as u64),
+                ]))?;
+                detail_rows += 1;
+            }
+        }
+    }
+    if let Some(appender) = &mut appender {
+        appender.flush()?;
+        info!(
+            "Wrote {} mint extensions to {}",
+            format::count(detail_rows),
+            db_path.unwrap_or_default()
+        );
+    }
+
+    println!("\n--- Token-2022 Extensions ---\n");
+    println!("Mints:                   {:>24}", format::count(mints));
+    println!(
+        "  With extensions:       {:>24}",
+        format::count(extended_mints)
+    );
+    println!("Token accounts:          {:>24}", format::count(accounts));
+    println!(
+        "  With extensions:       {:>24}",
+        format::count(extended_accounts)
+    );
+    println!("Unparsed accounts:       {:>24}", format::count(unparsed));
+
+    println!(
+        "\n{:>4} {:<32} {:>15} {:>15}",
+        "Type", "Extension", "Mints", "Accounts"
+    );
+    println!("{}", "-".repeat(69));
+    for (extension_type, extension_usage) in &usage {
+        println!(
+            "{:>4} {:<32} {:>15} {:>15}",
+            extension_type,
+            extension_name(*extension_type),
+            format::count(extension_usage.mints),
+            format::count(extension_usage.accounts)
+        );
+    }
+    Ok(())
+}
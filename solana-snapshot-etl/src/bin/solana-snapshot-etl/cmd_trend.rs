@@ -0,0 +1,133 @@
+use crate::cmd_stats;
+use crate::format;
+use crate::loader::SupportedLoader;
+use crate::programs;
+use crate::stats::OwnerStats;
+use log::info;
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::cache::ArtifactCache;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One owner at one slot in the trend CSV.
+#[derive(Serialize)]
+struct TrendRow {
+    slot: u64,
+    owner: String,
+    program: Option<&'static str>,
+    count: u64,
+    total_size: u64,
+}
+
+/// Stats of one snapshot in the series.
+struct SnapshotStats {
+    slot: u64,
+    accounts: u64,
+    total_size: u64,
+    owners: HashMap<Pubkey, OwnerStats>,
+}
+
+/// Full snapshot archives in `dir`, named `snapshot-<slot>-<hash>.tar.<ext>` by the
+/// validator. Incremental snapshots are skipped.
+fn find_snapshots(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| format!("Failed to list {:?}: {}", dir, e))? {
+        let path = entry?.path();
+        let is_snapshot = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("snapshot-"));
+        if is_snapshot && path.is_file() {
+            snapshots.push(path);
+        }
+    }
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+/// Collects the stats of every snapshot in `dir`, reusing cached reports and checkpoints,
+/// and writes the accounts and bytes of the `top` largest owners at each slot to `output`.
+pub fn run(
+    dir: &Path,
+    mut open: impl FnMut(&Path) -> Result<SupportedLoader, Box<dyn std::error::Error>>,
+    num_threads: usize,
+    cache: Option<&ArtifactCache>,
+    cache_variant: &str,
+    top: usize,
+    output: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let paths = find_snapshots(dir)?;
+    if paths.is_empty() {
+        return Err(format!("No snapshot archives found in {:?}", dir).into());
+    }
+
+    let mut series = Vec::with_capacity(paths.len());
+    for (i, path) in paths.iter().enumerate() {
+        info!("Snapshot {}/{}: {:?}", i + 1, paths.len(), path);
+        let mut loader = open(path)?;
+        let slot = loader.slot();
+        let stats = cmd_stats::collect(
+            &mut loader,
+            num_threads,
+            cache,
+            cache_variant,
+            false,
+            false,
+            None,
+        )?;
+        series.push(SnapshotStats {
+            slot,
+            accounts: stats.accounts_count(),
+            total_size: stats.total_size(),
+            owners: stats.owner_stats(),
+        });
+    }
+    series.sort_by_key(|snapshot| snapshot.slot);
+
+    // Owners are ranked by where they ended up
+    let latest = series.last().expect("at least one snapshot");
+    let mut owners: Vec<_> = latest.owners.iter().collect();
+    owners.sort_by(|a, b| b.1.total_size.cmp(&a.1.total_size).then(a.0.cmp(b.0)));
+    let owners: Vec<Pubkey> = owners
+        .into_iter()
+        .take(top)
+        .map(|(owner, _)| *owner)
+        .collect();
+
+    let mut writer = csv::Writer::from_path(output)?;
+    for snapshot in &series {
+        for owner in &owners {
+            let stats = snapshot.owners.get(owner).cloned().unwrap_or_default();
+            writer.serialize(TrendRow {
+                slot: snapshot.slot,
+                owner: owner.to_string(),
+                program: programs::name(owner),
+                count: stats.count,
+                total_size: stats.total_size,
+            })?;
+        }
+    }
+    writer.flush()?;
+    info!(
+        "Wrote {} owners over {} snapshots to {}",
+        owners.len(),
+        series.len(),
+        output
+    );
+
+    println!("\n--- Trend ---\n");
+    println!("{:>12} {:>15} {:>20}", "Slot", "Accounts", "Total Size");
+    println!("{}", "-".repeat(49));
+    for snapshot in &series {
+        println!(
+            "{:>12} {:>15} {:>20}",
+            snapshot.slot,
+            format::count(snapshot.accounts),
+            format::bytes(snapshot.total_size)
+        );
+    }
+    Ok(())
+}
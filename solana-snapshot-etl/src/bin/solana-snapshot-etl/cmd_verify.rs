@@ -0,0 +1,109 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use solana_sdk::hash::{hashv, Hash};
+use solana_snapshot_etl::accounts_hash::{compute_merkle_root, MERKLE_FANOUT};
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+
+/// Checks the capitalization and accounts hash recorded in the manifest of a full
+/// snapshot against its accounts.
+pub fn run(loader: &mut SupportedLoader) -> Result<(), Box<dyn std::error::Error>> {
+    let expected = loader.info().capitalization;
+    let expected_accounts_hash = loader.info().accounts_hash;
+    let epoch_accounts_hash = loader.manifest().extra_fields.epoch_accounts_hash;
+
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    let mut latest: LatestAccounts<(u64, Hash)> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            latest.insert(slot, &account, || {
+                Some((account.account_meta.lamports, *account.hash))
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let actual: u128 = latest.values().map(|(lamports, _)| *lamports as u128).sum();
+    info!(
+        "Summed lamports of {} accounts ({} stored versions)",
+        format::count(latest.len() as u64),
+        format::count(total_accounts)
+    );
+
+    let delta = actual as i128 - expected as i128;
+    let capitalization_passed = delta == 0;
+
+    // Deleted accounts are left out of the accounts hash
+    let mut hashes: Vec<_> = latest
+        .into_iter()
+        .map(|(pubkey, (_, hash))| (pubkey, hash))
+        .collect();
+    hashes.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    let hashes: Vec<Hash> = hashes.into_iter().map(|(_, hash)| hash).collect();
+    let accounts_hash = compute_merkle_root(&hashes, MERKLE_FANOUT);
+    let accounts_hash_passed = accounts_hash == expected_accounts_hash;
+
+    println!("\n--- Snapshot Verification ---\n");
+    println!(
+        "Manifest capitalization:  {:>25}",
+        format::lamports(expected)
+    );
+    println!(
+        "Summed account lamports:  {:>25}",
+        u64::try_from(actual)
+            .map(format::lamports)
+            .unwrap_or_else(|_| actual.to_string())
+    );
+    println!("Delta (lamports):         {:>25}", delta);
+    println!(
+        "Capitalization check:     {:>25}",
+        if capitalization_passed {
+            "PASS"
+        } else {
+            "FAIL"
+        }
+    );
+    println!();
+    println!("Manifest accounts hash:   {}", expected_accounts_hash);
+    println!("Computed accounts hash:   {}", accounts_hash);
+    println!(
+        "Accounts hash check:      {:>25}",
+        if accounts_hash_passed { "PASS" } else { "FAIL" }
+    );
+    // Archive names carry the snapshot hash, which mixes in the epoch accounts hash
+    if let Some(epoch_accounts_hash) = epoch_accounts_hash {
+        println!("Epoch accounts hash:      {}", epoch_accounts_hash);
+        println!(
+            "Computed snapshot hash:   {}",
+            hashv(&[accounts_hash.as_ref(), epoch_accounts_hash.as_ref()])
+        );
+    }
+
+    if !(capitalization_passed && accounts_hash_passed) {
+        return Err("Snapshot verification failed".into());
+    }
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use crate::format;
+use crate::latest::LatestAccounts;
+use crate::loader::SupportedLoader;
+use crate::progress;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use serde_json::json;
+use solana_sdk::system_program;
+use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::SnapshotExtractor;
+use std::fs;
+
+/// Percentiles reported for account balances.
+const PERCENTILES: [f64; 8] = [10.0, 25.0, 50.0, 75.0, 90.0, 99.0, 99.9, 99.99];
+
+pub fn run(
+    loader: &mut SupportedLoader,
+    lorenz_points: usize,
+    json_path: Option<&str>,
+    csv_path: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
+    )
+    .unwrap();
+    let spinner = progress::add(
+        ProgressBar::new_spinner()
+            .with_style(spinner_style)
+            .with_prefix("accs"),
+    );
+
+    let mut total_accounts: u64 = 0;
+    // Balances of system-owned accounts
+    let mut balances: LatestAccounts<u64> = LatestAccounts::new();
+
+    for append_vec in loader.iter() {
+        let append_vec = append_vec?;
+        let slot = append_vec.get_slot();
+        for account in append_vec_iter(&append_vec) {
+            total_accounts += 1;
+            if total_accounts % 10000 == 0 {
+                spinner.set_position(total_accounts);
+            }
+
+            balances.insert(slot, &account, || {
+                (account.account_meta.owner == system_program::id())
+                    .then_some(account.account_meta.lamports)
+            });
+        }
+    }
+    spinner.set_position(total_accounts);
+    spinner.finish();
+
+    let mut balances: Vec<u64> = balances.into_values().collect();
+    balances.sort_unstable();
+    if balances.is_empty() {
+        return Err("No system-owned accounts with lamports found".into());
+    }
+    info!(
+        "Analyzing balances of {} system-owned accounts",
+        format::count(balances.len() as u64)
+    );
+
+    let total: u128 = balances.iter().map(|&b| b as u128).sum();
+    let percentiles: Vec<(f64, u64)> = PERCENTILES
+        .iter()
+        .map(|&p| (p, percentile(&balances, p)))
+        .collect();
+    let lorenz = lorenz_curve(&balances, total, lorenz_points.max(1));
+    let gini = gini(&balances, total);
+    let top_share = |fraction: f64| {
+        let n = ((balances.len() as f64 * fraction).ceil() as usize).max(1);
+        let top: u128 = balances.iter().rev().take(n).map(|&b| b as u128).sum();
+        top as f64 / total as f64
+    };
+
+    println!("\n--- SOL Distribution (system-owned accounts) ---\n");
+    println!(
+        "Accounts:            {:>24}",
+        format::count(balances.len() as u64)
+    );
+    println!(
+        "Total balance:       {:>24}",
+        format::lamports(total.min(u64::MAX as u128) as u64)
+    );
+    println!(
+        "Mean balance:        {:>24}",
+        format::lamports((total / balances.len() as u128) as u64)
+    );
+    for (p, lamports) in &percentiles {
+        println!(
+            "{:<21}{:>24}",
+            format!("P{}:", p),
+            format::lamports(*lamports)
+        );
+    }
+    println!("Top 1% share:        {:>23.2}%", top_share(0.01) * 100.0);
+    println!("Top 10% share:       {:>23.2}%", top_share(0.1) * 100.0);
+    println!("Gini coefficient:    {:>24.4}", gini);
+
+    if let Some(json_path) = json_path {
+        let report = json!({
+            "slot": loader.slot(),
+            "accounts": balances.len(),
+            "total_lamports": total as u64,
+            "percentiles": percentiles
+                .iter()
+                .map(|(p, lamports)| json!({ "percentile": p, "lamports": lamports }))
+                .collect::<Vec<_>>(),
+            "top_1_percent_share": top_share(0.01),
+            "top_10_percent_share": top_share(0.1),
+            "gini": gini,
+            "lorenz_curve": lorenz
+                .iter()
+                .map(|(population, wealth)| json!([population, wealth]))
+                .collect::<Vec<_>>(),
+        });
+        fs::write(json_path, serde_json::to_vec_pretty(&report)?)?;
+        info!("Wrote wealth report to {}", json_path);
+    }
+    if let Some(csv_path) = csv_path {
+        let mut writer = csv::Writer::from_path(csv_path)?;
+        writer.write_record(["population_share", "wealth_share"])?;
+        for (population, wealth) in &lorenz {
+            writer.write_record([population.to_string(), wealth.to_string()])?;
+        }
+        writer.flush()?;
+        info!("Wrote Lorenz curve to {}", csv_path);
+    }
+    Ok(())
+}
+
+/// Nearest-rank percentile of sorted `balances`.
+fn percentile(balances: &[u64], p: f64) -> u64 {
+    let rank = (p / 100.0 * balances.len() as f64).ceil() as usize;
+    balances[rank.clamp(1, balances.len()) - 1]
+}
+
+/// Share of the total held by the poorest fraction of accounts, at `points` evenly
+/// spaced population shares from 0 to 1.
+fn lorenz_curve(balances: &[u64], total: u128, points: usize) -> Vec<(f64, f64)> {
+    let mut curve = vec![(0.0, 0.0)];
+    let mut cumulative: u128 = 0;
+    let mut counted = 0;
+    for point in 1..=points {
+        let until = balances.len() * point / points;
+        cumulative += balances[counted..until]
+            .iter()
+            .map(|&b| b as u128)
+            .sum::<u128>();
+        counted = until;
+        curve.push((
+            point as f64 / points as f64,
+            cumulative as f64 / total as f64,
+        ));
+    }
+    curve
+}
+
+/// Gini coefficient of sorted `balances`: 0 if all are equal, approaching 1 if one
+/// account holds everything.
+fn gini(balances: &[u64], total: u128) -> f64 {
+    let n = balances.len() as f64;
+    let weighted: f64 = balances
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| (i + 1) as f64 * b as f64)
+        .sum();
+    2.0 * weighted / (n * total as f64) - (n + 1.0) / n
+}
@@ -1,10 +1,12 @@
+use crate::format;
+use crate::progress;
 use indicatif::{ProgressBar, ProgressStyle};
 use solana_sdk::pubkey::Pubkey;
 use solana_snapshot_etl::append_vec::AppendVec;
 use solana_snapshot_etl::append_vec_iter;
 use solana_snapshot_etl::parallel::{AppendVecConsumer, GenericResult};
+use std::collections::HashSet;
 use std::io::Write;
-use std::rc::Rc;
 use zstd::stream::Encoder;
 
 /// A sink that counts bytes written but discards the data
@@ -46,9 +48,11 @@ impl BenchmarkStats {
             "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
         )
         .unwrap();
-        let accounts_spinner = ProgressBar::new_spinner()
-            .with_style(spinner_style)
-            .with_prefix("accs");
+        let accounts_spinner = progress::add(
+            ProgressBar::new_spinner()
+                .with_style(spinner_style)
+                .with_prefix("accs"),
+        );
 
         Self {
             accounts_spinner,
@@ -66,30 +70,40 @@ impl BenchmarkStats {
         };
 
         println!("\n--- Compression Benchmark Stats ---\n");
-        println!("Accounts scanned:     {:>15}", self.accounts_count);
-        println!("Accounts matched:     {:>15}", self.filtered_count);
-        println!("Total uncompressed:   {:>15} bytes", self.total_uncompressed);
-        println!("Total compressed:     {:>15} bytes", total_compressed);
-        println!("Compression ratio:    {:>15.4}", ratio);
         println!(
-            "Space savings:        {:>14.2}%",
-            (1.0 - ratio) * 100.0
+            "Accounts scanned:     {:>15}",
+            format::count(self.accounts_count)
         );
+        println!(
+            "Accounts matched:     {:>15}",
+            format::count(self.filtered_count)
+        );
+        println!(
+            "Total uncompressed:   {:>21}",
+            format::bytes_with_unit(self.total_uncompressed)
+        );
+        println!(
+            "Total compressed:     {:>21}",
+            format::bytes_with_unit(total_compressed)
+        );
+        println!("Compression ratio:    {:>15.4}", ratio);
+        println!("Space savings:        {:>14.2}%", (1.0 - ratio) * 100.0);
     }
 
     pub fn finish(&self) {
+        self.accounts_spinner.set_position(self.accounts_count);
         self.accounts_spinner.finish();
     }
 }
 
 pub struct CompressionBenchmarkConsumer {
     stats: BenchmarkStats,
-    owner_filter: Option<Pubkey>,
+    owner_filter: Option<HashSet<Pubkey>>,
     encoder: Option<Encoder<'static, CountingSink>>,
 }
 
 impl CompressionBenchmarkConsumer {
-    pub fn new(owner_filter: Option<Pubkey>, compression_level: i32) -> Self {
+    pub fn new(owner_filter: Option<HashSet<Pubkey>>, compression_level: i32) -> Self {
         let encoder = Encoder::new(CountingSink::new(), compression_level)
             .expect("Failed to create zstd encoder");
 
@@ -127,11 +141,10 @@ impl CompressionBenchmarkConsumer {
 }
 
 impl AppendVecConsumer for CompressionBenchmarkConsumer {
-    fn on_append_vec(&mut self, append_vec: AppendVec) -> GenericResult<()> {
+    fn on_append_vec(&mut self, append_vec: &AppendVec) -> GenericResult<()> {
         let encoder = self.encoder.as_mut().expect("encoder already finished");
 
-        for account in append_vec_iter(Rc::new(append_vec)) {
-            let account = account.access().unwrap();
+        for account in append_vec_iter(append_vec) {
             self.stats.accounts_count += 1;
 
             if self.stats.accounts_count % 1024 == 0 {
@@ -141,8 +154,8 @@ impl AppendVecConsumer for CompressionBenchmarkConsumer {
             }
 
             // Filter by owner (if specified)
-            if let Some(owner_filter) = self.owner_filter {
-                if account.account_meta.owner != owner_filter {
+            if let Some(owner_filter) = &self.owner_filter {
+                if !owner_filter.contains(&account.account_meta.owner) {
                     continue;
                 }
             }
@@ -138,6 +138,11 @@ pub struct TokenAccountCompressor {
 }
 
 impl TokenAccountCompressor {
+    /// Pubkey at `position` in the pubkey list.
+    pub fn pubkey(&self, position: usize) -> Pubkey {
+        self.state.pubkey_list[position].into()
+    }
+
     fn get_or_insert_pubkey_position(&mut self, pubkey: PubkeyBytes) -> usize {
         if let Some(&position) = self.pubkey_position.get(&pubkey) {
             position
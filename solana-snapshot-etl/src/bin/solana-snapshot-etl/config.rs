@@ -0,0 +1,99 @@
+//! `--config` files: TOML defaults for command-line options.
+//!
+//! Top-level keys are long option names, or `source` for the snapshot source, and tables
+//! hold the options of the subcommand they are named after:
+//!
+//! ```toml
+//! source = "/mnt/snapshots/snapshot-250000000-abc.tar.zst"
+//! threads = 16
+//! min-lamports = 1000000
+//!
+//! [dump-tokens]
+//! db = "/data/tokens.duckdb"
+//! ```
+//!
+//! Options given on the command line take precedence over the file.
+
+use clap::Command;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Returns the `--config` path given in `args`. It is needed before parsing, since the
+/// file changes the parser's defaults.
+pub fn path_from_args(args: &[OsString]) -> Option<PathBuf> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// Reads the file at `path` and makes its values the defaults of `command`.
+pub fn apply(command: Command, path: &Path) -> Result<Command, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    let table: toml::value::Table = toml::from_str(&contents)?;
+    apply_table(command, &table).map_err(Into::into)
+}
+
+fn apply_table(mut command: Command, table: &toml::value::Table) -> Result<Command, String> {
+    for (key, value) in table {
+        if let toml::Value::Table(options) = value {
+            let subcommand = command
+                .find_subcommand(key)
+                .cloned()
+                .ok_or_else(|| format!("unknown command [{}]", key))?;
+            let subcommand =
+                apply_table(subcommand, options).map_err(|e| format!("{} (in [{}])", e, key))?;
+            command = command.mut_subcommand(key, |_| subcommand);
+            continue;
+        }
+
+        let id = command
+            .get_arguments()
+            .find(|arg| {
+                arg.get_long() == Some(key.as_str())
+                    || (arg.is_positional() && arg.get_id() == key.as_str())
+            })
+            .map(|arg| arg.get_id().clone())
+            .ok_or_else(|| format!("unknown option '{}'", key))?;
+        let values = default_values(value).ok_or_else(|| {
+            format!(
+                "'{}' must be a string, number, boolean or array of these",
+                key
+            )
+        })?;
+        // Options the file provides are no longer required on the command line
+        command = command.mut_arg(id, |arg| {
+            arg.default_values(values)
+                .required(false)
+                .required_unless_present(None)
+        });
+    }
+    Ok(command)
+}
+
+fn default_values(value: &toml::Value) -> Option<Vec<String>> {
+    match value {
+        toml::Value::Array(items) => items.iter().map(scalar).collect(),
+        value => Some(vec![scalar(value)?]),
+    }
+}
+
+fn scalar(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Integer(n) => Some(n.to_string()),
+        toml::Value::Float(n) => Some(n.to_string()),
+        toml::Value::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,66 @@
+use crate::token::{MINT_ACCOUNT_LEN, TOKEN_ACCOUNT_LEN, TOKEN_PROGRAM_ID};
+use serde_json::{json, Value};
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec::StoredAccountMeta;
+use spl_token::state::{Account, Mint};
+use std::str::FromStr;
+
+fn opt_pubkey(value: COption<Pubkey>) -> Value {
+    Option::<Pubkey>::from(value)
+        .map(|pubkey| Value::String(pubkey.to_string()))
+        .unwrap_or(Value::Null)
+}
+
+/// Decodes account data for known account types.
+pub fn decode(account: &StoredAccountMeta) -> Option<Value> {
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
+    if account.account_meta.owner != token_program {
+        return None;
+    }
+    match account.data.len() {
+        TOKEN_ACCOUNT_LEN => {
+            let token_account = Account::unpack(account.data).ok()?;
+            Some(json!({
+                "type": "token_account",
+                "mint": token_account.mint.to_string(),
+                "owner": token_account.owner.to_string(),
+                "amount": token_account.amount,
+                "delegate": opt_pubkey(token_account.delegate),
+                "state": format!("{:?}", token_account.state),
+                "is_native": Option::<u64>::from(token_account.is_native),
+                "delegated_amount": token_account.delegated_amount,
+                "close_authority": opt_pubkey(token_account.close_authority),
+            }))
+        }
+        MINT_ACCOUNT_LEN => {
+            let mint = Mint::unpack(account.data).ok()?;
+            Some(json!({
+                "type": "mint",
+                "mint_authority": opt_pubkey(mint.mint_authority),
+                "supply": mint.supply,
+                "decimals": mint.decimals,
+                "is_initialized": mint.is_initialized,
+                "freeze_authority": opt_pubkey(mint.freeze_authority),
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Renders an account's metadata, plus decoded data where the type is known.
+pub fn account_json(account: &StoredAccountMeta, slot: u64) -> Value {
+    json!({
+        "pubkey": account.meta.pubkey.to_string(),
+        "owner": account.account_meta.owner.to_string(),
+        "lamports": account.account_meta.lamports,
+        "rent_epoch": account.account_meta.rent_epoch,
+        "executable": account.account_meta.executable,
+        "data_len": account.data.len(),
+        "slot": slot,
+        "write_version": account.meta.write_version,
+        "hash": account.hash.to_string(),
+        "decoded": decode(account),
+    })
+}
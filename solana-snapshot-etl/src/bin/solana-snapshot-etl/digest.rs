@@ -0,0 +1,93 @@
+use log::info;
+use solana_sdk::hash::Hasher;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Parses a hex-encoded SHA-256 digest.
+pub fn parse_sha256(hex: &str) -> Result<[u8; 32], String> {
+    let bytes = hex.as_bytes();
+    if bytes.len() != 64 {
+        return Err(format!("expected 64 hex characters, got {}", bytes.len()));
+    }
+    let mut digest = [0u8; 32];
+    for (byte, pair) in digest.iter_mut().zip(bytes.chunks(2)) {
+        let pair = std::str::from_utf8(pair).map_err(|e| e.to_string())?;
+        *byte =
+            u8::from_str_radix(pair, 16).map_err(|_| format!("invalid hex digit in {}", hex))?;
+    }
+    Ok(digest)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SHA-256 digest an archive is expected to have, shared with the [`VerifyingReader`]
+/// checking it.
+#[derive(Clone)]
+pub struct ExpectedDigest {
+    digest: [u8; 32],
+    verified: Arc<AtomicBool>,
+}
+
+impl ExpectedDigest {
+    pub fn new(digest: [u8; 32]) -> Self {
+        Self {
+            digest,
+            verified: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Whether the archive was read to the end and matched the digest.
+    pub fn is_verified(&self) -> bool {
+        self.verified.load(Ordering::Acquire)
+    }
+}
+
+/// Computes the SHA-256 digest of everything read, failing the read that hits
+/// the end of the stream if it doesn't match the expected digest. A stream that isn't
+/// read to the end is never verified, which [`ExpectedDigest::is_verified`] tells.
+pub struct VerifyingReader<R: Read> {
+    inner: R,
+    hasher: Option<Hasher>,
+    expected: ExpectedDigest,
+}
+
+impl<R: Read> VerifyingReader<R> {
+    pub fn new(inner: R, expected: ExpectedDigest) -> Self {
+        Self {
+            inner,
+            hasher: Some(Hasher::default()),
+            expected,
+        }
+    }
+}
+
+impl<R: Read> Read for VerifyingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            if let Some(hasher) = &mut self.hasher {
+                hasher.hash(&buf[..n]);
+            }
+        } else if !buf.is_empty() {
+            if let Some(hasher) = self.hasher.take() {
+                let actual = hasher.result().to_bytes();
+                if actual != self.expected.digest {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Archive SHA-256 mismatch: expected {}, got {}",
+                            to_hex(&self.expected.digest),
+                            to_hex(&actual)
+                        ),
+                    ));
+                }
+                self.expected.verified.store(true, Ordering::Release);
+                info!("Archive SHA-256 verified: {}", to_hex(&actual));
+            }
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,727 @@
+use log::{info, warn};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{
+    HeaderName, HeaderValue, ACCEPT_RANGES, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE,
+};
+use reqwest::StatusCode;
+use solana_sdk::hash::hash;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Size of the byte ranges fetched by each thread of a segmented download.
+const SEGMENT_SIZE: u64 = 32 * 1024 * 1024;
+
+/// How HTTP sources are fetched.
+#[derive(Clone, Debug, Default)]
+pub struct DownloadOptions {
+    /// Number of parallel range requests
+    pub threads: usize,
+    /// Directory keeping completed downloads, keyed by [`RemoteArchive`]
+    pub cache_dir: Option<PathBuf>,
+    /// Cap on the download rate in bytes per second
+    pub max_rate: Option<u64>,
+    pub retry: RetryPolicy,
+}
+
+/// How failed HTTP requests and dropped connections are retried.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Retries allowed without any bytes being read in between
+    pub attempts: u32,
+    /// Delay before the first retry, doubled for each further one
+    pub initial_delay: Duration,
+}
+
+impl RetryPolicy {
+    fn delay(&self, retries: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2u32.saturating_pow(retries))
+            .min(MAX_RETRY_DELAY)
+    }
+
+    /// Sends the request built by `request`, retrying transient failures.
+    fn send(&self, request: impl Fn() -> RequestBuilder) -> reqwest::Result<Response> {
+        let mut retries = 0;
+        loop {
+            match request().send().and_then(Response::error_for_status) {
+                Err(e) if retries < self.attempts && is_transient(&e) => {
+                    warn!(
+                        "Request failed (attempt {}/{}): {}",
+                        retries + 1,
+                        self.attempts,
+                        e
+                    );
+                    thread::sleep(self.delay(retries));
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Whether a failed request may succeed when retried: server errors, rate limiting,
+/// timeouts and connection failures.
+fn is_transient(e: &reqwest::Error) -> bool {
+    !e.is_builder()
+        && !e.is_redirect()
+        && e.status().map_or(true, |status| {
+            status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+        })
+}
+
+/// Like [`is_transient`], for errors surfaced while reading a response body.
+fn is_transient_io(e: &io::Error) -> bool {
+    e.get_ref()
+        .and_then(|inner| inner.downcast_ref::<reqwest::Error>())
+        .map_or(true, is_transient)
+}
+
+/// Checks that a `206 Partial Content` response starts at byte `start`, as requested.
+fn check_content_range(response: &Response, start: u64) -> io::Result<()> {
+    // Content-Range: bytes <first>-<last>/<length>
+    let first = response
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("bytes "))
+        .and_then(|range| range.split_once('-'))
+        .and_then(|(first, _)| first.parse::<u64>().ok());
+    match first {
+        Some(first) if first == start => Ok(()),
+        Some(first) => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "Server answered a range request for byte {} from byte {}",
+                start, first
+            ),
+        )),
+        None => Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Server answered a range request without a valid Content-Range",
+        )),
+    }
+}
+
+/// Parses a rate in bytes per second, with an optional K, M or G suffix (powers of 1024).
+pub fn parse_rate(rate: &str) -> Result<u64, String> {
+    let (digits, multiplier) = match rate.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&rate[..rate.len() - 1], 1 << 10),
+        Some('M') => (&rate[..rate.len() - 1], 1 << 20),
+        Some('G') => (&rate[..rate.len() - 1], 1 << 30),
+        _ => (rate, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|n| *n > 0)
+        .ok_or_else(|| format!("invalid rate '{}', expected e.g. 500K, 50M or 1G", rate))
+}
+
+/// Parses a non-negative, possibly fractional number of seconds.
+pub fn parse_seconds(secs: &str) -> Result<Duration, String> {
+    secs.parse::<f64>()
+        .ok()
+        .and_then(|n| Duration::try_from_secs_f64(n).ok())
+        .ok_or_else(|| {
+            format!(
+                "invalid duration '{}', expected seconds e.g. 1 or 0.5",
+                secs
+            )
+        })
+}
+
+/// The archive a URL refers to: the URL it redirects to, with the ETag, modification
+/// time and length the server reported. Keys cached downloads, so that a URL serving the
+/// latest snapshot isn't answered with an older one from the cache.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemoteArchive {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_length: Option<u64>,
+}
+
+impl RemoteArchive {
+    fn from_response(response: &Response) -> Self {
+        let header = |name: HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            url: response.url().to_string(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            content_length: response.content_length(),
+        }
+    }
+
+    /// Asks the server which archive `url` currently refers to, with a HEAD request.
+    pub fn probe(url: &str, retry: RetryPolicy) -> Option<Self> {
+        let client = Client::new();
+        match retry.send(|| client.head(url)) {
+            Ok(response) => Some(Self::from_response(&response)),
+            Err(e) => {
+                warn!("HEAD request failed, not using cached downloads: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Path at which a download of `archive` is cached in `cache_dir`. None if the server
+/// reported neither an ETag nor a modification time: archives published under the same
+/// URL often have the same length, which alone can't tell them apart.
+pub fn cache_path(cache_dir: &Path, archive: &RemoteArchive) -> Option<PathBuf> {
+    if archive.etag.is_none() && archive.last_modified.is_none() {
+        return None;
+    }
+    let file_name = reqwest::Url::parse(&archive.url)
+        .ok()
+        .and_then(|url| {
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back().map(str::to_string))
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "snapshot".to_string());
+    let key = format!(
+        "{}\n{}\n{}\n{}",
+        archive.url,
+        archive.etag.as_deref().unwrap_or_default(),
+        archive.last_modified.as_deref().unwrap_or_default(),
+        archive
+            .content_length
+            .map(|len| len.to_string())
+            .unwrap_or_default()
+    );
+    let key_hash = hash(key.as_bytes()).to_string();
+    Some(cache_dir.join(format!("{}-{}", &key_hash[..16], file_name)))
+}
+
+/// An opened HTTP source.
+pub enum Download {
+    /// A completed download of the same archive, kept in the cache directory
+    Cached(PathBuf),
+    /// The body, along with its length if the server reported one
+    Stream(Box<dyn Read + Send>, Option<u64>),
+}
+
+/// Opens an HTTP source, splitting it into byte ranges fetched by `options.threads`
+/// threads if there is more than one and the server supports range requests.
+/// With a cache directory, returns an earlier download of the same archive if there is
+/// one, and otherwise also writes the raw bytes to [`cache_path`].
+pub fn open(url: &str, options: &DownloadOptions) -> Result<Download, Box<dyn std::error::Error>> {
+    if let Some(cache_dir) = &options.cache_dir {
+        let cached = RemoteArchive::probe(url, options.retry)
+            .and_then(|archive| cache_path(cache_dir, &archive))
+            .filter(|path| path.is_file());
+        if let Some(cached) = cached {
+            return Ok(Download::Cached(cached));
+        }
+    }
+
+    let segmented = if options.threads > 1 {
+        SegmentedDownload::new(url, options.threads, options.retry)
+    } else {
+        None
+    };
+    let (mut download, archive): (Box<dyn Read + Send>, _) = match segmented {
+        Some(download) => {
+            let archive = download.archive.clone();
+            (Box::new(download), archive)
+        }
+        None => {
+            let download = ResumableDownload::new(url, options.retry)?;
+            let archive = download.archive();
+            (Box::new(download), archive)
+        }
+    };
+    if let Some(max_rate) = options.max_rate {
+        download = Box::new(ThrottledReader::new(download, max_rate));
+    }
+    if let Some(cache_dir) = &options.cache_dir {
+        match cache_path(cache_dir, &archive) {
+            Some(path) => {
+                fs::create_dir_all(cache_dir)?;
+                download = Box::new(CachingReader::new(download, path)?);
+            }
+            None => warn!(
+                "Server reported neither an ETag nor a Last-Modified time, not caching the download"
+            ),
+        }
+    }
+    Ok(Download::Stream(download, archive.content_length))
+}
+
+/// HTTP response body that reconnects when the connection drops, resuming
+/// with a `Range: bytes=N-` request at the first byte not yet read.
+///
+/// Reconnects go to the URL the first request was redirected to, and send its ETag in
+/// `If-Range`, so that a download is never resumed from another archive.
+pub struct ResumableDownload {
+    client: Client,
+    /// URL after redirects
+    url: String,
+    etag: Option<HeaderValue>,
+    archive: RemoteArchive,
+    response: Option<Response>,
+    /// Bytes read so far
+    position: u64,
+    content_length: Option<u64>,
+    retry: RetryPolicy,
+    retries: u32,
+}
+
+impl ResumableDownload {
+    pub fn new(url: &str, retry: RetryPolicy) -> reqwest::Result<Self> {
+        let client = Client::new();
+        let response = retry.send(|| client.get(url))?;
+        Ok(Self {
+            client,
+            url: response.url().to_string(),
+            etag: response.headers().get(ETAG).cloned(),
+            archive: RemoteArchive::from_response(&response),
+            content_length: response.content_length(),
+            response: Some(response),
+            position: 0,
+            retry,
+            retries: 0,
+        })
+    }
+
+    /// The archive being downloaded.
+    pub fn archive(&self) -> RemoteArchive {
+        self.archive.clone()
+    }
+
+    fn reconnect(&mut self) -> io::Result<()> {
+        self.response = None;
+        loop {
+            if self.retries >= self.retry.attempts {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "Download failed at byte {} after {} reconnect attempts",
+                        self.position, self.retry.attempts
+                    ),
+                ));
+            }
+            thread::sleep(self.retry.delay(self.retries));
+            self.retries += 1;
+
+            info!(
+                "Resuming download at byte {} (attempt {}/{})",
+                self.position, self.retries, self.retry.attempts
+            );
+            let mut request = self
+                .client
+                .get(&self.url)
+                .header(RANGE, format!("bytes={}-", self.position));
+            if let Some(etag) = &self.etag {
+                request = request.header(IF_RANGE, etag.clone());
+            }
+            match request.send().and_then(Response::error_for_status) {
+                Ok(response) if response.status() == StatusCode::PARTIAL_CONTENT => {
+                    check_content_range(&response, self.position)?;
+                    self.response = Some(response);
+                    return Ok(());
+                }
+                // With If-Range, servers send the whole archive again if it changed
+                Ok(response) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!(
+                            "Server did not resume the download (status {}): the archive changed or range requests aren't supported",
+                            response.status()
+                        ),
+                    ));
+                }
+                Err(e) if is_transient(&e) => warn!("Reconnect failed: {}", e),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::Other, e)),
+            }
+        }
+    }
+}
+
+impl Read for ResumableDownload {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let response = match &mut self.response {
+                Some(response) => response,
+                None => {
+                    self.reconnect()?;
+                    continue;
+                }
+            };
+            match response.read(buf) {
+                Ok(0)
+                    if !buf.is_empty()
+                        && self.content_length.is_some_and(|len| self.position < len) =>
+                {
+                    warn!(
+                        "Connection closed at byte {} of {}",
+                        self.position,
+                        self.content_length.unwrap()
+                    );
+                }
+                Ok(n) => {
+                    if n > 0 {
+                        self.position += n as u64;
+                        self.retries = 0;
+                    }
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => warn!("Download interrupted at byte {}: {}", self.position, e),
+            }
+            self.reconnect()?;
+        }
+    }
+}
+
+/// Segments fetched ahead of the reader, shared between the download threads.
+struct Segments {
+    /// Index of the segment the reader is waiting for
+    next: u64,
+    /// Index of the next segment to hand to a download thread
+    next_to_fetch: u64,
+    done: BTreeMap<u64, io::Result<Vec<u8>>>,
+}
+
+struct SegmentQueue {
+    segments: Mutex<Segments>,
+    changed: Condvar,
+    /// Segments allowed to be fetched ahead of the reader, bounding memory use
+    max_ahead: u64,
+    segment_count: u64,
+}
+
+/// HTTP download split into byte ranges fetched in parallel, read back in order.
+///
+/// Like [`ResumableDownload`], ranges are fetched from the URL the HEAD request was
+/// redirected to, with its ETag in `If-Range`.
+pub struct SegmentedDownload {
+    archive: RemoteArchive,
+    queue: Arc<SegmentQueue>,
+    current: io::Cursor<Vec<u8>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl SegmentedDownload {
+    /// Returns None, for the caller to download in a single stream, if the HEAD request
+    /// fails (some servers don't allow HEAD) or the server doesn't advertise range support
+    /// and a content length.
+    pub fn new(url: &str, threads: usize, retry: RetryPolicy) -> Option<Self> {
+        let client = Client::new();
+        let response = match retry.send(|| client.head(url)) {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("HEAD request failed, downloading in a single stream: {}", e);
+                return None;
+            }
+        };
+        let accepts_ranges = response
+            .headers()
+            .get(ACCEPT_RANGES)
+            .is_some_and(|value| value.as_bytes() == b"bytes");
+        let content_length = match response.content_length() {
+            Some(len) if accepts_ranges => len,
+            _ => {
+                warn!("Server does not support range requests, downloading in a single stream");
+                return None;
+            }
+        };
+        let archive = RemoteArchive::from_response(&response);
+        let url = archive.url.clone();
+        let etag = response.headers().get(ETAG).cloned();
+        info!(
+            "Downloading {} bytes with {} threads in {} MiB segments",
+            content_length,
+            threads,
+            SEGMENT_SIZE / (1024 * 1024)
+        );
+
+        let queue = Arc::new(SegmentQueue {
+            segments: Mutex::new(Segments {
+                next: 0,
+                next_to_fetch: 0,
+                done: BTreeMap::new(),
+            }),
+            changed: Condvar::new(),
+            max_ahead: 2 * threads as u64,
+            segment_count: content_length.div_ceil(SEGMENT_SIZE),
+        });
+        let workers = (0..threads)
+            .map(|_| {
+                let client = client.clone();
+                let url = url.clone();
+                let etag = etag.clone();
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || {
+                    fetch_segments(&client, &url, etag.as_ref(), content_length, &queue, retry)
+                })
+            })
+            .collect();
+
+        Some(Self {
+            archive,
+            queue,
+            current: io::Cursor::new(Vec::new()),
+            workers,
+        })
+    }
+
+    /// Waits for the next segment in order, returning None past the last one.
+    fn next_segment(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut segments = self.queue.segments.lock().unwrap();
+        if segments.next >= self.queue.segment_count {
+            return Ok(None);
+        }
+        loop {
+            let next = segments.next;
+            if let Some(segment) = segments.done.remove(&next) {
+                segments.next += 1;
+                self.queue.changed.notify_all();
+                return segment.map(Some);
+            }
+            segments = self.queue.changed.wait(segments).unwrap();
+        }
+    }
+}
+
+fn fetch_segments(
+    client: &Client,
+    url: &str,
+    etag: Option<&HeaderValue>,
+    content_length: u64,
+    queue: &SegmentQueue,
+    retry: RetryPolicy,
+) {
+    loop {
+        let index = {
+            let mut segments = queue.segments.lock().unwrap();
+            while segments.next_to_fetch < queue.segment_count
+                && segments.next_to_fetch >= segments.next + queue.max_ahead
+            {
+                segments = queue.changed.wait(segments).unwrap();
+            }
+            if segments.next_to_fetch >= queue.segment_count {
+                return;
+            }
+            segments.next_to_fetch += 1;
+            segments.next_to_fetch - 1
+        };
+
+        let start = index * SEGMENT_SIZE;
+        let end = (start + SEGMENT_SIZE).min(content_length);
+        let segment = fetch_range(client, url, etag, start, end, retry);
+        let failed = segment.is_err();
+        queue.segments.lock().unwrap().done.insert(index, segment);
+        queue.changed.notify_all();
+        if failed {
+            return;
+        }
+    }
+}
+
+/// Fetches bytes `start..end`, retrying failed requests.
+fn fetch_range(
+    client: &Client,
+    url: &str,
+    etag: Option<&HeaderValue>,
+    start: u64,
+    end: u64,
+    retry: RetryPolicy,
+) -> io::Result<Vec<u8>> {
+    let mut retries = 0;
+    loop {
+        let mut request = client
+            .get(url)
+            .header(RANGE, format!("bytes={}-{}", start, end - 1));
+        if let Some(etag) = etag {
+            request = request.header(IF_RANGE, etag.clone());
+        }
+        let result = request
+            .send()
+            .and_then(Response::error_for_status)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            .and_then(|mut response| {
+                // With If-Range, servers send the whole archive if it changed
+                if response.status() != StatusCode::PARTIAL_CONTENT {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!(
+                            "Range request answered with status {}: the archive changed or range requests aren't supported",
+                            response.status()
+                        ),
+                    ));
+                }
+                check_content_range(&response, start)?;
+                let mut buf = Vec::with_capacity((end - start) as usize);
+                response.read_to_end(&mut buf)?;
+                if buf.len() as u64 != end - start {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("Expected {} bytes, got {}", end - start, buf.len()),
+                    ));
+                }
+                Ok(buf)
+            });
+        match result {
+            Ok(buf) => return Ok(buf),
+            Err(e) if retries < retry.attempts && is_transient_io(&e) => {
+                warn!(
+                    "Failed to fetch bytes {}-{} (attempt {}/{}): {}",
+                    start,
+                    end - 1,
+                    retries + 1,
+                    retry.attempts,
+                    e
+                );
+                thread::sleep(retry.delay(retries));
+                retries += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+impl Read for SegmentedDownload {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.current.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            match self.next_segment()? {
+                Some(segment) => self.current = io::Cursor::new(segment),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+impl Drop for SegmentedDownload {
+    fn drop(&mut self) {
+        // Stop handing out segments so the download threads exit
+        {
+            let mut segments = self.queue.segments.lock().unwrap();
+            segments.next_to_fetch = self.queue.segment_count;
+        }
+        self.queue.changed.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Copies everything read to a `.partial` file, renamed to its final path once the
+/// stream is read to the end. Archive extractors read their stream to the end after the
+/// last entry. An unfinished `.partial` file is left as is, to be overwritten by the next
+/// download of the same archive.
+pub struct CachingReader<R: Read> {
+    inner: R,
+    file: Option<BufWriter<File>>,
+    partial_path: PathBuf,
+    path: PathBuf,
+}
+
+impl<R: Read> CachingReader<R> {
+    pub fn new(inner: R, path: PathBuf) -> io::Result<Self> {
+        let mut partial_path = path.clone().into_os_string();
+        partial_path.push(".partial");
+        let partial_path = PathBuf::from(partial_path);
+        info!("Caching download to {:?}", path);
+        Ok(Self {
+            inner,
+            file: Some(BufWriter::new(File::create(&partial_path)?)),
+            partial_path,
+            path,
+        })
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if let Some(file) = self.file.take() {
+            file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            fs::rename(&self.partial_path, &self.path)?;
+            info!("Cached download at {:?}", self.path);
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for CachingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            if let Some(file) = &mut self.file {
+                file.write_all(&buf[..n])?;
+            }
+        } else if !buf.is_empty() {
+            self.finish()?;
+        }
+        Ok(n)
+    }
+}
+
+impl<R: Read> Drop for CachingReader<R> {
+    fn drop(&mut self) {
+        if self.file.is_some() {
+            warn!("Download did not complete, not caching {:?}", self.path);
+        }
+    }
+}
+
+/// Limits the rate at which bytes are read from the inner reader, with a token bucket
+/// holding up to one second's worth of bytes. Time spent waiting on a stalled connection
+/// therefore allows a burst of at most one second's worth, not the whole stall's.
+pub struct ThrottledReader<R: Read> {
+    inner: R,
+    bytes_per_sec: u64,
+    /// Bytes that may be read without waiting. Negative after a read overdraws them,
+    /// until the wait for the missing bytes is over
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl<R: Read> ThrottledReader<R> {
+    pub fn new(inner: R, bytes_per_sec: u64) -> Self {
+        Self {
+            inner,
+            bytes_per_sec,
+            tokens: bytes_per_sec as f64,
+            refilled: Instant::now(),
+        }
+    }
+}
+
+impl<R: Read> Read for ThrottledReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Read at most a tenth of a second's worth at a time, to keep the rate smooth
+        let max_len = (self.bytes_per_sec / 10).max(1) as usize;
+        let len = buf.len().min(max_len);
+        let n = self.inner.read(&mut buf[..len])?;
+
+        let rate = self.bytes_per_sec as f64;
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.refilled).as_secs_f64() * rate)
+            .min(rate)
+            - n as f64;
+        self.refilled = now;
+        if self.tokens < 0.0 {
+            thread::sleep(Duration::from_secs_f64(-self.tokens / rate));
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,114 @@
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ELF_HEADER_LEN: usize = 64;
+const SECTION_HEADER_LEN: usize = 64;
+
+/// e_flags bit marking an SBFv2 binary.
+const EF_SBF_V2: u32 = 0x20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SbfVersion {
+    V1,
+    V2,
+}
+
+pub struct Section {
+    pub name: String,
+    pub size: u64,
+}
+
+pub struct ElfInfo {
+    pub sbf_version: SbfVersion,
+    pub sections: Vec<Section>,
+    /// Length of the ELF image; program accounts are usually padded beyond it.
+    pub len: usize,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn read_name(strtab: &[u8], offset: usize) -> String {
+    let name = strtab.get(offset..).unwrap_or(&[]);
+    let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..end]).into_owned()
+}
+
+/// Parses the ELF header and section table. Returns None if the data is not a
+/// little-endian ELF64 image or the section table is out of bounds.
+pub fn parse(data: &[u8]) -> Option<ElfInfo> {
+    if data.len() < ELF_HEADER_LEN
+        || &data[0..4] != ELF_MAGIC
+        || data[4] != ELFCLASS64
+        || data[5] != ELFDATA2LSB
+    {
+        return None;
+    }
+
+    let e_flags = read_u32(data, 48)?;
+    let sh_offset = read_u64(data, 40)? as usize;
+    let sh_entsize = read_u16(data, 58)? as usize;
+    let sh_num = read_u16(data, 60)? as usize;
+    let sh_strndx = read_u16(data, 62)? as usize;
+    if sh_entsize != SECTION_HEADER_LEN {
+        return None;
+    }
+
+    let table_end = sh_offset.checked_add(sh_num.checked_mul(sh_entsize)?)?;
+    let table = data.get(sh_offset..table_end)?;
+    let header = |index: usize| &table[index * sh_entsize..(index + 1) * sh_entsize];
+
+    let strtab = if sh_strndx < sh_num {
+        let strtab_header = header(sh_strndx);
+        let offset = read_u64(strtab_header, 24)? as usize;
+        let size = read_u64(strtab_header, 32)? as usize;
+        data.get(offset..offset.checked_add(size)?).unwrap_or(&[])
+    } else {
+        &[]
+    };
+
+    let mut len = table_end;
+    let mut sections = Vec::with_capacity(sh_num);
+    for index in 0..sh_num {
+        let section_header = header(index);
+        let name = read_u32(section_header, 0)? as usize;
+        let sh_type = read_u32(section_header, 4)?;
+        let offset = read_u64(section_header, 24)?;
+        let size = read_u64(section_header, 32)?;
+        // SHT_NOBITS sections (.bss) occupy no space in the file.
+        if sh_type != 8 {
+            len = len.max(offset.saturating_add(size) as usize);
+        }
+        sections.push(Section {
+            name: read_name(strtab, name),
+            size,
+        });
+    }
+
+    let sbf_version = if e_flags & EF_SBF_V2 != 0 {
+        SbfVersion::V2
+    } else {
+        SbfVersion::V1
+    };
+
+    Some(ElfInfo {
+        sbf_version,
+        sections,
+        len: len.min(data.len()),
+    })
+}
@@ -0,0 +1,101 @@
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use std::sync::OnceLock;
+
+/// How numbers are rendered in reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumberFormat {
+    /// Plain integers, suitable for piping into other tools
+    Raw,
+    /// Thousands separators, SI byte suffixes and SOL amounts
+    Human,
+}
+
+static NUMBER_FORMAT: OnceLock<NumberFormat> = OnceLock::new();
+
+/// Sets the report number format for the rest of the process.
+pub fn set_number_format(format: NumberFormat) {
+    let _ = NUMBER_FORMAT.set(format);
+}
+
+fn number_format() -> NumberFormat {
+    *NUMBER_FORMAT.get().unwrap_or(&NumberFormat::Raw)
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// Formats a count, e.g. `1234567` as `1,234,567`.
+pub fn count(n: u64) -> String {
+    count_wide(n.into())
+}
+
+/// Like [`count`], for sums that may not fit in a `u64`.
+pub fn count_wide(n: u128) -> String {
+    match number_format() {
+        NumberFormat::Raw => n.to_string(),
+        NumberFormat::Human => group_thousands(&n.to_string()),
+    }
+}
+
+/// Formats a byte size, e.g. `1234567` as `1.23 MB`.
+pub fn bytes(n: u64) -> String {
+    bytes_wide(n.into())
+}
+
+/// Like [`bytes`], for sums that may not fit in a `u64`.
+pub fn bytes_wide(n: u128) -> String {
+    const UNITS: [&str; 6] = ["kB", "MB", "GB", "TB", "PB", "EB"];
+    match number_format() {
+        NumberFormat::Raw => n.to_string(),
+        NumberFormat::Human if n < 1000 => format!("{} B", n),
+        NumberFormat::Human => {
+            let mut value = n as f64 / 1000.0;
+            let mut unit = 0;
+            while value >= 1000.0 && unit < UNITS.len() - 1 {
+                value /= 1000.0;
+                unit += 1;
+            }
+            format!("{:.2} {}", value, UNITS[unit])
+        }
+    }
+}
+
+/// Like [`bytes`], with the unit spelled out for plain integers, e.g. `1234567 bytes`.
+pub fn bytes_with_unit(n: u64) -> String {
+    match number_format() {
+        NumberFormat::Raw => format!("{} bytes", n),
+        NumberFormat::Human => bytes(n),
+    }
+}
+
+/// Formats a lamport amount, as SOL in human mode.
+pub fn lamports(n: u64) -> String {
+    lamports_wide(n.into())
+}
+
+/// Like [`lamports`], for sums that may not fit in a `u64`.
+pub fn lamports_wide(n: u128) -> String {
+    let lamports_per_sol = LAMPORTS_PER_SOL as u128;
+    match number_format() {
+        NumberFormat::Raw => n.to_string(),
+        NumberFormat::Human => format!(
+            "{}.{:04} SOL",
+            group_thousands(&(n / lamports_per_sol).to_string()),
+            (n % lamports_per_sol) / (lamports_per_sol / 10_000)
+        ),
+    }
+}
+
+/// Formats a signed change with `format_abs` for its magnitude, e.g. `+1,234`.
+pub fn signed(delta: i128, format_abs: fn(u128) -> String) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{}{}", sign, format_abs(delta.unsigned_abs()))
+}
@@ -0,0 +1,186 @@
+use borsh::BorshDeserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::io;
+
+/// SPL Governance instance deployed by Solana Labs. DAOs often deploy their own copy of the
+/// program, so accounts of other instances share the layout.
+pub const GOVERNANCE_PROGRAM_ID: &str = "GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw";
+
+/// `GovernanceAccountType` values of the account kinds that are decoded.
+const REALM_V1: u8 = 1;
+const TOKEN_OWNER_RECORD_V1: u8 = 2;
+const PROPOSAL_V1: u8 = 5;
+const VOTE_RECORD_V1: u8 = 7;
+const VOTE_RECORD_V2: u8 = 12;
+const PROPOSAL_V2: u8 = 14;
+const REALM_V2: u8 = 16;
+const TOKEN_OWNER_RECORD_V2: u8 = 17;
+
+pub struct Realm {
+    pub version: u8,
+    pub community_mint: Pubkey,
+    pub council_mint: Option<Pubkey>,
+    pub authority: Option<Pubkey>,
+    pub name: String,
+}
+
+pub struct TokenOwnerRecord {
+    pub version: u8,
+    pub realm: Pubkey,
+    pub governing_token_mint: Pubkey,
+    pub governing_token_owner: Pubkey,
+    pub governing_token_deposit_amount: u64,
+    pub outstanding_proposal_count: u8,
+    pub governance_delegate: Option<Pubkey>,
+}
+
+pub struct Proposal {
+    pub version: u8,
+    pub governance: Pubkey,
+    pub governing_token_mint: Pubkey,
+    pub state: u8,
+    pub token_owner_record: Pubkey,
+}
+
+pub struct VoteRecord {
+    pub version: u8,
+    pub proposal: Pubkey,
+    pub governing_token_owner: Pubkey,
+    pub is_relinquished: bool,
+    pub vote: &'static str,
+    pub voter_weight: u64,
+}
+
+pub enum GovernanceAccount {
+    Realm(Realm),
+    TokenOwnerRecord(TokenOwnerRecord),
+    Proposal(Proposal),
+    VoteRecord(VoteRecord),
+}
+
+/// Name of a `ProposalState`.
+pub fn proposal_state_name(state: u8) -> &'static str {
+    match state {
+        0 => "Draft",
+        1 => "SigningOff",
+        2 => "Voting",
+        3 => "Succeeded",
+        4 => "Executing",
+        5 => "Completed",
+        6 => "Cancelled",
+        7 => "Defeated",
+        8 => "ExecutingWithErrors",
+        9 => "Vetoed",
+        _ => "Unknown",
+    }
+}
+
+fn skip(data: &mut &[u8], len: usize) -> io::Result<()> {
+    if data.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    *data = &data[len..];
+    Ok(())
+}
+
+fn parse_realm(version: u8, data: &mut &[u8]) -> io::Result<Realm> {
+    let community_mint = Pubkey::deserialize(data)?;
+    // RealmConfig: two legacy bytes, 6 reserved bytes, the minimum community weight to
+    // create a governance (u64) and the max voter weight source (tag and u64)
+    skip(data, 25)?;
+    let council_mint = Option::<Pubkey>::deserialize(data)?;
+    // 6 reserved bytes and the voting proposal count of V1, a legacy field in V2
+    skip(data, 8)?;
+    Ok(Realm {
+        version,
+        community_mint,
+        council_mint,
+        authority: Option::<Pubkey>::deserialize(data)?,
+        name: String::deserialize(data)?,
+    })
+}
+
+fn parse_token_owner_record(version: u8, data: &mut &[u8]) -> io::Result<TokenOwnerRecord> {
+    let realm = Pubkey::deserialize(data)?;
+    let governing_token_mint = Pubkey::deserialize(data)?;
+    let governing_token_owner = Pubkey::deserialize(data)?;
+    let governing_token_deposit_amount = u64::deserialize(data)?;
+    // Vote counts: two u32 in V1, one u64 in V2
+    skip(data, 8)?;
+    let outstanding_proposal_count = u8::deserialize(data)?;
+    // 7 reserved bytes, the first holding the record version in V2
+    skip(data, 7)?;
+    Ok(TokenOwnerRecord {
+        version,
+        realm,
+        governing_token_mint,
+        governing_token_owner,
+        governing_token_deposit_amount,
+        outstanding_proposal_count,
+        governance_delegate: Option::<Pubkey>::deserialize(data)?,
+    })
+}
+
+fn parse_proposal(version: u8, data: &mut &[u8]) -> io::Result<Proposal> {
+    Ok(Proposal {
+        version,
+        governance: Pubkey::deserialize(data)?,
+        governing_token_mint: Pubkey::deserialize(data)?,
+        state: u8::deserialize(data)?,
+        token_owner_record: Pubkey::deserialize(data)?,
+    })
+}
+
+fn parse_vote_record(version: u8, data: &mut &[u8]) -> io::Result<VoteRecord> {
+    let proposal = Pubkey::deserialize(data)?;
+    let governing_token_owner = Pubkey::deserialize(data)?;
+    let is_relinquished = bool::deserialize(data)?;
+    // V1 stores a yes or no weight, V2 the weight followed by the vote
+    let (vote, voter_weight) = if version == 1 {
+        let vote = match u8::deserialize(data)? {
+            0 => "Yes",
+            _ => "No",
+        };
+        (vote, u64::deserialize(data)?)
+    } else {
+        let voter_weight = u64::deserialize(data)?;
+        let vote = match u8::deserialize(data)? {
+            0 => "Approve",
+            1 => "Deny",
+            2 => "Abstain",
+            3 => "Veto",
+            _ => "Unknown",
+        };
+        (vote, voter_weight)
+    };
+    Ok(VoteRecord {
+        version,
+        proposal,
+        governing_token_owner,
+        is_relinquished,
+        vote,
+        voter_weight,
+    })
+}
+
+/// Parses a realm, token owner record, proposal or vote record, or returns `None` for
+/// other governance accounts and data that doesn't deserialize.
+pub fn parse(data: &[u8]) -> Option<GovernanceAccount> {
+    let (&account_type, mut rest) = data.split_first()?;
+    let parsed = match account_type {
+        REALM_V1 => parse_realm(1, &mut rest).map(GovernanceAccount::Realm),
+        REALM_V2 => parse_realm(2, &mut rest).map(GovernanceAccount::Realm),
+        TOKEN_OWNER_RECORD_V1 => {
+            parse_token_owner_record(1, &mut rest).map(GovernanceAccount::TokenOwnerRecord)
+        }
+        TOKEN_OWNER_RECORD_V2 => {
+            parse_token_owner_record(2, &mut rest).map(GovernanceAccount::TokenOwnerRecord)
+        }
+        PROPOSAL_V1 => parse_proposal(1, &mut rest).map(GovernanceAccount::Proposal),
+        PROPOSAL_V2 => parse_proposal(2, &mut rest).map(GovernanceAccount::Proposal),
+        VOTE_RECORD_V1 => parse_vote_record(1, &mut rest).map(GovernanceAccount::VoteRecord),
+        VOTE_RECORD_V2 => parse_vote_record(2, &mut rest).map(GovernanceAccount::VoteRecord),
+        _ => return None,
+    };
+    parsed.ok()
+}
@@ -0,0 +1,97 @@
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use solana_snapshot_etl::append_vec::StoredAccountMeta;
+use solana_snapshot_etl::dedup::LatestVersionIndex;
+use std::collections::hash_map;
+use std::collections::HashMap;
+
+/// Values decoded from the latest live version of each account.
+///
+/// Snapshots keep older versions of accounts in the append vecs of earlier slots, and a
+/// zero-lamport latest version marks a deleted account. Every stored version has to be
+/// offered to [`LatestAccounts::insert`], including those a command isn't interested in,
+/// so that an account that was closed or reassigned hides its older versions.
+pub struct LatestAccounts<T> {
+    versions: LatestVersionIndex,
+    values: HashMap<Pubkey, T>,
+}
+
+impl<T> LatestAccounts<T> {
+    pub fn new() -> Self {
+        Self {
+            versions: LatestVersionIndex::default(),
+            values: HashMap::new(),
+        }
+    }
+
+    /// Records a stored version of an account. If it is the latest version seen so far,
+    /// `value` decodes what is kept for it; accounts for which it returns None, and
+    /// deleted accounts, are dropped.
+    pub fn insert(
+        &mut self,
+        slot: Slot,
+        account: &StoredAccountMeta,
+        value: impl FnOnce() -> Option<T>,
+    ) {
+        let pubkey = account.meta.pubkey;
+        if !self
+            .versions
+            .update(pubkey, slot, account.meta.write_version)
+        {
+            return;
+        }
+        match (account.account_meta.lamports > 0).then(value).flatten() {
+            Some(value) => {
+                self.values.insert(pubkey, value);
+            }
+            None => {
+                self.values.remove(&pubkey);
+            }
+        }
+    }
+
+    pub fn get(&self, pubkey: &Pubkey) -> Option<&T> {
+        self.values.get(pubkey)
+    }
+
+    /// Number of live accounts kept.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn iter(&self) -> hash_map::Iter<'_, Pubkey, T> {
+        self.values.iter()
+    }
+
+    pub fn values(&self) -> hash_map::Values<'_, Pubkey, T> {
+        self.values.values()
+    }
+
+    pub fn into_values(self) -> hash_map::IntoValues<Pubkey, T> {
+        self.values.into_values()
+    }
+}
+
+impl<T> Default for LatestAccounts<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> IntoIterator for LatestAccounts<T> {
+    type Item = (Pubkey, T);
+    type IntoIter = hash_map::IntoIter<Pubkey, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a LatestAccounts<T> {
+    type Item = (&'a Pubkey, &'a T);
+    type IntoIter = hash_map::Iter<'a, Pubkey, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
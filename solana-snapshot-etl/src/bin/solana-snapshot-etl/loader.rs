@@ -1,12 +1,21 @@
+use crate::digest::{ExpectedDigest, VerifyingReader};
+use crate::download::{self, Download, DownloadOptions};
+use crate::progress::{self, ArchiveProgress};
 use indicatif::{ProgressBar, ProgressBarIter, ProgressStyle};
 use log::info;
-use reqwest::blocking::Response;
+use solana_snapshot_etl::append_vec::{AccountMask, OversizedAccounts};
 use solana_snapshot_etl::archived::ArchiveSnapshotExtractor;
+use solana_snapshot_etl::layered::LayeredSnapshotExtractor;
+use solana_snapshot_etl::lenient::{LenientSnapshotExtractor, StorageErrors};
+use solana_snapshot_etl::mask::MappedSnapshotExtractor;
 use solana_snapshot_etl::unpacked::UnpackedSnapshotExtractor;
-use solana_snapshot_etl::{AppendVecIterator, ReadProgressTracking, SnapshotExtractor};
+use solana_snapshot_etl::{
+    AppendVecIterator, ReadProgressTracking, SnapshotExtractor, SnapshotInfo, SnapshotManifest,
+};
 use std::fs::File;
 use std::io::{IoSliceMut, Read};
 use std::path::Path;
+use std::sync::Arc;
 
 pub struct LoadProgressTracking {}
 
@@ -17,13 +26,13 @@ impl ReadProgressTracking for LoadProgressTracking {
         rd: Box<dyn Read>,
         file_len: u64,
     ) -> Box<dyn Read> {
-        let progress_bar = ProgressBar::new(file_len).with_style(
+        let progress_bar = progress::add(ProgressBar::new(file_len).with_style(
             ProgressStyle::with_template(
                 "{prefix:>10.bold.dim} {spinner:.green} [{bar:.cyan/blue}] {bytes}/{total_bytes} ({percent}%)",
             )
             .unwrap()
             .progress_chars("#>-"),
-        );
+        ));
         progress_bar.set_prefix("manifest");
         Box::new(LoadProgressTracker {
             rd: progress_bar.wrap_read(rd),
@@ -63,49 +72,186 @@ impl Read for LoadProgressTracker {
 
 pub enum SupportedLoader {
     Unpacked(UnpackedSnapshotExtractor),
-    ArchiveFile(ArchiveSnapshotExtractor<File>),
-    ArchiveDownload(ArchiveSnapshotExtractor<Response>),
+    ArchiveFile(ArchiveSnapshotExtractor<Box<dyn Read + Send>>),
+    ArchiveDownload(ArchiveSnapshotExtractor<Box<dyn Read + Send>>),
+    Layered(Box<LayeredSnapshotExtractor<SupportedLoader, SupportedLoader>>),
+    Mapped(Box<MappedSnapshotExtractor<SupportedLoader>>),
+    Lenient(Box<LenientSnapshotExtractor<SupportedLoader>>),
 }
 
 impl SupportedLoader {
+    /// Opens `source`. Archives are checked against `expected_sha256` while streaming.
     pub fn new(
         source: &str,
         progress_tracking: Box<dyn ReadProgressTracking>,
+        download_options: &DownloadOptions,
+        expected_sha256: Option<ExpectedDigest>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        if source.starts_with("http://") || source.starts_with("https://") {
-            Self::new_download(source)
+        let (stream, len) = if source.starts_with("http://") || source.starts_with("https://") {
+            match download::open(source, download_options)? {
+                Download::Cached(cached) => {
+                    info!("Using cached download {:?}", cached);
+                    return Self::new_file(&cached, progress_tracking, expected_sha256);
+                }
+                Download::Stream(stream, len) => {
+                    info!("Streaming snapshot from HTTP");
+                    (stream, len)
+                }
+            }
+        } else if source.starts_with("s3://") {
+            Self::open_s3(source)?
+        } else if source.starts_with("gs://") || source.starts_with("az://") {
+            Self::open_cloud(source)?
         } else {
-            Self::new_file(source.as_ref(), progress_tracking).map_err(Into::into)
+            return Self::new_file(source.as_ref(), progress_tracking, expected_sha256);
+        };
+        let stream = Box::new(ArchiveProgress::new(stream, len));
+        let loader = ArchiveSnapshotExtractor::from_reader(verified(stream, expected_sha256))?;
+        Ok(Self::ArchiveDownload(loader))
+    }
+
+    /// Layers an incremental snapshot on top of a full snapshot.
+    pub fn layered(full: Self, incremental: Self) -> solana_snapshot_etl::Result<Self> {
+        info!(
+            "Layering incremental snapshot at slot {} over full snapshot at slot {}",
+            incremental.slot(),
+            full.slot()
+        );
+        Ok(Self::Layered(Box::new(LayeredSnapshotExtractor::new(
+            full,
+            incremental,
+        )?)))
+    }
+
+    /// Returns the full snapshot an incremental snapshot is layered over, if any.
+    pub fn full_snapshot(&self) -> Option<&SupportedLoader> {
+        match self {
+            SupportedLoader::Layered(loader) => Some(loader.full()),
+            SupportedLoader::Mapped(loader) => loader.inner().full_snapshot(),
+            _ => None,
         }
     }
 
-    fn new_download(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let resp = reqwest::blocking::get(url)?;
-        let loader = ArchiveSnapshotExtractor::from_reader(resp)?;
-        info!("Streaming snapshot from HTTP");
-        Ok(Self::ArchiveDownload(loader))
+    /// Hides the accounts matched by `mask` from all iterators.
+    pub fn masked(loader: Self, mask: Arc<dyn AccountMask>) -> Self {
+        Self::Mapped(Box::new(MappedSnapshotExtractor::with_mask(loader, mask)))
+    }
+
+    /// Caps account data lengths, reporting oversized entries to `oversized`.
+    pub fn limited(loader: Self, max_data_len: u64, oversized: Arc<OversizedAccounts>) -> Self {
+        Self::Mapped(Box::new(MappedSnapshotExtractor::new(
+            loader,
+            Box::new(move |append_vec| {
+                append_vec.with_max_data_len(max_data_len, Some(Arc::clone(&oversized)))
+            }),
+        )))
+    }
+
+    /// Skips unreadable append vecs, reporting them and corrupt ones to `errors`.
+    pub fn lenient(loader: Self, errors: Arc<StorageErrors>) -> Self {
+        Self::Lenient(Box::new(LenientSnapshotExtractor::new(loader, errors)))
+    }
+
+    #[cfg(feature = "s3")]
+    fn open_s3(
+        url: &str,
+    ) -> Result<(Box<dyn Read + Send>, Option<u64>), Box<dyn std::error::Error>> {
+        info!("Streaming snapshot from S3");
+        let download = crate::s3::S3Download::new(url)?;
+        let len = download.content_length;
+        Ok((Box::new(download), len))
+    }
+
+    #[cfg(not(feature = "s3"))]
+    fn open_s3(
+        _url: &str,
+    ) -> Result<(Box<dyn Read + Send>, Option<u64>), Box<dyn std::error::Error>> {
+        Err("S3 sources require building with the `s3` feature".into())
+    }
+
+    #[cfg(any(feature = "gcs", feature = "azure"))]
+    fn open_cloud(
+        url: &str,
+    ) -> Result<(Box<dyn Read + Send>, Option<u64>), Box<dyn std::error::Error>> {
+        info!("Streaming snapshot from object storage");
+        let download = crate::cloud::CloudDownload::new(url)?;
+        let len = download.size;
+        Ok((Box::new(download), Some(len)))
+    }
+
+    #[cfg(not(any(feature = "gcs", feature = "azure")))]
+    fn open_cloud(
+        _url: &str,
+    ) -> Result<(Box<dyn Read + Send>, Option<u64>), Box<dyn std::error::Error>> {
+        Err("gs:// and az:// sources require building with the `gcs` or `azure` feature".into())
     }
 
     fn new_file(
         path: &Path,
         progress_tracking: Box<dyn ReadProgressTracking>,
-    ) -> solana_snapshot_etl::Result<Self> {
+        expected_sha256: Option<ExpectedDigest>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         Ok(if path.is_dir() {
+            if expected_sha256.is_some() {
+                return Err("--expected-hash only applies to snapshot archives".into());
+            }
             info!("Reading unpacked snapshot");
             Self::Unpacked(UnpackedSnapshotExtractor::open(path, progress_tracking)?)
         } else {
             info!("Reading snapshot archive");
-            Self::ArchiveFile(ArchiveSnapshotExtractor::open(path)?)
+            let file = File::open(path)?;
+            let len = file.metadata()?.len();
+            let file = Box::new(ArchiveProgress::new(file, Some(len)));
+            Self::ArchiveFile(ArchiveSnapshotExtractor::from_reader(verified(
+                file,
+                expected_sha256,
+            ))?)
         })
     }
 }
 
+/// Wraps `stream` in a SHA-256 check if a digest is expected.
+fn verified(
+    stream: Box<dyn Read + Send>,
+    expected_sha256: Option<ExpectedDigest>,
+) -> Box<dyn Read + Send> {
+    match expected_sha256 {
+        Some(expected) => Box::new(VerifyingReader::new(stream, expected)),
+        None => stream,
+    }
+}
+
 impl SnapshotExtractor for SupportedLoader {
     fn iter(&mut self) -> AppendVecIterator<'_> {
         match self {
             SupportedLoader::Unpacked(loader) => Box::new(loader.iter()),
             SupportedLoader::ArchiveFile(loader) => Box::new(loader.iter()),
             SupportedLoader::ArchiveDownload(loader) => Box::new(loader.iter()),
+            SupportedLoader::Layered(loader) => Box::new(loader.iter()),
+            SupportedLoader::Mapped(loader) => Box::new(loader.iter()),
+            SupportedLoader::Lenient(loader) => Box::new(loader.iter()),
+        }
+    }
+
+    fn manifest(&self) -> &SnapshotManifest {
+        match self {
+            SupportedLoader::Unpacked(loader) => loader.manifest(),
+            SupportedLoader::ArchiveFile(loader) => loader.manifest(),
+            SupportedLoader::ArchiveDownload(loader) => loader.manifest(),
+            SupportedLoader::Layered(loader) => loader.manifest(),
+            SupportedLoader::Mapped(loader) => loader.manifest(),
+            SupportedLoader::Lenient(loader) => loader.manifest(),
+        }
+    }
+
+    fn info(&self) -> &SnapshotInfo {
+        match self {
+            SupportedLoader::Unpacked(loader) => loader.info(),
+            SupportedLoader::ArchiveFile(loader) => loader.info(),
+            SupportedLoader::ArchiveDownload(loader) => loader.info(),
+            SupportedLoader::Layered(loader) => loader.info(),
+            SupportedLoader::Mapped(loader) => loader.info(),
+            SupportedLoader::Lenient(loader) => loader.info(),
         }
     }
 }
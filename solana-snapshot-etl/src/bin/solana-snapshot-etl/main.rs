@@ -1,40 +1,460 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use cmd_dump_tokens::{DumpTokensFormat, DumpTokensOptions, DumpTokensOutput};
+use cmd_sample::SampleSize;
+use cmd_stats::StatsOutputs;
+use cmd_summary::SummaryFormat;
+use digest::ExpectedDigest;
+use download::{DownloadOptions, RetryPolicy};
+use format::NumberFormat;
 use loader::{LoadProgressTracking, SupportedLoader};
-use log::{error, info};
-use solana_sdk::pubkey::Pubkey;
-use std::str::FromStr;
+use log::{error, info, warn};
+use notify::RunReport;
+use progress::ProgressMode;
+use solana_sdk::hash::Hash;
+use solana_snapshot_etl::append_vec::{OversizedAccounts, DEFAULT_MAX_DATA_LEN};
+use solana_snapshot_etl::cache::{
+    variant_name, ArtifactCache, MANIFEST_ARTIFACT, SKIP_INDEX_ARTIFACT,
+};
+use solana_snapshot_etl::dedup::SkipIndex;
+use solana_snapshot_etl::expr::AccountExpr;
+use solana_snapshot_etl::lenient::StorageErrors;
+use solana_snapshot_etl::mask::{AccountFilter, Memcmp, ZeroLamportMask};
+use solana_snapshot_etl::SnapshotExtractor;
+use stats::{StatsFormat, StatsGrouping, StatsSortKey, StatsTableOptions};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+mod batch_appender;
+#[cfg(any(feature = "gcs", feature = "azure"))]
+mod cloud;
+mod cmd_audit;
+mod cmd_bloom_filter;
+mod cmd_compressed_diff;
 mod cmd_compression_benchmark;
 mod cmd_custom_compress;
+mod cmd_dangling_mints;
 mod cmd_debug;
+mod cmd_diff;
+mod cmd_dump_governance;
+mod cmd_dump_metadata;
+mod cmd_dump_sns;
+mod cmd_dump_stakes;
+mod cmd_dump_sysvars;
 mod cmd_dump_tokens;
+mod cmd_dump_votes;
+mod cmd_duplicates;
+mod cmd_elf_analysis;
+mod cmd_features;
+mod cmd_frozen_accounts;
+mod cmd_histogram;
+mod cmd_holders;
+mod cmd_index;
+mod cmd_info;
+mod cmd_largest_accounts;
+mod cmd_lookup;
+mod cmd_merkle_trees;
+mod cmd_nft_collections;
+mod cmd_nft_mints;
+mod cmd_nonce_accounts;
+#[cfg(feature = "wasm")]
+mod cmd_plugin;
+mod cmd_programs;
+mod cmd_rent;
+mod cmd_richlist;
+mod cmd_sample;
+mod cmd_sample_bundle;
+mod cmd_schema_stats;
+#[cfg(feature = "scripting")]
+mod cmd_script;
+mod cmd_stake_hygiene;
 mod cmd_stats;
+mod cmd_summary;
+mod cmd_token_audit;
+mod cmd_token_extensions;
+mod cmd_trend;
+mod cmd_verify;
+mod cmd_wealth;
 mod compression_benchmark;
 mod compressor;
+mod config;
+mod decode;
+mod digest;
+mod download;
+mod elf;
+mod format;
+mod governance;
+mod latest;
 mod loader;
+mod merkle_tree;
 mod mpl_metadata;
+mod notify;
+mod owners;
+mod programs;
+mod progress;
+mod pseudonym;
+mod rpc;
+#[cfg(feature = "s3")]
+mod s3;
+mod sampling;
+mod schema;
+mod sketch;
+mod sns;
+mod stake;
 mod stats;
 mod token;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
-    #[clap(help = "Snapshot source (unpacked snapshot, archive file, or HTTP link)")]
-    source: String,
+    #[clap(
+        required_unless_present = "from_rpc",
+        help = "Snapshot source (unpacked snapshot, archive file, HTTP link, or s3://, gs:// or az:// URL)"
+    )]
+    source: Option<String>,
+
+    #[clap(
+        long,
+        conflicts_with_all = ["source", "incremental"],
+        help = "Stream the latest full and incremental snapshots served by this RPC node"
+    )]
+    from_rpc: Option<String>,
+
+    #[clap(
+        long,
+        help = "Incremental snapshot to layer on top of the full snapshot source"
+    )]
+    incremental: Option<String>,
+
+    #[clap(
+        long,
+        help = "Only process the latest version of each account (reads the snapshot twice, unless an earlier run cached its skip index)"
+    )]
+    dedup: bool,
+
+    #[clap(long, help = "Skip zero-lamport accounts, which mark deleted accounts")]
+    skip_zero_lamports: bool,
+
+    #[clap(
+        long,
+        default_value_t = DEFAULT_MAX_DATA_LEN,
+        help = "Treat accounts claiming more data than this many bytes as corrupt and skip them (defaults to the runtime's 10 MiB limit)"
+    )]
+    max_data_len: u64,
+
+    #[clap(
+        long,
+        help = "Skip append vecs of the full snapshot that fail to load and report them at the end instead of aborting (disables the artifact cache)"
+    )]
+    lenient: bool,
+
+    #[clap(
+        long,
+        conflicts_with = "non_executable",
+        help = "Only process executable accounts"
+    )]
+    executable_only: bool,
+
+    #[clap(
+        long,
+        visible_alias = "non-executable-only",
+        help = "Only process non-executable accounts"
+    )]
+    non_executable: bool,
+
+    #[clap(
+        long,
+        conflicts_with_all = ["rent_exempt_only", "rent_paying_only"],
+        help = "Only process accounts with this rent epoch"
+    )]
+    rent_epoch: Option<u64>,
+
+    #[clap(
+        long,
+        conflicts_with = "rent_paying_only",
+        help = "Only process rent-exempt accounts (rent epoch u64::MAX)"
+    )]
+    rent_exempt_only: bool,
+
+    #[clap(long, help = "Only process rent-paying accounts")]
+    rent_paying_only: bool,
+
+    #[clap(long, help = "Only process accounts with at least this many lamports")]
+    min_lamports: Option<u64>,
+
+    #[clap(long, help = "Only process accounts with at most this many lamports")]
+    max_lamports: Option<u64>,
+
+    #[clap(
+        long,
+        value_delimiter = ',',
+        help = "Skip accounts owned by these pubkeys (repeatable, comma-separated or @file)"
+    )]
+    exclude_owner: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Only process accounts with at least this many data bytes"
+    )]
+    min_data_size: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Only process accounts with at most this many data bytes (unlike --max-data-len, larger accounts are valid, just hidden)"
+    )]
+    max_data_size: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Only process accounts whose data has these base58 bytes at this offset, as offset:bytes (repeatable)"
+    )]
+    memcmp: Vec<Memcmp>,
+
+    #[clap(
+        long = "where",
+        value_name = "EXPR",
+        help = "Only process accounts matching this expression, e.g. \"owner = '<pubkey>' AND data_len > 1000\""
+    )]
+    where_expr: Option<AccountExpr>,
+
+    #[clap(
+        long,
+        help = "Worker threads for commands that process accounts in parallel (defaults to half the CPUs)"
+    )]
+    threads: Option<usize>,
+
+    #[clap(
+        long,
+        default_value_t = 1,
+        help = "Download HTTP sources in parallel byte ranges with this many threads"
+    )]
+    download_threads: usize,
+
+    #[clap(
+        long,
+        value_parser = download::parse_rate,
+        help = "Limit HTTP downloads to this many bytes per second (e.g. 50M)"
+    )]
+    max_download_rate: Option<u64>,
+
+    #[clap(
+        long,
+        default_value_t = 10,
+        help = "Retry failed HTTP requests and dropped connections this many times"
+    )]
+    http_retries: u32,
+
+    #[clap(
+        long,
+        default_value = "1",
+        value_parser = download::parse_seconds,
+        help = "Seconds to wait before the first HTTP retry, doubled for each further one"
+    )]
+    http_retry_backoff: Duration,
+
+    #[clap(
+        long,
+        help = "Keep HTTP downloads in this directory and reuse them for the same URL"
+    )]
+    cache_dir: Option<PathBuf>,
+
+    // The hash in snapshot file names is the bank's snapshot hash, not a digest of the archive
+    #[clap(
+        long,
+        value_parser = digest::parse_sha256,
+        help = "Fail unless the source archive has this hex-encoded SHA-256 digest"
+    )]
+    expected_hash: Option<[u8; 32]>,
+
+    #[clap(
+        long,
+        help = "Disable the derived artifact cache (~/.cache/solana-snapshot-etl)"
+    )]
+    no_cache: bool,
+
+    #[clap(
+        long,
+        conflicts_with = "raw",
+        help = "Render numbers with thousands separators, SI sizes and SOL amounts"
+    )]
+    human: bool,
+
+    #[clap(
+        long,
+        help = "Render numbers as plain integers, overriding human = true in a --config file"
+    )]
+    raw: bool,
+
+    #[clap(long, help = "Don't show progress")]
+    quiet: bool,
+
+    #[clap(
+        long,
+        value_enum,
+        default_value = "bars",
+        help = "How to show progress"
+    )]
+    progress: ProgressMode,
+
+    #[clap(
+        long,
+        default_value = "10",
+        value_parser = download::parse_seconds,
+        help = "Seconds between JSON progress lines with --progress json"
+    )]
+    progress_interval: Duration,
+
+    #[clap(
+        long,
+        help = "POST a JSON summary of the run to this URL when it finishes or fails"
+    )]
+    notify_url: Option<String>,
+
+    #[clap(
+        long,
+        help = "TOML file with default option values; command-line options take precedence"
+    )]
+    config: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "JSON file mapping program IDs to names, extending the built-in owner labels"
+    )]
+    program_labels: Option<PathBuf>,
 
     #[clap(subcommand)]
     command: Command,
 }
 
+impl Args {
+    fn source(&self) -> &str {
+        self.source
+            .as_deref()
+            .expect("source is required unless --from-rpc fills it in")
+    }
+}
+
+impl Command {
+    /// Commands reporting on the latest version of each account. A filter applied before that
+    /// version is picked would make an older one look latest.
+    fn uses_latest_versions(&self) -> bool {
+        matches!(
+            self,
+            Command::DumpGovernance { .. }
+                | Command::DumpSns { .. }
+                | Command::DumpStakes { .. }
+                | Command::DumpSysvars
+                | Command::DumpVotes { .. }
+                | Command::ElfAnalysis { .. }
+                | Command::Programs { .. }
+                | Command::DanglingMints { .. }
+                | Command::FrozenAccounts { .. }
+                | Command::Holders { .. }
+                | Command::MerkleTrees { .. }
+                | Command::NftCollections { .. }
+                | Command::NftMints { .. }
+                | Command::NonceAccounts { .. }
+                | Command::Features { .. }
+                | Command::StakeHygiene { .. }
+                | Command::BloomFilter { .. }
+                | Command::Wealth { .. }
+                | Command::Richlist { .. }
+                | Command::LargestAccounts { .. }
+                | Command::Rent { .. }
+                | Command::SchemaStats { .. }
+                | Command::TokenAudit { .. }
+                | Command::TokenExtensions { .. }
+        )
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Command {
+    /// Print the snapshot manifest fields without reading any accounts
+    Info,
+
     /// Collect and display account statistics by owner
-    Stats,
+    Stats {
+        #[clap(
+            long,
+            value_enum,
+            default_value = "owner",
+            help = "Group accounts by owner, or by owner category from the program labels"
+        )]
+        group_by: StatsGrouping,
+
+        #[clap(
+            long,
+            value_enum,
+            default_value = "size",
+            help = "Sort owners by this column"
+        )]
+        sort: StatsSortKey,
+
+        #[clap(long, default_value = "100", help = "Number of owners to list")]
+        top: usize,
+
+        #[clap(
+            long,
+            default_value = "0",
+            help = "Fold owners with fewer accounts into an \"other\" row"
+        )]
+        min_count: u64,
+
+        #[clap(
+            long,
+            default_value = "0",
+            help = "Fold owners with less data (in bytes) into an \"other\" row"
+        )]
+        min_bytes: u64,
+
+        #[clap(
+            long,
+            help = "Also write the stats of all owners to this file, ignoring --top and the minimums"
+        )]
+        output: Option<String>,
+
+        #[clap(
+            long,
+            value_enum,
+            default_value = "csv",
+            help = "Format of the --output file"
+        )]
+        format: StatsFormat,
+
+        #[clap(long, help = "Also report P50/P90/max data length per owner")]
+        size_percentiles: bool,
+
+        #[clap(
+            long,
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Checkpoint the stats to the artifact cache every N million accounts, so an interrupted run can be resumed"
+        )]
+        checkpoint_every: Option<u64>,
+
+        #[clap(
+            long,
+            help = "Also append the stats of all owners to the owner_stats table of this DuckDB database, keyed by slot"
+        )]
+        db: Option<String>,
+    },
+
+    /// Print headline numbers: slot, epoch, account count, data size and top owners
+    Summary {
+        #[clap(long, value_enum, default_value = "text", help = "Output format")]
+        format: SummaryFormat,
+    },
 
     /// Benchmark zstd compression for accounts owned by a specific program
     CompressionBenchmark {
-        #[clap(long, help = "Filter accounts by this owner pubkey")]
-        owner: String,
+        #[clap(
+            long,
+            required = true,
+            value_delimiter = ',',
+            help = "Only include accounts owned by these pubkeys, or \"all\" (repeatable, comma-separated or @file)"
+        )]
+        owner: Vec<String>,
 
         #[clap(long, default_value = "3", help = "Zstd compression level (1-22)")]
         level: i32,
@@ -42,15 +462,141 @@ enum Command {
 
     /// Print a few sample accounts filtered by owner and exit
     Debug {
-        #[clap(long, help = "Filter accounts by this owner pubkey")]
-        owner: String,
+        #[clap(
+            long,
+            required = true,
+            value_delimiter = ',',
+            help = "Print accounts owned by any of these pubkeys (repeatable, comma-separated or @file)"
+        )]
+        owner: Vec<String>,
 
         #[clap(long, default_value = "5", help = "Number of accounts to print")]
         count: usize,
     },
 
+    /// Print every stored version of specific accounts, with full metadata and data
+    Lookup {
+        #[clap(
+            required = true,
+            value_delimiter = ',',
+            help = "Pubkeys to look up (comma-separated or @file)"
+        )]
+        pubkeys: Vec<String>,
+    },
+
+    /// Build or query an on-disk pubkey index for point lookups
+    Index {
+        #[clap(subcommand)]
+        command: IndexCommand,
+    },
+
     /// Dump all token accounts to a DuckDB database
     DumpTokens {
+        #[clap(long, help = "Path to the DuckDB database file, for --format duckdb")]
+        db: Option<String>,
+
+        #[clap(
+            long,
+            value_enum,
+            default_value = "duckdb",
+            help = "Write a DuckDB database or Parquet files"
+        )]
+        format: DumpTokensFormat,
+
+        #[clap(
+            long,
+            help = "Directory to write a directory of Parquet files per table to, for --format parquet"
+        )]
+        out_dir: Option<PathBuf>,
+
+        #[clap(
+            long,
+            help = "JSON5 file selecting and renaming output columns per table"
+        )]
+        schema: Option<String>,
+
+        #[clap(
+            long,
+            help = "Replace pubkeys with tokens hashed with the key in this file, consistent across tables (the key may also be set in SOLANA_SNAPSHOT_ETL_HASH_KEY)"
+        )]
+        hash_key_file: Option<PathBuf>,
+
+        #[clap(
+            long,
+            default_value = "1000000",
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Commit the rows written so far every N rows"
+        )]
+        flush_every: u64,
+
+        #[clap(
+            long,
+            default_value = "50000",
+            value_parser = clap::value_parser!(usize).range(1..),
+            help = "Rows buffered in memory per table before they are appended as one batch"
+        )]
+        batch_rows: usize,
+
+        #[clap(
+            long,
+            help = "Index token_accounts by mint, owner and pubkey after loading it"
+        )]
+        create_indexes: bool,
+
+        #[clap(
+            long,
+            help = "Append rows with a snapshot_slot column instead of replacing the tables, replacing only rows of the same slot"
+        )]
+        append: bool,
+
+        #[clap(
+            long,
+            default_value = "0",
+            help = "Skip token accounts holding less than this amount, in the mint's base units"
+        )]
+        min_amount: u64,
+    },
+
+    /// Dump SPL Governance realms, token owner records, proposals and votes to a DuckDB database
+    DumpGovernance {
+        #[clap(long, help = "Path to the DuckDB database file")]
+        db: String,
+
+        #[clap(
+            long = "program",
+            value_delimiter = ',',
+            default_value = governance::GOVERNANCE_PROGRAM_ID,
+            help = "Governance program instances to dump (repeatable, comma-separated or @file)"
+        )]
+        programs: Vec<String>,
+    },
+
+    /// Dump Metaplex metadata and edition accounts to a DuckDB database
+    DumpMetadata {
+        #[clap(long, help = "Path to the DuckDB database file")]
+        db: String,
+    },
+
+    /// Dump .sol domains of the Name Service with their owners to a DuckDB database
+    DumpSns {
+        #[clap(long, help = "Path to the DuckDB database file")]
+        db: String,
+    },
+
+    /// Dump stake accounts and the stake delegated to every vote account to a DuckDB database
+    DumpStakes {
+        #[clap(long, help = "Path to the DuckDB database file")]
+        db: String,
+
+        #[clap(long, default_value = "20", help = "Number of vote accounts to print")]
+        top: usize,
+    },
+
+    /// Print the Clock, Rent, EpochSchedule, SlotHashes and StakeHistory sysvars as JSON
+    DumpSysvars,
+
+    /// Dump vote accounts and their epoch credits to a DuckDB database
+    DumpVotes {
         #[clap(long, help = "Path to the DuckDB database file")]
         db: String,
     },
@@ -63,55 +609,1166 @@ enum Command {
         #[clap(long, help = "Maximum number of accounts to process")]
         max_accounts: Option<usize>,
     },
+
+    /// Compare the token state written by custom-compress at the source path with a later one
+    CompressedDiff {
+        #[clap(help = "Token state written by custom-compress from a later snapshot")]
+        other: String,
+
+        #[clap(long, default_value = "20", help = "Number of mints to list")]
+        top: usize,
+    },
+
+    /// Analyze deployed program binaries and find duplicate deployments
+    ElfAnalysis {
+        #[clap(long, help = "Print aggregated ELF section sizes")]
+        sections: bool,
+    },
+
+    /// Pair upgradeable programs with their ProgramData and report authorities, deploy slots and sizes
+    Programs {
+        #[clap(long, default_value = "20", help = "Number of programs to list")]
+        top: usize,
+
+        #[clap(
+            long,
+            help = "Also write the ProgramData address, upgrade authority, last deployed slot and binary size of every program to this CSV file"
+        )]
+        csv: Option<String>,
+    },
+
+    /// Print the distribution of account data lengths, overall and for the top owners
+    Histogram {
+        #[clap(
+            long,
+            default_value = "10",
+            help = "Number of owners to print, ranked by total data size"
+        )]
+        top_owners: usize,
+
+        #[clap(
+            long,
+            help = "Also write the histograms of all owners to this CSV file"
+        )]
+        csv: Option<String>,
+    },
+
+    /// Print a uniform random sample of live accounts as JSON lines, reading the snapshot twice
+    /// to find the latest version of each
+    Sample {
+        #[clap(
+            long,
+            required_unless_present = "count",
+            conflicts_with = "count",
+            help = "Fraction of accounts to sample, e.g. 0.001"
+        )]
+        rate: Option<f64>,
+
+        #[clap(long, help = "Number of accounts to sample")]
+        count: Option<usize>,
+
+        #[clap(long, help = "Random seed, for reproducible samples")]
+        seed: Option<u64>,
+
+        #[clap(long, help = "Write samples to this file instead of stdout")]
+        output: Option<String>,
+    },
+
+    /// Write a few decoded sample accounts for each of the top owners to a directory
+    SampleBundle {
+        #[clap(long, help = "Output directory for the bundle")]
+        output: String,
+
+        #[clap(
+            long,
+            default_value = "3",
+            help = "Number of sample accounts per owner"
+        )]
+        per_owner: usize,
+
+        #[clap(
+            long,
+            default_value = "50",
+            help = "Number of owners to sample, ranked by total data size"
+        )]
+        top_owners: usize,
+    },
+
+    /// Report token accounts whose mint account is missing from the snapshot
+    DanglingMints {
+        #[clap(long, default_value = "50", help = "Number of missing mints to list")]
+        top: usize,
+    },
+
+    /// Report frozen token accounts per mint, with the mint's freeze authority
+    FrozenAccounts {
+        #[clap(long, default_value = "50", help = "Number of mints to list")]
+        top: usize,
+    },
+
+    /// List the largest token accounts of a mint with their owners
+    Holders {
+        #[clap(long, help = "Mint whose token accounts to list")]
+        mint: String,
+
+        #[clap(long, default_value = "20", help = "Number of token accounts to list")]
+        top: usize,
+    },
+
+    /// Report SPL account-compression merkle trees by shape and Bubblegum tree configs
+    MerkleTrees {
+        #[clap(long, default_value = "20", help = "Number of tree shapes to list")]
+        top: usize,
+    },
+
+    /// Group NFT metadata by verified collection and report items, holders and metadata size
+    NftCollections {
+        #[clap(long, default_value = "20", help = "Number of collections to list")]
+        top: usize,
+    },
+
+    /// Find mints with a supply of 1 and no decimals and report who holds them
+    NftMints {
+        #[clap(long, default_value = "20", help = "Number of holders to list")]
+        top: usize,
+
+        #[clap(
+            long,
+            help = "Also write the mint, holder token account and owner of every held NFT to this CSV file"
+        )]
+        csv: Option<String>,
+    },
+
+    /// Report durable nonce accounts, the lamports they lock up and their authorities
+    NonceAccounts {
+        #[clap(
+            long,
+            default_value = "20",
+            help = "Number of nonce authorities to list"
+        )]
+        top: usize,
+
+        #[clap(
+            long,
+            help = "Also write the authority, blockhash and fee of every nonce account to this CSV file"
+        )]
+        csv: Option<String>,
+    },
+
+    /// Report the runtime features active at the snapshot slot
+    Features {
+        #[clap(
+            long,
+            value_delimiter = ',',
+            help = "Feature IDs expected to be active, to diff against (comma-separated or @file)"
+        )]
+        expected: Vec<String>,
+    },
+
+    /// Report stake delegated to missing or delinquent validators and idle deactivated stake
+    StakeHygiene {
+        #[clap(
+            long,
+            default_value = "50",
+            help = "Number of withdraw authorities to list"
+        )]
+        top: usize,
+    },
+
+    /// Report accounts violating chain invariants, such as system-owned accounts with data
+    Audit {
+        #[clap(
+            long,
+            default_value = "10",
+            help = "Number of example accounts to list per violation"
+        )]
+        examples: usize,
+    },
+
+    /// Write a Bloom filter of the pubkeys of all live accounts to a file
+    BloomFilter {
+        #[clap(long, help = "Path to the output file")]
+        output: String,
+
+        #[clap(long, default_value = "0.001", help = "Target false positive rate")]
+        fp_rate: f64,
+
+        #[clap(
+            long,
+            help = "Number of accounts to size the filter for (default: an upper bound from the manifest)"
+        )]
+        expected_accounts: Option<u64>,
+    },
+
+    /// Report how SOL is distributed across system-owned accounts, with the Gini coefficient
+    Wealth {
+        #[clap(
+            long,
+            default_value = "100",
+            help = "Number of Lorenz curve points to export"
+        )]
+        lorenz_points: usize,
+
+        #[clap(long, help = "Write the full report to this JSON file")]
+        json: Option<String>,
+
+        #[clap(long, help = "Write the Lorenz curve to this CSV file")]
+        csv: Option<String>,
+    },
+
+    /// List the accounts holding the most lamports, with their owner programs
+    Richlist {
+        #[clap(long, default_value = "20", help = "Number of accounts to list")]
+        top: usize,
+    },
+
+    /// List the accounts with the most data, with their owner programs
+    LargestAccounts {
+        #[clap(long, default_value = "20", help = "Number of accounts to list")]
+        top: usize,
+    },
+
+    /// Report rent-exempt reserves per program, under-funded accounts and reclaimable lamports
+    Rent {
+        #[clap(long, default_value = "20", help = "Number of owner programs to list")]
+        top: usize,
+    },
+
+    /// Count a program's accounts per type, grouped by their 8-byte Anchor discriminator
+    SchemaStats {
+        #[clap(long, help = "Program owning the accounts")]
+        owner: String,
+
+        #[clap(
+            long,
+            value_delimiter = ',',
+            help = "Anchor account type names to label discriminators with (comma-separated)"
+        )]
+        type_names: Vec<String>,
+
+        #[clap(long, default_value = "50", help = "Number of account types to list")]
+        top: usize,
+    },
+
+    /// Check account lamports and the accounts hash against the snapshot manifest
+    Verify,
+
+    /// Report pubkeys stored in more than one append vec and the bytes taken by stale versions
+    Duplicates {
+        #[clap(long, default_value = "20", help = "Number of pubkeys to list")]
+        top: usize,
+    },
+
+    /// Run the stats pass on every snapshot archive in the source directory and write
+    /// per-owner growth curves as CSV
+    Trend {
+        #[clap(
+            long,
+            help = "Path to the CSV file of accounts and bytes per owner and slot"
+        )]
+        output: String,
+
+        #[clap(
+            long,
+            default_value = "100",
+            help = "Number of owners to include, ranked by total data size in the latest snapshot"
+        )]
+        top: usize,
+    },
+
+    /// Compare the source snapshot with a later one: accounts created, deleted and changed
+    Diff {
+        #[clap(help = "Later snapshot to compare with, read with the same options and filters")]
+        other: String,
+
+        #[clap(long, default_value = "20", help = "Number of owners to list")]
+        top: usize,
+
+        #[clap(
+            long,
+            help = "Also write every changed account to the account_changes table of this DuckDB database"
+        )]
+        db: Option<String>,
+    },
+
+    /// Compare the amounts held in each mint's token accounts with its recorded supply
+    TokenAudit {
+        #[clap(long, default_value = "50", help = "Number of diverging mints to list")]
+        top: usize,
+    },
+
+    /// Count Token-2022 mints and token accounts per extension type
+    TokenExtensions {
+        #[clap(
+            long,
+            help = "Also write the extensions of every mint to the mint_extensions table of this DuckDB database"
+        )]
+        db: Option<String>,
+    },
+
+    /// Run a Rhai script over every account, emitting JSON rows and metrics
+    Script {
+        #[clap(long, help = "Path to a Rhai script defining fn process(account)")]
+        script: PathBuf,
+
+        #[clap(long, help = "Write emitted rows to this file instead of stdout")]
+        output: Option<String>,
+    },
+
+    /// Run a sandboxed WebAssembly account consumer over every account
+    Plugin {
+        #[clap(
+            long,
+            help = "Path to a WebAssembly module implementing the consumer ABI"
+        )]
+        plugin: PathBuf,
+
+        #[clap(long, help = "Write emitted lines to this file instead of stdout")]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum IndexCommand {
+    /// Write an index of the latest version of every account to a file
+    Build {
+        #[clap(help = "Path to the index file")]
+        path: PathBuf,
+    },
+
+    /// Print accounts of an unpacked snapshot, found through its index
+    Get {
+        #[clap(long, help = "Path to the index file built for this snapshot")]
+        index: PathBuf,
+
+        #[clap(
+            required = true,
+            value_delimiter = ',',
+            help = "Pubkeys to look up (comma-separated or @file)"
+        )]
+        pubkeys: Vec<String>,
+    },
 }
 
 fn main() {
     env_logger::init_from_env(
         env_logger::Env::default().filter_or(env_logger::DEFAULT_FILTER_ENV, "info"),
     );
-    if let Err(e) = _main() {
+    let argv: Vec<OsString> = std::env::args_os().collect();
+    let mut cli = Args::command();
+    if let Some(path) = config::path_from_args(&argv) {
+        cli = config::apply(cli, &path).unwrap_or_else(|e| {
+            error!("Failed to read config file {:?}: {}", path, e);
+            std::process::exit(1);
+        });
+    }
+    let matches = cli.get_matches_from(argv);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let command = matches.subcommand_name().unwrap_or_default().to_string();
+    let notify_url = args.notify_url.clone();
+
+    let start = Instant::now();
+    let mut report = RunReport::default();
+    let result = _main(args, &mut report);
+    if let Some(url) = &notify_url {
+        let error = result.as_ref().err().map(|e| e.as_ref());
+        notify::send(url, &command, &report, start.elapsed(), error);
+    }
+    if let Err(e) = result {
         error!("{}", e);
         std::process::exit(1);
     }
 }
 
-fn _main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+fn _main(mut args: Args, report: &mut RunReport) -> Result<(), Box<dyn std::error::Error>> {
+    // Conflicts are only checked between options given on the command line, so --raw may
+    // come with human = true from a config file
+    let number_format = if args.human && !args.raw {
+        NumberFormat::Human
+    } else {
+        NumberFormat::Raw
+    };
+    format::set_number_format(number_format);
+    progress::init(args.quiet, args.progress, args.progress_interval);
+    if let Some(path) = &args.config {
+        info!("Using option defaults from {:?}", path);
+    }
+    if let Some(path) = &args.program_labels {
+        let count = programs::load_labels(path)?;
+        info!("Loaded {} program labels from {:?}", count, path);
+    }
 
-    let mut loader = SupportedLoader::new(&args.source, Box::new(LoadProgressTracking {}))?;
-    info!("Processing snapshot: {}", &args.source);
+    if let Some(rpc_url) = &args.from_rpc {
+        let snapshots = rpc::discover(rpc_url)?;
+        args.source = Some(snapshots.full);
+        args.incremental = snapshots.incremental;
+    }
 
-    let num_threads = num_cpus::get() / 2;
-    info!("Using {} threads", num_threads);
+    // Point lookups don't scan the snapshot
+    if let Command::Index {
+        command: IndexCommand::Get { index, pubkeys },
+    } = &args.command
+    {
+        return cmd_index::get(Path::new(args.source()), index, &owners::parse(pubkeys)?);
+    }
+    // Compressed token states are read without a snapshot
+    if let Command::CompressedDiff { other, top } = &args.command {
+        return cmd_compressed_diff::run(args.source(), other, *top);
+    }
+    // The source of a trend is a directory of snapshots, opened one after the other
+    if let Command::Trend { output, top } = &args.command {
+        let filter = account_filter(&args)?;
+        let open = |path: &Path| -> Result<SupportedLoader, Box<dyn std::error::Error>> {
+            let source = path.to_string_lossy();
+            let loader = open_snapshot(
+                &args,
+                &source,
+                None,
+                None,
+                Arc::clone(&report.oversized),
+                Arc::clone(&report.storage_errors),
+            )?;
+            let loader = mask_dedup(&args, &source, None, loader)?;
+            Ok(mask_filtered(&args, &filter, loader))
+        };
+        return cmd_trend::run(
+            Path::new(args.source()),
+            open,
+            num_threads(&args),
+            open_cache(&args).as_ref(),
+            &cache_variant(&args, &filter, None),
+            *top,
+            output,
+        );
+    }
+
+    if let Command::Verify = args.command {
+        check_verifiable(&args, &account_filter(&args)?)?;
+    }
+
+    let oversized = Arc::clone(&report.oversized);
+    let storage_errors = Arc::clone(&report.storage_errors);
+    let expected_digest = args.expected_hash.map(ExpectedDigest::new);
+    let mut loader = open_loader(
+        &args,
+        expected_digest.clone(),
+        Arc::clone(&oversized),
+        Arc::clone(&storage_errors),
+    )?;
+    report.slot = Some(loader.slot());
+    if let Command::Info = args.command {
+        cmd_info::run(&loader)?;
+        return check_digest(&mut loader, expected_digest.as_ref());
+    }
+    // Samples are drawn from the latest live version of each account
+    if let Command::Sample { .. } = args.command {
+        args.dedup = true;
+        args.skip_zero_lamports = true;
+    }
+    let filter = account_filter(&args)?;
+    // Older versions are hidden first, so that the filters only see the latest version
+    if args.command.uses_latest_versions()
+        && !args.dedup
+        && (args.skip_zero_lamports || args.where_expr.is_some() || !filter.is_empty())
+    {
+        info!("Account filters apply to the latest version of each account, enabling --dedup");
+        args.dedup = true;
+    }
+    // The index points at the latest version of every account, which a filter could hide
+    if let Command::Index { .. } = args.command {
+        if args.dedup || args.skip_zero_lamports || args.where_expr.is_some() || !filter.is_empty()
+        {
+            warn!("Account filters don't apply to the index, which covers every account");
+        }
+    } else {
+        loader = mask_dedup(&args, args.source(), args.incremental.as_deref(), loader)?;
+        loader = mask_filtered(&args, &filter, loader);
+    }
+    info!("Processing snapshot: {}", args.source());
+    let other_loader = match &args.command {
+        Command::Diff { other, .. } => {
+            info!("Comparing with snapshot: {}", other);
+            let other_loader = open_snapshot(
+                &args,
+                other,
+                None,
+                None,
+                Arc::clone(&oversized),
+                Arc::clone(&storage_errors),
+            )?;
+            Some(mask_filtered(&args, &filter, other_loader))
+        }
+        _ => None,
+    };
+
+    let cache = open_cache(&args);
+    if let Some(cache) = &cache {
+        cache_manifest(cache, &loader);
+    }
+    let cache_variant = cache_variant(
+        &args,
+        &filter,
+        loader.full_snapshot().map(|full| full.bank_hash()),
+    );
+    let num_threads = num_threads(&args);
+
+    // Machine-readable output must be the only thing on stdout
+    let json_output = matches!(
+        args.command,
+        Command::Summary {
+            format: SummaryFormat::Json
+        } | Command::Sample { output: None, .. }
+    );
 
     match args.command {
-        Command::Stats => {
-            cmd_stats::run(&mut loader, num_threads)?;
+        Command::Info => unreachable!("handled before reading accounts"),
+        Command::Trend { .. } | Command::CompressedDiff { .. } => {
+            unreachable!("handled before opening the source")
+        }
+        Command::Stats {
+            group_by,
+            sort,
+            top,
+            min_count,
+            min_bytes,
+            output,
+            format,
+            size_percentiles,
+            checkpoint_every,
+            db,
+        } => {
+            let table = StatsTableOptions {
+                group_by,
+                sort,
+                top,
+                min_count,
+                min_bytes,
+                data_len_percentiles: size_percentiles,
+            };
+            cmd_stats::run(
+                &mut loader,
+                num_threads,
+                cache.as_ref(),
+                &cache_variant,
+                checkpoint_every.map(|millions| millions * 1_000_000),
+                &table,
+                &StatsOutputs {
+                    file: output.as_deref().map(|path| (path, format)),
+                    db: db.as_deref(),
+                },
+            )?;
+        }
+        Command::Summary { format } => {
+            cmd_summary::run(
+                &mut loader,
+                num_threads,
+                cache.as_ref(),
+                &cache_variant,
+                format,
+            )?;
         }
         Command::CompressionBenchmark { owner, level } => {
-            let owner_filter = if owner == "all" {
+            let owner_filter = if owner.iter().any(|owner| owner == "all") {
                 None
             } else {
-                Some(
-                    Pubkey::from_str(&owner)
-                        .map_err(|e| format!("Invalid owner pubkey '{}': {}", owner, e))?,
-                )
+                Some(owners::parse(&owner)?)
             };
             cmd_compression_benchmark::run(&mut loader, owner_filter, level)?;
         }
         Command::Debug { owner, count } => {
-            let owner_pubkey = Pubkey::from_str(&owner)
-                .map_err(|e| format!("Invalid owner pubkey '{}': {}", owner, e))?;
-            cmd_debug::run(&mut loader, owner_pubkey, count)?;
+            cmd_debug::run(&mut loader, &owners::parse(&owner)?, count)?;
+        }
+        Command::Lookup { pubkeys } => {
+            cmd_lookup::run(&mut loader, &owners::parse(&pubkeys)?)?;
+        }
+        Command::Index { command } => match command {
+            IndexCommand::Build { path } => cmd_index::build(&mut loader, &path)?,
+            IndexCommand::Get { .. } => unreachable!("handled before reading accounts"),
+        },
+        Command::DumpTokens {
+            db,
+            format,
+            out_dir,
+            schema,
+            hash_key_file,
+            flush_every,
+            batch_rows,
+            create_indexes,
+            append,
+            min_amount,
+        } => {
+            let output = match (format, &db, &out_dir) {
+                (DumpTokensFormat::Duckdb, Some(db), _) => DumpTokensOutput::Database(db),
+                (DumpTokensFormat::Parquet, _, Some(dir)) => DumpTokensOutput::Parquet(dir),
+                (DumpTokensFormat::Duckdb, None, _) => {
+                    return Err("dump-tokens --format duckdb requires --db".into())
+                }
+                (DumpTokensFormat::Parquet, _, None) => {
+                    return Err("dump-tokens --format parquet requires --out-dir".into())
+                }
+            };
+            // Keys given on the command line would show up in the process list and history
+            let hash_key = match hash_key_file {
+                Some(path) => Some(
+                    std::fs::read(&path)
+                        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?,
+                ),
+                None => std::env::var(pseudonym::HASH_KEY_ENV)
+                    .ok()
+                    .map(String::into_bytes),
+            };
+            let options = DumpTokensOptions {
+                output,
+                schema_path: schema.as_deref(),
+                hash_key: hash_key.as_deref(),
+                flush_every,
+                batch_rows,
+                create_indexes,
+                append,
+                min_amount,
+            };
+            cmd_dump_tokens::run(&mut loader, &options)?;
+        }
+        Command::DumpGovernance { db, programs } => {
+            cmd_dump_governance::run(&mut loader, &db, &owners::parse(&programs)?)?;
+        }
+        Command::DumpMetadata { db } => {
+            cmd_dump_metadata::run(&mut loader, &db)?;
+        }
+        Command::DumpSns { db } => {
+            cmd_dump_sns::run(&mut loader, &db)?;
         }
-        Command::DumpTokens { db } => {
-            cmd_dump_tokens::run(&mut loader, &db)?;
+        Command::DumpStakes { db, top } => {
+            cmd_dump_stakes::run(&mut loader, &db, top)?;
         }
-        Command::CustomCompress { output, max_accounts } => {
+        Command::DumpSysvars => {
+            cmd_dump_sysvars::run(&mut loader)?;
+        }
+        Command::DumpVotes { db } => {
+            cmd_dump_votes::run(&mut loader, &db)?;
+        }
+        Command::CustomCompress {
+            output,
+            max_accounts,
+        } => {
             cmd_custom_compress::run(&mut loader, &output, max_accounts)?;
         }
+        Command::ElfAnalysis { sections } => {
+            cmd_elf_analysis::run(&mut loader, sections)?;
+        }
+        Command::Programs { top, csv } => {
+            cmd_programs::run(&mut loader, top, csv.as_deref())?;
+        }
+        Command::Histogram { top_owners, csv } => {
+            cmd_histogram::run(&mut loader, top_owners, csv.as_deref())?;
+        }
+        Command::Sample {
+            rate,
+            count,
+            seed,
+            output,
+        } => {
+            let size = match (rate, count) {
+                (_, Some(count)) => SampleSize::Count(count),
+                (Some(rate), None) => SampleSize::Rate(rate),
+                (None, None) => unreachable!("clap requires --rate or --count"),
+            };
+            cmd_sample::run(&mut loader, size, seed, output.as_deref())?;
+        }
+        Command::SampleBundle {
+            output,
+            per_owner,
+            top_owners,
+        } => {
+            cmd_sample_bundle::run(
+                &mut loader,
+                cache.as_ref(),
+                &cache_variant,
+                &output,
+                per_owner,
+                top_owners,
+            )?;
+        }
+        Command::DanglingMints { top } => {
+            cmd_dangling_mints::run(&mut loader, top)?;
+        }
+        Command::FrozenAccounts { top } => {
+            cmd_frozen_accounts::run(&mut loader, top)?;
+        }
+        Command::Holders { mint, top } => {
+            let mint = owners::parse_pubkey(&mint)?;
+            cmd_holders::run(&mut loader, &mint, top)?;
+        }
+        Command::MerkleTrees { top } => {
+            cmd_merkle_trees::run(&mut loader, top)?;
+        }
+        Command::NftCollections { top } => {
+            cmd_nft_collections::run(&mut loader, top)?;
+        }
+        Command::NftMints { top, csv } => {
+            cmd_nft_mints::run(&mut loader, top, csv.as_deref())?;
+        }
+        Command::NonceAccounts { top, csv } => {
+            cmd_nonce_accounts::run(&mut loader, top, csv.as_deref())?;
+        }
+        Command::Features { expected } => {
+            cmd_features::run(&mut loader, &owners::parse(&expected)?)?;
+        }
+        Command::StakeHygiene { top } => {
+            cmd_stake_hygiene::run(&mut loader, top)?;
+        }
+        Command::Audit { examples } => {
+            cmd_audit::run(&mut loader, examples)?;
+        }
+        Command::BloomFilter {
+            output,
+            fp_rate,
+            expected_accounts,
+        } => {
+            cmd_bloom_filter::run(&mut loader, &output, fp_rate, expected_accounts)?;
+        }
+        Command::Wealth {
+            lorenz_points,
+            json,
+            csv,
+        } => {
+            cmd_wealth::run(&mut loader, lorenz_points, json.as_deref(), csv.as_deref())?;
+        }
+        Command::Richlist { top } => {
+            cmd_richlist::run(&mut loader, top)?;
+        }
+        Command::LargestAccounts { top } => {
+            cmd_largest_accounts::run(&mut loader, top)?;
+        }
+        Command::Rent { top } => {
+            cmd_rent::run(&mut loader, top)?;
+        }
+        Command::SchemaStats {
+            owner,
+            type_names,
+            top,
+        } => {
+            let program = owners::parse_pubkey(&owner)?;
+            cmd_schema_stats::run(&mut loader, &program, &type_names, top)?;
+        }
+        Command::Verify => {
+            cmd_verify::run(&mut loader)?;
+        }
+        Command::Duplicates { top } => {
+            cmd_duplicates::run(&mut loader, top)?;
+        }
+        Command::Diff { top, db, .. } => {
+            let mut other_loader = other_loader.expect("opened for diff");
+            cmd_diff::run(&mut loader, &mut other_loader, top, db.as_deref())?;
+        }
+        Command::TokenAudit { top } => {
+            cmd_token_audit::run(&mut loader, top)?;
+        }
+        Command::TokenExtensions { db } => {
+            cmd_token_extensions::run(&mut loader, db.as_deref())?;
+        }
+        Command::Script { script, output } => {
+            run_script(&mut loader, &script, output.as_deref())?;
+        }
+        Command::Plugin { plugin, output } => {
+            run_plugin(&mut loader, &plugin, output.as_deref())?;
+        }
+    }
+    check_digest(&mut loader, expected_digest.as_ref())?;
+
+    if json_output {
+        return Ok(());
     }
 
+    print_oversized_report(&oversized);
+    print_storage_error_report(&storage_errors);
+
     println!("Done!");
     Ok(())
 }
+
+fn open_loader(
+    args: &Args,
+    expected_digest: Option<ExpectedDigest>,
+    oversized: Arc<OversizedAccounts>,
+    storage_errors: Arc<StorageErrors>,
+) -> Result<SupportedLoader, Box<dyn std::error::Error>> {
+    open_snapshot(
+        args,
+        args.source(),
+        args.incremental.as_deref(),
+        expected_digest,
+        oversized,
+        storage_errors,
+    )
+}
+
+/// Opens `source`, layered with `incremental`, using the global loading options.
+fn open_snapshot(
+    args: &Args,
+    source: &str,
+    incremental: Option<&str>,
+    expected_digest: Option<ExpectedDigest>,
+    oversized: Arc<OversizedAccounts>,
+    storage_errors: Arc<StorageErrors>,
+) -> Result<SupportedLoader, Box<dyn std::error::Error>> {
+    let download_options = DownloadOptions {
+        threads: args.download_threads,
+        cache_dir: args.cache_dir.clone(),
+        max_rate: args.max_download_rate,
+        retry: RetryPolicy {
+            attempts: args.http_retries,
+            initial_delay: args.http_retry_backoff,
+        },
+    };
+    let mut loader = SupportedLoader::new(
+        source,
+        Box::new(LoadProgressTracking {}),
+        &download_options,
+        expected_digest,
+    )?;
+    // Only the full snapshot may skip storages: a skipped incremental storage would expose
+    // the stale full versions of its accounts
+    if args.lenient {
+        loader = SupportedLoader::lenient(loader, storage_errors);
+    }
+    if let Some(incremental) = incremental {
+        let incremental = SupportedLoader::new(
+            incremental,
+            Box::new(LoadProgressTracking {}),
+            &download_options,
+            None,
+        )?;
+        loader = SupportedLoader::layered(loader, incremental)?;
+    }
+    Ok(SupportedLoader::limited(
+        loader,
+        args.max_data_len,
+        oversized,
+    ))
+}
+
+/// Commands may stop reading before the end of the archive, so the rest of it is read
+/// for its SHA-256 digest to be checked. Fails if the digest still wasn't verified.
+fn check_digest(
+    loader: &mut SupportedLoader,
+    expected: Option<&ExpectedDigest>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    for append_vec in loader.iter() {
+        append_vec?;
+    }
+    if !expected.is_verified() {
+        return Err("Archive SHA-256 was not verified: the archive wasn't read to the end".into());
+    }
+    Ok(())
+}
+
+/// With `--dedup`, hides all but the latest version of each account. The older versions
+/// are found by a first pass over `source`, unless their skip index is cached.
+fn mask_dedup(
+    args: &Args,
+    source: &str,
+    incremental: Option<&str>,
+    loader: SupportedLoader,
+) -> Result<SupportedLoader, Box<dyn std::error::Error>> {
+    if !args.dedup {
+        return Ok(loader);
+    }
+    let cache = open_cache(args);
+    let bank_hash = loader.bank_hash();
+    // The entries past the data length cap are never seen, so they don't hide older versions
+    let mut options = Vec::new();
+    if let Some(full) = loader.full_snapshot() {
+        options.push(format!("incremental-over={}", full.bank_hash()));
+    }
+    if args.max_data_len != DEFAULT_MAX_DATA_LEN {
+        options.push(format!("max-data-len={}", args.max_data_len));
+    }
+    let artifact = variant_name(SKIP_INDEX_ARTIFACT, &options.join(","));
+    if let Some(cached) = cache
+        .as_ref()
+        .map(|c| c.read(&bank_hash, &artifact))
+        .transpose()?
+        .flatten()
+    {
+        info!("Using cached skip index for bank hash {}", bank_hash);
+        let index = SkipIndex::from_bytes(&cached)?;
+        return Ok(SupportedLoader::masked(loader, Arc::new(index)));
+    }
+    info!("Building skip index of older account versions (first pass)");
+    // Oversized entries are reported by the second pass
+    let mut first_pass = open_snapshot(
+        args,
+        source,
+        incremental,
+        None,
+        Arc::new(OversizedAccounts::default()),
+        Arc::new(StorageErrors::default()),
+    )?;
+    let index = SkipIndex::build(first_pass.iter())?;
+    if let Some(cache) = &cache {
+        if let Err(e) = cache.write(&bank_hash, &artifact, &index.to_bytes()?) {
+            warn!("Failed to cache skip index: {}", e);
+        }
+    }
+    Ok(SupportedLoader::masked(loader, Arc::new(index)))
+}
+
+/// The accounts hash and capitalization of the manifest cover every live account of a
+/// full snapshot, so `verify` refuses options that would hide some of them.
+fn check_verifiable(args: &Args, filter: &AccountFilter) -> Result<(), Box<dyn std::error::Error>> {
+    if args.incremental.is_some() {
+        return Err(
+            "verify only checks full snapshots: the manifest of an incremental snapshot hashes the accounts it changed, not the layered state"
+                .into(),
+        );
+    }
+    let mut options = Vec::new();
+    if args.dedup {
+        options.push("--dedup");
+    }
+    if args.skip_zero_lamports {
+        options.push("--skip-zero-lamports");
+    }
+    if args.max_data_len != DEFAULT_MAX_DATA_LEN {
+        options.push("--max-data-len");
+    }
+    if args.where_expr.is_some() {
+        options.push("--where");
+    }
+    if !filter.is_empty() {
+        options.push("account filters");
+    }
+    if !options.is_empty() {
+        return Err(format!(
+            "verify can't be combined with {}, which hide accounts from the checks",
+            options.join(", ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Lenient runs never use the cache, since their reports may be missing skipped storages.
+fn open_cache(args: &Args) -> Option<ArtifactCache> {
+    if args.no_cache || args.lenient {
+        None
+    } else {
+        ArtifactCache::open_default()
+    }
+}
+
+/// Options that change the results of cached reports. Reports of an incremental snapshot
+/// layered over `full_bank_hash` are cached apart from those of the incremental alone,
+/// which has the same bank hash.
+fn cache_variant(args: &Args, filter: &AccountFilter, full_bank_hash: Option<Hash>) -> String {
+    let mut cache_variant = Vec::new();
+    if let Some(full_bank_hash) = full_bank_hash {
+        cache_variant.push(format!("incremental-over={}", full_bank_hash));
+    }
+    if args.dedup {
+        cache_variant.push("dedup".to_string());
+    }
+    if args.skip_zero_lamports {
+        cache_variant.push("skip-zero-lamports".to_string());
+    }
+    if args.max_data_len != DEFAULT_MAX_DATA_LEN {
+        cache_variant.push(format!("max-data-len={}", args.max_data_len));
+    }
+    if !filter.is_empty() {
+        cache_variant.push(format!("{:?}", filter));
+    }
+    if let Some(expr) = &args.where_expr {
+        cache_variant.push(format!("where={:?}", expr));
+    }
+    cache_variant.join(",")
+}
+
+fn num_threads(args: &Args) -> usize {
+    let num_threads = args.threads.unwrap_or_else(|| num_cpus::get() / 2);
+    info!("Using {} threads", num_threads);
+    num_threads
+}
+
+/// Hides the accounts excluded by the global filters.
+fn mask_filtered(
+    args: &Args,
+    filter: &AccountFilter,
+    mut loader: SupportedLoader,
+) -> SupportedLoader {
+    if args.skip_zero_lamports {
+        loader = SupportedLoader::masked(loader, Arc::new(ZeroLamportMask));
+    }
+    if !filter.is_empty() {
+        loader = SupportedLoader::masked(loader, Arc::new(filter.clone()));
+    }
+    if let Some(expr) = &args.where_expr {
+        loader = SupportedLoader::masked(loader, Arc::new(expr.clone()));
+    }
+    loader
+}
+
+fn account_filter(args: &Args) -> Result<AccountFilter, Box<dyn std::error::Error>> {
+    let executable = if args.executable_only {
+        Some(true)
+    } else if args.non_executable {
+        Some(false)
+    } else {
+        None
+    };
+    let rent_exempt = if args.rent_exempt_only {
+        Some(true)
+    } else if args.rent_paying_only {
+        Some(false)
+    } else {
+        None
+    };
+    if let (Some(min), Some(max)) = (args.min_lamports, args.max_lamports) {
+        if min > max {
+            return Err(format!(
+                "--min-lamports {} is greater than --max-lamports {}, no account would match",
+                min, max
+            )
+            .into());
+        }
+    }
+    if let (Some(min), Some(max)) = (args.min_data_size, args.max_data_size) {
+        if min > max {
+            return Err(format!(
+                "--min-data-size {} is greater than --max-data-size {}, no account would match",
+                min, max
+            )
+            .into());
+        }
+    }
+    Ok(AccountFilter {
+        executable,
+        rent_epoch: args.rent_epoch,
+        rent_exempt,
+        min_lamports: args.min_lamports,
+        max_lamports: args.max_lamports,
+        excluded_owners: owners::parse(&args.exclude_owner)?.into_iter().collect(),
+        min_data_len: args.min_data_size,
+        max_data_len: args.max_data_size,
+        memcmp: args.memcmp.clone(),
+    })
+}
+
+#[cfg(feature = "scripting")]
+fn run_script(
+    loader: &mut SupportedLoader,
+    script: &Path,
+    output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    cmd_script::run(loader, script, output)
+}
+
+#[cfg(not(feature = "scripting"))]
+fn run_script(
+    _loader: &mut SupportedLoader,
+    _script: &Path,
+    _output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("The script command requires building with the `scripting` feature".into())
+}
+
+#[cfg(feature = "wasm")]
+fn run_plugin(
+    loader: &mut SupportedLoader,
+    plugin: &Path,
+    output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    cmd_plugin::run(loader, plugin, output)
+}
+
+#[cfg(not(feature = "wasm"))]
+fn run_plugin(
+    _loader: &mut SupportedLoader,
+    _plugin: &Path,
+    _output: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("The plugin command requires building with the `wasm` feature".into())
+}
+
+fn print_oversized_report(oversized: &OversizedAccounts) {
+    let entries = oversized.entries();
+    if entries.is_empty() {
+        return;
+    }
+    warn!(
+        "{} account entries exceeded the data length cap",
+        entries.len()
+    );
+    println!("\n--- Oversized Account Entries ---\n");
+    println!(
+        "{:<12} {:>12} {:<45} {:>20} {:<10}",
+        "Slot", "Offset", "Pubkey", "Data Len", "Action"
+    );
+    println!("{}", "-".repeat(103));
+    for entry in entries {
+        println!(
+            "{:<12} {:>12} {:<45} {:>20} {:<10}",
+            entry.slot,
+            entry.offset,
+            entry.pubkey.to_string(),
+            entry.data_len,
+            if entry.skipped {
+                "skipped"
+            } else {
+                "truncated"
+            }
+        );
+    }
+}
+
+fn print_storage_error_report(storage_errors: &StorageErrors) {
+    let entries = storage_errors.entries();
+    if entries.is_empty() {
+        return;
+    }
+    warn!(
+        "{} append vecs could not be fully read; results are partial",
+        entries.len()
+    );
+    println!("\n--- Append Vec Errors ---\n");
+    println!("{:<30} {:>12} {:<60}", "File", "Offset", "Error");
+    println!("{}", "-".repeat(104));
+    for entry in entries {
+        println!(
+            "{:<30} {:>12} {:<60}",
+            entry.file,
+            entry
+                .offset
+                .map(|offset| offset.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            entry.error
+        );
+    }
+}
+
+fn cache_manifest(cache: &ArtifactCache, loader: &SupportedLoader) {
+    let bank_hash = loader.bank_hash();
+    if cache.artifact_path(&bank_hash, MANIFEST_ARTIFACT).is_file() {
+        return;
+    }
+    let info = loader.info();
+    let manifest = serde_json::json!({
+        "slot": info.slot,
+        "epoch": info.epoch,
+        "block_height": info.block_height,
+        "capitalization": info.capitalization,
+        "bank_hash": bank_hash.to_string(),
+        "accounts_hash": info.accounts_hash.to_string(),
+        "storages": info.storages,
+    });
+    if let Err(e) = cache.write(
+        &bank_hash,
+        MANIFEST_ARTIFACT,
+        manifest.to_string().as_bytes(),
+    ) {
+        warn!("Failed to cache manifest summary: {}", e);
+    }
+}
@@ -0,0 +1,96 @@
+use solana_sdk::hash::hash;
+use solana_sdk::pubkey::Pubkey;
+
+pub const ACCOUNT_COMPRESSION_PROGRAM_ID: &str = "cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK";
+pub const BUBBLEGUM_PROGRAM_ID: &str = "BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY";
+
+/// `CompressionAccountType::ConcurrentMerkleTree`, the first byte of a tree account.
+const ACCOUNT_TYPE_TREE: u8 = 1;
+/// `ConcurrentMerkleTreeHeaderData::V1`, the only header version.
+const HEADER_VERSION_V1: u8 = 0;
+/// Account type, header version and the V1 header: max buffer size (u32), max depth
+/// (u32), authority (32), creation slot (u64) and 6 bytes of flags and padding.
+const HEADER_LEN: usize = 56;
+const NODE_LEN: usize = 32;
+/// Largest tree dimensions the account-compression program accepts.
+const MAX_DEPTH: u32 = 30;
+const MAX_BUFFER_SIZE: u32 = 2048;
+/// Leaf index (u32) and padding (u32) ending change log entries and the rightmost proof.
+const INDEX_LEN: usize = 8;
+
+/// Header and fill level of a concurrent merkle tree account.
+pub struct MerkleTree {
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+    pub authority: Pubkey,
+    /// Leaves appended so far, the index of the rightmost leaf plus one
+    pub leaves: u64,
+    /// Levels of the tree cached on-chain below the root, 0 without a canopy
+    pub canopy_depth: u32,
+    pub canopy_bytes: u64,
+}
+
+/// Length of a `ConcurrentMerkleTree<max_depth, max_buffer_size>`: the sequence number,
+/// active index and buffer size (u64 each), the change log ring buffer, then the
+/// rightmost proof.
+fn tree_len(max_depth: usize, max_buffer_size: usize) -> usize {
+    let path_len = max_depth * NODE_LEN;
+    3 * 8 + max_buffer_size * (NODE_LEN + path_len + INDEX_LEN) + path_len + NODE_LEN + INDEX_LEN
+}
+
+/// Parses an account of the SPL account-compression program, or returns `None` for
+/// uninitialized trees and data that doesn't match the layout its header describes.
+pub fn parse_tree(data: &[u8]) -> Option<MerkleTree> {
+    if data.len() < HEADER_LEN || data[0] != ACCOUNT_TYPE_TREE || data[1] != HEADER_VERSION_V1 {
+        return None;
+    }
+    let max_buffer_size = u32::from_le_bytes(data[2..6].try_into().unwrap());
+    let max_depth = u32::from_le_bytes(data[6..10].try_into().unwrap());
+    let authority = Pubkey::try_from(&data[10..42]).unwrap();
+    if max_depth > MAX_DEPTH || max_buffer_size > MAX_BUFFER_SIZE {
+        return None;
+    }
+
+    let tree_end = HEADER_LEN + tree_len(max_depth as usize, max_buffer_size as usize);
+    if data.len() < tree_end || (data.len() - tree_end) % NODE_LEN != 0 {
+        return None;
+    }
+    let rightmost_index = tree_end - INDEX_LEN;
+    let leaves = u32::from_le_bytes(
+        data[rightmost_index..rightmost_index + 4]
+            .try_into()
+            .unwrap(),
+    );
+
+    // A canopy of depth d caches the 2^(d+1) - 2 nodes of the levels below the root
+    let canopy_nodes = (data.len() - tree_end) / NODE_LEN;
+    let canopy_depth = (canopy_nodes + 2).ilog2() - 1;
+    Some(MerkleTree {
+        max_depth,
+        max_buffer_size,
+        authority,
+        leaves: leaves as u64,
+        canopy_depth,
+        canopy_bytes: (canopy_nodes * NODE_LEN) as u64,
+    })
+}
+
+/// Bubblegum `TreeConfig`, the tree authority of every Bubblegum merkle tree.
+pub struct TreeConfig {
+    pub total_mint_capacity: u64,
+    pub num_minted: u64,
+}
+
+/// Parses a Bubblegum account, or returns `None` for accounts other than tree configs.
+pub fn parse_tree_config(data: &[u8]) -> Option<TreeConfig> {
+    // Anchor prefixes accounts with a discriminator hashed from the account name
+    let discriminator = hash(b"account:TreeConfig");
+    if data.len() < 88 || data[0..8] != discriminator.as_ref()[0..8] {
+        return None;
+    }
+    // The tree creator and tree delegate come first
+    Some(TreeConfig {
+        total_mint_capacity: u64::from_le_bytes(data[72..80].try_into().unwrap()),
+        num_minted: u64::from_le_bytes(data[80..88].try_into().unwrap()),
+    })
+}
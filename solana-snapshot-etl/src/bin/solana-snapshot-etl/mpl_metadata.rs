@@ -78,7 +78,67 @@ pub struct Uses {
     pub total: u64,
 }
 
+/// Prefix shared by `MasterEditionV1` and `MasterEditionV2` accounts.
+#[derive(BorshDeserialize)]
+pub struct MasterEdition {
+    pub supply: u64,
+    pub max_supply: Option<u64>,
+}
+
+/// Print edition of a master edition, stored as `EditionV1`.
+#[derive(BorshDeserialize)]
+pub struct Edition {
+    pub parent: Pubkey,
+    pub edition: u64,
+}
+
 #[derive(BorshDeserialize)]
 pub enum CollectionDetails {
     V1 { size: u64 },
 }
+
+/// Fields of a metadata account, with the extensions appended by later program versions
+/// set to `None` in accounts written before them.
+pub struct ParsedMetadata {
+    pub metadata: Metadata,
+    pub ext: Option<MetadataExtV1_2>,
+}
+
+/// Parses the data of a metadata account, or returns `None` for other account kinds
+/// and data that doesn't deserialize.
+pub fn parse_metadata(mut data: &[u8]) -> Option<ParsedMetadata> {
+    if !matches!(
+        AccountKey::deserialize(&mut data),
+        Ok(AccountKey::MetadataV1)
+    ) {
+        return None;
+    }
+    let metadata = Metadata::deserialize(&mut data).ok()?;
+    // Older accounts end early or are zero-padded, so missing extensions aren't errors
+    let ext = MetadataExt::deserialize(&mut data)
+        .ok()
+        .and_then(|_| MetadataExtV1_2::deserialize(&mut data).ok());
+    Some(ParsedMetadata { metadata, ext })
+}
+
+/// Master or print edition account.
+pub enum ParsedEdition {
+    Master(MasterEdition),
+    Print(Edition),
+}
+
+/// Parses the data of an edition account, or returns `None` for other account kinds
+/// and data that doesn't deserialize.
+pub fn parse_edition(mut data: &[u8]) -> Option<ParsedEdition> {
+    match AccountKey::deserialize(&mut data).ok()? {
+        AccountKey::MasterEditionV1 | AccountKey::MasterEditionV2 => {
+            MasterEdition::deserialize(&mut data)
+                .ok()
+                .map(ParsedEdition::Master)
+        }
+        AccountKey::EditionV1 => Edition::deserialize(&mut data)
+            .ok()
+            .map(ParsedEdition::Print),
+        _ => None,
+    }
+}
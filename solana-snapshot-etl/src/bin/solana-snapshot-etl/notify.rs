@@ -0,0 +1,58 @@
+use crate::progress;
+use log::{info, warn};
+use reqwest::blocking::Client;
+use serde_json::json;
+use solana_snapshot_etl::append_vec::OversizedAccounts;
+use solana_snapshot_etl::lenient::StorageErrors;
+use std::sync::Arc;
+use std::time::Duration;
+
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What a run found out along the way, reported to `--notify-url` when it ends.
+#[derive(Default)]
+pub struct RunReport {
+    pub slot: Option<u64>,
+    pub oversized: Arc<OversizedAccounts>,
+    pub storage_errors: Arc<StorageErrors>,
+}
+
+/// POSTs a JSON summary of a finished or failed run to `url`. Failures to deliver
+/// it are logged, not returned, so they don't change the run's exit status.
+pub fn send(
+    url: &str,
+    command: &str,
+    report: &RunReport,
+    duration: Duration,
+    error: Option<&dyn std::error::Error>,
+) {
+    let storage_errors = report.storage_errors.entries();
+    let body = json!({
+        "command": command,
+        "status": if error.is_some() { "failed" } else { "succeeded" },
+        "slot": report.slot,
+        "duration_secs": duration.as_secs_f64(),
+        "counts": progress::positions(),
+        "oversized_accounts": report.oversized.entries().len(),
+        "storage_errors": storage_errors
+            .iter()
+            .map(|entry| json!({
+                "file": entry.file,
+                "offset": entry.offset,
+                "error": entry.error,
+            }))
+            .collect::<Vec<_>>(),
+        "error": error.map(|e| e.to_string()),
+    });
+    let result = Client::new()
+        .post(url)
+        .timeout(NOTIFY_TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()
+        .and_then(|response| response.error_for_status());
+    match result {
+        Ok(_) => info!("Sent run notification to {}", url),
+        Err(e) => warn!("Failed to send run notification to {}: {}", url, e),
+    }
+}
@@ -0,0 +1,29 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::fs;
+use std::str::FromStr;
+
+/// Parses pubkey arguments, such as `--owner` values, into a set. Values starting with `@`
+/// name a file listing one pubkey per line, ignoring blank lines and `#` comments.
+pub fn parse(values: &[String]) -> Result<HashSet<Pubkey>, Box<dyn std::error::Error>> {
+    let mut owners = HashSet::new();
+    for value in values {
+        let Some(path) = value.strip_prefix('@') else {
+            owners.insert(parse_pubkey(value)?);
+            continue;
+        };
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read pubkey list '{}': {}", path, e))?;
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if !line.is_empty() {
+                owners.insert(parse_pubkey(line)?);
+            }
+        }
+    }
+    Ok(owners)
+}
+
+pub fn parse_pubkey(s: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(s).map_err(|e| format!("Invalid pubkey '{}': {}", s, e))
+}
@@ -0,0 +1,150 @@
+use crate::governance::GOVERNANCE_PROGRAM_ID;
+use crate::merkle_tree::{ACCOUNT_COMPRESSION_PROGRAM_ID, BUBBLEGUM_PROGRAM_ID};
+use crate::mpl_metadata;
+use crate::sns::NAME_SERVICE_PROGRAM_ID;
+use crate::token::{ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{
+    bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable, compute_budget, config,
+    ed25519_program, native_loader, secp256k1_program, stake, system_program, sysvar, vote,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Category of native programs and sysvars.
+const SYSTEM: &str = "system";
+/// Category of loaders and general-purpose programs other programs build on.
+const INFRA: &str = "infra";
+const NFT: &str = "NFT";
+
+const KNOWN_PROGRAMS: [(&str, &str, &str); 8] = [
+    (TOKEN_PROGRAM_ID, "Token Program", INFRA),
+    (TOKEN_2022_PROGRAM_ID, "Token-2022 Program", INFRA),
+    (
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        "Associated Token Program",
+        INFRA,
+    ),
+    (
+        "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr",
+        "Memo Program",
+        INFRA,
+    ),
+    (
+        ACCOUNT_COMPRESSION_PROGRAM_ID,
+        "SPL Account Compression",
+        INFRA,
+    ),
+    (BUBBLEGUM_PROGRAM_ID, "Metaplex Bubblegum", NFT),
+    (GOVERNANCE_PROGRAM_ID, "SPL Governance", INFRA),
+    (NAME_SERVICE_PROGRAM_ID, "SPL Name Service", INFRA),
+];
+
+/// A program label from a labels file: either just a name, or a name and/or category.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LabelEntry {
+    Name(String),
+    Detailed {
+        name: Option<String>,
+        category: Option<String>,
+    },
+}
+
+struct UserLabel {
+    name: Option<String>,
+    category: Option<String>,
+}
+
+/// Labels loaded with `load_labels`, taking precedence over the built-in ones.
+static USER_LABELS: OnceLock<HashMap<Pubkey, UserLabel>> = OnceLock::new();
+
+/// Loads additional program labels for the rest of the process from a JSON file mapping
+/// program IDs to names, or to objects with a `name` and a `category`, e.g.
+/// `{"JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4": {"name": "Jupiter", "category": "DeFi"}}`.
+pub fn load_labels(path: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read program labels {:?}: {}", path, e))?;
+    let entries: HashMap<String, LabelEntry> = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid program labels {:?}: {}", path, e))?;
+    let mut labels = HashMap::with_capacity(entries.len());
+    for (id, entry) in entries {
+        let id = Pubkey::from_str(&id).map_err(|e| format!("Invalid pubkey '{}': {}", id, e))?;
+        let label = match entry {
+            LabelEntry::Name(name) => UserLabel {
+                name: Some(name),
+                category: None,
+            },
+            LabelEntry::Detailed { name, category } => UserLabel { name, category },
+        };
+        labels.insert(id, label);
+    }
+    let count = labels.len();
+    let _ = USER_LABELS.set(labels);
+    Ok(count)
+}
+
+fn user_label(program: &Pubkey) -> Option<&'static UserLabel> {
+    USER_LABELS.get().and_then(|labels| labels.get(program))
+}
+
+/// Built-in name and category of a well-known program.
+fn builtin(program: &Pubkey) -> Option<(&'static str, &'static str)> {
+    let builtin = [
+        (system_program::id(), "System Program", SYSTEM),
+        (vote::program::id(), "Vote Program", SYSTEM),
+        (stake::program::id(), "Stake Program", SYSTEM),
+        (config::program::id(), "Config Program", SYSTEM),
+        (sysvar::id(), "Sysvar", SYSTEM),
+        (compute_budget::id(), "Compute Budget Program", INFRA),
+        (ed25519_program::id(), "Ed25519 Program", INFRA),
+        (secp256k1_program::id(), "Secp256k1 Program", INFRA),
+        (native_loader::id(), "Native Loader", INFRA),
+        (
+            bpf_loader_deprecated::id(),
+            "BPF Loader (deprecated)",
+            INFRA,
+        ),
+        (bpf_loader::id(), "BPF Loader", INFRA),
+        (
+            bpf_loader_upgradeable::id(),
+            "BPF Upgradeable Loader",
+            INFRA,
+        ),
+        (mpl_metadata::id(), "Metaplex Metadata", NFT),
+    ];
+    if let Some((_, name, category)) = builtin.iter().find(|(id, _, _)| id == program) {
+        return Some((*name, *category));
+    }
+    let program = program.to_string();
+    KNOWN_PROGRAMS
+        .iter()
+        .find(|(id, _, _)| *id == program)
+        .map(|(_, name, category)| (*name, *category))
+}
+
+/// Name of a well-known program, for annotating owners in reports.
+pub fn name(program: &Pubkey) -> Option<&'static str> {
+    user_label(program)
+        .and_then(|label| label.name.as_deref())
+        .or_else(|| builtin(program).map(|(name, _)| name))
+}
+
+/// Category of a well-known program, such as "system", "infra", "NFT" or a category from
+/// the labels file.
+pub fn category(program: &Pubkey) -> Option<&'static str> {
+    user_label(program)
+        .and_then(|label| label.category.as_deref())
+        .or_else(|| builtin(program).map(|(_, category)| category))
+}
+
+/// The program's name if it is well known, otherwise its pubkey.
+pub fn label(program: &Pubkey) -> String {
+    name(program)
+        .map(str::to_string)
+        .unwrap_or_else(|| program.to_string())
+}
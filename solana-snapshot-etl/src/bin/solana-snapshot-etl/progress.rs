@@ -0,0 +1,141 @@
+use clap::ValueEnum;
+use indicatif::{MultiProgress, ProgressBar, ProgressBarIter, ProgressDrawTarget, ProgressStyle};
+use serde_json::json;
+use std::io::{self, Read};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ProgressMode {
+    /// Interactive progress bars
+    Bars,
+    /// One JSON object per line on stderr, every `--progress-interval` seconds
+    Json,
+}
+
+/// All progress bars are drawn through this, so that bars active at the same
+/// time (e.g. the archive bar and a command's account spinner) don't overwrite each other.
+fn multi() -> &'static MultiProgress {
+    static MULTI: OnceLock<MultiProgress> = OnceLock::new();
+    MULTI.get_or_init(MultiProgress::new)
+}
+
+/// Every bar added so far, for JSON progress reports.
+fn bars() -> &'static Mutex<Vec<ProgressBar>> {
+    static BARS: Mutex<Vec<ProgressBar>> = Mutex::new(Vec::new());
+    &BARS
+}
+
+/// Registers a progress bar, to be drawn or reported according to the [`ProgressMode`].
+pub fn add(progress_bar: ProgressBar) -> ProgressBar {
+    let progress_bar = multi().add(progress_bar);
+    bars().lock().unwrap().push(progress_bar.clone());
+    progress_bar
+}
+
+/// Sets how progress is shown. Bars are hidden when `quiet` is set or JSON reports
+/// are requested; JSON reports are printed every `interval` by a background thread.
+pub fn init(quiet: bool, mode: ProgressMode, interval: Duration) {
+    if quiet || mode == ProgressMode::Json {
+        multi().set_draw_target(ProgressDrawTarget::hidden());
+    }
+    if !quiet && mode == ProgressMode::Json {
+        let start = Instant::now();
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            eprintln!("{}", json_report(start.elapsed()));
+        });
+    }
+}
+
+/// Position, length, rate and, for bars with a length, ETA of each bar, keyed by the
+/// bar's prefix: `archive` counts bytes of the source archive, `accs` counts accounts
+/// processed by the command.
+/// When several bars share a prefix, e.g. over two passes, the newest one is reported.
+fn json_report(elapsed: Duration) -> serde_json::Value {
+    let progress: serde_json::Map<_, _> = bars()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|progress_bar| {
+            (
+                progress_bar.prefix(),
+                json!({
+                    "position": progress_bar.position(),
+                    "length": progress_bar.length(),
+                    "per_sec": progress_bar.per_sec(),
+                    "eta_secs": progress_bar
+                        .length()
+                        .map(|_| progress_bar.eta().as_secs_f64()),
+                    "finished": progress_bar.is_finished(),
+                }),
+            )
+        })
+        .collect();
+    json!({
+        "elapsed_secs": elapsed.as_secs_f64(),
+        "progress": progress,
+    })
+}
+
+/// Current position of each bar, keyed by prefix like [`json_report`].
+pub fn positions() -> serde_json::Map<String, serde_json::Value> {
+    bars()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|progress_bar| (progress_bar.prefix(), progress_bar.position().into()))
+        .collect()
+}
+
+/// Tracks how much of a snapshot archive has been consumed. Accounts are scanned
+/// as the archive streams, so with a known length this shows the percent and ETA
+/// of the whole scan.
+pub struct ArchiveProgress<R: Read> {
+    progress_bar: ProgressBar,
+    rd: ProgressBarIter<R>,
+}
+
+impl<R: Read> ArchiveProgress<R> {
+    pub fn new(rd: R, len: Option<u64>) -> Self {
+        let progress_bar = match len {
+            Some(len) => ProgressBar::new(len).with_style(
+                ProgressStyle::with_template(
+                    "{prefix:>10.bold.dim} {spinner:.green} [{bar:.cyan/blue}] {bytes}/{total_bytes} ({percent}%) {bytes_per_sec} eta {eta}",
+                )
+                .unwrap()
+                .progress_chars("#>-"),
+            ),
+            None => ProgressBar::new_spinner().with_style(
+                ProgressStyle::with_template(
+                    "{prefix:>10.bold.dim} {spinner:.green} {bytes} {bytes_per_sec}",
+                )
+                .unwrap(),
+            ),
+        };
+        let progress_bar = add(progress_bar.with_prefix("archive"));
+        Self {
+            rd: progress_bar.wrap_read(rd),
+            progress_bar,
+        }
+    }
+}
+
+impl<R: Read> Read for ArchiveProgress<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.rd.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            self.progress_bar.finish();
+        }
+        Ok(n)
+    }
+}
+
+impl<R: Read> Drop for ArchiveProgress<R> {
+    fn drop(&mut self) {
+        if !self.progress_bar.is_finished() {
+            self.progress_bar.abandon();
+        }
+    }
+}
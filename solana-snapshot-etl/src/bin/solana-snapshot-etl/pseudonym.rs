@@ -0,0 +1,41 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt::Write;
+
+/// Environment variable holding the hash key when no key file is given.
+pub const HASH_KEY_ENV: &str = "SOLANA_SNAPSHOT_ETL_HASH_KEY";
+
+/// Number of hash bytes kept in a token.
+const TOKEN_LEN: usize = 16;
+
+/// Renders pubkeys for output tables, optionally replacing them with keyed-hash tokens.
+///
+/// A token is the hex-encoded prefix of `HMAC-SHA256(key, pubkey)`, so a pubkey maps to
+/// the same token in every table of a run, but can't be recovered without the key.
+pub struct PubkeyEncoder {
+    mac: Option<Hmac<Sha256>>,
+}
+
+impl PubkeyEncoder {
+    pub fn new(hash_key: Option<&[u8]>) -> Self {
+        Self {
+            mac: hash_key
+                .map(|key| Hmac::new_from_slice(key).expect("HMAC accepts keys of any length")),
+        }
+    }
+
+    pub fn encode(&self, pubkey: &Pubkey) -> String {
+        let Some(mac) = &self.mac else {
+            return pubkey.to_string();
+        };
+        let mut mac = mac.clone();
+        mac.update(pubkey.as_ref());
+        let digest = mac.finalize().into_bytes();
+        let mut token = String::with_capacity(TOKEN_LEN * 2);
+        for byte in &digest[..TOKEN_LEN] {
+            write!(token, "{:02x}", byte).unwrap();
+        }
+        token
+    }
+}
@@ -0,0 +1,97 @@
+use log::info;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+/// Download URLs of the latest snapshots served by a node.
+pub struct RpcSnapshots {
+    pub full: String,
+    pub incremental: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HighestSnapshotSlot {
+    full: u64,
+    incremental: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<serde_json::Value>,
+}
+
+/// Queries the node's highest full and incremental snapshot slots and resolves the
+/// archives it serves for them.
+///
+/// Nodes redirect `/snapshot.tar.bz2` and `/incremental-snapshot.tar.bz2` to their latest
+/// archives, whatever their actual compression.
+pub fn discover(rpc_url: &str) -> Result<RpcSnapshots, Box<dyn std::error::Error>> {
+    let rpc_url = rpc_url.trim_end_matches('/');
+    let client = Client::new();
+    let body = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "getHighestSnapshotSlot",
+    });
+    let response: RpcResponse<HighestSnapshotSlot> = serde_json::from_slice(
+        &client
+            .post(rpc_url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()?
+            .error_for_status()?
+            .bytes()?,
+    )?;
+    let slots = match (response.result, response.error) {
+        (Some(slots), _) => slots,
+        (None, error) => {
+            return Err(format!(
+                "getHighestSnapshotSlot failed: {}",
+                error.unwrap_or_default()
+            )
+            .into())
+        }
+    };
+    info!(
+        "Node {} has full snapshot at slot {} and incremental snapshot at slot {:?}",
+        rpc_url, slots.full, slots.incremental
+    );
+
+    let full = resolve(
+        &client,
+        &format!("{}/snapshot.tar.bz2", rpc_url),
+        &format!("snapshot-{}-", slots.full),
+    )?;
+    let incremental = match slots.incremental {
+        Some(slot) => Some(resolve(
+            &client,
+            &format!("{}/incremental-snapshot.tar.bz2", rpc_url),
+            &format!("incremental-snapshot-{}-{}-", slots.full, slot),
+        )?),
+        None => None,
+    };
+    Ok(RpcSnapshots { full, incremental })
+}
+
+/// Follows the node's redirect to the archive it currently serves, failing if it isn't
+/// the one named by `expected_prefix`, e.g. because a newer snapshot was taken in between.
+/// Streaming another archive would pair a full and an incremental snapshot that don't match.
+fn resolve(
+    client: &Client,
+    url: &str,
+    expected_prefix: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let response = client.head(url).send()?.error_for_status()?;
+    let resolved = response.url().to_string();
+    let file_name = resolved.rsplit('/').next().unwrap_or_default();
+    if !file_name.starts_with(expected_prefix) {
+        return Err(format!(
+            "Node serves {} instead of the {}* archive it reported",
+            file_name, expected_prefix
+        )
+        .into());
+    }
+    info!("Resolved snapshot archive: {}", resolved);
+    Ok(resolved)
+}
@@ -0,0 +1,60 @@
+use aws_sdk_s3::Client;
+use log::info;
+use std::io::{self, Read};
+use tokio::runtime::Runtime;
+use tokio_util::io::SyncIoBridge;
+
+/// Object body streamed from S3, read synchronously.
+pub struct S3Download {
+    pub content_length: Option<u64>,
+    reader: SyncIoBridge<Box<dyn tokio::io::AsyncRead + Send + Unpin>>,
+    /// Drives the SDK's connection; declared last so the body is dropped first
+    _runtime: Runtime,
+}
+
+/// Splits an `s3://bucket/key` URL into bucket and key.
+pub fn parse_url(url: &str) -> Option<(&str, &str)> {
+    let (bucket, key) = url.strip_prefix("s3://")?.split_once('/')?;
+    (!bucket.is_empty() && !key.is_empty()).then_some((bucket, key))
+}
+
+impl S3Download {
+    /// Starts streaming the object, using the default AWS credential and region chain.
+    pub fn new(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (bucket, key) = parse_url(url).ok_or_else(|| format!("Invalid S3 URL '{}'", url))?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        let object = runtime.block_on(async {
+            let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+            Client::new(&config)
+                .get_object()
+                .bucket(bucket)
+                .key(key)
+                .send()
+                .await
+        })?;
+        let content_length = object
+            .content_length()
+            .and_then(|len| u64::try_from(len).ok());
+        info!(
+            "Streaming s3://{}/{} ({} bytes)",
+            bucket,
+            key,
+            content_length.unwrap_or_default()
+        );
+        let body: Box<dyn tokio::io::AsyncRead + Send + Unpin> =
+            Box::new(object.body.into_async_read());
+        Ok(Self {
+            content_length,
+            reader: SyncIoBridge::new_with_handle(body, runtime.handle().clone()),
+            _runtime: runtime,
+        })
+    }
+}
+
+impl Read for S3Download {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
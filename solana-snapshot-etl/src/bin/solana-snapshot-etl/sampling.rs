@@ -0,0 +1,71 @@
+/// SplitMix64 generator; sampling does not need cryptographic randomness.
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly distributed value in `0..bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+}
+
+/// Fixed-size uniform sample over a stream of unknown length (Algorithm R).
+pub struct Reservoir<T> {
+    capacity: usize,
+    seen: u64,
+    items: Vec<T>,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: 0,
+            items: Vec::with_capacity(capacity.min(1024)),
+        }
+    }
+
+    /// Returns the slot the next item should be written to, or None if it is not sampled.
+    /// Lets callers avoid building items (e.g. copying account data) that would be dropped.
+    pub fn offer(&mut self, rng: &mut SplitMix64) -> Option<usize> {
+        self.seen += 1;
+        if self.items.len() < self.capacity {
+            return Some(self.items.len());
+        }
+        let index = rng.below(self.seen) as usize;
+        (index < self.capacity).then_some(index)
+    }
+
+    pub fn put(&mut self, index: usize, item: T) {
+        if index == self.items.len() {
+            self.items.push(item);
+        } else {
+            self.items[index] = item;
+        }
+    }
+
+    pub fn into_items(self) -> Vec<T> {
+        self.items
+    }
+}
@@ -0,0 +1,157 @@
+use duckdb::types::Value;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Column produced by a table sink.
+#[derive(Clone, Copy)]
+pub struct Column {
+    pub name: &'static str,
+    pub sql_type: &'static str,
+}
+
+/// Per-table column selection and renaming, loaded from a JSON5 file:
+///
+/// ```json5
+/// {
+///   // Keep only these columns, renamed to the given names
+///   token_accounts: { pubkey: "address", owner: "wallet", amount: "amount" },
+/// }
+/// ```
+///
+/// Tables missing from the file keep all their columns, and a table listed in the file
+/// must keep at least one.
+#[derive(Default, Deserialize)]
+#[serde(transparent)]
+pub struct SchemaMapping {
+    tables: HashMap<String, HashMap<String, String>>,
+}
+
+impl SchemaMapping {
+    /// Loads a mapping for a sink writing `tables`, rejecting mappings of other tables.
+    pub fn load(path: &str, tables: &[&str]) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let mapping: Self = json5::from_str(&contents)?;
+        if let Some(unknown) = mapping
+            .tables
+            .keys()
+            .find(|table| !tables.contains(&table.as_str()))
+        {
+            return Err(format!(
+                "Unknown table '{}' in {}, expected one of: {}",
+                unknown,
+                path,
+                tables.join(", ")
+            )
+            .into());
+        }
+        Ok(mapping)
+    }
+
+    /// Resolves the output columns for a table, rejecting unknown source columns.
+    pub fn table(&self, table: &str, columns: &[Column]) -> Result<TableSchema, String> {
+        let selected = match self.tables.get(table) {
+            None => columns
+                .iter()
+                .enumerate()
+                .map(|(index, column)| {
+                    (index, column.name, column.name.to_string(), column.sql_type)
+                })
+                .collect(),
+            Some(mapping) => {
+                if let Some(unknown) = mapping
+                    .keys()
+                    .find(|name| !columns.iter().any(|column| column.name == name.as_str()))
+                {
+                    return Err(format!("Unknown column '{}' in table '{}'", unknown, table));
+                }
+                columns
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, column)| {
+                        mapping
+                            .get(column.name)
+                            .map(|renamed| (index, column.name, renamed.clone(), column.sql_type))
+                    })
+                    .collect::<Vec<_>>()
+            }
+        };
+        if selected.is_empty() {
+            return Err(format!(
+                "The column mapping leaves no column in table '{}'",
+                table
+            ));
+        }
+        Ok(TableSchema {
+            name: table.to_string(),
+            columns: selected,
+        })
+    }
+}
+
+pub struct TableSchema {
+    pub name: String,
+    /// (source column index, source name, output name, SQL type)
+    columns: Vec<(usize, &'static str, String, &'static str)>,
+}
+
+impl TableSchema {
+    /// Returns the output name and SQL type of each selected column.
+    pub fn columns(&self) -> impl Iterator<Item = (&str, &'static str)> {
+        self.columns
+            .iter()
+            .map(|(_, _, name, sql_type)| (name.as_str(), *sql_type))
+    }
+
+    /// Output name of a source column, or `None` if the mapping leaves it out.
+    pub fn output_name(&self, source: &str) -> Option<&str> {
+        self.columns
+            .iter()
+            .find(|(_, name, _, _)| *name == source)
+            .map(|(_, _, output, _)| output.as_str())
+    }
+
+    /// The table name, quoted for use in SQL.
+    pub fn quoted_name(&self) -> String {
+        quote_identifier(&self.name)
+    }
+
+    pub fn create_table_sql(&self) -> String {
+        format!(
+            "CREATE TABLE {} ({});",
+            self.quoted_name(),
+            self.column_definitions()
+        )
+    }
+
+    /// Like `create_table_sql`, keeping the table if it already exists.
+    pub fn create_table_if_not_exists_sql(&self) -> String {
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} ({});",
+            self.quoted_name(),
+            self.column_definitions()
+        )
+    }
+
+    fn column_definitions(&self) -> String {
+        let columns: Vec<_> = self
+            .columns
+            .iter()
+            .map(|(_, _, name, sql_type)| format!("{} {}", quote_identifier(name), sql_type))
+            .collect();
+        columns.join(", ")
+    }
+
+    /// Picks the selected columns out of a full row.
+    pub fn select(&self, mut row: Vec<Value>) -> Vec<Value> {
+        self.columns
+            .iter()
+            .map(|(index, _, _, _)| std::mem::replace(&mut row[*index], Value::Null))
+            .collect()
+    }
+}
+
+/// Quotes a table or column name for SQL, so that renamed columns may be keywords or
+/// contain any character.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Number of bits after the leading one that a bucket distinguishes.
+const MANTISSA_BITS: u32 = 4;
+/// Values below this are bucketed exactly.
+const EXACT_LIMIT: u64 = 2 << MANTISSA_BITS;
+
+/// Streaming, mergeable quantile sketch of data lengths.
+///
+/// Lengths are counted in log-linear buckets: below 32 every length has its own bucket,
+/// above that each power of two is split into 16 buckets, so reported quantiles are
+/// within 1/16 (6.25%) of the true value. Buckets are stored sparsely, so owners whose
+/// accounts all have the same length only take a single entry.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct LengthSketch {
+    buckets: BTreeMap<u16, u64>,
+    count: u64,
+    max: u64,
+}
+
+impl LengthSketch {
+    pub fn insert(&mut self, len: u64) {
+        *self.buckets.entry(bucket(len)).or_default() += 1;
+        self.count += 1;
+        self.max = self.max.max(len);
+    }
+
+    pub fn merge(&mut self, other: &LengthSketch) {
+        for (&bucket, &count) in &other.buckets {
+            *self.buckets.entry(bucket).or_default() += count;
+        }
+        self.count += other.count;
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// Nearest-rank quantile for `q` in `[0, 1]`, reported as the upper bound of the
+    /// bucket holding it (but never above the largest length seen).
+    pub fn quantile(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((q * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        for (&bucket, &count) in &self.buckets {
+            seen += count;
+            if seen >= rank {
+                return bucket_upper_bound(bucket).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+fn bucket(len: u64) -> u16 {
+    if len < EXACT_LIMIT {
+        return len as u16;
+    }
+    let exponent = u64::BITS - 1 - len.leading_zeros();
+    let shift = exponent - MANTISSA_BITS;
+    let mantissa = (len >> shift) & ((1 << MANTISSA_BITS) - 1);
+    (((shift + 1) << MANTISSA_BITS) as u64 + mantissa) as u16
+}
+
+fn bucket_upper_bound(bucket: u16) -> u64 {
+    let bucket = bucket as u64;
+    if bucket < EXACT_LIMIT {
+        return bucket;
+    }
+    let shift = (bucket >> MANTISSA_BITS) - 1;
+    let mantissa = bucket & ((1 << MANTISSA_BITS) - 1);
+    let lower = ((1 << MANTISSA_BITS) + mantissa) << shift;
+    lower + ((1 << shift) - 1)
+}
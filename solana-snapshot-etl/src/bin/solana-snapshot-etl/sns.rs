@@ -0,0 +1,55 @@
+use solana_sdk::hash::hashv;
+use solana_sdk::pubkey::Pubkey;
+
+pub const NAME_SERVICE_PROGRAM_ID: &str = "namesLPneVptA9Z5rqUDD9tMTWEJwofgaYwp8cawRkX";
+/// Root name account of the `.sol` top-level domain, the parent of every `.sol` domain.
+pub const SOL_TLD: &str = "58PwtjSDuFHuUkYjH9BYnnQKHfwo9reZhC2zMJv9JPkx";
+/// Class of the reverse lookup records storing the name of a domain.
+pub const REVERSE_LOOKUP_CLASS: &str = "33m47vH6Eav6jr5Ry86XjhRft2jRBLDnDgPSHoquXi2Z";
+
+/// Prefix hashed with a name to derive its name account.
+const HASH_PREFIX: &str = "SPL Name Service";
+/// Parent name, owner and class, followed by the record's data.
+const NAME_RECORD_HEADER_LEN: usize = 96;
+
+pub struct NameRecordHeader {
+    pub parent_name: Pubkey,
+    pub owner: Pubkey,
+    pub class: Pubkey,
+}
+
+/// Parses the header of a name record, or returns `None` if the account is too short.
+pub fn parse_header(data: &[u8]) -> Option<NameRecordHeader> {
+    if data.len() < NAME_RECORD_HEADER_LEN {
+        return None;
+    }
+    Some(NameRecordHeader {
+        parent_name: Pubkey::try_from(&data[0..32]).unwrap(),
+        owner: Pubkey::try_from(&data[32..64]).unwrap(),
+        class: Pubkey::try_from(&data[64..96]).unwrap(),
+    })
+}
+
+/// Name stored in a reverse lookup record: a borsh string after the header.
+pub fn parse_reverse_lookup(data: &[u8]) -> Option<String> {
+    let data = data.get(NAME_RECORD_HEADER_LEN..)?;
+    let len = u32::from_le_bytes(data.get(0..4)?.try_into().unwrap()) as usize;
+    let name = data.get(4..4 + len)?;
+    Some(String::from_utf8_lossy(name).into_owned())
+}
+
+/// Address of the reverse lookup record of the name account `domain`, as derived by
+/// `spl_name_service::state::get_name_account_key` with the reverse lookup class and no
+/// parent.
+pub fn reverse_lookup_key(domain: &Pubkey, program: &Pubkey, class: &Pubkey) -> Pubkey {
+    let hashed_name = hashv(&[HASH_PREFIX.as_bytes(), domain.to_string().as_bytes()]);
+    let (key, _) = Pubkey::find_program_address(
+        &[
+            hashed_name.as_ref(),
+            class.as_ref(),
+            Pubkey::default().as_ref(),
+        ],
+        program,
+    );
+    key
+}
@@ -0,0 +1,31 @@
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::stake::state::{Delegation, StakeState};
+use solana_snapshot_etl::append_vec::StoredAccountMeta;
+
+/// Decoded stake account.
+pub struct StakeRecord {
+    pub pubkey: Pubkey,
+    pub lamports: u64,
+    pub rent_exempt_reserve: u64,
+    pub staker: Pubkey,
+    pub withdrawer: Pubkey,
+    pub delegation: Option<Delegation>,
+}
+
+/// Decodes an account owned by the stake program. Returns None for uninitialized
+/// accounts and the rewards pool.
+pub fn parse_stake(account: &StoredAccountMeta) -> Option<StakeRecord> {
+    let (meta, delegation) = match bincode::deserialize::<StakeState>(account.data).ok()? {
+        StakeState::Initialized(meta) => (meta, None),
+        StakeState::Stake(meta, stake) => (meta, Some(stake.delegation)),
+        StakeState::Uninitialized | StakeState::RewardsPool => return None,
+    };
+    Some(StakeRecord {
+        pubkey: account.meta.pubkey,
+        lamports: account.account_meta.lamports,
+        rent_exempt_reserve: meta.rent_exempt_reserve,
+        staker: meta.authorized.staker,
+        withdrawer: meta.authorized.withdrawer,
+        delegation,
+    })
+}
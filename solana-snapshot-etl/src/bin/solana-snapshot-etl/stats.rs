@@ -1,94 +1,659 @@
+use crate::format;
+use crate::programs;
+use crate::progress;
+use crate::sketch::LengthSketch;
+use clap::ValueEnum;
+use duckdb::{params, Connection};
 use indicatif::{ProgressBar, ProgressStyle};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_sdk::bpf_loader_upgradeable;
+use solana_sdk::hash::Hash;
 use solana_sdk::pubkey::Pubkey;
-use solana_snapshot_etl::append_vec::AppendVec;
+use solana_snapshot_etl::append_vec::{AppendVec, StoredAccountMeta};
 use solana_snapshot_etl::append_vec_iter;
+use solana_snapshot_etl::cache::ArtifactCache;
 use solana_snapshot_etl::parallel::{AppendVecConsumer, AppendVecConsumerFactory, GenericResult};
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 
+/// Bincode tag of `UpgradeableLoaderState::ProgramData`.
+const PROGRAMDATA_TAG: u32 = 3;
+
+/// How `stats` groups accounts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StatsGrouping {
+    Owner,
+    /// The owner's category in the program label registry
+    Category,
+}
+
+/// Column the per-owner stats table is sorted by, largest first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StatsSortKey {
+    /// Number of accounts
+    Count,
+    /// Total data size
+    Size,
+    /// Average data size
+    Avg,
+}
+
+/// Format of the full stats written with `--output`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StatsFormat {
+    Csv,
+    /// An array of owner objects
+    Json,
+}
+
+/// One owner in the stats written with `--output`.
+#[derive(Serialize)]
+struct OwnerStatsExportRow {
+    owner: String,
+    program: Option<&'static str>,
+    category: Option<&'static str>,
+    count: u64,
+    empty_count: u64,
+    total_size: u64,
+    avg_size: u64,
+    total_lamports: u64,
+    executable_count: u64,
+    executable_size: u64,
+    data_len_p50: Option<u64>,
+    data_len_p90: Option<u64>,
+    data_len_max: Option<u64>,
+}
+
+/// How the stats table groups accounts, which owners it lists, and in which order.
+pub struct StatsTableOptions {
+    pub group_by: StatsGrouping,
+    pub sort: StatsSortKey,
+    pub top: usize,
+    /// Owners with fewer accounts are folded into the "other" row
+    pub min_count: u64,
+    /// Owners with less data are folded into the "other" row
+    pub min_bytes: u64,
+    /// Also list data length percentiles of the listed owners
+    pub data_len_percentiles: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct OwnerStats {
     pub count: u64,
+    /// Accounts without data, holding only lamports
+    pub empty_count: u64,
     pub total_size: u64,
+    pub total_lamports: u64,
+    /// Executable accounts, i.e. programs
+    pub executable_count: u64,
+    /// Data of executable accounts and of the ProgramData accounts holding the bytecode
+    /// of upgradeable programs
+    pub executable_size: u64,
+    /// Distribution of data lengths, if tracked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_lens: Option<LengthSketch>,
+}
+
+impl OwnerStats {
+    fn merge(&mut self, other: &OwnerStats) {
+        self.count += other.count;
+        self.empty_count += other.empty_count;
+        self.total_size += other.total_size;
+        self.total_lamports += other.total_lamports;
+        self.executable_count += other.executable_count;
+        self.executable_size += other.executable_size;
+        if let Some(data_lens) = &other.data_lens {
+            self.data_lens
+                .get_or_insert_with(LengthSketch::default)
+                .merge(data_lens);
+        }
+    }
+
+    pub fn avg_size(&self) -> u64 {
+        if self.count > 0 {
+            self.total_size / self.count
+        } else {
+            0
+        }
+    }
+
+    fn sort_key(&self, key: StatsSortKey) -> u64 {
+        match key {
+            StatsSortKey::Count => self.count,
+            StatsSortKey::Size => self.total_size,
+            StatsSortKey::Avg => self.avg_size(),
+        }
+    }
+}
+
+/// Serialized form of [`SharedStats`], stored in the artifact cache.
+#[derive(Serialize, Deserialize)]
+struct StatsReport {
+    accounts_count: u64,
+    owners: Vec<OwnerStatsRow>,
+}
+
+/// Serialized state of an unfinished run, stored in the artifact cache.
+#[derive(Serialize, Deserialize)]
+struct StatsCheckpoint {
+    /// (slot, id) of the append vecs whose accounts are included in the report
+    append_vecs: Vec<(u64, u64)>,
+    #[serde(flatten)]
+    report: StatsReport,
+}
+
+/// Where and how often [`SharedStats`] checkpoints its state while collecting.
+pub struct Checkpoints {
+    cache: ArtifactCache,
+    bank_hash: Hash,
+    artifact: String,
+    /// Accounts between checkpoints
+    every: u64,
+    /// Keeps concurrent flushes from writing the artifact at the same time
+    write_lock: Mutex<()>,
+}
+
+impl Checkpoints {
+    pub fn new(cache: ArtifactCache, bank_hash: Hash, artifact: String, every: u64) -> Self {
+        Self {
+            cache,
+            bank_hash,
+            artifact,
+            every,
+            write_lock: Mutex::new(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct OwnerStatsRow {
+    owner: String,
+    #[serde(flatten)]
+    stats: OwnerStats,
 }
 
 pub struct SharedStats {
     accounts_spinner: ProgressBar,
     accounts_count: AtomicU64,
     stats_by_owner: Mutex<HashMap<Pubkey, OwnerStats>>,
+    /// (slot, id) of the append vecs whose accounts are included in `stats_by_owner`.
+    /// Locked after `stats_by_owner` when both are needed.
+    append_vecs: Mutex<HashSet<(u64, u64)>>,
+    /// Print interim stats every million accounts
+    progress_reports: bool,
+    checkpoints: OnceLock<Checkpoints>,
 }
 
 impl SharedStats {
     pub fn new() -> Arc<Self> {
+        Self::with_progress_reports(true)
+    }
+
+    pub fn with_progress_reports(progress_reports: bool) -> Arc<Self> {
         let spinner_style = ProgressStyle::with_template(
             "{prefix:>10.bold.dim} {spinner} rate={per_sec}/s total={human_pos}",
         )
         .unwrap();
-        let accounts_spinner = ProgressBar::new_spinner()
-            .with_style(spinner_style)
-            .with_prefix("accs");
+        let accounts_spinner = progress::add(
+            ProgressBar::new_spinner()
+                .with_style(spinner_style)
+                .with_prefix("accs"),
+        );
 
         Arc::new(Self {
             accounts_spinner,
             accounts_count: AtomicU64::new(0),
             stats_by_owner: Mutex::new(HashMap::new()),
+            append_vecs: Mutex::new(HashSet::new()),
+            progress_reports,
+            checkpoints: OnceLock::new(),
         })
     }
 
-    pub fn print_stats(&self, top_n: Option<usize>) {
-        let top_n = top_n.unwrap_or(100);
+    /// Restores the state saved by a checkpoint, so that collecting continues with the
+    /// append vecs it does not include.
+    pub fn from_checkpoint(
+        bytes: &[u8],
+        progress_reports: bool,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let checkpoint: StatsCheckpoint = serde_json::from_slice(bytes)?;
+        let shared = Self::with_progress_reports(progress_reports);
+        shared.restore(checkpoint.report)?;
+        shared
+            .append_vecs
+            .lock()
+            .unwrap()
+            .extend(checkpoint.append_vecs);
+        shared
+            .accounts_spinner
+            .set_position(shared.accounts_count());
+        Ok(shared)
+    }
+
+    /// Writes a checkpoint every `checkpoints.every` accounts from now on.
+    pub fn enable_checkpoints(&self, checkpoints: Checkpoints) {
+        let _ = self.checkpoints.set(checkpoints);
+    }
+
+    fn write_checkpoint(
+        &self,
+        checkpoints: &Checkpoints,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let _guard = checkpoints.write_lock.lock().unwrap();
+        let stats_map = self.stats_by_owner.lock().unwrap();
+        let checkpoint = StatsCheckpoint {
+            append_vecs: self.append_vecs.lock().unwrap().iter().copied().collect(),
+            report: self.report(&stats_map),
+        };
+        drop(stats_map);
+        let path = checkpoints.cache.write(
+            &checkpoints.bank_hash,
+            &checkpoints.artifact,
+            &serde_json::to_vec(&checkpoint)?,
+        )?;
+        info!(
+            "Checkpointed stats of {} accounts to {:?}",
+            format::count(checkpoint.report.accounts_count),
+            path
+        );
+        Ok(())
+    }
+
+    /// Whether the accounts of an append vec are already included, because they were
+    /// restored from a checkpoint.
+    fn includes(&self, append_vec: (u64, u64)) -> bool {
+        self.append_vecs.lock().unwrap().contains(&append_vec)
+    }
+
+    /// Accounts a consumer buffers before merging them into the shared stats.
+    fn flush_interval(&self) -> u64 {
+        match self.checkpoints.get() {
+            Some(checkpoints) => FLUSH_INTERVAL.min(checkpoints.every),
+            None => FLUSH_INTERVAL,
+        }
+    }
+
+    /// Prints the owners matching `options`, with the remaining owners summed up in an
+    /// "other" row.
+    pub fn print_stats(&self, options: &StatsTableOptions) {
         let accounts_count = self.accounts_count.load(Ordering::Relaxed);
-        println!("\n--- Account Stats by Owner (Top {}) ---\n", top_n);
+        let sorted_by = match options.sort {
+            StatsSortKey::Count => "Count",
+            StatsSortKey::Size => "Total Size",
+            StatsSortKey::Avg => "Avg Size",
+        };
+        println!(
+            "\n--- Account Stats by Owner (Top {} by {}) ---\n",
+            options.top, sorted_by
+        );
 
         let stats_map = self.stats_by_owner.lock().unwrap();
         let mut stats: Vec<_> = stats_map.iter().collect();
-        stats.sort_by(|a, b| b.1.total_size.cmp(&a.1.total_size));
+        stats.sort_by_key(|(owner, stats)| (Reverse(stats.sort_key(options.sort)), **owner));
 
-        let total_count: u64 = stats.iter().map(|(_, s)| s.count).sum();
-        let total_size: u64 = stats.iter().map(|(_, s)| s.total_size).sum();
+        let mut total = OwnerStats::default();
+        for (_, owner_stats) in &stats {
+            total.merge(owner_stats);
+        }
 
         println!(
-            "{:<45} {:>15} {:>20} {:>15}",
-            "Owner", "Count", "Total Size (bytes)", "Avg Size"
+            "{:<45} {:>15} {:>20} {:>24} {:>15} {:>15}  Program",
+            "Owner", "Count", "Total Size (bytes)", "Lamports", "Avg Size", "Empty"
         );
-        println!("{}", "-".repeat(97));
+        println!("{}", "-".repeat(163));
 
-        for (owner, owner_stats) in stats.into_iter().take(top_n) {
-            let avg_size = if owner_stats.count > 0 {
-                owner_stats.total_size / owner_stats.count
-            } else {
-                0
-            };
+        let (listed, other): (Vec<_>, Vec<_>) = stats
+            .into_iter()
+            .partition(|(_, s)| s.count >= options.min_count && s.total_size >= options.min_bytes);
+        let other_owners = other.len() + listed.len().saturating_sub(options.top);
+        let mut other_stats = OwnerStats::default();
+        for (_, owner_stats) in other.iter().chain(listed.iter().skip(options.top)) {
+            other_stats.merge(owner_stats);
+        }
+
+        for (owner, owner_stats) in listed.into_iter().take(options.top) {
             println!(
-                "{:<45} {:>15} {:>20} {:>15}",
+                "{:<45} {:>15} {:>20} {:>24} {:>15} {:>15}  {}",
                 owner.to_string(),
-                owner_stats.count,
-                owner_stats.total_size,
-                avg_size
+                format::count(owner_stats.count),
+                format::bytes(owner_stats.total_size),
+                format::lamports(owner_stats.total_lamports),
+                format::bytes(owner_stats.avg_size()),
+                format::count(owner_stats.empty_count),
+                programs::name(owner).unwrap_or_default()
+            );
+        }
+        if other_owners > 0 {
+            println!(
+                "{:<45} {:>15} {:>20} {:>24} {:>15} {:>15}",
+                format!("OTHER ({} owners)", format::count(other_owners as u64)),
+                format::count(other_stats.count),
+                format::bytes(other_stats.total_size),
+                format::lamports(other_stats.total_lamports),
+                format::bytes(other_stats.avg_size()),
+                format::count(other_stats.empty_count)
+            );
+        }
+
+        println!("{}", "-".repeat(163));
+        println!(
+            "{:<45} {:>15} {:>20} {:>24} {:>15} {:>15}",
+            "TOTAL",
+            format::count(total.count),
+            format::bytes(total.total_size),
+            format::lamports(total.total_lamports),
+            format::bytes(total.avg_size()),
+            format::count(total.empty_count)
+        );
+        println!("\nAccounts processed: {}", format::count(accounts_count));
+    }
+
+    /// Prints data length percentiles of the owners the stats table lists. Requires the
+    /// stats to have been collected with data length tracking.
+    pub fn print_data_len_percentiles(&self, options: &StatsTableOptions) {
+        println!("\n--- Data Length Percentiles by Owner ---\n");
+
+        let stats_map = self.stats_by_owner.lock().unwrap();
+        let mut stats: Vec<_> = stats_map
+            .iter()
+            .filter(|(_, s)| s.count >= options.min_count && s.total_size >= options.min_bytes)
+            .filter_map(|(owner, s)| Some((owner, s, s.data_lens.as_ref()?)))
+            .collect();
+        stats.sort_by_key(|(owner, s, _)| (Reverse(s.sort_key(options.sort)), **owner));
+
+        println!(
+            "{:<45} {:>15} {:>12} {:>12} {:>12}  Program",
+            "Owner", "Count", "P50", "P90", "Max"
+        );
+        println!("{}", "-".repeat(122));
+        for (owner, owner_stats, data_lens) in stats.into_iter().take(options.top) {
+            println!(
+                "{:<45} {:>15} {:>12} {:>12} {:>12}  {}",
+                owner.to_string(),
+                format::count(owner_stats.count),
+                format::bytes(data_lens.quantile(0.5)),
+                format::bytes(data_lens.quantile(0.9)),
+                format::bytes(data_lens.max()),
+                programs::name(owner).unwrap_or_default()
+            );
+        }
+    }
+
+    /// Prints how much of the data is program bytecode, by the loaders owning it.
+    pub fn print_executable_stats(&self) {
+        println!("\n--- Executable Accounts by Loader ---\n");
+
+        let stats_map = self.stats_by_owner.lock().unwrap();
+        let mut loaders: Vec<_> = stats_map
+            .iter()
+            .filter(|(_, stats)| stats.executable_size > 0)
+            .collect();
+        loaders.sort_by_key(|(owner, stats)| (Reverse(stats.executable_size), **owner));
+        let total_size: u64 = stats_map.values().map(|s| s.total_size).sum();
+        let share = |size: u64| {
+            if total_size > 0 {
+                size as f64 / total_size as f64 * 100.0
+            } else {
+                0.0
+            }
+        };
+
+        println!(
+            "{:<45} {:>15} {:>20} {:>8}",
+            "Loader", "Programs", "Bytecode Size", "% Data"
+        );
+        println!("{}", "-".repeat(91));
+        let mut executable = OwnerStats::default();
+        for (owner, stats) in loaders {
+            executable.merge(stats);
+            println!(
+                "{:<45} {:>15} {:>20} {:>7.2}%",
+                programs::label(owner),
+                format::count(stats.executable_count),
+                format::bytes(stats.executable_size),
+                share(stats.executable_size)
             );
         }
+        println!("{}", "-".repeat(91));
+        println!(
+            "{:<45} {:>15} {:>20} {:>7.2}%",
+            "TOTAL",
+            format::count(executable.executable_count),
+            format::bytes(executable.executable_size),
+            share(executable.executable_size)
+        );
+        println!(
+            "\nState (non-executable) size: {}",
+            format::bytes(total_size - executable.executable_size)
+        );
+    }
 
-        println!("{}", "-".repeat(97));
+    /// Prints stats rolled up by owner program category, with uncategorized owners last.
+    pub fn print_category_stats(&self) {
+        println!("\n--- Account Stats by Program Category ---\n");
+
+        let stats_map = self.stats_by_owner.lock().unwrap();
+        let mut by_category: HashMap<Option<&str>, (u64, OwnerStats)> = HashMap::new();
+        for (owner, owner_stats) in stats_map.iter() {
+            let (owners, stats) = by_category.entry(programs::category(owner)).or_default();
+            *owners += 1;
+            stats.merge(owner_stats);
+        }
+        let mut categories: Vec<_> = by_category.into_iter().collect();
+        categories
+            .sort_by_key(|(category, (_, stats))| (category.is_none(), Reverse(stats.total_size)));
+        let total_size: u64 = stats_map.values().map(|s| s.total_size).sum();
+
+        println!(
+            "{:<20} {:>10} {:>15} {:>20} {:>8} {:>24}",
+            "Category", "Owners", "Count", "Total Size (bytes)", "% Size", "Lamports"
+        );
+        println!("{}", "-".repeat(102));
+        for (category, (owners, stats)) in categories {
+            let share = if total_size > 0 {
+                stats.total_size as f64 / total_size as f64 * 100.0
+            } else {
+                0.0
+            };
+            println!(
+                "{:<20} {:>10} {:>15} {:>20} {:>7.2}% {:>24}",
+                category.unwrap_or("uncategorized"),
+                format::count(owners),
+                format::count(stats.count),
+                format::bytes(stats.total_size),
+                share,
+                format::lamports(stats.total_lamports)
+            );
+        }
         println!(
-            "{:<45} {:>15} {:>20}",
-            "TOTAL", total_count, total_size
+            "\nAccounts processed: {}",
+            format::count(self.accounts_count.load(Ordering::Relaxed))
         );
-        println!("\nAccounts processed: {}", accounts_count);
     }
 
     pub fn finish(&self) {
         self.accounts_spinner.finish();
     }
+
+    /// Returns the owners with the largest total data size.
+    pub fn top_owners(&self, n: usize) -> Vec<Pubkey> {
+        self.top_owner_stats(n)
+            .into_iter()
+            .map(|(owner, _)| owner)
+            .collect()
+    }
+
+    /// Returns the stats of the owners with the largest total data size.
+    pub fn top_owner_stats(&self, n: usize) -> Vec<(Pubkey, OwnerStats)> {
+        let stats_map = self.stats_by_owner.lock().unwrap();
+        let mut stats: Vec<_> = stats_map.iter().collect();
+        stats.sort_by(|a, b| b.1.total_size.cmp(&a.1.total_size));
+        stats
+            .into_iter()
+            .take(n)
+            .map(|(owner, stats)| (*owner, stats.clone()))
+            .collect()
+    }
+
+    /// Returns the stats of every owner.
+    pub fn owner_stats(&self) -> HashMap<Pubkey, OwnerStats> {
+        self.stats_by_owner.lock().unwrap().clone()
+    }
+
+    pub fn accounts_count(&self) -> u64 {
+        self.accounts_count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total data size of all accounts.
+    pub fn total_size(&self) -> u64 {
+        let stats_map = self.stats_by_owner.lock().unwrap();
+        stats_map.values().map(|stats| stats.total_size).sum()
+    }
+
+    /// Writes the stats of all owners, sorted by `sort`, to `path`.
+    pub fn write_stats(
+        &self,
+        path: &str,
+        format: StatsFormat,
+        sort: StatsSortKey,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let stats_map = self.stats_by_owner.lock().unwrap();
+        let mut stats: Vec<_> = stats_map.iter().collect();
+        stats.sort_by_key(|(owner, stats)| (Reverse(stats.sort_key(sort)), **owner));
+        let rows = stats.into_iter().map(|(owner, stats)| OwnerStatsExportRow {
+            owner: owner.to_string(),
+            program: programs::name(owner),
+            category: programs::category(owner),
+            count: stats.count,
+            empty_count: stats.empty_count,
+            total_size: stats.total_size,
+            avg_size: stats.avg_size(),
+            total_lamports: stats.total_lamports,
+            executable_count: stats.executable_count,
+            executable_size: stats.executable_size,
+            data_len_p50: stats.data_lens.as_ref().map(|s| s.quantile(0.5)),
+            data_len_p90: stats.data_lens.as_ref().map(|s| s.quantile(0.9)),
+            data_len_max: stats.data_lens.as_ref().map(LengthSketch::max),
+        });
+
+        match format {
+            StatsFormat::Csv => {
+                let mut writer = csv::Writer::from_path(path)?;
+                for row in rows {
+                    writer.serialize(row)?;
+                }
+                writer.flush()?;
+            }
+            StatsFormat::Json => {
+                let rows: Vec<_> = rows.collect();
+                fs::write(path, serde_json::to_vec_pretty(&rows)?)?;
+            }
+        }
+        info!("Wrote stats of {} owners to {}", stats_map.len(), path);
+        Ok(())
+    }
+
+    /// The accounts count is only updated with `stats_by_owner` locked, so it matches
+    /// `stats_map`.
+    fn report(&self, stats_map: &HashMap<Pubkey, OwnerStats>) -> StatsReport {
+        StatsReport {
+            accounts_count: self.accounts_count.load(Ordering::Relaxed),
+            owners: stats_map
+                .iter()
+                .map(|(owner, stats)| OwnerStatsRow {
+                    owner: owner.to_string(),
+                    stats: stats.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn restore(&self, report: StatsReport) -> Result<(), Box<dyn std::error::Error>> {
+        self.accounts_count
+            .store(report.accounts_count, Ordering::Relaxed);
+        let mut stats_map = self.stats_by_owner.lock().unwrap();
+        for row in report.owners {
+            stats_map.insert(Pubkey::from_str(&row.owner)?, row.stats);
+        }
+        Ok(())
+    }
+
+    /// Appends the stats of all owners at `slot` to the `owner_stats` table of a DuckDB
+    /// database, replacing any earlier run for the same slot, so that runs over a series
+    /// of snapshots build up a time series.
+    pub fn write_db(&self, db_path: &str, slot: u64) -> Result<(), Box<dyn std::error::Error>> {
+        info!("Opening DuckDB database: {}", db_path);
+        let mut conn = Connection::open(db_path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS owner_stats (
+                slot UBIGINT NOT NULL,
+                owner VARCHAR NOT NULL,
+                count UBIGINT NOT NULL,
+                bytes UBIGINT NOT NULL,
+                lamports UBIGINT NOT NULL
+             );",
+        )?;
+
+        let stats_map = self.stats_by_owner.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM owner_stats WHERE slot = ?", params![slot])?;
+        {
+            let mut appender = tx.appender("owner_stats")?;
+            for (owner, stats) in stats_map.iter() {
+                appender.append_row(params![
+                    slot,
+                    owner.to_string(),
+                    stats.count,
+                    stats.total_size,
+                    stats.total_lamports
+                ])?;
+            }
+        }
+        tx.commit()?;
+        info!(
+            "Wrote stats of {} owners at slot {} to {}",
+            stats_map.len(),
+            slot,
+            db_path
+        );
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        let stats_map = self.stats_by_owner.lock().unwrap();
+        serde_json::to_vec(&self.report(&stats_map))
+    }
+
+    pub fn from_json(bytes: &[u8]) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let report: StatsReport = serde_json::from_slice(bytes)?;
+        let shared = Self::new();
+        shared.restore(report)?;
+        Ok(shared)
+    }
 }
 
 pub struct StatsConsumerFactory {
     shared: Arc<SharedStats>,
+    data_lens: bool,
 }
 
 impl StatsConsumerFactory {
     pub fn new(shared: Arc<SharedStats>) -> Self {
-        Self { shared }
+        Self {
+            shared,
+            data_lens: false,
+        }
+    }
+
+    /// Also tracks the distribution of data lengths per owner.
+    pub fn with_data_lens(mut self, data_lens: bool) -> Self {
+        self.data_lens = data_lens;
+        self
     }
 }
 
@@ -100,6 +665,8 @@ impl AppendVecConsumerFactory for StatsConsumerFactory {
             shared: Arc::clone(&self.shared),
             local_stats: HashMap::new(),
             local_count: 0,
+            local_append_vecs: Vec::new(),
+            data_lens: self.data_lens,
         })
     }
 }
@@ -110,62 +677,105 @@ pub struct StatsConsumer {
     shared: Arc<SharedStats>,
     local_stats: HashMap<Pubkey, OwnerStats>,
     local_count: u64,
+    /// (slot, id) of the append vecs counted in `local_stats`
+    local_append_vecs: Vec<(u64, u64)>,
+    data_lens: bool,
 }
 
 impl StatsConsumer {
     fn flush(&mut self) {
-        if self.local_count == 0 {
+        if self.local_append_vecs.is_empty() {
             return;
         }
 
         let mut shared_stats = self.shared.stats_by_owner.lock().unwrap();
         for (owner, local) in self.local_stats.drain() {
-            let entry = shared_stats.entry(owner).or_insert(OwnerStats {
-                count: 0,
-                total_size: 0,
-            });
-            entry.count += local.count;
-            entry.total_size += local.total_size;
+            shared_stats.entry(owner).or_default().merge(&local);
         }
-        drop(shared_stats);
-
-        let new_count = self
+        self.shared
+            .append_vecs
+            .lock()
+            .unwrap()
+            .extend(self.local_append_vecs.drain(..));
+        let old_count = self
             .shared
             .accounts_count
-            .fetch_add(self.local_count, Ordering::Relaxed)
-            + self.local_count;
+            .fetch_add(self.local_count, Ordering::Relaxed);
+        drop(shared_stats);
+
+        let new_count = old_count + self.local_count;
         self.shared.accounts_spinner.set_position(new_count);
 
         // Print stats every million accounts
-        let old_millions = (new_count - self.local_count) / 1_000_000;
+        let old_millions = old_count / 1_000_000;
         let new_millions = new_count / 1_000_000;
-        if new_millions > old_millions {
-            self.shared.print_stats(Some(10));
+        if self.shared.progress_reports && new_millions > old_millions {
+            self.shared.print_stats(&StatsTableOptions {
+                group_by: StatsGrouping::Owner,
+                sort: StatsSortKey::Size,
+                top: 10,
+                min_count: 0,
+                min_bytes: 0,
+                data_len_percentiles: false,
+            });
+        }
+        if let Some(checkpoints) = self.shared.checkpoints.get() {
+            if new_count / checkpoints.every > old_count / checkpoints.every {
+                // A failed checkpoint only costs the ability to resume, so collecting goes on
+                if let Err(e) = self.shared.write_checkpoint(checkpoints) {
+                    warn!("Failed to write stats checkpoint: {}", e);
+                }
+            }
         }
 
         self.local_count = 0;
     }
 }
 
+/// Whether `account` is the ProgramData account of an upgradeable program.
+fn is_programdata(account: &StoredAccountMeta) -> bool {
+    account.account_meta.owner == bpf_loader_upgradeable::id()
+        && account.data.starts_with(&PROGRAMDATA_TAG.to_le_bytes())
+}
+
 impl AppendVecConsumer for StatsConsumer {
-    fn on_append_vec(&mut self, append_vec: AppendVec) -> GenericResult<()> {
-        for account in append_vec_iter(Rc::new(append_vec)) {
-            let account = account.access().unwrap();
+    fn on_append_vec(&mut self, append_vec: &AppendVec) -> GenericResult<()> {
+        let id = (append_vec.get_slot(), append_vec.get_id());
+        if self.shared.includes(id) {
+            return Ok(());
+        }
+        for account in append_vec_iter(append_vec) {
             let owner = account.account_meta.owner;
             let data_len = account.data.len() as u64;
 
-            let entry = self.local_stats.entry(owner).or_insert(OwnerStats {
-                count: 0,
-                total_size: 0,
-            });
+            let entry = self.local_stats.entry(owner).or_default();
             entry.count += 1;
+            if data_len == 0 {
+                entry.empty_count += 1;
+            }
             entry.total_size += data_len;
+            entry.total_lamports += account.account_meta.lamports;
+            if account.account_meta.executable {
+                entry.executable_count += 1;
+                entry.executable_size += data_len;
+            } else if is_programdata(&account) {
+                // Upgradeable programs keep their bytecode in a non-executable account
+                entry.executable_size += data_len;
+            }
+            if self.data_lens {
+                entry
+                    .data_lens
+                    .get_or_insert_with(LengthSketch::default)
+                    .insert(data_len);
+            }
 
             self.local_count += 1;
+        }
+        self.local_append_vecs.push(id);
 
-            if self.local_count >= FLUSH_INTERVAL {
-                self.flush();
-            }
+        // Only whole append vecs are flushed, so checkpoints can tell which to skip
+        if self.local_count >= self.shared.flush_interval() {
+            self.flush();
         }
         Ok(())
     }
@@ -1,4 +1,25 @@
 pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
 pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
 pub const TOKEN_ACCOUNT_LEN: usize = 165;
 pub const MINT_ACCOUNT_LEN: usize = 82;
+pub const MULTISIG_ACCOUNT_LEN: usize = 355;
+/// `AccountState::Frozen` in the state byte of a token account.
+pub const ACCOUNT_STATE_FROZEN: u8 = 2;
+/// Account type byte Token-2022 stores after the base state of extended accounts, which
+/// mints pad to the length of a token account.
+const TOKEN_2022_ACCOUNT_TYPE_MINT: u8 = 1;
+const TOKEN_2022_ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+/// Whether `data` holds a mint: the 82-byte base state, which Token-2022 mints extend.
+pub fn is_mint(data: &[u8], is_token_2022: bool) -> bool {
+    data.len() == MINT_ACCOUNT_LEN
+        || (is_token_2022 && data.get(TOKEN_ACCOUNT_LEN) == Some(&TOKEN_2022_ACCOUNT_TYPE_MINT))
+}
+
+/// Whether `data` holds a token account: the 165-byte base state, which Token-2022
+/// accounts extend.
+pub fn is_token_account(data: &[u8], is_token_2022: bool) -> bool {
+    data.len() == TOKEN_ACCOUNT_LEN
+        || (is_token_2022 && data.get(TOKEN_ACCOUNT_LEN) == Some(&TOKEN_2022_ACCOUNT_TYPE_ACCOUNT))
+}
@@ -0,0 +1,142 @@
+//! Bloom filters over account pubkeys, answering "did this account exist at the
+//! snapshot slot" without keeping the snapshot around.
+//!
+//! Filters are written as follows, with integers in little-endian:
+//!
+//! - magic `SSEBLOOM`
+//! - slot (u64) and bank hash (32 bytes) of the snapshot
+//! - number of hash functions `k` (u32) and number of bits `m` (u64)
+//! - the bits, `ceil(m / 8)` bytes, bit `i` being bit `i % 8` of byte `i / 8`
+//!
+//! A pubkey sets bits `(h1 + i * h2) mod m` for `i` in `0..k`, where `h1` and `h2`
+//! are the first two u64 words of the SHA-256 hash of the pubkey.
+
+use crate::{Result, SnapshotError};
+use solana_sdk::clock::Slot;
+use solana_sdk::hash::{hash, Hash};
+use solana_sdk::pubkey::Pubkey;
+use std::f64::consts::LN_2;
+use std::io::{Read, Write};
+
+const BLOOM_MAGIC: &[u8; 8] = b"SSEBLOOM";
+
+pub struct PubkeyBloomFilter {
+    slot: Slot,
+    bank_hash: Hash,
+    num_hashes: u32,
+    num_bits: u64,
+    bits: Vec<u8>,
+}
+
+impl PubkeyBloomFilter {
+    /// Creates an empty filter sized for `expected_items` pubkeys at a false positive
+    /// rate of `fp_rate`.
+    pub fn new(expected_items: u64, fp_rate: f64, slot: Slot, bank_hash: Hash) -> Self {
+        assert!(
+            fp_rate > 0.0 && fp_rate < 1.0,
+            "false positive rate must be between 0 and 1"
+        );
+        let items = expected_items.max(1) as f64;
+        let num_bits = (-items * fp_rate.ln() / (LN_2 * LN_2)).ceil().max(8.0) as u64;
+        let num_hashes = ((num_bits as f64 / items) * LN_2).round().max(1.0) as u32;
+        Self {
+            slot,
+            bank_hash,
+            num_hashes,
+            num_bits,
+            bits: vec![0; num_bits.div_ceil(8) as usize],
+        }
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    pub fn bank_hash(&self) -> &Hash {
+        &self.bank_hash
+    }
+
+    pub fn num_hashes(&self) -> u32 {
+        self.num_hashes
+    }
+
+    pub fn num_bits(&self) -> u64 {
+        self.num_bits
+    }
+
+    pub fn insert(&mut self, pubkey: &Pubkey) {
+        for bit in self.bit_indexes(pubkey) {
+            self.bits[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+    }
+
+    /// Returns false if `pubkey` was certainly not inserted, true if it probably was.
+    pub fn contains(&self, pubkey: &Pubkey) -> bool {
+        self.bit_indexes(pubkey)
+            .all(|bit| self.bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0)
+    }
+
+    /// Estimated false positive rate after inserting `items` pubkeys.
+    pub fn false_positive_rate(&self, items: u64) -> f64 {
+        let k = self.num_hashes as f64;
+        (1.0 - (-k * items as f64 / self.num_bits as f64).exp()).powf(k)
+    }
+
+    fn bit_indexes(&self, pubkey: &Pubkey) -> impl Iterator<Item = u64> {
+        let digest = hash(pubkey.as_ref()).to_bytes();
+        let h1 = u64::from_le_bytes(digest[0..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap());
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(BLOOM_MAGIC)?;
+        writer.write_all(&self.slot.to_le_bytes())?;
+        writer.write_all(self.bank_hash.as_ref())?;
+        writer.write_all(&self.num_hashes.to_le_bytes())?;
+        writer.write_all(&self.num_bits.to_le_bytes())?;
+        writer.write_all(&self.bits)?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != BLOOM_MAGIC {
+            return Err(SnapshotError::InvalidBloomFilter(
+                "not a pubkey Bloom filter",
+            ));
+        }
+        let mut slot = [0u8; 8];
+        reader.read_exact(&mut slot)?;
+        let mut bank_hash = [0u8; 32];
+        reader.read_exact(&mut bank_hash)?;
+        let mut num_hashes = [0u8; 4];
+        reader.read_exact(&mut num_hashes)?;
+        let mut num_bits = [0u8; 8];
+        reader.read_exact(&mut num_bits)?;
+        let num_hashes = u32::from_le_bytes(num_hashes);
+        let num_bits = u64::from_le_bytes(num_bits);
+        if num_hashes == 0 || num_bits == 0 {
+            return Err(SnapshotError::InvalidBloomFilter("empty filter parameters"));
+        }
+        // The bits are read as they come rather than allocated up front, so a corrupt
+        // header can't claim more bits than the file holds
+        let len = num_bits.div_ceil(8);
+        let mut bits = Vec::new();
+        reader.take(len.saturating_add(1)).read_to_end(&mut bits)?;
+        if bits.len() as u64 != len {
+            return Err(SnapshotError::InvalidBloomFilter(
+                "number of bits doesn't match the file length",
+            ));
+        }
+        Ok(Self {
+            slot: u64::from_le_bytes(slot),
+            bank_hash: Hash::new_from_array(bank_hash),
+            num_hashes,
+            num_bits,
+            bits,
+        })
+    }
+}
@@ -0,0 +1,106 @@
+use crate::Result;
+use log::debug;
+use solana_sdk::hash::{hash, Hash};
+use std::fs;
+use std::path::PathBuf;
+
+const CACHE_DIR_NAME: &str = "solana-snapshot-etl";
+
+/// Artifact holding a JSON summary of the snapshot manifest.
+pub const MANIFEST_ARTIFACT: &str = "manifest.json";
+
+/// Artifact holding the aggregated per-owner stats report.
+pub const STATS_ARTIFACT: &str = "stats.json";
+
+/// Artifact holding the locations of the older account versions hidden by `--dedup`, so
+/// that later runs don't need a first pass.
+pub const SKIP_INDEX_ARTIFACT: &str = "skip-index.bin";
+
+/// Artifact holding the partial per-owner stats of an interrupted run, from which the
+/// next run resumes.
+pub const STATS_CHECKPOINT_ARTIFACT: &str = "stats.checkpoint.json";
+
+/// Version of the artifact formats, bumped whenever one of them changes so that artifacts
+/// written by other versions are never read back.
+pub const CACHE_FORMAT_VERSION: u32 = 2;
+
+/// Derives the name of an artifact computed with non-default options,
+/// e.g. `stats.json` becomes `stats.1f3a9c0e.json`.
+pub fn variant_name(name: &str, options: &str) -> String {
+    if options.is_empty() {
+        return name.to_string();
+    }
+    let digest = hash(options.as_bytes()).to_string();
+    match name.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}.{}.{}", stem, &digest[..8], ext),
+        None => format!("{}.{}", name, &digest[..8]),
+    }
+}
+
+/// Content-addressed store for artifacts derived from a snapshot.
+///
+/// Artifacts are grouped in one directory per snapshot, named after the bank hash,
+/// so repeated runs against the same snapshot can reuse earlier results. Snapshot
+/// directories live under a directory named after [`CACHE_FORMAT_VERSION`].
+#[derive(Clone)]
+pub struct ArtifactCache {
+    root: PathBuf,
+}
+
+impl ArtifactCache {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Opens the cache in `$XDG_CACHE_HOME/solana-snapshot-etl`,
+    /// falling back to `~/.cache/solana-snapshot-etl`.
+    pub fn open_default() -> Option<Self> {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(Self::new(base.join(CACHE_DIR_NAME)))
+    }
+
+    pub fn root(&self) -> &PathBuf {
+        &self.root
+    }
+
+    pub fn artifact_path(&self, bank_hash: &Hash, name: &str) -> PathBuf {
+        self.root
+            .join(format!("v{}", CACHE_FORMAT_VERSION))
+            .join(bank_hash.to_string())
+            .join(name)
+    }
+
+    /// Returns the contents of a cached artifact, if present.
+    pub fn read(&self, bank_hash: &Hash, name: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.artifact_path(bank_hash, name);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        debug!("Cache hit: {:?}", path);
+        Ok(Some(fs::read(path)?))
+    }
+
+    /// Stores an artifact, replacing any previous version atomically.
+    pub fn write(&self, bank_hash: &Hash, name: &str, contents: &[u8]) -> Result<PathBuf> {
+        let path = self.artifact_path(bank_hash, name);
+        let dir = path.parent().expect("artifact path has a parent");
+        fs::create_dir_all(dir)?;
+        let tmp_path = dir.join(format!(".{}.tmp", name));
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, &path)?;
+        debug!("Cached artifact: {:?}", path);
+        Ok(path)
+    }
+
+    /// Removes an artifact, if present.
+    pub fn remove(&self, bank_hash: &Hash, name: &str) -> Result<()> {
+        let path = self.artifact_path(bank_hash, name);
+        if path.is_file() {
+            fs::remove_file(&path)?;
+            debug!("Removed artifact: {:?}", path);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use crate::append_vec::{AccountMask, StoredAccountMeta, StoredMetaWriteVersion};
+use crate::{append_vec_iter, AppendVecIterator, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+/// Latest (slot, write version) of every account in a snapshot.
+///
+/// Snapshots keep older versions of accounts in the append vecs of earlier slots.
+/// The index takes ~48 bytes per distinct account.
+#[derive(Default)]
+pub struct LatestVersionIndex {
+    latest: HashMap<Pubkey, (Slot, StoredMetaWriteVersion)>,
+}
+
+impl LatestVersionIndex {
+    /// Records a version of an account and returns true if it is now the latest one.
+    /// Of two equal versions, the one recorded last wins.
+    pub fn update(
+        &mut self,
+        pubkey: Pubkey,
+        slot: Slot,
+        write_version: StoredMetaWriteVersion,
+    ) -> bool {
+        let version = (slot, write_version);
+        match self.latest.entry(pubkey) {
+            Entry::Occupied(entry) if *entry.get() > version => false,
+            Entry::Occupied(mut entry) => {
+                entry.insert(version);
+                true
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(version);
+                true
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.latest.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_empty()
+    }
+}
+
+/// Slot and ID of an append vec, and the offset of an account stored in it.
+type StoredLocation = (Slot, u64, usize);
+
+/// Locations of the superseded versions of accounts, hidden by `--dedup`.
+///
+/// Building the index takes a full pass and ~64 bytes per distinct account; only the
+/// superseded versions are kept afterwards. Of two equal versions of an account, e.g.
+/// duplicates within one append vec, the one stored last is kept, as in
+/// [`LatestVersionIndex`].
+#[derive(Default, Serialize, Deserialize)]
+pub struct SkipIndex {
+    skipped: HashSet<StoredLocation>,
+}
+
+impl SkipIndex {
+    pub fn build(iterator: AppendVecIterator<'_>) -> Result<Self> {
+        let mut latest: HashMap<Pubkey, ((Slot, StoredMetaWriteVersion), StoredLocation)> =
+            HashMap::new();
+        let mut skipped = HashSet::new();
+        for append_vec in iterator {
+            let append_vec = append_vec?;
+            let slot = append_vec.get_slot();
+            for account in append_vec_iter(&append_vec) {
+                let version = (slot, account.meta.write_version);
+                let location = (slot, append_vec.get_id(), account.offset);
+                match latest.entry(account.meta.pubkey) {
+                    Entry::Occupied(entry) if entry.get().0 > version => {
+                        skipped.insert(location);
+                    }
+                    Entry::Occupied(mut entry) => {
+                        let (_, superseded) = entry.insert((version, location));
+                        skipped.insert(superseded);
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert((version, location));
+                    }
+                }
+            }
+        }
+        info!(
+            "Indexed latest versions of {} accounts, skipping {} older versions",
+            latest.len(),
+            skipped.len()
+        );
+        Ok(Self { skipped })
+    }
+
+    /// Serializes the index, e.g. to cache it with [`crate::cache::SKIP_INDEX_ARTIFACT`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+
+    /// Number of superseded versions.
+    pub fn len(&self) -> usize {
+        self.skipped.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.skipped.is_empty()
+    }
+}
+
+impl AccountMask for SkipIndex {
+    fn is_hidden(&self, slot: u64, id: u64, account: &StoredAccountMeta) -> bool {
+        self.skipped.contains(&(slot, id, account.offset))
+    }
+}
@@ -0,0 +1,334 @@
+//! Per-account filter expressions, such as
+//! `owner = 'TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA' AND data_len > 1000`.
+//!
+//! An expression compares account fields to literals with `=`, `!=`, `<`, `<=`, `>`, `>=`
+//! or `IN (...)`, and combines comparisons with `AND`, `OR`, `NOT` and parentheses.
+//! Fields are `pubkey` and `owner` (compared to quoted base58 pubkeys), `lamports`,
+//! `data_len`, `rent_epoch`, `slot` (integers) and `executable` (`true` or `false`).
+//! Keywords and field names are case-insensitive.
+
+use crate::append_vec::{AccountMask, StoredAccountMeta};
+use solana_sdk::pubkey::Pubkey;
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::FromStr;
+use std::vec::IntoIter;
+
+/// A parsed filter expression, hiding the accounts it doesn't match.
+#[derive(Clone)]
+pub struct AccountExpr {
+    source: String,
+    expr: Expr,
+}
+
+impl AccountExpr {
+    pub fn matches(&self, slot: u64, account: &StoredAccountMeta) -> bool {
+        self.expr.eval(slot, account)
+    }
+}
+
+impl FromStr for AccountExpr {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut tokens = tokenize(source)?.into_iter().peekable();
+        let expr = parse_or(&mut tokens)?;
+        if let Some(token) = tokens.next() {
+            return Err(format!("unexpected {} after expression", token));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+}
+
+impl fmt::Debug for AccountExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.source)
+    }
+}
+
+impl AccountMask for AccountExpr {
+    fn is_hidden(&self, slot: u64, _id: u64, account: &StoredAccountMeta) -> bool {
+        !self.matches(slot, account)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Pubkey,
+    Owner,
+    Lamports,
+    DataLen,
+    RentEpoch,
+    Slot,
+    Executable,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "pubkey" => Field::Pubkey,
+            "owner" => Field::Owner,
+            "lamports" => Field::Lamports,
+            "data_len" => Field::DataLen,
+            "rent_epoch" => Field::RentEpoch,
+            "slot" => Field::Slot,
+            "executable" => Field::Executable,
+            _ => return None,
+        })
+    }
+
+    fn value(self, slot: u64, account: &StoredAccountMeta) -> Value {
+        match self {
+            Field::Pubkey => Value::Pubkey(account.meta.pubkey),
+            Field::Owner => Value::Pubkey(account.account_meta.owner),
+            Field::Lamports => Value::Int(account.account_meta.lamports),
+            Field::DataLen => Value::Int(account.meta.data_len),
+            Field::RentEpoch => Value::Int(account.account_meta.rent_epoch),
+            Field::Slot => Value::Int(slot),
+            Field::Executable => Value::Bool(account.account_meta.executable),
+        }
+    }
+
+    /// Whether the field's values have an order, rather than only equality.
+    fn is_ordered(self) -> bool {
+        !matches!(self, Field::Pubkey | Field::Owner | Field::Executable)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Value {
+    Pubkey(Pubkey),
+    Int(u64),
+    Bool(bool),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering.is_eq(),
+            Op::Ne => ordering.is_ne(),
+            Op::Lt => ordering.is_lt(),
+            Op::Le => ordering.is_le(),
+            Op::Gt => ordering.is_gt(),
+            Op::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Value),
+    In(Field, Vec<Value>),
+}
+
+impl Expr {
+    fn eval(&self, slot: u64, account: &StoredAccountMeta) -> bool {
+        match self {
+            Expr::And(a, b) => a.eval(slot, account) && b.eval(slot, account),
+            Expr::Or(a, b) => a.eval(slot, account) || b.eval(slot, account),
+            Expr::Not(a) => !a.eval(slot, account),
+            Expr::Compare(field, op, value) => op.holds(field.value(slot, account).cmp(value)),
+            Expr::In(field, values) => values.contains(&field.value(slot, account)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(u64),
+    Str(String),
+    Op(Op),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Int(n) => write!(f, "{}", n),
+            Token::Str(s) => write!(f, "string '{}'", s),
+            Token::Op(op) => write!(f, "operator {:?}", op),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = match c {
+            '(' | ')' | ',' | '=' => {
+                chars.next();
+                match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    ',' => Token::Comma,
+                    _ => Token::Op(Op::Eq),
+                }
+            }
+            '!' | '<' | '>' => {
+                chars.next();
+                let eq = chars.next_if_eq(&'=').is_some();
+                match (c, eq) {
+                    ('!', true) => Token::Op(Op::Ne),
+                    ('<', true) => Token::Op(Op::Le),
+                    ('<', false) if chars.next_if_eq(&'>').is_some() => Token::Op(Op::Ne),
+                    ('<', false) => Token::Op(Op::Lt),
+                    ('>', true) => Token::Op(Op::Ge),
+                    ('>', false) => Token::Op(Op::Gt),
+                    _ => return Err("expected '=' after '!'".to_string()),
+                }
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some(next) => s.push(next),
+                        None => return Err(format!("unterminated string {}{}", c, s)),
+                    }
+                }
+                Token::Str(s)
+            }
+            c if c.is_ascii_digit() => {
+                let mut digits = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '_') {
+                    digits.push(c);
+                }
+                let n = digits
+                    .replace('_', "")
+                    .parse()
+                    .map_err(|e| format!("invalid number {}: {}", digits, e))?;
+                Token::Int(n)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                Token::Ident(name.to_ascii_lowercase())
+            }
+            c => return Err(format!("unexpected character '{}'", c)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+type Tokens = Peekable<IntoIter<Token>>;
+
+fn next_is_keyword(tokens: &mut Tokens, keyword: &str) -> bool {
+    tokens
+        .next_if(|token| matches!(token, Token::Ident(name) if name == keyword))
+        .is_some()
+}
+
+fn expect(tokens: &mut Tokens, expected: Token) -> Result<(), String> {
+    match tokens.next() {
+        Some(token) if token == expected => Ok(()),
+        Some(token) => Err(format!("expected {}, found {}", expected, token)),
+        None => Err(format!("expected {}, found end of expression", expected)),
+    }
+}
+
+fn parse_or(tokens: &mut Tokens) -> Result<Expr, String> {
+    let mut expr = parse_and(tokens)?;
+    while next_is_keyword(tokens, "or") {
+        expr = Expr::Or(Box::new(expr), Box::new(parse_and(tokens)?));
+    }
+    Ok(expr)
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<Expr, String> {
+    let mut expr = parse_not(tokens)?;
+    while next_is_keyword(tokens, "and") {
+        expr = Expr::And(Box::new(expr), Box::new(parse_not(tokens)?));
+    }
+    Ok(expr)
+}
+
+fn parse_not(tokens: &mut Tokens) -> Result<Expr, String> {
+    if next_is_keyword(tokens, "not") {
+        return Ok(Expr::Not(Box::new(parse_not(tokens)?)));
+    }
+    if tokens.next_if_eq(&Token::LParen).is_some() {
+        let expr = parse_or(tokens)?;
+        expect(tokens, Token::RParen)?;
+        return Ok(expr);
+    }
+    parse_comparison(tokens)
+}
+
+fn parse_comparison(tokens: &mut Tokens) -> Result<Expr, String> {
+    let field = match tokens.next() {
+        Some(Token::Ident(name)) => {
+            Field::from_name(&name).ok_or_else(|| format!("unknown field '{}'", name))?
+        }
+        Some(token) => return Err(format!("expected a field name, found {}", token)),
+        None => return Err("expected a field name, found end of expression".to_string()),
+    };
+
+    if next_is_keyword(tokens, "in") {
+        expect(tokens, Token::LParen)?;
+        let mut values = vec![parse_value(tokens, field)?];
+        while tokens.next_if_eq(&Token::Comma).is_some() {
+            values.push(parse_value(tokens, field)?);
+        }
+        expect(tokens, Token::RParen)?;
+        return Ok(Expr::In(field, values));
+    }
+
+    let op = match tokens.next() {
+        Some(Token::Op(op)) => op,
+        Some(token) => return Err(format!("expected a comparison operator, found {}", token)),
+        None => return Err("expected a comparison operator, found end of expression".to_string()),
+    };
+    if !field.is_ordered() && !matches!(op, Op::Eq | Op::Ne) {
+        return Err(format!("{:?} can only be compared with = or !=", field));
+    }
+    Ok(Expr::Compare(field, op, parse_value(tokens, field)?))
+}
+
+fn parse_value(tokens: &mut Tokens, field: Field) -> Result<Value, String> {
+    let token = tokens
+        .next()
+        .ok_or_else(|| format!("expected a value for {:?}", field))?;
+    match (field, token) {
+        (Field::Pubkey | Field::Owner, Token::Str(s)) => Pubkey::from_str(&s)
+            .map(Value::Pubkey)
+            .map_err(|e| format!("invalid pubkey '{}': {}", s, e)),
+        (Field::Executable, Token::Ident(name)) if name == "true" || name == "false" => {
+            Ok(Value::Bool(name == "true"))
+        }
+        (field, Token::Int(n)) if field.is_ordered() => Ok(Value::Int(n)),
+        (field, token) => Err(format!("invalid value {} for {:?}", token, field)),
+    }
+}
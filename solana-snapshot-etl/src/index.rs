@@ -0,0 +1,221 @@
+//! On-disk pubkey index serving point lookups in unpacked snapshots.
+//!
+//! The index maps each pubkey to the append vec and offset of its latest version.
+//! It is laid out as follows, with integers in little-endian:
+//!
+//! - magic `SSEIDX01` and the bank hash of the indexed snapshot (32 bytes)
+//! - the number of append vecs (u32), followed by the slot and id (u64 each) of each
+//! - the number of accounts (u64), followed by entries sorted by pubkey: the pubkey
+//!   (32 bytes), the position of its append vec in the list above (u32) and the offset
+//!   of the account in that append vec (u64)
+
+use crate::append_vec::StoredMetaWriteVersion;
+use crate::unpacked::UnpackedSnapshotExtractor;
+use crate::{
+    append_vec_iter, AppendVecIterator, ReadProgressTracking, Result, SnapshotError,
+    SnapshotExtractor, StoredAccountMetaHandle,
+};
+use log::info;
+use memmap2::Mmap;
+use solana_sdk::clock::Slot;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+const INDEX_MAGIC: &[u8; 8] = b"SSEIDX01";
+const HEADER_LEN: usize = INDEX_MAGIC.len() + 32;
+const STORAGE_LEN: usize = 16;
+const ENTRY_LEN: usize = 32 + 4 + 8;
+
+/// Where the latest version of an account is stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccountLocation {
+    pub slot: Slot,
+    /// Append vec ID, unique within the slot
+    pub id: u64,
+    /// Offset of the account in the append vec
+    pub offset: u64,
+}
+
+/// Writes an index of the latest version of every account in `iterator` to `path`,
+/// returning the number of accounts indexed.
+///
+/// The index is sorted in memory, taking ~64 bytes per stored account while building.
+pub fn build_index(iterator: AppendVecIterator<'_>, bank_hash: &Hash, path: &Path) -> Result<u64> {
+    let mut storages: Vec<(Slot, u64)> = Vec::new();
+    let mut entries: Vec<(Pubkey, Slot, StoredMetaWriteVersion, u32, u64)> = Vec::new();
+    for append_vec in iterator {
+        let append_vec = append_vec?;
+        let storage = u32::try_from(storages.len()).expect("fewer than 2^32 append vecs");
+        storages.push((append_vec.get_slot(), append_vec.get_id()));
+        for account in append_vec_iter(&append_vec) {
+            entries.push((
+                account.meta.pubkey,
+                append_vec.get_slot(),
+                account.meta.write_version,
+                storage,
+                account.offset as u64,
+            ));
+        }
+    }
+
+    // Newest version of each pubkey first, then keep only that one. Of two equal versions,
+    // the one stored last wins, as in `SkipIndex`
+    entries.sort_unstable_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then((b.1, b.2, b.3, b.4).cmp(&(a.1, a.2, a.3, a.4)))
+    });
+    entries.dedup_by_key(|entry| entry.0);
+
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    writer.write_all(INDEX_MAGIC)?;
+    writer.write_all(bank_hash.as_ref())?;
+    writer.write_all(&(storages.len() as u32).to_le_bytes())?;
+    for (slot, id) in &storages {
+        writer.write_all(&slot.to_le_bytes())?;
+        writer.write_all(&id.to_le_bytes())?;
+    }
+    writer.write_all(&(entries.len() as u64).to_le_bytes())?;
+    for (pubkey, _, _, storage, offset) in &entries {
+        writer.write_all(pubkey.as_ref())?;
+        writer.write_all(&storage.to_le_bytes())?;
+        writer.write_all(&offset.to_le_bytes())?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+    fs::rename(&tmp_path, path)?;
+
+    info!("Indexed {} accounts in {:?}", entries.len(), path);
+    Ok(entries.len() as u64)
+}
+
+/// An unpacked snapshot with a pubkey index built by [`build_index`], serving point
+/// lookups without scanning the snapshot.
+pub struct IndexedSnapshot {
+    snapshot: UnpackedSnapshotExtractor,
+    index: Mmap,
+    storages: Vec<(Slot, u64)>,
+    entries_start: usize,
+    len: usize,
+}
+
+impl IndexedSnapshot {
+    /// Opens the unpacked snapshot at `snapshot_dir` with the index at `index_path`.
+    /// Fails if the index was built for a different snapshot.
+    pub fn open(
+        snapshot_dir: &Path,
+        index_path: &Path,
+        progress_tracking: Box<dyn ReadProgressTracking>,
+    ) -> Result<Self> {
+        let snapshot = UnpackedSnapshotExtractor::open(snapshot_dir, progress_tracking)?;
+        let file = File::open(index_path)?;
+        let index = unsafe { Mmap::map(&file)? };
+
+        let invalid = |reason: &str| SnapshotError::InvalidIndex(reason.to_string());
+        if index.len() < HEADER_LEN + 4 || &index[..INDEX_MAGIC.len()] != INDEX_MAGIC {
+            return Err(invalid("not a pubkey index"));
+        }
+        let bank_hash = Hash::new(&index[INDEX_MAGIC.len()..HEADER_LEN]);
+        if bank_hash != snapshot.bank_hash() {
+            return Err(SnapshotError::IndexMismatch(bank_hash));
+        }
+
+        let num_storages = read_u32(&index, HEADER_LEN) as usize;
+        let storages_start = HEADER_LEN + 4;
+        let len_start = storages_start + num_storages * STORAGE_LEN;
+        if index.len() < len_start + 8 {
+            return Err(invalid("truncated append vec list"));
+        }
+        let storages = (0..num_storages)
+            .map(|i| {
+                let start = storages_start + i * STORAGE_LEN;
+                (read_u64(&index, start), read_u64(&index, start + 8))
+            })
+            .collect();
+        let len = read_u64(&index, len_start) as usize;
+        let entries_start = len_start + 8;
+        let expected_len = len
+            .checked_mul(ENTRY_LEN)
+            .and_then(|entries_len| entries_len.checked_add(entries_start));
+        if expected_len != Some(index.len()) {
+            return Err(invalid("entry count doesn't match file size"));
+        }
+
+        Ok(Self {
+            snapshot,
+            index,
+            storages,
+            entries_start,
+            len,
+        })
+    }
+
+    /// Number of indexed accounts.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn snapshot(&self) -> &UnpackedSnapshotExtractor {
+        &self.snapshot
+    }
+
+    /// Finds where the latest version of `pubkey` is stored, by binary search.
+    pub fn locate(&self, pubkey: &Pubkey) -> Result<Option<AccountLocation>> {
+        let (mut lo, mut hi) = (0, self.len);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let start = self.entries_start + mid * ENTRY_LEN;
+            match self.index[start..start + 32].cmp(pubkey.as_ref()) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => {
+                    let storage = read_u32(&self.index, start + 32) as usize;
+                    let (slot, id) = *self.storages.get(storage).ok_or_else(|| {
+                        SnapshotError::InvalidIndex(format!("unknown append vec #{}", storage))
+                    })?;
+                    return Ok(Some(AccountLocation {
+                        slot,
+                        id,
+                        offset: read_u64(&self.index, start + 36),
+                    }));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the latest version of `pubkey`, or None if it's not in the snapshot.
+    pub fn get(&self, pubkey: &Pubkey) -> Result<Option<StoredAccountMetaHandle>> {
+        let Some(location) = self.locate(pubkey)? else {
+            return Ok(None);
+        };
+        let append_vec = self.snapshot.append_vec(location.slot, location.id)?;
+        StoredAccountMetaHandle::new(Arc::new(append_vec), location.offset as usize)
+            .filter(|account| account.access().meta.pubkey == *pubkey)
+            .map(Some)
+            .ok_or_else(|| {
+                SnapshotError::InvalidIndex(format!(
+                    "no account {} at offset {} of append vec {}.{}",
+                    pubkey, location.offset, location.slot, location.id
+                ))
+            })
+    }
+}
+
+fn read_u32(buf: &[u8], start: usize) -> u32 {
+    u32::from_le_bytes(buf[start..start + 4].try_into().unwrap())
+}
+
+fn read_u64(buf: &[u8], start: usize) -> u64 {
+    u64::from_le_bytes(buf[start..start + 8].try_into().unwrap())
+}
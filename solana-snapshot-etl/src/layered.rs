@@ -0,0 +1,107 @@
+use crate::{
+    append_vec_iter, AppendVecIterator, Result, SnapshotError, SnapshotExtractor, SnapshotInfo,
+    SnapshotManifest,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Combines a full snapshot with an incremental snapshot on top of it.
+///
+/// Append vecs of the incremental layer are yielded first. Accounts they contain are
+/// then shadowed in the full snapshot's append vecs, so iterators only see the latest
+/// layer's version of each account. An incremental append vec with corrupt entries is an
+/// error, since accounts past the corruption would expose their stale full versions.
+pub struct LayeredSnapshotExtractor<F, I> {
+    full: F,
+    incremental: I,
+    info: SnapshotInfo,
+}
+
+impl<F, I> LayeredSnapshotExtractor<F, I>
+where
+    F: SnapshotExtractor,
+    I: SnapshotExtractor,
+{
+    pub fn new(full: F, incremental: I) -> Result<Self> {
+        if incremental.slot() <= full.slot() {
+            return Err(SnapshotError::IncrementalSlotMismatch {
+                full: full.slot(),
+                incremental: incremental.slot(),
+            });
+        }
+        if let Some(base) = incremental.manifest().incremental_base_slot() {
+            if base != full.slot() {
+                return Err(SnapshotError::IncrementalBaseMismatch {
+                    full: full.slot(),
+                    base,
+                    incremental: incremental.slot(),
+                });
+            }
+        }
+        // Bank fields come from the incremental layer, storages from both
+        let info = SnapshotInfo {
+            storages: full.info().storages + incremental.info().storages,
+            ..incremental.info().clone()
+        };
+        Ok(Self {
+            full,
+            incremental,
+            info,
+        })
+    }
+
+    pub fn full(&self) -> &F {
+        &self.full
+    }
+
+    pub fn incremental(&self) -> &I {
+        &self.incremental
+    }
+}
+
+impl<F, I> SnapshotExtractor for LayeredSnapshotExtractor<F, I>
+where
+    F: SnapshotExtractor,
+    I: SnapshotExtractor,
+{
+    fn iter(&mut self) -> AppendVecIterator<'_> {
+        let collected = Rc::new(RefCell::new(HashSet::<Pubkey>::new()));
+
+        let collector = Rc::clone(&collected);
+        let incremental = self.incremental.iter().map(move |append_vec| {
+            let append_vec = append_vec?;
+            if let Some(offset) = append_vec.find_corruption() {
+                return Err(SnapshotError::storage(
+                    append_vec.get_slot(),
+                    append_vec.get_id(),
+                    SnapshotError::CorruptEntry(offset),
+                ));
+            }
+            collector
+                .borrow_mut()
+                .extend(append_vec_iter(&append_vec).map(|account| account.meta.pubkey));
+            Ok(append_vec)
+        });
+
+        // Only built once the incremental layer is exhausted.
+        let mut shadowed: Option<Arc<HashSet<Pubkey>>> = None;
+        let full = self.full.iter().map(move |append_vec| {
+            let shadowed = shadowed.get_or_insert_with(|| Arc::new(collected.take()));
+            append_vec.map(|append_vec| append_vec.with_mask(Arc::clone(shadowed) as _))
+        });
+
+        Box::new(incremental.chain(full))
+    }
+
+    /// Returns the manifest of the incremental layer.
+    fn manifest(&self) -> &SnapshotManifest {
+        self.incremental.manifest()
+    }
+
+    fn info(&self) -> &SnapshotInfo {
+        &self.info
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{AppendVecIterator, SnapshotError, SnapshotExtractor, SnapshotInfo, SnapshotManifest};
+use log::warn;
+use std::sync::{Arc, Mutex};
+
+/// An append vec that couldn't be read, or only partially.
+#[derive(Clone, Debug)]
+pub struct StorageError {
+    /// Append vec file name, `<slot>.<id>`
+    pub file: String,
+    /// Offset of the first unparsable entry, if the append vec was read up to there
+    pub offset: Option<usize>,
+    pub error: String,
+}
+
+/// Collects storage errors across append vecs.
+#[derive(Default)]
+pub struct StorageErrors {
+    entries: Mutex<Vec<StorageError>>,
+}
+
+impl StorageErrors {
+    pub fn record(&self, entry: StorageError) {
+        self.entries.lock().unwrap().push(entry);
+    }
+
+    pub fn entries(&self) -> Vec<StorageError> {
+        self.entries.lock().unwrap().clone()
+    }
+}
+
+/// Skips append vecs that fail to load instead of aborting, and reports append vecs
+/// with corrupt entries, so damaged snapshots can still be partially analyzed.
+///
+/// Errors not attributable to a single append vec are still returned.
+pub struct LenientSnapshotExtractor<E> {
+    inner: E,
+    errors: Arc<StorageErrors>,
+}
+
+impl<E: SnapshotExtractor> LenientSnapshotExtractor<E> {
+    pub fn new(inner: E, errors: Arc<StorageErrors>) -> Self {
+        Self { inner, errors }
+    }
+}
+
+impl<E: SnapshotExtractor> SnapshotExtractor for LenientSnapshotExtractor<E> {
+    fn iter(&mut self) -> AppendVecIterator<'_> {
+        let errors = Arc::clone(&self.errors);
+        Box::new(
+            self.inner
+                .iter()
+                .filter_map(move |append_vec| match append_vec {
+                    Ok(append_vec) => {
+                        if let Some(offset) = append_vec.find_corruption() {
+                            let file = format!("{}.{}", append_vec.get_slot(), append_vec.get_id());
+                            warn!(
+                                "Append vec {} is corrupt at offset {} of {}",
+                                file,
+                                offset,
+                                append_vec.len()
+                            );
+                            errors.record(StorageError {
+                                file,
+                                offset: Some(offset),
+                                error: "unparsable account entry".to_string(),
+                            });
+                        }
+                        Some(Ok(append_vec))
+                    }
+                    Err(SnapshotError::Storage { slot, id, source }) => {
+                        warn!("Skipping append vec {}.{}: {}", slot, id, source);
+                        errors.record(StorageError {
+                            file: format!("{}.{}", slot, id),
+                            offset: None,
+                            error: source.to_string(),
+                        });
+                        None
+                    }
+                    Err(e) => Some(Err(e)),
+                }),
+        )
+    }
+
+    fn manifest(&self) -> &SnapshotManifest {
+        self.inner.manifest()
+    }
+
+    fn info(&self) -> &SnapshotInfo {
+        self.inner.info()
+    }
+}
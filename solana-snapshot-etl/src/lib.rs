@@ -1,15 +1,35 @@
+use log::info;
+use solana_runtime::epoch_stakes::EpochStakes;
+use solana_sdk::clock::{Epoch, Slot};
+use solana_sdk::epoch_schedule::EpochSchedule;
+use solana_sdk::hash::Hash;
+use solana_sdk::rent::Rent;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::io::Read;
 use std::path::Path;
-use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
 use thiserror::Error;
 
 pub mod append_vec;
 pub mod solana;
 
+pub mod accounts_hash;
 pub mod archived;
+#[cfg(feature = "async")]
+pub mod async_archived;
+pub mod bloom;
+pub mod cache;
+pub mod dedup;
+pub mod expr;
+pub mod index;
+pub mod layered;
+pub mod lenient;
+pub mod mask;
+pub mod tiered;
 pub mod unpacked;
 
 #[cfg(feature = "parallel")]
@@ -17,7 +37,7 @@ pub mod parallel;
 
 use crate::append_vec::{AppendVec, StoredAccountMeta};
 use crate::solana::{
-    deserialize_from, AccountsDbFields, DeserializableVersionedBank,
+    deserialize_from, AccountsDbFields, DeserializableVersionedBank, ExtraFields,
     SerializableAccountStorageEntry,
 };
 
@@ -33,16 +53,176 @@ pub enum SnapshotError {
     NoStatusCache,
     #[error("No snapshot manifest file found")]
     NoSnapshotManifest,
+    #[error("Unknown archive compression, expected zstd, gzip, bzip2 or lz4")]
+    UnknownArchiveCompression,
     #[error("Unexpected AppendVec")]
     UnexpectedAppendVec,
+    #[error(
+        "Incremental snapshot at slot {incremental} does not follow full snapshot at slot {full}"
+    )]
+    IncrementalSlotMismatch { full: Slot, incremental: Slot },
+    #[error(
+        "Incremental snapshot at slot {incremental} is based on slot {base}, not on the full snapshot at slot {full}"
+    )]
+    IncrementalBaseMismatch {
+        full: Slot,
+        base: Slot,
+        incremental: Slot,
+    },
+    #[error("Invalid Bloom filter: {0}")]
+    InvalidBloomFilter(&'static str),
+    #[error("Invalid pubkey index: {0}")]
+    InvalidIndex(String),
+    #[error("Pubkey index was built for a different snapshot (bank hash {0})")]
+    IndexMismatch(Hash),
+    #[error("Unparsable account entry at offset {0}")]
+    CorruptEntry(usize),
+    #[error("Append vec {slot}.{id}: {source}")]
+    Storage {
+        slot: Slot,
+        id: u64,
+        source: Box<SnapshotError>,
+    },
+}
+
+impl SnapshotError {
+    /// Attributes an error to the append vec `id` of `slot`.
+    pub fn storage(slot: Slot, id: u64, error: SnapshotError) -> Self {
+        Self::Storage {
+            slot,
+            id,
+            source: Box::new(error),
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, SnapshotError>;
 
 pub type AppendVecIterator<'a> = Box<dyn Iterator<Item = Result<AppendVec>> + 'a>;
 
+/// Headline fields of a snapshot manifest, available without reading any append vecs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    pub slot: Slot,
+    pub epoch: Epoch,
+    pub block_height: u64,
+    /// Total lamports in existence
+    pub capitalization: u64,
+    pub bank_hash: Hash,
+    pub accounts_hash: Hash,
+    /// Number of append vecs listed in the manifest
+    pub storages: usize,
+}
+
+/// Deserialized snapshot manifest: the bank fields followed by the accounts DB fields.
+pub struct SnapshotManifest {
+    pub bank: DeserializableVersionedBank,
+    pub accounts_db_fields: AccountsDbFields<SerializableAccountStorageEntry>,
+    pub extra_fields: ExtraFields,
+}
+
+impl SnapshotManifest {
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self> {
+        let pre_unpack = Instant::now();
+        let bank: DeserializableVersionedBank = deserialize_from(&mut reader)?;
+        let bank_post_time = Instant::now();
+
+        let accounts_db_fields: AccountsDbFields<SerializableAccountStorageEntry> =
+            deserialize_from(&mut reader)?;
+        let extra_fields: ExtraFields = deserialize_from(&mut reader)?;
+        let accounts_db_fields_post_time = Instant::now();
+
+        info!("Read bank fields in {:?}", bank_post_time - pre_unpack);
+        info!(
+            "Read accounts DB fields in {:?}",
+            accounts_db_fields_post_time - bank_post_time
+        );
+
+        Ok(Self {
+            bank,
+            accounts_db_fields,
+            extra_fields,
+        })
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.bank.slot
+    }
+
+    pub fn epoch_schedule(&self) -> &EpochSchedule {
+        &self.bank.epoch_schedule
+    }
+
+    pub fn rent(&self) -> &Rent {
+        &self.bank.rent_collector.rent
+    }
+
+    /// Stakes of the epochs around the snapshot slot, used for leader schedules.
+    pub fn epoch_stakes(&self) -> &HashMap<Epoch, EpochStakes> {
+        &self.bank.epoch_stakes
+    }
+
+    /// Slot of the full snapshot this incremental snapshot was taken on top of, if the
+    /// manifest records it.
+    pub fn incremental_base_slot(&self) -> Option<Slot> {
+        self.extra_fields
+            .incremental_snapshot_persistence
+            .as_ref()
+            .map(|persistence| persistence.full_slot)
+    }
+
+    pub fn info(&self) -> SnapshotInfo {
+        SnapshotInfo {
+            slot: self.bank.slot,
+            epoch: self.bank.epoch,
+            block_height: self.bank.block_height,
+            capitalization: self.bank.capitalization,
+            bank_hash: self.bank.hash,
+            accounts_hash: self.accounts_db_fields.3.accounts_hash,
+            storages: self.accounts_db_fields.0.values().map(Vec::len).sum(),
+        }
+    }
+}
+
 pub trait SnapshotExtractor: Sized {
     fn iter(&mut self) -> AppendVecIterator<'_>;
+
+    /// Returns the deserialized snapshot manifest.
+    fn manifest(&self) -> &SnapshotManifest;
+
+    /// Returns the headline fields read from the snapshot manifest.
+    fn info(&self) -> &SnapshotInfo;
+
+    /// Returns the bank hash recorded in the snapshot manifest.
+    fn bank_hash(&self) -> Hash {
+        self.info().bank_hash
+    }
+
+    /// Returns the slot at which the snapshot was taken.
+    fn slot(&self) -> Slot {
+        self.info().slot
+    }
+
+    /// Hands a `Send` iterator over all accounts to `f`, which runs on a separate thread
+    /// while the current thread drives the extractor.
+    ///
+    /// ```ignore
+    /// use rayon::prelude::*;
+    /// let total: u64 = extractor.par_account_iter(|accounts| {
+    ///     accounts
+    ///         .par_bridge()
+    ///         .map(|account| account.unwrap().access().account_meta.lamports)
+    ///         .sum()
+    /// });
+    /// ```
+    #[cfg(feature = "parallel")]
+    fn par_account_iter<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(parallel::AccountReceiver) -> R + Send,
+        R: Send,
+    {
+        parallel::with_account_receiver(self.iter(), f)
+    }
 }
 
 fn parse_append_vec_name(name: &OsStr) -> Option<(u64, u64)> {
@@ -56,36 +236,68 @@ fn parse_append_vec_name(name: &OsStr) -> Option<(u64, u64)> {
     }
 }
 
-pub fn append_vec_iter(append_vec: Rc<AppendVec>) -> impl Iterator<Item = StoredAccountMetaHandle> {
-    let mut offsets = Vec::<usize>::new();
+/// Iterates over the accounts stored in an append vec, borrowing their data in place.
+///
+/// Accounts hidden by one of the append vec's masks, and entries exceeding the append vec's
+/// data length cap, are skipped.
+pub fn append_vec_iter(append_vec: &AppendVec) -> impl Iterator<Item = StoredAccountMeta<'_>> {
+    let mut offset = 0usize;
+    std::iter::from_fn(move || loop {
+        let (account, next_offset) = append_vec.next_account(offset)?;
+        offset = next_offset;
+        match account {
+            Some(account) if !append_vec.is_hidden(&account) => return Some(account),
+            _ => continue,
+        }
+    })
+}
+
+/// Iterates over the accounts of a shared append vec.
+///
+/// Unlike [`append_vec_iter`], the yielded handles own a reference to the append vec
+/// and are `Send`, so they can be handed to other threads (e.g. via rayon).
+pub fn owned_append_vec_iter(
+    append_vec: Arc<AppendVec>,
+) -> impl Iterator<Item = StoredAccountMetaHandle> + Send {
     let mut offset = 0usize;
-    loop {
-        match append_vec.get_account(offset) {
-            None => break,
-            Some((_, next_offset)) => {
-                offsets.push(offset);
-                offset = next_offset;
+    std::iter::from_fn(move || loop {
+        let (account, next_offset) = append_vec.next_account(offset)?;
+        let account_offset = offset;
+        offset = next_offset;
+        match account {
+            Some(account) if !append_vec.is_hidden(&account) => {
+                return Some(StoredAccountMetaHandle {
+                    append_vec: Arc::clone(&append_vec),
+                    offset: account_offset,
+                })
             }
+            _ => continue,
         }
-    }
-    let append_vec = Rc::clone(&append_vec);
-    offsets
-        .into_iter()
-        .map(move |offset| StoredAccountMetaHandle::new(Rc::clone(&append_vec), offset))
+    })
 }
 
+#[derive(Clone)]
 pub struct StoredAccountMetaHandle {
-    append_vec: Rc<AppendVec>,
+    append_vec: Arc<AppendVec>,
     offset: usize,
 }
 
 impl StoredAccountMetaHandle {
-    pub fn new(append_vec: Rc<AppendVec>, offset: usize) -> StoredAccountMetaHandle {
-        Self { append_vec, offset }
+    /// Returns a handle to the account at `offset`, or None if no account is stored there.
+    pub fn new(append_vec: Arc<AppendVec>, offset: usize) -> Option<StoredAccountMetaHandle> {
+        append_vec.get_account(offset)?;
+        Some(Self { append_vec, offset })
+    }
+
+    pub fn access(&self) -> StoredAccountMeta<'_> {
+        self.append_vec
+            .get_account(self.offset)
+            .expect("account offset validated on construction")
+            .0
     }
 
-    pub fn access(&self) -> Option<StoredAccountMeta<'_>> {
-        Some(self.append_vec.get_account(self.offset)?.0)
+    pub fn slot(&self) -> u64 {
+        self.append_vec.get_slot()
     }
 }
 
@@ -0,0 +1,162 @@
+use crate::append_vec::{AccountMask, StoredAccountMeta};
+use crate::{AppendVec, AppendVecIterator, SnapshotExtractor, SnapshotInfo, SnapshotManifest};
+use solana_sdk::clock::Epoch;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::sync::Arc;
+
+pub type AppendVecMap = Box<dyn FnMut(AppendVec) -> AppendVec>;
+
+/// Applies a transformation, such as an [`AccountMask`], to every append vec of an extractor.
+pub struct MappedSnapshotExtractor<E> {
+    inner: E,
+    map: AppendVecMap,
+}
+
+impl<E: SnapshotExtractor> MappedSnapshotExtractor<E> {
+    pub fn new(inner: E, map: AppendVecMap) -> Self {
+        Self { inner, map }
+    }
+
+    pub fn with_mask(inner: E, mask: Arc<dyn AccountMask>) -> Self {
+        Self::new(
+            inner,
+            Box::new(move |append_vec| append_vec.with_mask(Arc::clone(&mask))),
+        )
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+}
+
+impl<E: SnapshotExtractor> SnapshotExtractor for MappedSnapshotExtractor<E> {
+    fn iter(&mut self) -> AppendVecIterator<'_> {
+        let map = &mut self.map;
+        Box::new(
+            self.inner
+                .iter()
+                .map(move |append_vec| append_vec.map(&mut *map)),
+        )
+    }
+
+    fn manifest(&self) -> &SnapshotManifest {
+        self.inner.manifest()
+    }
+
+    fn info(&self) -> &SnapshotInfo {
+        self.inner.info()
+    }
+}
+
+/// Hides zero-lamport accounts, which mark deleted accounts.
+pub struct ZeroLamportMask;
+
+impl AccountMask for ZeroLamportMask {
+    fn is_hidden(&self, _slot: u64, _id: u64, account: &StoredAccountMeta) -> bool {
+        account.account_meta.lamports == 0
+    }
+}
+
+/// Hides accounts by their metadata and data bytes.
+#[derive(Clone, Debug, Default)]
+pub struct AccountFilter {
+    /// Only keep accounts with this executable flag
+    pub executable: Option<bool>,
+    /// Only keep accounts with this rent epoch
+    pub rent_epoch: Option<Epoch>,
+    /// Only keep rent-exempt accounts, whose rent epoch is `Epoch::MAX`, or only
+    /// rent-paying ones
+    pub rent_exempt: Option<bool>,
+    /// Only keep accounts holding at least this many lamports
+    pub min_lamports: Option<u64>,
+    /// Only keep accounts holding at most this many lamports
+    pub max_lamports: Option<u64>,
+    /// Hide accounts owned by any of these programs
+    pub excluded_owners: BTreeSet<Pubkey>,
+    /// Only keep accounts with at least this many data bytes
+    pub min_data_len: Option<u64>,
+    /// Only keep accounts with at most this many data bytes. Unlike the cap of
+    /// [`AppendVec::with_max_data_len`](crate::append_vec::AppendVec::with_max_data_len),
+    /// larger accounts are valid, just hidden.
+    pub max_data_len: Option<u64>,
+    /// Only keep accounts matching all of these
+    pub memcmp: Vec<Memcmp>,
+}
+
+impl AccountFilter {
+    /// Returns true if the filter keeps every account.
+    pub fn is_empty(&self) -> bool {
+        self.executable.is_none()
+            && self.rent_epoch.is_none()
+            && self.rent_exempt.is_none()
+            && self.min_lamports.is_none()
+            && self.max_lamports.is_none()
+            && self.excluded_owners.is_empty()
+            && self.min_data_len.is_none()
+            && self.max_data_len.is_none()
+            && self.memcmp.is_empty()
+    }
+
+    pub fn matches(&self, account: &StoredAccountMeta) -> bool {
+        let meta = account.account_meta;
+        let data_len = account.meta.data_len;
+        self.executable
+            .map_or(true, |executable| meta.executable == executable)
+            && self
+                .rent_epoch
+                .map_or(true, |rent_epoch| meta.rent_epoch == rent_epoch)
+            && self.rent_exempt.map_or(true, |rent_exempt| {
+                (meta.rent_epoch == Epoch::MAX) == rent_exempt
+            })
+            && self.min_lamports.map_or(true, |min| meta.lamports >= min)
+            && self.max_lamports.map_or(true, |max| meta.lamports <= max)
+            && !self.excluded_owners.contains(&meta.owner)
+            && self.min_data_len.map_or(true, |min| data_len >= min)
+            && self.max_data_len.map_or(true, |max| data_len <= max)
+            && self
+                .memcmp
+                .iter()
+                .all(|memcmp| memcmp.matches(account.data))
+    }
+}
+
+impl AccountMask for AccountFilter {
+    fn is_hidden(&self, _slot: u64, _id: u64, account: &StoredAccountMeta) -> bool {
+        !self.matches(account)
+    }
+}
+
+/// Matches accounts whose data contains `bytes` at `offset`, like the `memcmp` filter
+/// of the `getProgramAccounts` RPC method.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Memcmp {
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+}
+
+impl Memcmp {
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.get(self.offset..)
+            .is_some_and(|rest| rest.starts_with(&self.bytes))
+    }
+}
+
+impl FromStr for Memcmp {
+    type Err = String;
+
+    /// Parses `offset:base58bytes`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (offset, bytes) = s
+            .split_once(':')
+            .ok_or_else(|| format!("invalid memcmp filter '{}', expected offset:base58bytes", s))?;
+        let offset = offset
+            .parse()
+            .map_err(|e| format!("invalid memcmp offset '{}': {}", offset, e))?;
+        let bytes = bs58::decode(bytes)
+            .into_vec()
+            .map_err(|e| format!("invalid memcmp bytes '{}': {}", bytes, e))?;
+        Ok(Self { offset, bytes })
+    }
+}
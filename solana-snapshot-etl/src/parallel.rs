@@ -1,5 +1,6 @@
-use crate::{AppendVec, AppendVecIterator};
-use crossbeam::sync::WaitGroup;
+use crate::{owned_append_vec_iter, AppendVec, AppendVecIterator, Result, StoredAccountMetaHandle};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
 pub type GenericResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -8,10 +9,108 @@ pub trait AppendVecConsumerFactory {
     fn new_consumer(&mut self) -> GenericResult<Self::Consumer>;
 }
 
+/// Receives append vecs on a worker thread. Since 0.4.0 the append vec is borrowed, so that
+/// a [`ConsumerRegistry`] can hand the same one to every registered consumer.
 pub trait AppendVecConsumer {
-    fn on_append_vec(&mut self, append_vec: AppendVec) -> GenericResult<()>;
+    fn on_append_vec(&mut self, append_vec: &AppendVec) -> GenericResult<()>;
 }
 
+/// Object-safe form of [`AppendVecConsumerFactory`], for [`ConsumerFactoryRegistry`].
+trait DynConsumerFactory {
+    fn new_boxed_consumer(&mut self) -> GenericResult<Box<dyn AppendVecConsumer + Send>>;
+}
+
+impl<F: AppendVecConsumerFactory> DynConsumerFactory for F {
+    fn new_boxed_consumer(&mut self) -> GenericResult<Box<dyn AppendVecConsumer + Send>> {
+        Ok(Box::new(self.new_consumer()?))
+    }
+}
+
+/// Hands each append vec to every registered consumer, so that several analyses share
+/// one scan of the snapshot.
+///
+/// Consumers are dropped with the registry; results are read from state they share with
+/// the caller, or written out when they are dropped.
+#[derive(Default)]
+pub struct ConsumerRegistry {
+    consumers: Vec<Box<dyn AppendVecConsumer + Send>>,
+}
+
+impl ConsumerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<C>(&mut self, consumer: C) -> &mut Self
+    where
+        C: AppendVecConsumer + Send + 'static,
+    {
+        self.consumers.push(Box::new(consumer));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.consumers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.consumers.is_empty()
+    }
+}
+
+impl AppendVecConsumer for ConsumerRegistry {
+    fn on_append_vec(&mut self, append_vec: &AppendVec) -> GenericResult<()> {
+        for consumer in &mut self.consumers {
+            consumer.on_append_vec(append_vec)?;
+        }
+        Ok(())
+    }
+}
+
+/// Registry of consumer factories for [`par_iter_append_vecs`]. Each worker thread gets a
+/// [`ConsumerRegistry`] with one consumer from every registered factory.
+#[derive(Default)]
+pub struct ConsumerFactoryRegistry {
+    factories: Vec<Box<dyn DynConsumerFactory>>,
+}
+
+impl ConsumerFactoryRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<F>(&mut self, factory: F) -> &mut Self
+    where
+        F: AppendVecConsumerFactory + 'static,
+    {
+        self.factories.push(Box::new(factory));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.factories.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.factories.is_empty()
+    }
+}
+
+impl AppendVecConsumerFactory for ConsumerFactoryRegistry {
+    type Consumer = ConsumerRegistry;
+
+    fn new_consumer(&mut self) -> GenericResult<Self::Consumer> {
+        let consumers = self
+            .factories
+            .iter_mut()
+            .map(|factory| factory.new_boxed_consumer())
+            .collect::<GenericResult<_>>()?;
+        Ok(ConsumerRegistry { consumers })
+    }
+}
+
+/// Hands append vecs to consumers on `num_threads` worker threads. The first consumer
+/// error stops the iteration and is returned.
 pub fn par_iter_append_vecs<A>(
     iterator: AppendVecIterator<'_>,
     consumers: &mut A,
@@ -22,28 +121,271 @@ where
 {
     let (tx, rx) = crossbeam::channel::bounded::<AppendVec>(num_threads);
 
-    let wg = WaitGroup::new();
     let mut consumer_vec = Vec::with_capacity(num_threads);
     for _ in 0..num_threads {
         consumer_vec.push(consumers.new_consumer()?);
     }
 
-    for mut consumer in consumer_vec {
+    std::thread::scope(|scope| {
+        // Errors are strings since they cross threads
+        let workers: Vec<_> = consumer_vec
+            .into_iter()
+            .map(|mut consumer| {
+                let rx = rx.clone();
+                scope.spawn(move || -> std::result::Result<(), String> {
+                    while let Ok(item) = rx.recv() {
+                        consumer.on_append_vec(&item).map_err(|e| e.to_string())?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        drop(rx);
+
+        let mut result: GenericResult<()> = Ok(());
+        for append_vec in iterator {
+            // A worker only stops early when its consumer failed or panicked
+            if workers.iter().any(|worker| worker.is_finished()) {
+                break;
+            }
+            let append_vec = match append_vec {
+                Ok(append_vec) => append_vec,
+                Err(e) => {
+                    result = Err(e.into());
+                    break;
+                }
+            };
+            if tx.send(append_vec).is_err() {
+                break;
+            }
+        }
+        drop(tx);
+        for worker in workers {
+            let worker_result = worker
+                .join()
+                .unwrap_or_else(|e| std::panic::resume_unwind(e));
+            if let (Ok(()), Err(e)) = (&result, worker_result) {
+                result = Err(e.into());
+            }
+        }
+        result
+    })
+}
+
+/// Options for [`par_map_append_vecs`].
+#[derive(Clone, Copy, Debug)]
+pub struct ParallelOptions {
+    pub num_threads: usize,
+    /// Deliver outputs to the collector in the order the extractor yields the append vecs,
+    /// instead of in completion order.
+    pub ordered: bool,
+    /// Most append vecs handed to mappers past the earliest one whose output wasn't
+    /// delivered yet. Reading pauses while the window is full, which bounds the outputs
+    /// held back for reordering. Raised to `num_threads` if lower.
+    pub reorder_window: usize,
+}
+
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        Self {
+            num_threads: 1,
+            ordered: false,
+            reorder_window: 64,
+        }
+    }
+}
+
+pub trait AppendVecMapperFactory {
+    type Mapper: AppendVecMapper + Send + 'static;
+    fn new_mapper(&mut self) -> GenericResult<Self::Mapper>;
+}
+
+/// Processes an append vec on a worker thread, producing an output for the collector.
+pub trait AppendVecMapper {
+    type Output: Send + 'static;
+    fn map_append_vec(&mut self, append_vec: AppendVec) -> GenericResult<Self::Output>;
+}
+
+/// Receives mapper outputs on the calling thread.
+pub trait AppendVecCollector<T> {
+    fn collect(&mut self, slot: u64, output: T) -> GenericResult<()>;
+}
+
+type MapperOutput<A> = <<A as AppendVecMapperFactory>::Mapper as AppendVecMapper>::Output;
+
+/// Output of a mapper for the append vec at position `seq`, with errors as strings since
+/// they cross threads.
+type Mapped<T> = (u64, u64, std::result::Result<T, String>);
+
+/// Reports a mapper that panics on the append vec at position `seq`. Without its output,
+/// the reading loop would wait for it forever once the window is full.
+struct PanicGuard<'a, T> {
+    out_tx: &'a crossbeam::channel::Sender<Mapped<T>>,
+    seq: u64,
+    slot: u64,
+}
+
+impl<T> Drop for PanicGuard<'_, T> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            let error = format!("Mapper thread panicked on slot {}", self.slot);
+            let _ = self.out_tx.send((self.seq, self.slot, Err(error)));
+        }
+    }
+}
+
+/// Hands mapper outputs to the collector, holding back those that arrive early when
+/// delivering in order.
+struct Delivery<'a, T, C> {
+    collector: &'a mut C,
+    ordered: bool,
+    pending: BTreeMap<u64, (u64, T)>,
+    delivered: u64,
+}
+
+impl<'a, T, C: AppendVecCollector<T>> Delivery<'a, T, C> {
+    fn receive(&mut self, (seq, slot, output): Mapped<T>) -> GenericResult<()> {
+        let output = output?;
+        if !self.ordered {
+            self.delivered += 1;
+            return self.collector.collect(slot, output);
+        }
+        self.pending.insert(seq, (slot, output));
+        while let Some((slot, output)) = self.pending.remove(&self.delivered) {
+            self.delivered += 1;
+            self.collector.collect(slot, output)?;
+        }
+        Ok(())
+    }
+}
+
+/// Maps append vecs in parallel and hands the outputs to a single collector.
+///
+/// With `options.ordered`, outputs are delivered in the order the extractor yields the
+/// append vecs, which makes the collector's view reproducible across runs. Mapper and
+/// collector errors stop the iteration and are returned.
+pub fn par_map_append_vecs<A, C>(
+    iterator: AppendVecIterator<'_>,
+    mappers: &mut A,
+    collector: &mut C,
+    options: ParallelOptions,
+) -> GenericResult<()>
+where
+    A: AppendVecMapperFactory,
+    C: AppendVecCollector<MapperOutput<A>>,
+{
+    let num_threads = options.num_threads.max(1);
+    let window = options.reorder_window.max(num_threads) as u64;
+    let (tx, rx) = crossbeam::channel::bounded::<(u64, AppendVec)>(num_threads);
+    let (out_tx, out_rx) = crossbeam::channel::unbounded::<Mapped<MapperOutput<A>>>();
+
+    let mut mapper_vec = Vec::with_capacity(num_threads);
+    for _ in 0..num_threads {
+        mapper_vec.push(mappers.new_mapper()?);
+    }
+
+    for mut mapper in mapper_vec {
         let rx = rx.clone();
-        let wg = wg.clone();
+        let out_tx = out_tx.clone();
         std::thread::spawn(move || {
-            while let Ok(item) = rx.recv() {
-                consumer.on_append_vec(item).expect("insert failed")
+            while let Ok((seq, append_vec)) = rx.recv() {
+                let slot = append_vec.get_slot();
+                let _guard = PanicGuard {
+                    out_tx: &out_tx,
+                    seq,
+                    slot,
+                };
+                let output = mapper.map_append_vec(append_vec).map_err(|e| e.to_string());
+                if out_tx.send((seq, slot, output)).is_err() {
+                    break;
+                }
             }
-            drop(wg);
         });
     }
+    drop(rx);
+    drop(out_tx);
 
+    let mut delivery = Delivery {
+        collector,
+        ordered: options.ordered,
+        pending: BTreeMap::new(),
+        delivered: 0,
+    };
+    let mut sent: u64 = 0;
     for append_vec in iterator {
         let append_vec = append_vec?;
-        tx.send(append_vec).expect("failed to send AppendVec");
+        // Wait for earlier outputs rather than reading past the window
+        while sent >= delivery.delivered + window {
+            let mapped = out_rx
+                .recv()
+                .map_err(|_| "Mapper threads stopped before finishing")?;
+            delivery.receive(mapped)?;
+        }
+        tx.send((sent, append_vec))
+            .map_err(|_| "Mapper threads stopped before finishing")?;
+        sent += 1;
+        while let Ok(mapped) = out_rx.try_recv() {
+            delivery.receive(mapped)?;
+        }
     }
     drop(tx);
-    wg.wait();
+    for mapped in out_rx.iter() {
+        delivery.receive(mapped)?;
+    }
+    // A mapper that panicked never returns its output
+    if delivery.delivered != sent {
+        return Err("Mapper threads stopped before finishing".into());
+    }
     Ok(())
 }
+
+/// Number of append vecs decoded ahead of the account consumer.
+const ACCOUNT_PREFETCH: usize = 16;
+
+/// A `Send` iterator over all accounts of a snapshot, fed by the thread that drives the
+/// extractor. Suitable for `rayon::iter::ParallelBridge::par_bridge`.
+pub struct AccountReceiver {
+    rx: crossbeam::channel::Receiver<Result<AppendVec>>,
+    current: Option<Box<dyn Iterator<Item = StoredAccountMetaHandle> + Send>>,
+}
+
+impl Iterator for AccountReceiver {
+    type Item = Result<StoredAccountMetaHandle>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(account) = self.current.as_mut().and_then(Iterator::next) {
+                return Some(Ok(account));
+            }
+            match self.rx.recv().ok()? {
+                Ok(append_vec) => {
+                    self.current = Some(Box::new(owned_append_vec_iter(Arc::new(append_vec))))
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Runs `f` on a separate thread with an [`AccountReceiver`] while the calling thread
+/// reads append vecs from `iterator`, and returns the result of `f`.
+pub fn with_account_receiver<F, R>(iterator: AppendVecIterator<'_>, f: F) -> R
+where
+    F: FnOnce(AccountReceiver) -> R + Send,
+    R: Send,
+{
+    let (tx, rx) = crossbeam::channel::bounded(ACCOUNT_PREFETCH);
+    std::thread::scope(|scope| {
+        let worker = scope.spawn(move || f(AccountReceiver { rx, current: None }));
+        for append_vec in iterator {
+            // The receiver was dropped, so the consumer is done.
+            if tx.send(append_vec).is_err() {
+                break;
+            }
+        }
+        drop(tx);
+        worker
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e))
+    })
+}
@@ -99,8 +99,11 @@ pub struct DeserializableVersionedBank {
 
 #[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq, Eq, AbiExample)]
 pub struct BankHashInfo {
+    /// Hash of the accounts written in the snapshot slot
     pub hash: Hash,
-    pub snapshot_hash: Hash,
+    /// Merkle root over the latest versions of all live accounts. The snapshot hash that
+    /// names archives also mixes in the epoch accounts hash, if any.
+    pub accounts_hash: Hash,
     pub stats: BankHashStats,
 }
 
@@ -118,6 +121,31 @@ pub struct AccountsDbFields<T>(
     pub Vec<(Slot, Hash)>,
 );
 
+/// Hashes and capitalization of the full snapshot an incremental snapshot was taken on
+/// top of, and of the accounts that changed since.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BankIncrementalSnapshotPersistence {
+    /// Slot of the full snapshot
+    pub full_slot: Slot,
+    pub full_hash: Hash,
+    pub full_capitalization: u64,
+    pub incremental_hash: Hash,
+    pub incremental_capitalization: u64,
+}
+
+/// Fields following the accounts DB fields, which older manifests end before.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExtraFields {
+    #[serde(deserialize_with = "default_on_eof")]
+    pub lamports_per_signature: u64,
+    /// Only present in incremental snapshots
+    #[serde(deserialize_with = "default_on_eof")]
+    pub incremental_snapshot_persistence: Option<BankIncrementalSnapshotPersistence>,
+    /// Only present while an epoch accounts hash calculation is in flight
+    #[serde(deserialize_with = "default_on_eof")]
+    pub epoch_accounts_hash: Option<Hash>,
+}
+
 pub type SerializedAppendVecId = usize;
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize)]
@@ -0,0 +1,248 @@
+//! Reader for the tiered "hot storage" account file format written by newer validators.
+//!
+//! A hot storage file holds, in order: the account blocks, the index block (all addresses
+//! followed by all account offsets), the owners block, and a fixed-size footer ending with
+//! [`FILE_MAGIC_NUMBER`]. Only the formats used by hot storage are supported: hot account
+//! metas, addresses-then-offsets index, addresses-only owners and aligned raw account blocks.
+
+use crate::append_vec::{AccountMeta, StoredMeta};
+use solana_sdk::clock::Epoch;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use std::io;
+use std::mem;
+
+/// Last eight bytes of every tiered storage file.
+pub const FILE_MAGIC_NUMBER: u64 = u64::from_le_bytes(*b"AnzaTech");
+
+/// Rent epoch of accounts that don't store one, unless they have no lamports.
+const RENT_EXEMPT_RENT_EPOCH: Epoch = Epoch::MAX;
+
+/// Account offsets in the index block are stored in units of this many bytes.
+const HOT_ACCOUNT_ALIGNMENT: usize = 8;
+
+/// Size of `HotAccountMeta`: lamports, packed fields and flags.
+const HOT_ACCOUNT_META_SIZE: usize = 16;
+
+/// format_version, footer_size and magic number at the very end of the file.
+const FOOTER_TAIL_SIZE: usize = 24;
+
+/// Size of the footer fields up to and including the account hash range.
+const FOOTER_FIELDS_SIZE: usize = 144;
+
+const ACCOUNT_META_FORMAT_HOT: u16 = 0;
+const OWNERS_BLOCK_FORMAT_ADDRESSES_ONLY: u16 = 0;
+const INDEX_BLOCK_FORMAT_ADDRESSES_THEN_OFFSETS: u16 = 0;
+const ACCOUNT_BLOCK_FORMAT_ALIGNED_RAW: u16 = 0;
+
+const FLAG_HAS_RENT_EPOCH: u32 = 1 << 0;
+const FLAG_EXECUTABLE: u32 = 1 << 1;
+
+/// Returns whether `bytes` end with the tiered storage magic number.
+pub fn is_tiered_storage(bytes: &[u8]) -> bool {
+    bytes.len() >= FOOTER_TAIL_SIZE && read_u64(bytes, bytes.len() - 8) == Some(FILE_MAGIC_NUMBER)
+}
+
+/// An account read from a hot storage file.
+pub struct HotAccount<'a> {
+    pub pubkey: &'a Pubkey,
+    pub owner: &'a Pubkey,
+    pub lamports: u64,
+    pub rent_epoch: Epoch,
+    pub executable: bool,
+    pub data: &'a [u8],
+}
+
+/// Parsed footer of a hot storage file, borrowing the file contents.
+pub struct HotStorageReader<'a> {
+    bytes: &'a [u8],
+    account_count: usize,
+    index_block_offset: usize,
+    owners_block_offset: usize,
+    owner_count: usize,
+}
+
+impl<'a> HotStorageReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> io::Result<Self> {
+        if !is_tiered_storage(bytes) {
+            return Err(invalid("missing tiered storage magic number"));
+        }
+        let footer_size = read_u64(bytes, bytes.len() - 16)
+            .and_then(|size| usize::try_from(size).ok())
+            .filter(|size| *size >= FOOTER_FIELDS_SIZE + FOOTER_TAIL_SIZE && *size <= bytes.len())
+            .ok_or_else(|| invalid("bad footer size"))?;
+        let footer = bytes.len() - footer_size;
+
+        let format = |index: usize| read_u16(bytes, footer + 2 * index).unwrap();
+        if format(0) != ACCOUNT_META_FORMAT_HOT
+            || format(1) != OWNERS_BLOCK_FORMAT_ADDRESSES_ONLY
+            || format(2) != INDEX_BLOCK_FORMAT_ADDRESSES_THEN_OFFSETS
+            || format(3) != ACCOUNT_BLOCK_FORMAT_ALIGNED_RAW
+        {
+            return Err(invalid(
+                "unsupported tiered storage format, expected hot storage",
+            ));
+        }
+
+        let field = |offset: usize| read_u64(bytes, footer + offset).unwrap() as usize;
+        let field_u32 = |offset: usize| read_u32(bytes, footer + offset).unwrap() as usize;
+        let reader = Self {
+            bytes,
+            account_count: field_u32(8),
+            owner_count: field_u32(24),
+            index_block_offset: field(32),
+            owners_block_offset: field(40),
+        };
+
+        let index_block_end = reader
+            .account_count
+            .checked_mul(mem::size_of::<Pubkey>() + mem::size_of::<u32>())
+            .and_then(|size| size.checked_add(reader.index_block_offset));
+        let owners_block_end = reader
+            .owner_count
+            .checked_mul(mem::size_of::<Pubkey>())
+            .and_then(|size| size.checked_add(reader.owners_block_offset));
+        if index_block_end.map_or(true, |end| end > footer)
+            || owners_block_end.map_or(true, |end| end > footer)
+        {
+            return Err(invalid("index or owners block runs into the footer"));
+        }
+        Ok(reader)
+    }
+
+    pub fn len(&self) -> usize {
+        self.account_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.account_count == 0
+    }
+
+    /// Returns the account at position `index` of the index block.
+    pub fn get_account(&self, index: usize) -> io::Result<HotAccount<'a>> {
+        let pubkey = self.pubkey_at(self.index_block_offset + index * mem::size_of::<Pubkey>())?;
+        let start = self.account_offset(index)?;
+        let end = if index + 1 < self.account_count {
+            self.account_offset(index + 1)?
+        } else {
+            self.index_block_offset
+        };
+        let block = start
+            .checked_add(HOT_ACCOUNT_META_SIZE)
+            .filter(|meta_end| *meta_end <= end)
+            .and_then(|_| self.bytes.get(start..end))
+            .ok_or_else(|| invalid("account block out of bounds"))?;
+
+        let lamports = read_u64(block, 0).unwrap();
+        let packed_fields = read_u32(block, 8).unwrap();
+        let flags = read_u32(block, 12).unwrap();
+        let padding = (packed_fields & 0b111) as usize;
+        let owner_index = (packed_fields >> 3) as usize;
+        if owner_index >= self.owner_count {
+            return Err(invalid("owner index out of bounds"));
+        }
+        let owner =
+            self.pubkey_at(self.owners_block_offset + owner_index * mem::size_of::<Pubkey>())?;
+
+        let optional_fields_size = if flags & FLAG_HAS_RENT_EPOCH != 0 {
+            mem::size_of::<Epoch>()
+        } else {
+            0
+        };
+        let data_end = block
+            .len()
+            .checked_sub(optional_fields_size + padding)
+            .filter(|end| *end >= HOT_ACCOUNT_META_SIZE)
+            .ok_or_else(|| invalid("account block too small for its optional fields"))?;
+        let rent_epoch = if flags & FLAG_HAS_RENT_EPOCH != 0 {
+            read_u64(block, data_end + padding).unwrap()
+        } else if lamports != 0 {
+            RENT_EXEMPT_RENT_EPOCH
+        } else {
+            Epoch::default()
+        };
+
+        Ok(HotAccount {
+            pubkey,
+            owner,
+            lamports,
+            rent_epoch,
+            executable: flags & FLAG_EXECUTABLE != 0,
+            data: &block[HOT_ACCOUNT_META_SIZE..data_end],
+        })
+    }
+
+    pub fn accounts(&self) -> impl Iterator<Item = io::Result<HotAccount<'a>>> + '_ {
+        (0..self.account_count).map(|index| self.get_account(index))
+    }
+
+    /// Re-encodes all accounts in the classic append vec layout, so they can be served
+    /// by [`AppendVec`](crate::append_vec::AppendVec). Hot storage keeps neither write
+    /// versions nor account hashes; both are zeroed.
+    pub fn to_append_vec_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(self.index_block_offset);
+        for account in self.accounts() {
+            let account = account?;
+            let start = buf.len();
+            buf.extend_from_slice(&0u64.to_le_bytes());
+            buf.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+            buf.extend_from_slice(account.pubkey.as_ref());
+            buf.resize(start + mem::size_of::<StoredMeta>(), 0);
+
+            let start = buf.len();
+            buf.extend_from_slice(&account.lamports.to_le_bytes());
+            buf.extend_from_slice(&account.rent_epoch.to_le_bytes());
+            buf.extend_from_slice(account.owner.as_ref());
+            buf.push(account.executable as u8);
+            buf.resize(start + mem::size_of::<AccountMeta>(), 0);
+
+            buf.extend_from_slice(Hash::default().as_ref());
+            buf.extend_from_slice(account.data);
+            let aligned = (buf.len() + 7) & !7;
+            buf.resize(aligned, 0);
+        }
+        Ok(buf)
+    }
+
+    fn account_offset(&self, index: usize) -> io::Result<usize> {
+        let offsets_start = self.index_block_offset + self.account_count * mem::size_of::<Pubkey>();
+        read_u32(self.bytes, offsets_start + index * mem::size_of::<u32>())
+            .map(|offset| offset as usize * HOT_ACCOUNT_ALIGNMENT)
+            .filter(|offset| *offset < self.index_block_offset)
+            .ok_or_else(|| invalid("account offset out of bounds"))
+    }
+
+    fn pubkey_at(&self, offset: usize) -> io::Result<&'a Pubkey> {
+        let bytes = self
+            .bytes
+            .get(offset..offset + mem::size_of::<Pubkey>())
+            .ok_or_else(|| invalid("pubkey out of bounds"))?;
+        //UNSAFE: Pubkey is a transparent wrapper around [u8; 32] with alignment 1
+        Ok(unsafe { &*(bytes.as_ptr() as *const Pubkey) })
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("tiered storage: {}", message),
+    )
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
@@ -1,7 +1,6 @@
 use crate::{
-    deserialize_from, parse_append_vec_name, AccountsDbFields, AppendVec, AppendVecIterator,
-    DeserializableVersionedBank, ReadProgressTracking, Result, SerializableAccountStorageEntry,
-    SnapshotError, SnapshotExtractor, SNAPSHOTS_DIR,
+    parse_append_vec_name, AppendVec, AppendVecIterator, ReadProgressTracking, Result,
+    SnapshotError, SnapshotExtractor, SnapshotInfo, SnapshotManifest, SNAPSHOTS_DIR,
 };
 use itertools::Itertools;
 use log::info;
@@ -10,18 +9,26 @@ use std::fs::OpenOptions;
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::time::Instant;
 
 /// Extracts account data from snapshots that were unarchived to a file system.
 pub struct UnpackedSnapshotExtractor {
     root: PathBuf,
-    accounts_db_fields: AccountsDbFields<SerializableAccountStorageEntry>,
+    manifest: SnapshotManifest,
+    info: SnapshotInfo,
 }
 
 impl SnapshotExtractor for UnpackedSnapshotExtractor {
     fn iter(&mut self) -> AppendVecIterator<'_> {
         Box::new(self.unboxed_iter())
     }
+
+    fn manifest(&self) -> &SnapshotManifest {
+        &self.manifest
+    }
+
+    fn info(&self) -> &SnapshotInfo {
+        &self.info
+    }
 }
 
 impl UnpackedSnapshotExtractor {
@@ -51,35 +58,19 @@ impl UnpackedSnapshotExtractor {
         );
         let mut snapshot_file = BufReader::new(snapshot_file);
 
-        let pre_unpack = Instant::now();
-        let versioned_bank: DeserializableVersionedBank = deserialize_from(&mut snapshot_file)?;
-        drop(versioned_bank);
-        let versioned_bank_post_time = Instant::now();
-
-        let accounts_db_fields: AccountsDbFields<SerializableAccountStorageEntry> =
-            deserialize_from(&mut snapshot_file)?;
-        let accounts_db_fields_post_time = Instant::now();
+        let manifest = SnapshotManifest::read_from(&mut snapshot_file)?;
+        let info = manifest.info();
         drop(snapshot_file);
 
-        info!(
-            "Read bank fields in {:?}",
-            versioned_bank_post_time - pre_unpack
-        );
-        info!(
-            "Read accounts DB fields in {:?}",
-            accounts_db_fields_post_time - versioned_bank_post_time
-        );
-
         Ok(UnpackedSnapshotExtractor {
             root: path.to_path_buf(),
-            accounts_db_fields,
+            manifest,
+            info,
         })
     }
 
     pub fn unboxed_iter(&self) -> impl Iterator<Item = Result<AppendVec>> + '_ {
-        std::iter::once(self.iter_streams())
-            .flatten_ok()
-            .flatten()
+        std::iter::once(self.iter_streams()).flatten_ok().flatten()
     }
 
     fn iter_streams(&self) -> Result<impl Iterator<Item = Result<AppendVec>> + '_> {
@@ -93,11 +84,20 @@ impl UnpackedSnapshotExtractor {
             })
             .map(move |((slot, version), name)| {
                 self.open_append_vec(slot, version, &accounts_dir.join(name))
+                    .map_err(|e| SnapshotError::storage(slot, version, e))
             }))
     }
 
+    /// Opens the append vec `id` of `slot`.
+    pub fn append_vec(&self, slot: u64, id: u64) -> Result<AppendVec> {
+        let path = self.root.join("accounts").join(format!("{}.{}", slot, id));
+        self.open_append_vec(slot, id, &path)
+            .map_err(|e| SnapshotError::storage(slot, id, e))
+    }
+
     fn open_append_vec(&self, slot: u64, id: u64, path: &Path) -> Result<AppendVec> {
         let known_vecs = self
+            .manifest
             .accounts_db_fields
             .0
             .get(&slot)
@@ -113,6 +113,7 @@ impl UnpackedSnapshotExtractor {
             path,
             known_vec.accounts_current_len,
             slot,
+            id,
         )?)
     }
 }
@@ -0,0 +1,4406 @@
+//! Runs every CLI command against the synthesized snapshot in `common`.
+
+mod common;
+
+use common::Fixture;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::system_program;
+use solana_snapshot_etl::bloom::PubkeyBloomFilter;
+use solana_snapshot_etl::dedup::SkipIndex;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::str::FromStr;
+
+/// Runs the binary on the fixture with `global_args` placed before the source.
+fn run(fixture: &Fixture, global_args: &[&str], command: &[&str]) -> Output {
+    run_source(fixture.path(), global_args, command)
+}
+
+fn run_source(source: &Path, global_args: &[&str], command: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_solana-snapshot-etl"))
+        .args(["--no-cache", "--raw"])
+        .args(global_args)
+        .arg(source)
+        .args(command)
+        .output()
+        .expect("failed to run solana-snapshot-etl")
+}
+
+fn run_ok(fixture: &Fixture, global_args: &[&str], command: &[&str]) -> String {
+    let output = run(fixture, global_args, command);
+    assert!(
+        output.status.success(),
+        "{:?} failed: {}",
+        command,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn summary_json(fixture: &Fixture, global_args: &[&str]) -> Value {
+    let stdout = run_ok(fixture, global_args, &["summary", "--format", "json"]);
+    serde_json::from_str(stdout.trim()).unwrap()
+}
+
+/// Value of a `Label:   value` report line.
+fn report_value<'a>(stdout: &'a str, label: &str) -> &'a str {
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix(label))
+        .unwrap_or_else(|| panic!("no {:?} line in:\n{}", label, stdout))
+        .trim()
+}
+
+#[test]
+fn info_reads_manifest() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["info"]);
+    assert_eq!(report_value(&stdout, "Slot:"), common::SLOT.to_string());
+    assert_eq!(report_value(&stdout, "Epoch:"), common::EPOCH.to_string());
+    assert_eq!(
+        report_value(&stdout, "Block height:"),
+        common::BLOCK_HEIGHT.to_string()
+    );
+    assert_eq!(
+        report_value(&stdout, "Capitalization:"),
+        common::CAPITALIZATION.to_string()
+    );
+    assert_eq!(report_value(&stdout, "Storages:"), "2");
+    assert_eq!(
+        report_value(&stdout, "Bank hash:"),
+        common::bank_hash().to_string()
+    );
+}
+
+#[test]
+fn summary_json_counts_all_versions() {
+    let fixture = Fixture::new();
+    let summary = summary_json(&fixture, &[]);
+    assert_eq!(summary["slot"], common::SLOT);
+    assert_eq!(summary["epoch"], common::EPOCH);
+    assert_eq!(summary["total_accounts"], 6);
+    assert_eq!(summary["total_data_bytes"], 82 + 165 + 165 + 10);
+    assert_eq!(summary["capitalization"], common::CAPITALIZATION);
+
+    let top_owner = &summary["top_owners"][0];
+    assert_eq!(top_owner["owner"], common::TOKEN_PROGRAM_ID);
+    assert_eq!(top_owner["count"], 3);
+    assert_eq!(top_owner["total_size"], 82 + 165 + 165);
+}
+
+#[test]
+fn summary_dedup_keeps_latest_versions() {
+    let fixture = Fixture::new();
+    let summary = summary_json(&fixture, &["--dedup"]);
+    assert_eq!(summary["total_accounts"], 5);
+}
+
+#[test]
+fn summary_dedup_keeps_one_of_equal_versions() {
+    let mut storages = common::storages();
+    let copy = |data_len| common::StoredAccount {
+        pubkey: common::ALICE,
+        write_version: 10,
+        lamports: common::ALICE_LAMPORTS,
+        owner: system_program::id(),
+        executable: false,
+        data: vec![0; data_len],
+    };
+    // Rewrites of Alice's latest version in another append vec of the same slot, one of
+    // them duplicated within that append vec
+    storages.insert((common::SLOT, 2), vec![copy(1), copy(2), copy(2)]);
+    let fixture = Fixture::with_storages(storages);
+
+    let summary = summary_json(&fixture, &["--dedup"]);
+    assert_eq!(summary["total_accounts"], 5);
+    // Of the copies in that append vec, the one stored last may be kept, not the others
+    let data_bytes = summary["total_data_bytes"].as_u64().unwrap();
+    assert!([82 + 165 + 165 + 10, 82 + 165 + 165 + 10 + 2].contains(&data_bytes));
+}
+
+#[test]
+fn dedup_reuses_cached_skip_index() {
+    let fixture = Fixture::new();
+    let cache_home = fixture.path().with_extension("cache");
+    let snapshot_cache = cache_home
+        .join("solana-snapshot-etl")
+        .join(format!(
+            "v{}",
+            solana_snapshot_etl::cache::CACHE_FORMAT_VERSION
+        ))
+        .join(common::bank_hash().to_string());
+    let summary = || {
+        let output = Command::new(env!("CARGO_BIN_EXE_solana-snapshot-etl"))
+            .env("XDG_CACHE_HOME", &cache_home)
+            .args(["--raw", "--dedup"])
+            .arg(fixture.path())
+            .args(["summary", "--format", "json"])
+            .output()
+            .expect("failed to run solana-snapshot-etl");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice::<Value>(&output.stdout).unwrap()
+    };
+
+    assert_eq!(summary()["total_accounts"], 5);
+    let manifest: Value =
+        serde_json::from_slice(&std::fs::read(snapshot_cache.join("manifest.json")).unwrap())
+            .unwrap();
+    assert_eq!(manifest["slot"], common::SLOT);
+    assert_eq!(manifest["epoch"], common::EPOCH);
+    assert_eq!(manifest["block_height"], common::BLOCK_HEIGHT);
+    assert_eq!(manifest["capitalization"], common::CAPITALIZATION);
+    assert_eq!(manifest["bank_hash"], common::bank_hash().to_string());
+    assert_eq!(
+        manifest["accounts_hash"],
+        common::accounts_hash(&common::storages()).to_string()
+    );
+    assert_eq!(manifest["storages"], common::storages().len());
+    let skip_index_path = snapshot_cache.join("skip-index.bin");
+    let skip_index = std::fs::read(&skip_index_path).unwrap();
+    let skipped = SkipIndex::from_bytes(&skip_index).unwrap();
+    // Alice's version from the old slot
+    assert_eq!(skipped.len(), 1);
+
+    // A cached index that skips nothing is used instead of a first pass
+    for entry in std::fs::read_dir(&snapshot_cache).unwrap() {
+        std::fs::remove_file(entry.unwrap().path()).unwrap();
+    }
+    std::fs::write(&skip_index_path, SkipIndex::default().to_bytes().unwrap()).unwrap();
+    let total_accounts = summary()["total_accounts"].clone();
+    std::fs::remove_dir_all(&cache_home).unwrap();
+    assert_eq!(total_accounts, 6);
+}
+
+#[test]
+fn summary_lamport_filter() {
+    let fixture = Fixture::new();
+    let summary = summary_json(&fixture, &["--min-lamports", "1000000000"]);
+    // Both versions of ALICE
+    assert_eq!(summary["total_accounts"], 2);
+    assert_eq!(summary["total_data_bytes"], 0);
+}
+
+#[test]
+fn summary_lamport_range() {
+    let fixture = Fixture::new();
+    let summary = summary_json(&fixture, &["--max-lamports", "1000000"]);
+    // Only the odd dust account
+    assert_eq!(summary["total_accounts"], 1);
+    assert_eq!(summary["total_data_bytes"], 10);
+
+    let summary = summary_json(
+        &fixture,
+        &["--min-lamports", "1000000", "--max-lamports", "1500000"],
+    );
+    assert_eq!(summary["total_accounts"], 1);
+    assert_eq!(summary["total_data_bytes"], 82);
+
+    let output = run(
+        &fixture,
+        &["--min-lamports", "2", "--max-lamports", "1"],
+        &["summary"],
+    );
+    assert!(!output.status.success());
+}
+
+#[test]
+fn summary_executable_filter() {
+    let fixture = Fixture::new();
+    let summary = summary_json(&fixture, &["--executable-only"]);
+    assert_eq!(summary["total_accounts"], 0);
+
+    let summary = summary_json(&fixture, &["--non-executable-only"]);
+    assert_eq!(summary["total_accounts"], 6);
+
+    let output = run(
+        &fixture,
+        &["--executable-only", "--non-executable"],
+        &["summary"],
+    );
+    assert!(!output.status.success());
+}
+
+#[test]
+fn summary_rent_exempt_filter() {
+    let fixture = Fixture::new();
+    // The fixture stores rent epoch 0 everywhere
+    let summary = summary_json(&fixture, &["--rent-exempt-only"]);
+    assert_eq!(summary["total_accounts"], 0);
+
+    let summary = summary_json(&fixture, &["--rent-paying-only"]);
+    assert_eq!(summary["total_accounts"], 6);
+
+    for flag in ["--rent-exempt-only", "--rent-paying-only"] {
+        let output = run(&fixture, &["--rent-epoch", "0", flag], &["summary"]);
+        assert!(!output.status.success(), "{} accepted", flag);
+    }
+}
+
+#[test]
+fn summary_excludes_owner() {
+    let fixture = Fixture::new();
+    let summary = summary_json(&fixture, &["--exclude-owner", common::TOKEN_PROGRAM_ID]);
+    // Both versions of ALICE and the odd system account
+    assert_eq!(summary["total_accounts"], 3);
+    assert_eq!(summary["total_data_bytes"], 10);
+}
+
+#[test]
+fn summary_data_len_range() {
+    let fixture = Fixture::new();
+    let summary = summary_json(
+        &fixture,
+        &["--min-data-size", "165", "--max-data-size", "165"],
+    );
+    // The two token accounts
+    assert_eq!(summary["total_accounts"], 2);
+    assert_eq!(summary["total_data_bytes"], 165 + 165);
+
+    let output = run(
+        &fixture,
+        &["--min-data-size", "200", "--max-data-size", "100"],
+        &["summary"],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no account would match"));
+}
+
+#[test]
+fn summary_memcmp_filter() {
+    let fixture = Fixture::new();
+    // Token accounts start with their mint
+    let mint_filter = format!("0:{}", common::MINT);
+    let summary = summary_json(&fixture, &["--memcmp", &mint_filter]);
+    assert_eq!(summary["total_accounts"], 1);
+
+    // ...followed by their owner
+    let owner_filter = format!("32:{}", common::BOB);
+    let summary = summary_json(&fixture, &["--memcmp", &owner_filter]);
+    assert_eq!(summary["total_accounts"], 2);
+    let summary = summary_json(
+        &fixture,
+        &["--memcmp", &mint_filter, "--memcmp", &owner_filter],
+    );
+    assert_eq!(summary["total_accounts"], 1);
+}
+
+#[test]
+fn summary_where_expression() {
+    let fixture = Fixture::new();
+    let token_accounts = format!(
+        "owner = '{}' AND data_len = 165 AND NOT pubkey IN ('{}')",
+        common::TOKEN_PROGRAM_ID,
+        common::DANGLING_TOKEN_ACCOUNT
+    );
+    let summary = summary_json(&fixture, &["--where", &token_accounts]);
+    assert_eq!(summary["total_accounts"], 1);
+
+    let summary = summary_json(
+        &fixture,
+        &[
+            "--where",
+            "slot < 100 or (lamports <= 5000 and executable = false)",
+        ],
+    );
+    // The old version of ALICE and the odd account
+    assert_eq!(summary["total_accounts"], 2);
+
+    let output = run(&fixture, &["--where", "lamports > 'abc'"], &["summary"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn stats_groups_by_owner() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["stats"]);
+    assert_eq!(report_value(&stdout, "Accounts processed:"), "6");
+    let token_row = stdout
+        .lines()
+        .find(|line| line.starts_with(common::TOKEN_PROGRAM_ID))
+        .expect("no token program row");
+    let fields: Vec<&str> = token_row.split_whitespace().collect();
+    assert_eq!(fields[1..3], ["3", "412"]);
+    assert_eq!(
+        fields[3],
+        (common::MINT_LAMPORTS + 2 * common::TOKEN_ACCOUNT_LAMPORTS).to_string()
+    );
+    assert!(token_row.ends_with("Token Program"));
+    // The fixture holds no program bytecode
+    assert_eq!(report_value(&stdout, "State (non-executable) size:"), "422");
+}
+
+#[test]
+fn stats_folds_small_owners_into_other() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["stats", "--min-bytes", "100"]);
+    assert!(!stdout
+        .lines()
+        .any(|line| line.starts_with("11111111111111111111111111111111")));
+    let other_row = stdout
+        .lines()
+        .find(|line| line.starts_with("OTHER (1 owners)"))
+        .expect("no other row");
+    let fields: Vec<&str> = other_row.split_whitespace().collect();
+    assert_eq!(fields[3..5], ["3", "10"]);
+}
+
+#[test]
+fn stats_writes_all_owners_to_output() {
+    let fixture = Fixture::new();
+    let csv_path = fixture.path().with_extension("stats.csv");
+    let json_path = fixture.path().with_extension("stats.json");
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "stats",
+            "--top",
+            "1",
+            "--output",
+            csv_path.to_str().unwrap(),
+        ],
+    );
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "stats",
+            "--min-bytes",
+            "1000",
+            "--output",
+            json_path.to_str().unwrap(),
+            "--format",
+            "json",
+        ],
+    );
+    let csv = std::fs::read_to_string(&csv_path).unwrap();
+    let json: Value = serde_json::from_slice(&std::fs::read(&json_path).unwrap()).unwrap();
+    std::fs::remove_file(&csv_path).unwrap();
+    std::fs::remove_file(&json_path).unwrap();
+
+    // Neither --top nor the minimums truncate the output file
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "owner,program,category,count,empty_count,total_size,avg_size,total_lamports,\
+         executable_count,executable_size,data_len_p50,data_len_p90,data_len_max"
+    );
+    // Data length percentiles are only tracked with --size-percentiles
+    assert_eq!(
+        lines[1],
+        format!(
+            "{},Token Program,infra,3,0,412,137,{},0,0,,,",
+            common::TOKEN_PROGRAM_ID,
+            common::MINT_LAMPORTS + 2 * common::TOKEN_ACCOUNT_LAMPORTS
+        )
+    );
+    assert_eq!(lines.len(), 3);
+    assert_eq!(json.as_array().unwrap().len(), 2);
+    assert_eq!(json[1]["program"], "System Program");
+    assert_eq!(json[1]["total_size"], 10);
+    // Both versions of ALICE hold only lamports
+    assert_eq!(json[1]["empty_count"], 2);
+}
+
+#[test]
+fn stats_attributes_programdata_to_the_upgradeable_loader() {
+    let fixture = Fixture::with_storages(upgradeable_program_storages(
+        Pubkey::new_from_array([96; 32]),
+        Pubkey::new_from_array([97; 32]),
+    ));
+    let json_path = fixture.path().with_extension("stats.json");
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "stats",
+            "--output",
+            json_path.to_str().unwrap(),
+            "--format",
+            "json",
+        ],
+    );
+    let json: Value = serde_json::from_slice(&std::fs::read(&json_path).unwrap()).unwrap();
+    std::fs::remove_file(&json_path).unwrap();
+
+    let loader = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|owner| owner["owner"] == solana_sdk::bpf_loader_upgradeable::id().to_string())
+        .expect("no upgradeable loader row");
+    // The Program account and the ProgramData account holding the bytecode
+    let programdata_len = 45 + 100;
+    assert_eq!(loader["executable_count"], 1);
+    assert_eq!(loader["executable_size"], 36 + programdata_len);
+}
+
+#[test]
+fn stats_resumes_from_checkpoint() {
+    let fixture = Fixture::new();
+    let cache_home = fixture.path().with_extension("cache");
+    let snapshot_cache = cache_home
+        .join("solana-snapshot-etl")
+        .join(format!(
+            "v{}",
+            solana_snapshot_etl::cache::CACHE_FORMAT_VERSION
+        ))
+        .join(common::bank_hash().to_string());
+    std::fs::create_dir_all(&snapshot_cache).unwrap();
+    // A checkpoint taken after the old append vec, with inflated counts so that
+    // recounting it would show
+    let checkpoint_path = snapshot_cache.join("stats.checkpoint.json");
+    let checkpoint = serde_json::json!({
+        "append_vecs": [[common::OLD_SLOT, 0]],
+        "accounts_count": 100,
+        "owners": [{
+            "owner": "11111111111111111111111111111111",
+            "count": 100,
+            "empty_count": 100,
+            "total_size": 0,
+            "total_lamports": 0,
+            "executable_count": 0,
+            "executable_size": 0,
+        }],
+    });
+    std::fs::write(&checkpoint_path, checkpoint.to_string()).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_solana-snapshot-etl"))
+        .env("XDG_CACHE_HOME", &cache_home)
+        .arg("--raw")
+        .arg(fixture.path())
+        .args(["stats", "--checkpoint-every", "1"])
+        .output()
+        .expect("failed to run solana-snapshot-etl");
+    let checkpoint_left = checkpoint_path.exists();
+    std::fs::remove_dir_all(&cache_home).unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    // Only the 5 accounts of the newer append vec are added to the checkpoint
+    assert_eq!(report_value(&stdout, "Accounts processed:"), "105");
+    let system_row = stdout
+        .lines()
+        .find(|line| line.starts_with("11111111111111111111111111111111"))
+        .expect("no system program row");
+    let fields: Vec<&str> = system_row.split_whitespace().collect();
+    assert_eq!(fields[1..3], ["102", "10"]);
+    // The finished report replaces the checkpoint
+    assert!(!checkpoint_left);
+}
+
+#[test]
+fn stats_reports_data_len_percentiles() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["stats", "--size-percentiles"]);
+    let (_, percentiles) = stdout
+        .split_once("--- Data Length Percentiles by Owner ---")
+        .expect("no percentiles section");
+    let row = |owner: &str| {
+        percentiles
+            .lines()
+            .find(|line| line.starts_with(owner))
+            .unwrap()
+            .split_whitespace()
+            .skip(1)
+            .take(4)
+            .collect::<Vec<_>>()
+    };
+    // Mint (82 bytes) and two token accounts (165 bytes)
+    assert_eq!(row(common::TOKEN_PROGRAM_ID), ["3", "165", "165", "165"]);
+    // Two versions of ALICE without data and ODD_ACCOUNT
+    assert_eq!(
+        row("11111111111111111111111111111111"),
+        ["3", "0", "10", "10"]
+    );
+}
+
+#[test]
+fn stats_sorts_and_limits_owners() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["stats", "--sort", "avg", "--top", "1"]);
+    let owners: Vec<&str> = stdout
+        .lines()
+        .filter(|line| {
+            line.starts_with(common::TOKEN_PROGRAM_ID)
+                || line.starts_with("11111111111111111111111111111111")
+        })
+        .collect();
+    assert_eq!(owners.len(), 1);
+    assert!(owners[0].starts_with(common::TOKEN_PROGRAM_ID));
+    // Totals still cover all owners
+    assert!(stdout
+        .lines()
+        .any(|line| line.starts_with("TOTAL") && line.split_whitespace().any(|f| f == "422")));
+}
+
+#[test]
+fn program_labels_extend_builtin_names() {
+    let fixture = Fixture::new();
+    let labels_path = fixture.path().with_extension("labels.json");
+    std::fs::write(
+        &labels_path,
+        format!(r#"{{"{}": "SPL Token"}}"#, common::TOKEN_PROGRAM_ID),
+    )
+    .unwrap();
+    let stdout = run_ok(
+        &fixture,
+        &["--program-labels", labels_path.to_str().unwrap()],
+        &["stats"],
+    );
+    std::fs::remove_file(&labels_path).unwrap();
+
+    let row = |owner: &str| {
+        stdout
+            .lines()
+            .find(|line| line.starts_with(owner))
+            .unwrap()
+            .to_string()
+    };
+    assert!(row(common::TOKEN_PROGRAM_ID).ends_with("SPL Token"));
+    assert!(row("11111111111111111111111111111111").ends_with("System Program"));
+}
+
+#[test]
+fn stats_groups_by_category() {
+    let fixture = Fixture::new();
+    let labels_path = fixture.path().with_extension("categories.json");
+    std::fs::write(
+        &labels_path,
+        format!(
+            r#"{{"{}": {{"category": "DeFi"}}}}"#,
+            common::TOKEN_PROGRAM_ID
+        ),
+    )
+    .unwrap();
+    let stdout = run_ok(
+        &fixture,
+        &["--program-labels", labels_path.to_str().unwrap()],
+        &["stats", "--group-by", "category"],
+    );
+    std::fs::remove_file(&labels_path).unwrap();
+
+    let row = |category: &str| {
+        stdout
+            .lines()
+            .find(|line| line.starts_with(category))
+            .unwrap()
+            .split_whitespace()
+            .skip(1)
+            .take(3)
+            .collect::<Vec<_>>()
+    };
+    // Owners, accounts and data bytes
+    assert_eq!(row("DeFi"), ["1", "3", "412"]);
+    assert_eq!(row("system"), ["1", "3", "10"]);
+}
+
+#[test]
+fn verify_passes() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["verify"]);
+    assert_eq!(report_value(&stdout, "Capitalization check:"), "PASS");
+    assert_eq!(report_value(&stdout, "Accounts hash check:"), "PASS");
+    assert_eq!(report_value(&stdout, "Delta (lamports):"), "0");
+}
+
+#[test]
+fn merkle_root_matches_known_vector() {
+    use solana_snapshot_etl::accounts_hash::{compute_merkle_root, MERKLE_FANOUT};
+    // Two levels: a full chunk of 16 leaves and a partial one of 4
+    let hashes: Vec<solana_sdk::hash::Hash> =
+        (0..20u8).map(|i| solana_sdk::hash::hash(&[i])).collect();
+    assert_eq!(
+        compute_merkle_root(&hashes, MERKLE_FANOUT).to_string(),
+        "CvPbLmTeV5BXbvSyRoVr6kkFr4mfA2R5TeR4ckjP9LFy"
+    );
+    assert_eq!(
+        compute_merkle_root(&[], MERKLE_FANOUT),
+        solana_sdk::hash::hash(&[])
+    );
+}
+
+#[test]
+fn verify_rejects_hidden_accounts() {
+    let fixture = Fixture::new();
+    for args in [
+        &["--dedup"][..],
+        &["--skip-zero-lamports"],
+        &["--max-data-len", "100"],
+        &["--where", "lamports > 0"],
+        &["--min-lamports", "1"],
+    ] {
+        let output = run(&fixture, args, &["verify"]);
+        assert!(!output.status.success(), "{:?}", args);
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("hide accounts from the checks"),
+            "{:?}",
+            args
+        );
+    }
+
+    let incremental = Fixture::incremental(incremental_storages(), common::SLOT);
+    let incremental_arg = format!("--incremental={}", incremental.path().display());
+    let output = run(&fixture, &[&incremental_arg], &["verify"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("only checks full snapshots"));
+}
+
+#[test]
+fn duplicates_finds_rewritten_account() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["duplicates"]);
+    assert_eq!(report_value(&stdout, "Stored accounts:"), "6");
+    assert_eq!(report_value(&stdout, "Distinct pubkeys:"), "5");
+    assert_eq!(report_value(&stdout, "Pubkeys in >1 append vec:"), "1");
+    assert!(stdout.contains(&common::ALICE.to_string()));
+}
+
+#[test]
+fn duplicates_counts_versions_within_one_append_vec() {
+    let mut storages = common::storages();
+    storages
+        .get_mut(&(common::SLOT, 1))
+        .unwrap()
+        .push(common::StoredAccount {
+            pubkey: common::ODD_ACCOUNT,
+            write_version: 15,
+            lamports: common::ODD_LAMPORTS,
+            owner: system_program::id(),
+            executable: false,
+            data: vec![0xbb; 10],
+        });
+    let fixture = Fixture::with_storages(storages);
+    let stdout = run_ok(&fixture, &[], &["duplicates"]);
+    assert_eq!(report_value(&stdout, "Pubkeys with >1 version:"), "2");
+    assert_eq!(report_value(&stdout, "Pubkeys in >1 append vec:"), "1");
+    // Alice's old version and the first version of the odd account: 136 bytes of headers
+    // each, plus the 10 bytes of data padded to 16
+    assert!(report_value(&stdout, "Wasted bytes:").starts_with("288 "));
+    assert!(stdout.contains(&common::ODD_ACCOUNT.to_string()));
+}
+
+#[test]
+fn audit_flags_system_account_with_data() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["audit"]);
+    assert_eq!(report_value(&stdout, "Accounts audited"), "6");
+    assert_eq!(
+        report_value(&stdout, "System-owned accounts with non-nonce data"),
+        "1"
+    );
+    assert_eq!(
+        report_value(&stdout, "Executable accounts not owned by a loader"),
+        "0"
+    );
+    assert!(stdout.contains(&common::ODD_ACCOUNT.to_string()));
+}
+
+#[test]
+fn trend_writes_owner_stats_per_snapshot() {
+    let fixture = Fixture::new();
+    fixture.archive("snapshot-100-fixture.tar.zst", |file| {
+        zstd::Encoder::new(file, 3).unwrap().auto_finish()
+    });
+    let csv_path = fixture.path().with_extension("trend.csv");
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "trend",
+            "--output",
+            csv_path.to_str().unwrap(),
+            "--top",
+            "1",
+        ],
+    );
+    let csv = std::fs::read_to_string(&csv_path).unwrap();
+    std::fs::remove_file(&csv_path).unwrap();
+
+    // The unpacked snapshot next to the archive is not part of the series
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "slot,owner,program,count,total_size".to_string(),
+            format!(
+                "{},{},Token Program,3,412",
+                common::SLOT,
+                common::TOKEN_PROGRAM_ID
+            ),
+        ]
+    );
+}
+
+#[test]
+fn diff_reports_created_deleted_and_changed_accounts() {
+    // The earlier snapshot only has the old append vec, plus an account deleted since
+    let gone = common::StoredAccount {
+        pubkey: Pubkey::new_from_array([8; 32]),
+        write_version: 2,
+        lamports: 1_000,
+        owner: system_program::id(),
+        executable: false,
+        data: vec![],
+    };
+    let mut storages = common::storages();
+    storages.retain(|(slot, _), _| *slot == common::OLD_SLOT);
+    storages.values_mut().next().unwrap().push(gone);
+    let before = Fixture::with_storages(storages);
+    let after = Fixture::new();
+    let db = after.path().join("diff.duckdb");
+
+    let stdout = run_ok(
+        &before,
+        &[],
+        &[
+            "diff",
+            after.path().to_str().unwrap(),
+            "--db",
+            db.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(report_value(&stdout, "Created accounts:"), "4");
+    assert_eq!(report_value(&stdout, "Deleted accounts:"), "1");
+    assert_eq!(report_value(&stdout, "Changed accounts:"), "1");
+    assert_eq!(report_value(&stdout, "  Lamports changed:"), "1");
+    assert_eq!(report_value(&stdout, "  Data changed:"), "0");
+    assert_eq!(
+        report_value(&stdout, "Lamports change:"),
+        format!(
+            "-{}",
+            common::ALICE_OLD_LAMPORTS + 1_000 - common::CAPITALIZATION
+        )
+    );
+    assert_eq!(report_value(&stdout, "Data size change:"), "+422");
+    let token_row = stdout
+        .lines()
+        .find(|line| line.starts_with("Token Program"))
+        .expect("no token program row");
+    let fields: Vec<&str> = token_row.split_whitespace().collect();
+    assert_eq!(fields[2..5], ["3", "0", "0"]);
+
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT change FROM account_changes ORDER BY change, pubkey"
+        ),
+        ["changed", "created", "created", "created", "created", "deleted"]
+    );
+}
+
+#[test]
+fn token_extensions_counts_extension_types() {
+    let token_2022_program =
+        Pubkey::from_str("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb").unwrap();
+    // Mint padded to 165 bytes, account type 1, then a 108-byte TransferFeeConfig entry
+    let mut mint_data = vec![0; 165];
+    mint_data.push(1);
+    mint_data.extend_from_slice(&1u16.to_le_bytes());
+    mint_data.extend_from_slice(&108u16.to_le_bytes());
+    mint_data.extend_from_slice(&[0; 108]);
+    // Token account with account type 2 and an empty ImmutableOwner entry
+    let mut account_data = vec![0; 165];
+    account_data.push(2);
+    account_data.extend_from_slice(&7u16.to_le_bytes());
+    account_data.extend_from_slice(&0u16.to_le_bytes());
+    let extended_mint = Pubkey::new_from_array([9; 32]);
+
+    let mut storages = common::storages();
+    let accounts = storages.get_mut(&(common::SLOT, 1)).unwrap();
+    for (seed, data) in [(9, mint_data), (10, account_data), (11, vec![0; 165])] {
+        accounts.push(common::StoredAccount {
+            pubkey: Pubkey::new_from_array([seed; 32]),
+            write_version: seed as u64 * 10,
+            lamports: 3_000_000,
+            owner: token_2022_program,
+            executable: false,
+            data,
+        });
+    }
+    let fixture = Fixture::with_storages(storages);
+    let db = fixture.path().join("extensions.duckdb");
+
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["token-extensions", "--db", db.to_str().unwrap()],
+    );
+    assert_eq!(report_value(&stdout, "Mints:"), "1");
+    assert_eq!(report_value(&stdout, "Token accounts:"), "2");
+    assert_eq!(report_value(&stdout, "Unparsed accounts:"), "0");
+    let row = |name: &str| {
+        let line = stdout
+            .lines()
+            .find(|line| line.split_whitespace().nth(1) == Some(name))
+            .unwrap_or_else(|| panic!("no {} row", name));
+        line.split_whitespace().skip(2).collect::<Vec<_>>()
+    };
+    assert_eq!(row("TransferFeeConfig"), ["1", "0"]);
+    assert_eq!(row("ImmutableOwner"), ["0", "1"]);
+
+    assert_eq!(
+        query_strings(&db, "SELECT mint || ':' || extension FROM mint_extensions"),
+        [format!("{}:TransferFeeConfig", extended_mint)]
+    );
+}
+
+#[test]
+fn frozen_accounts_reports_frozen_token_accounts() {
+    let mut storages = common::storages();
+    for account in storages.values_mut().flatten() {
+        if account.pubkey == common::TOKEN_ACCOUNT {
+            account.data[108] = 2; // AccountState::Frozen
+        }
+    }
+    let fixture = Fixture::with_storages(storages);
+
+    let stdout = run_ok(&fixture, &[], &["frozen-accounts"]);
+    assert_eq!(report_value(&stdout, "Token accounts checked:"), "2");
+    assert_eq!(report_value(&stdout, "Frozen accounts:"), "1");
+    assert_eq!(report_value(&stdout, "Mints with frozen accs:"), "1");
+    let mint_row = stdout
+        .lines()
+        .find(|line| line.starts_with(&common::MINT.to_string()))
+        .expect("no mint row");
+    let fields: Vec<&str> = mint_row.split_whitespace().collect();
+    assert_eq!(fields[1..], ["1", "1", "100.00%", "500", "500", "(none)"]);
+
+    let db = fixture.path().join("tokens.duckdb");
+    run_ok(
+        &fixture,
+        &[],
+        &["dump-tokens", "--db", db.to_str().unwrap()],
+    );
+    assert_eq!(
+        query_strings(&db, "SELECT pubkey FROM token_accounts WHERE frozen"),
+        [common::TOKEN_ACCOUNT.to_string()]
+    );
+}
+
+#[test]
+fn frozen_accounts_counts_latest_versions() {
+    let mut storages = common::storages();
+    // The dangling token account was frozen in an older version and thawed since
+    let mut frozen = common::token_account_data(&common::MISSING_MINT, &common::BOB, 7);
+    frozen[108] = 2; // AccountState::Frozen
+    storages.insert(
+        (common::OLD_SLOT, 1),
+        vec![common::StoredAccount {
+            pubkey: common::DANGLING_TOKEN_ACCOUNT,
+            write_version: 2,
+            lamports: common::TOKEN_ACCOUNT_LAMPORTS,
+            owner: Pubkey::from_str(common::TOKEN_PROGRAM_ID).unwrap(),
+            executable: false,
+            data: frozen,
+        }],
+    );
+    let fixture = Fixture::with_storages(storages);
+
+    let stdout = run_ok(&fixture, &[], &["frozen-accounts"]);
+    assert_eq!(report_value(&stdout, "Token accounts checked:"), "2");
+    assert_eq!(report_value(&stdout, "Frozen accounts:"), "0");
+    assert_eq!(report_value(&stdout, "Mints with frozen accs:"), "0");
+}
+
+#[test]
+fn holders_lists_token_accounts_of_mint() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["holders", "--mint", &common::MINT.to_string()],
+    );
+    assert_eq!(report_value(&stdout, "Token accounts:"), "1");
+    assert_eq!(report_value(&stdout, "Holders:"), "1");
+    assert_eq!(
+        report_value(&stdout, "Total amount:"),
+        common::TOKEN_AMOUNT.to_string()
+    );
+    let row = stdout
+        .lines()
+        .find(|line| line.trim_start().starts_with("1 "))
+        .expect("no rank 1 row");
+    let fields: Vec<&str> = row.split_whitespace().collect();
+    assert_eq!(
+        fields,
+        [
+            "1".to_string(),
+            common::TOKEN_ACCOUNT.to_string(),
+            common::BOB.to_string(),
+            common::TOKEN_AMOUNT.to_string(),
+            "100.00%".to_string()
+        ]
+    );
+}
+
+#[test]
+fn holders_skips_closed_token_accounts() {
+    // ALICE held the mint in an account that has since been closed
+    let closed = Pubkey::new_from_array([0x21; 32]);
+    let mut storages = common::storages();
+    storages
+        .get_mut(&(common::OLD_SLOT, 0))
+        .unwrap()
+        .push(common::StoredAccount {
+            pubkey: closed,
+            write_version: 2,
+            lamports: common::TOKEN_ACCOUNT_LAMPORTS,
+            owner: Pubkey::from_str(common::TOKEN_PROGRAM_ID).unwrap(),
+            executable: false,
+            data: common::token_account_data(&common::MINT, &common::ALICE, 100),
+        });
+    storages
+        .get_mut(&(common::SLOT, 1))
+        .unwrap()
+        .push(common::StoredAccount {
+            pubkey: closed,
+            write_version: 15,
+            lamports: 0,
+            owner: system_program::id(),
+            executable: false,
+            data: vec![],
+        });
+    let fixture = Fixture::with_storages(storages);
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["holders", "--mint", &common::MINT.to_string()],
+    );
+    assert_eq!(report_value(&stdout, "Token accounts:"), "1");
+    assert_eq!(report_value(&stdout, "Holders:"), "1");
+    assert!(!stdout.contains(&common::ALICE.to_string()));
+}
+
+#[test]
+fn nft_mints_reports_holders() {
+    // Turn the fixture mint into a single-unit mint held by its token account
+    let mut storages = common::storages();
+    for account in storages.values_mut().flatten() {
+        if account.pubkey == common::MINT {
+            account.data[36..44].copy_from_slice(&1u64.to_le_bytes());
+            account.data[44] = 0;
+        } else if account.pubkey == common::TOKEN_ACCOUNT {
+            account.data[64..72].copy_from_slice(&1u64.to_le_bytes());
+        }
+    }
+    // An older version of the token account, before the NFT was transferred to BOB
+    storages.insert(
+        (common::OLD_SLOT, 1),
+        vec![common::StoredAccount {
+            pubkey: common::TOKEN_ACCOUNT,
+            write_version: 2,
+            lamports: common::TOKEN_ACCOUNT_LAMPORTS,
+            owner: Pubkey::from_str(common::TOKEN_PROGRAM_ID).unwrap(),
+            executable: false,
+            data: common::token_account_data(&common::MINT, &common::ALICE, 1),
+        }],
+    );
+    let fixture = Fixture::with_storages(storages);
+    let csv = fixture.path().join("nfts.csv");
+
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["nft-mints", "--csv", csv.to_str().unwrap()],
+    );
+    assert_eq!(report_value(&stdout, "NFT-like mints:"), "1");
+    assert_eq!(report_value(&stdout, "  Held:"), "1");
+    assert_eq!(report_value(&stdout, "  With mint authority:"), "1");
+    assert_eq!(report_value(&stdout, "  With freeze authority:"), "0");
+    assert_eq!(report_value(&stdout, "Distinct holders:"), "1");
+
+    let csv = std::fs::read_to_string(&csv).unwrap();
+    assert_eq!(
+        csv.lines().collect::<Vec<_>>(),
+        [
+            "mint,token_account,owner".to_string(),
+            format!("{},{},{}", common::MINT, common::TOKEN_ACCOUNT, common::BOB)
+        ]
+    );
+}
+
+#[test]
+fn nft_collections_groups_verified_items() {
+    let metadata_program = Pubkey::from_str(common::METADATA_PROGRAM_ID).unwrap();
+    let collection = Pubkey::new_from_array([20; 32]);
+    // The fixture mint is held by its token account, the second item has no holder
+    // and the third only claims the collection
+    let items = [
+        (common::MINT, true),
+        (Pubkey::new_from_array([21; 32]), true),
+        (Pubkey::new_from_array([22; 32]), false),
+    ];
+    let mut storages = common::storages();
+    for account in storages.values_mut().flatten() {
+        if account.pubkey == common::TOKEN_ACCOUNT {
+            account.data[64..72].copy_from_slice(&1u64.to_le_bytes());
+        }
+    }
+    let accounts = storages.get_mut(&(common::SLOT, 1)).unwrap();
+    for (i, (mint, verified)) in items.into_iter().enumerate() {
+        accounts.push(common::StoredAccount {
+            pubkey: Pubkey::new_from_array([30 + i as u8; 32]),
+            write_version: 20 + i as u64,
+            lamports: 5_616_720,
+            owner: metadata_program,
+            executable: false,
+            data: common::metadata_data(&mint, Some((collection, verified))),
+        });
+    }
+    // An older version of the second item's metadata, before its collection was verified
+    storages.insert(
+        (common::OLD_SLOT, 1),
+        vec![common::StoredAccount {
+            pubkey: Pubkey::new_from_array([31; 32]),
+            write_version: 2,
+            lamports: 5_616_720,
+            owner: metadata_program,
+            executable: false,
+            data: common::metadata_data(&items[1].0, Some((collection, false))),
+        }],
+    );
+    let fixture = Fixture::with_storages(storages);
+
+    let stdout = run_ok(&fixture, &[], &["nft-collections"]);
+    assert_eq!(report_value(&stdout, "Metadata accounts:"), "3");
+    assert_eq!(report_value(&stdout, "  Verified items:"), "2");
+    assert_eq!(report_value(&stdout, "  Unverified items:"), "1");
+    assert_eq!(report_value(&stdout, "Collections:"), "1");
+    let row = stdout
+        .lines()
+        .find(|line| line.starts_with(&collection.to_string()))
+        .expect("no collection row");
+    let fields: Vec<&str> = row.split_whitespace().collect();
+    let metadata_bytes = (2 * common::METADATA_ACCOUNT_LEN).to_string();
+    assert_eq!(fields[1..], ["2", "1", metadata_bytes.as_str()]);
+}
+
+#[test]
+fn merkle_trees_reports_tree_shapes() {
+    let config = Pubkey::new_from_array([50; 32]);
+    // Depth 3, buffer 8 tree with 5 leaves and a depth 1 canopy: the header, the
+    // sequence number, active index and buffer size, 8 change logs, the rightmost proof
+    // ending in the leaf index, then 2 canopy nodes
+    let mut tree = vec![1, 0];
+    tree.extend_from_slice(&8u32.to_le_bytes());
+    tree.extend_from_slice(&3u32.to_le_bytes());
+    tree.extend_from_slice(config.as_ref());
+    tree.resize(56 + 24 + 8 * (32 + 3 * 32 + 8) + 3 * 32 + 32, 0);
+    tree.extend_from_slice(&5u64.to_le_bytes());
+    tree.resize(tree.len() + 2 * 32, 0);
+    let tree_len = tree.len();
+    // Bubblegum tree config: discriminator, creator, delegate, capacity, minted, flags
+    let discriminator = solana_sdk::hash::hash(b"account:TreeConfig");
+    let mut tree_config = discriminator.as_ref()[0..8].to_vec();
+    tree_config.resize(72, 0);
+    tree_config.extend_from_slice(&8u64.to_le_bytes());
+    tree_config.extend_from_slice(&5u64.to_le_bytes());
+    tree_config.resize(96, 0);
+
+    let accounts = [
+        (
+            Pubkey::new_from_array([51; 32]),
+            "cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK",
+            tree,
+        ),
+        (
+            config,
+            "BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY",
+            tree_config,
+        ),
+    ];
+    let mut storages = common::storages();
+    let stored = storages.get_mut(&(common::SLOT, 1)).unwrap();
+    for (i, (pubkey, owner, data)) in accounts.into_iter().enumerate() {
+        stored.push(common::StoredAccount {
+            pubkey,
+            write_version: 50 + i as u64,
+            lamports: 10_000_000,
+            owner: Pubkey::from_str(owner).unwrap(),
+            executable: false,
+            data,
+        });
+    }
+    let fixture = Fixture::with_storages(storages);
+
+    let stdout = run_ok(&fixture, &[], &["merkle-trees"]);
+    assert_eq!(report_value(&stdout, "Merkle trees:"), "1");
+    assert_eq!(report_value(&stdout, "  Bubblegum trees:"), "1");
+    assert_eq!(report_value(&stdout, "Leaves appended:"), "5");
+    assert_eq!(report_value(&stdout, "Leaf capacity:"), "8");
+    assert_eq!(report_value(&stdout, "Tree bytes:"), tree_len.to_string());
+    assert_eq!(report_value(&stdout, "  Canopy bytes:"), "64");
+    assert_eq!(report_value(&stdout, "  Minted:"), "5");
+    let row = stdout
+        .lines()
+        .find(|line| line.trim_start().starts_with("3 "))
+        .expect("no tree shape row");
+    let fields: Vec<&str> = row.split_whitespace().collect();
+    assert_eq!(
+        fields,
+        ["3", "8", "1", "1", "5", tree_len.to_string().as_str()]
+    );
+}
+
+#[test]
+fn token_audit_reports_supply_mismatch() {
+    let mut storages = common::storages();
+    // An older version of the token account, holding the whole supply before a burn
+    storages.insert(
+        (common::OLD_SLOT, 1),
+        vec![common::StoredAccount {
+            pubkey: common::TOKEN_ACCOUNT,
+            write_version: 2,
+            lamports: common::TOKEN_ACCOUNT_LAMPORTS,
+            owner: Pubkey::from_str(common::TOKEN_PROGRAM_ID).unwrap(),
+            executable: false,
+            data: common::token_account_data(&common::MINT, &common::BOB, 1_000),
+        }],
+    );
+    let fixture = Fixture::with_storages(storages);
+    let stdout = run_ok(&fixture, &[], &["token-audit"]);
+    assert_eq!(report_value(&stdout, "Mints checked:"), "1");
+    assert_eq!(report_value(&stdout, "Mints diverging:"), "1");
+    // The fixture mint records a supply of 1,000 but only 500 are held
+    let mint_row = stdout
+        .lines()
+        .find(|line| line.starts_with(&common::MINT.to_string()))
+        .expect("no mint row");
+    let fields: Vec<&str> = mint_row.split_whitespace().collect();
+    assert_eq!(fields[1..], ["1000", "500", "-500", "1"]);
+}
+
+#[test]
+fn token_audit_reports_differences_past_u64() {
+    // Two token accounts holding the largest amount each overflow a u64 together
+    let mut storages = common::storages();
+    for account in storages.values_mut().flatten() {
+        if account.pubkey == common::TOKEN_ACCOUNT {
+            account.data[64..72].copy_from_slice(&u64::MAX.to_le_bytes());
+        }
+    }
+    storages
+        .get_mut(&(common::SLOT, 1))
+        .unwrap()
+        .push(common::StoredAccount {
+            pubkey: Pubkey::new_unique(),
+            write_version: 20,
+            lamports: common::TOKEN_ACCOUNT_LAMPORTS,
+            owner: Pubkey::from_str(common::TOKEN_PROGRAM_ID).unwrap(),
+            executable: false,
+            data: common::token_account_data(&common::MINT, &common::BOB, u64::MAX),
+        });
+    let fixture = Fixture::with_storages(storages);
+    let stdout = run_ok(&fixture, &[], &["token-audit"]);
+    let mint_row = stdout
+        .lines()
+        .find(|line| line.starts_with(&common::MINT.to_string()))
+        .expect("no mint row");
+    let fields: Vec<&str> = mint_row.split_whitespace().collect();
+    let held = 2 * u64::MAX as u128;
+    let difference = format!("+{}", held - 1_000);
+    assert_eq!(
+        fields[1..],
+        ["1000", held.to_string().as_str(), difference.as_str(), "2"]
+    );
+}
+
+#[test]
+fn dangling_mints_reports_missing_mint() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["dangling-mints"]);
+    assert_eq!(report_value(&stdout, "Token accounts checked:"), "2");
+    assert_eq!(report_value(&stdout, "Mints found:"), "1");
+    assert_eq!(report_value(&stdout, "Missing mints:"), "1");
+    assert!(stdout.contains(&common::MISSING_MINT.to_string()));
+}
+
+#[test]
+fn dangling_mints_reports_closed_mint() {
+    let mut storages = common::storages();
+    // The mint was closed after its token account was last written
+    storages.insert(
+        (common::SLOT, 2),
+        vec![common::StoredAccount {
+            pubkey: common::MINT,
+            write_version: 20,
+            lamports: 0,
+            owner: system_program::id(),
+            executable: false,
+            data: vec![],
+        }],
+    );
+    let fixture = Fixture::with_storages(storages);
+
+    let stdout = run_ok(&fixture, &[], &["dangling-mints"]);
+    assert_eq!(report_value(&stdout, "Token accounts checked:"), "2");
+    assert_eq!(report_value(&stdout, "Mints found:"), "0");
+    assert_eq!(report_value(&stdout, "Missing mints:"), "2");
+    assert_eq!(report_value(&stdout, "Dangling token accounts:"), "2");
+    assert!(stdout
+        .lines()
+        .any(|line| line.starts_with(&common::MINT.to_string())));
+}
+
+fn query_strings(db: &Path, sql: &str) -> Vec<String> {
+    let conn = duckdb::Connection::open(db).unwrap();
+    let mut stmt = conn.prepare(sql).unwrap();
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0)).unwrap();
+    rows.map(|row| row.unwrap()).collect()
+}
+
+fn query_count(db: &Path, table: &str) -> u64 {
+    let conn = duckdb::Connection::open(db).unwrap();
+    conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+        row.get(0)
+    })
+    .unwrap()
+}
+
+#[test]
+fn stats_appends_owner_stats_to_db() {
+    let fixture = Fixture::new();
+    let db = fixture.path().join("stats.duckdb");
+    // Rerunning a snapshot replaces its rows rather than duplicating them
+    for _ in 0..2 {
+        run_ok(&fixture, &[], &["stats", "--db", db.to_str().unwrap()]);
+    }
+
+    assert_eq!(query_count(&db, "owner_stats"), 2);
+    let conn = duckdb::Connection::open(&db).unwrap();
+    let (slot, count, bytes): (u64, u64, u64) = conn
+        .query_row(
+            "SELECT slot, count, bytes FROM owner_stats WHERE owner = ?",
+            [common::TOKEN_PROGRAM_ID],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!((slot, count, bytes), (common::SLOT, 3, 412));
+}
+
+#[test]
+fn dump_tokens_writes_rows() {
+    let fixture = Fixture::new();
+    let db = fixture.path().join("tokens.duckdb");
+    run_ok(
+        &fixture,
+        &[],
+        &["dump-tokens", "--db", db.to_str().unwrap()],
+    );
+
+    assert_eq!(query_count(&db, "token_accounts"), 2);
+    assert_eq!(query_count(&db, "mints"), 1);
+    assert_eq!(
+        query_strings(&db, "SELECT pubkey FROM mints"),
+        vec![common::MINT.to_string()]
+    );
+    let conn = duckdb::Connection::open(&db).unwrap();
+    let row: (u64, u8, u64, Option<String>, Option<u64>) = conn
+        .query_row(
+            "SELECT amount, state, lamports, delegate, is_native
+             FROM token_accounts WHERE pubkey = ?",
+            [common::TOKEN_ACCOUNT.to_string()],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            },
+        )
+        .unwrap();
+    let (amount, state, lamports, delegate, is_native) = row;
+    assert_eq!(amount, common::TOKEN_AMOUNT);
+    assert_eq!(state, 1);
+    assert_eq!(lamports, common::TOKEN_ACCOUNT_LAMPORTS);
+    assert_eq!((delegate, is_native), (None, None));
+}
+
+#[test]
+fn dump_tokens_maps_columns() {
+    let fixture = Fixture::new();
+    let db = fixture.path().join("mapped.duckdb");
+    let schema = fixture.path().join("schema.json5");
+    // Renamed columns may be keywords or contain quotes
+    std::fs::write(
+        &schema,
+        r#"{ token_accounts: { pubkey: "select", amount: "the \"amount\"" } }"#,
+    )
+    .unwrap();
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "dump-tokens",
+            "--db",
+            db.to_str().unwrap(),
+            "--schema",
+            schema.to_str().unwrap(),
+        ],
+    );
+
+    let conn = duckdb::Connection::open(&db).unwrap();
+    let amount: u64 = conn
+        .query_row(
+            r#"SELECT "the ""amount""" FROM token_accounts WHERE "select" = ?"#,
+            [common::TOKEN_ACCOUNT.to_string()],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(amount, common::TOKEN_AMOUNT);
+    assert_eq!(query_count(&db, "mints"), 1);
+}
+
+#[test]
+fn dump_tokens_appends_mapped_rows_in_small_batches() {
+    let fixture = Fixture::new();
+    let db = fixture.path().join("mapped-append.duckdb");
+    let schema = fixture.path().join("append-schema.json5");
+    std::fs::write(
+        &schema,
+        r#"{
+            token_accounts: { pubkey: "address", amount: "amount", snapshot_slot: "snapshot slot" },
+            mints: { pubkey: "address", snapshot_slot: "slot" },
+            multisigs: { pubkey: "address", snapshot_slot: "slot" },
+        }"#,
+    )
+    .unwrap();
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "dump-tokens",
+            "--db",
+            db.to_str().unwrap(),
+            "--schema",
+            schema.to_str().unwrap(),
+            "--append",
+            "--batch-rows",
+            "1",
+        ],
+    );
+
+    let conn = duckdb::Connection::open(&db).unwrap();
+    let (amount, slot): (u64, u64) = conn
+        .query_row(
+            "SELECT amount, \"snapshot slot\" FROM token_accounts WHERE address = ?",
+            [common::TOKEN_ACCOUNT.to_string()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!((amount, slot), (common::TOKEN_AMOUNT, common::SLOT));
+    assert_eq!(query_count(&db, "token_accounts"), 2);
+}
+
+#[test]
+fn dump_tokens_rejects_bad_column_mappings() {
+    let fixture = Fixture::new();
+    let db = fixture.path().join("rejected.duckdb");
+    let schema = fixture.path().join("bad-schema.json5");
+    for (mapping, error) in [
+        (
+            "{ token_acounts: { pubkey: \"address\" } }",
+            "Unknown table 'token_acounts'",
+        ),
+        ("{ mints: {} }", "leaves no column in table 'mints'"),
+        (
+            "{ mints: { pubkey: \"address\", supplied: \"x\" } }",
+            "Unknown column 'supplied'",
+        ),
+    ] {
+        std::fs::write(&schema, mapping).unwrap();
+        let output = run(
+            &fixture,
+            &[],
+            &[
+                "dump-tokens",
+                "--db",
+                db.to_str().unwrap(),
+                "--schema",
+                schema.to_str().unwrap(),
+            ],
+        );
+        assert!(!output.status.success(), "{} accepted", mapping);
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains(error),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn dump_tokens_commits_in_small_transactions() {
+    let fixture = Fixture::new();
+    let db = fixture.path().join("tokens.duckdb");
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "dump-tokens",
+            "--db",
+            db.to_str().unwrap(),
+            "--flush-every",
+            "1",
+            "--batch-rows",
+            "1",
+        ],
+    );
+
+    assert_eq!(query_count(&db, "token_accounts"), 2);
+    assert_eq!(query_count(&db, "mints"), 1);
+}
+
+#[test]
+fn dump_tokens_skips_dust_accounts() {
+    let fixture = Fixture::new();
+    let db = fixture.path().join("tokens.duckdb");
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "dump-tokens",
+            "--db",
+            db.to_str().unwrap(),
+            "--min-amount",
+            "100",
+        ],
+    );
+
+    assert_eq!(
+        query_strings(&db, "SELECT pubkey FROM token_accounts"),
+        [common::TOKEN_ACCOUNT.to_string()]
+    );
+    assert_eq!(query_count(&db, "mints"), 1);
+}
+
+#[test]
+fn dump_tokens_appends_rows_by_slot() {
+    let fixture = Fixture::new();
+    let db = fixture.path().join("tokens.duckdb");
+    // Rerunning a snapshot replaces its rows rather than duplicating them
+    for _ in 0..2 {
+        run_ok(
+            &fixture,
+            &[],
+            &["dump-tokens", "--db", db.to_str().unwrap(), "--append"],
+        );
+    }
+
+    assert_eq!(query_count(&db, "token_accounts"), 2);
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT DISTINCT CAST(snapshot_slot AS VARCHAR) FROM mints"
+        ),
+        [common::SLOT.to_string()]
+    );
+}
+
+#[test]
+fn dump_tokens_refuses_to_append_to_other_columns() {
+    let fixture = Fixture::new();
+    let db = fixture.path().join("replaced.duckdb");
+    // Tables written without --append have no snapshot_slot column
+    run_ok(
+        &fixture,
+        &[],
+        &["dump-tokens", "--db", db.to_str().unwrap()],
+    );
+    let output = run(
+        &fixture,
+        &[],
+        &["dump-tokens", "--db", db.to_str().unwrap(), "--append"],
+    );
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Can't append to table token_accounts")
+    );
+    assert_eq!(query_count(&db, "token_accounts"), 2);
+}
+
+#[test]
+fn dump_tokens_writes_parquet() {
+    let fixture = Fixture::new();
+    let out_dir = fixture.path().join("tokens");
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "dump-tokens",
+            "--format",
+            "parquet",
+            "--out-dir",
+            out_dir.to_str().unwrap(),
+        ],
+    );
+
+    // Only the Parquet directories are left behind
+    let mut entries: Vec<String> = std::fs::read_dir(&out_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    entries.sort();
+    assert_eq!(entries, ["mints", "multisigs", "token_accounts"]);
+    let conn = duckdb::Connection::open_in_memory().unwrap();
+    let count = |table: &str| -> u64 {
+        let files = out_dir.join(table).join("*.parquet");
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM read_parquet('{}')", files.display()),
+            [],
+            |row| row.get(0),
+        )
+        .unwrap()
+    };
+    assert_eq!(count("token_accounts"), 2);
+    assert_eq!(count("mints"), 1);
+}
+
+#[test]
+fn dump_tokens_creates_indexes() {
+    let fixture = Fixture::new();
+    let db = fixture.path().join("tokens.duckdb");
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "dump-tokens",
+            "--db",
+            db.to_str().unwrap(),
+            "--create-indexes",
+        ],
+    );
+
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT index_name FROM duckdb_indexes() ORDER BY index_name"
+        ),
+        [
+            "token_accounts_mint_idx",
+            "token_accounts_owner_idx",
+            "token_accounts_pubkey_idx"
+        ]
+    );
+}
+
+#[test]
+fn dump_tokens_writes_multisigs() {
+    // 2-of-3 multisig: m, n, is_initialized, then 11 signer slots
+    let mut data = vec![2, 3, 1];
+    for signer in 1..=11 {
+        let seed = if signer <= 3 { signer } else { 0 };
+        data.extend_from_slice(&[seed; 32]);
+    }
+    let multisig = Pubkey::new_from_array([12; 32]);
+    let mut storages = common::storages();
+    storages
+        .get_mut(&(common::SLOT, 1))
+        .unwrap()
+        .push(common::StoredAccount {
+            pubkey: multisig,
+            write_version: 120,
+            lamports: 3_000_000,
+            owner: Pubkey::from_str(common::TOKEN_PROGRAM_ID).unwrap(),
+            executable: false,
+            data,
+        });
+    let fixture = Fixture::with_storages(storages);
+    let db = fixture.path().join("tokens.duckdb");
+    run_ok(
+        &fixture,
+        &[],
+        &["dump-tokens", "--db", db.to_str().unwrap()],
+    );
+
+    assert_eq!(query_count(&db, "multisigs"), 1);
+    let signers: Vec<String> = (1..=3)
+        .map(|seed| Pubkey::new_from_array([seed; 32]).to_string())
+        .collect();
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT pubkey || ':' || m || ':' || n || ':' || signers FROM multisigs"
+        ),
+        [format!("{}:2:3:{}", multisig, signers.join(","))]
+    );
+}
+
+#[test]
+fn dump_metadata_writes_editions() {
+    let metadata_program = Pubkey::from_str(common::METADATA_PROGRAM_ID).unwrap();
+    let master = Pubkey::new_from_array([40; 32]);
+    let print = Pubkey::new_from_array([41; 32]);
+    // MasterEditionV2 with 3 of at most 10 prints, and the second print of it
+    let mut master_data = vec![6];
+    master_data.extend_from_slice(&3u64.to_le_bytes());
+    master_data.push(1);
+    master_data.extend_from_slice(&10u64.to_le_bytes());
+    let mut print_data = vec![1];
+    print_data.extend_from_slice(master.as_ref());
+    print_data.extend_from_slice(&2u64.to_le_bytes());
+    let accounts = [
+        (
+            Pubkey::new_from_array([42; 32]),
+            common::metadata_data(&common::MINT, None),
+        ),
+        (master, master_data),
+        (print, print_data),
+    ];
+    let mut storages = common::storages();
+    let stored = storages.get_mut(&(common::SLOT, 1)).unwrap();
+    for (i, (pubkey, data)) in accounts.into_iter().enumerate() {
+        stored.push(common::StoredAccount {
+            pubkey,
+            write_version: 40 + i as u64,
+            lamports: 2_853_600,
+            owner: metadata_program,
+            executable: false,
+            data,
+        });
+    }
+    let fixture = Fixture::with_storages(storages);
+    let db = fixture.path().join("metadata.duckdb");
+    run_ok(
+        &fixture,
+        &[],
+        &["dump-metadata", "--db", db.to_str().unwrap()],
+    );
+
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT mint || ':' || name || ':' || symbol FROM metadata"
+        ),
+        [format!("{}:Item:ITEM", common::MINT)]
+    );
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT kind || ':' || coalesce(max_supply::VARCHAR, parent || '#' || edition)
+             FROM editions ORDER BY kind"
+        ),
+        ["master:10".to_string(), format!("print:{}#2", master)]
+    );
+}
+
+#[test]
+fn dump_governance_writes_tables() {
+    let realm = Pubkey::new_from_array([60; 32]);
+    let proposal = Pubkey::new_from_array([61; 32]);
+    // RealmV2: community mint, config with the council mint, reserved bytes, no authority
+    let mut realm_data = vec![16];
+    realm_data.extend_from_slice(common::MINT.as_ref());
+    realm_data.resize(1 + 32 + 25, 0);
+    realm_data.push(1);
+    realm_data.extend_from_slice(common::ALICE.as_ref());
+    realm_data.resize(realm_data.len() + 8, 0);
+    realm_data.push(0);
+    realm_data.extend_from_slice(&3u32.to_le_bytes());
+    realm_data.extend_from_slice(b"DAO");
+    // TokenOwnerRecordV2 of BOB depositing 500, with no delegate
+    let mut record_data = vec![17];
+    for key in [realm, common::MINT, common::BOB] {
+        record_data.extend_from_slice(key.as_ref());
+    }
+    record_data.extend_from_slice(&500u64.to_le_bytes());
+    record_data.resize(record_data.len() + 16, 0);
+    record_data.push(0);
+    // The same record before BOB deposited the last 300
+    let mut old_record_data = record_data.clone();
+    old_record_data[97..105].copy_from_slice(&200u64.to_le_bytes());
+    // ProposalV2 in the voting state, and BOB's vote to deny it
+    let mut proposal_data = vec![14];
+    proposal_data.extend_from_slice(Pubkey::new_from_array([62; 32]).as_ref());
+    proposal_data.extend_from_slice(common::MINT.as_ref());
+    proposal_data.push(2);
+    proposal_data.extend_from_slice(Pubkey::new_from_array([63; 32]).as_ref());
+    let mut vote_data = vec![12];
+    vote_data.extend_from_slice(proposal.as_ref());
+    vote_data.extend_from_slice(common::BOB.as_ref());
+    vote_data.push(0);
+    vote_data.extend_from_slice(&500u64.to_le_bytes());
+    vote_data.push(1);
+
+    let accounts = [
+        (realm, realm_data),
+        (Pubkey::new_from_array([64; 32]), record_data),
+        (proposal, proposal_data),
+        (Pubkey::new_from_array([65; 32]), vote_data),
+    ];
+    let mut storages = common::storages();
+    let stored = storages.get_mut(&(common::SLOT, 1)).unwrap();
+    for (i, (pubkey, data)) in accounts.into_iter().enumerate() {
+        stored.push(common::StoredAccount {
+            pubkey,
+            write_version: 60 + i as u64,
+            lamports: 2_000_000,
+            owner: Pubkey::from_str("GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw").unwrap(),
+            executable: false,
+            data,
+        });
+    }
+    storages.insert(
+        (common::OLD_SLOT, 1),
+        vec![common::StoredAccount {
+            pubkey: Pubkey::new_from_array([64; 32]),
+            write_version: 2,
+            lamports: 2_000_000,
+            owner: Pubkey::from_str("GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw").unwrap(),
+            executable: false,
+            data: old_record_data,
+        }],
+    );
+    let fixture = Fixture::with_storages(storages);
+    let db = fixture.path().join("governance.duckdb");
+    run_ok(
+        &fixture,
+        &[],
+        &["dump-governance", "--db", db.to_str().unwrap()],
+    );
+
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT name || ':' || council_mint || ':' || coalesce(authority, '-') FROM realms"
+        ),
+        [format!("DAO:{}:-", common::ALICE)]
+    );
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT governing_token_owner || ':' || deposit_amount FROM token_owner_records"
+        ),
+        [format!("{}:500", common::BOB)]
+    );
+    assert_eq!(
+        query_strings(&db, "SELECT state FROM proposals"),
+        ["Voting"]
+    );
+    assert_eq!(
+        query_strings(&db, "SELECT vote || ':' || voter_weight FROM vote_records"),
+        ["Deny:500"]
+    );
+}
+
+#[test]
+fn dump_sns_names_domains() {
+    let name_program = Pubkey::from_str("namesLPneVptA9Z5rqUDD9tMTWEJwofgaYwp8cawRkX").unwrap();
+    let sol_tld = Pubkey::from_str("58PwtjSDuFHuUkYjH9BYnnQKHfwo9reZhC2zMJv9JPkx").unwrap();
+    let reverse_class = Pubkey::from_str("33m47vH6Eav6jr5Ry86XjhRft2jRBLDnDgPSHoquXi2Z").unwrap();
+    let named = Pubkey::new_from_array([70; 32]);
+    let unnamed = Pubkey::new_from_array([71; 32]);
+    let domain_data = |owner: &Pubkey| {
+        let mut data = sol_tld.to_bytes().to_vec();
+        data.extend_from_slice(owner.as_ref());
+        data.resize(96 + 32, 0);
+        data
+    };
+    // The reverse lookup record of a domain is derived from the domain's address
+    let hashed =
+        solana_sdk::hash::hashv(&["SPL Name Service".as_bytes(), named.to_string().as_bytes()]);
+    let (reverse, _) = Pubkey::find_program_address(
+        &[
+            hashed.as_ref(),
+            reverse_class.as_ref(),
+            Pubkey::default().as_ref(),
+        ],
+        &name_program,
+    );
+    let mut reverse_data = vec![0; 32];
+    reverse_data.extend_from_slice(common::ALICE.as_ref());
+    reverse_data.extend_from_slice(reverse_class.as_ref());
+    reverse_data.extend_from_slice(&5u32.to_le_bytes());
+    reverse_data.extend_from_slice(b"alice");
+
+    let accounts = [
+        (named, domain_data(&common::BOB)),
+        (unnamed, domain_data(&common::ALICE)),
+        (reverse, reverse_data),
+    ];
+    let mut storages = common::storages();
+    let stored = storages.get_mut(&(common::SLOT, 1)).unwrap();
+    for (i, (pubkey, data)) in accounts.into_iter().enumerate() {
+        stored.push(common::StoredAccount {
+            pubkey,
+            write_version: 70 + i as u64,
+            lamports: 2_000_000,
+            owner: name_program,
+            executable: false,
+            data,
+        });
+    }
+    let fixture = Fixture::with_storages(storages);
+    let db = fixture.path().join("sns.duckdb");
+    run_ok(&fixture, &[], &["dump-sns", "--db", db.to_str().unwrap()]);
+
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT coalesce(name, '-') || ':' || owner FROM sol_domains ORDER BY name"
+        ),
+        [
+            format!("alice:{}", common::BOB),
+            format!("-:{}", common::ALICE)
+        ]
+    );
+}
+
+#[test]
+fn dump_tokens_pseudonymizes_pubkeys() {
+    use hmac::{Hmac, Mac};
+
+    let fixture = Fixture::new();
+    let db = fixture.path().join("tokens.duckdb");
+    let key_file = fixture.path().join("hash.key");
+    std::fs::write(&key_file, b"secret").unwrap();
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "dump-tokens",
+            "--db",
+            db.to_str().unwrap(),
+            "--hash-key-file",
+            key_file.to_str().unwrap(),
+        ],
+    );
+
+    let pubkeys = query_strings(&db, "SELECT pubkey FROM token_accounts");
+    assert_eq!(pubkeys.len(), 2);
+    for pubkey in &pubkeys {
+        assert_eq!(pubkey.len(), 32);
+        assert!(pubkey.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+    // Tokens are the prefix of HMAC-SHA256(key, pubkey), the same in every table
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"secret").unwrap();
+    mac.update(common::MINT.as_ref());
+    let mint_token: String = mac.finalize().into_bytes()[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    assert_eq!(
+        query_strings(&db, "SELECT pubkey FROM mints"),
+        [mint_token.clone()]
+    );
+    let referenced = query_strings(&db, "SELECT DISTINCT mint FROM token_accounts");
+    assert!(referenced.contains(&mint_token));
+
+    // The key may also come from the environment
+    let output = Command::new(env!("CARGO_BIN_EXE_solana-snapshot-etl"))
+        .env("SOLANA_SNAPSHOT_ETL_HASH_KEY", "secret")
+        .args(["--no-cache", "--raw"])
+        .arg(fixture.path())
+        .args(["dump-tokens", "--db", db.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        query_strings(&db, "SELECT pubkey FROM mints"),
+        [mint_token.clone()]
+    );
+}
+
+#[test]
+fn truncated_storage_fails_unless_lenient() {
+    let fixture = Fixture::with_truncated_storage();
+    let output = run(&fixture, &[], &["stats"]);
+    assert!(!output.status.success());
+
+    let stdout = run_ok(&fixture, &["--lenient"], &["stats"]);
+    assert_eq!(report_value(&stdout, "Accounts processed:"), "6");
+    assert!(stdout.contains("Append Vec Errors"));
+}
+
+#[test]
+fn max_data_len_skips_larger_accounts() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &["--max-data-len", "100"], &["stats"]);
+    // Both 165-byte token accounts are skipped and reported, the 82-byte mint is kept
+    assert_eq!(report_value(&stdout, "Accounts processed:"), "4");
+    assert!(stdout.contains("--- Oversized Account Entries ---"));
+    let skipped = stdout
+        .lines()
+        .filter(|line| line.trim_end().ends_with("skipped"))
+        .count();
+    assert_eq!(skipped, 2);
+}
+
+#[test]
+fn corrupt_data_len_abandons_rest_of_append_vec() {
+    let mut storages = common::storages();
+    storages.insert(
+        (common::SLOT, 4),
+        [90u8, 91, 92]
+            .into_iter()
+            .enumerate()
+            .map(|(i, byte)| common::StoredAccount {
+                pubkey: Pubkey::new_from_array([byte; 32]),
+                write_version: 90 + i as u64,
+                lamports: 1_000_000,
+                owner: system_program::id(),
+                executable: false,
+                data: vec![],
+            })
+            .collect(),
+    );
+    let fixture = Fixture::with_storages(storages);
+    // Entries without data take 136 bytes; the second one now claims 20 MiB of data,
+    // more than the 10 MiB default cap and the rest of the file
+    let path = fixture
+        .path()
+        .join("accounts")
+        .join(format!("{}.4", common::SLOT));
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[136 + 8..136 + 16].copy_from_slice(&(20u64 << 20).to_le_bytes());
+    std::fs::write(&path, bytes).unwrap();
+
+    let stdout = run_ok(&fixture, &[], &["stats"]);
+    assert_eq!(report_value(&stdout, "Accounts processed:"), "7");
+    let entry = stdout
+        .lines()
+        .find(|line| line.contains(&Pubkey::new_from_array([91; 32]).to_string()))
+        .expect("no oversized entry");
+    let fields: Vec<&str> = entry.split_whitespace().collect();
+    assert_eq!(fields[0], common::SLOT.to_string());
+    assert_eq!(fields[1], "136");
+    assert_eq!(fields[3..], ["20971520", "truncated"]);
+}
+
+#[test]
+fn debug_prints_owned_accounts() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &[
+            "debug",
+            "--owner",
+            common::TOKEN_PROGRAM_ID,
+            "--count",
+            "10",
+        ],
+    );
+    assert_eq!(stdout.matches("--- Account ").count(), 3);
+    assert!(stdout.contains(&common::MINT.to_string()));
+    assert!(stdout.contains(&common::TOKEN_ACCOUNT.to_string()));
+}
+
+#[test]
+fn debug_matches_any_of_several_owners() {
+    let fixture = Fixture::new();
+    let system_program = solana_sdk::system_program::id().to_string();
+    let owners = format!("{},{}", common::TOKEN_PROGRAM_ID, system_program);
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["debug", "--owner", &owners, "--count", "10"],
+    );
+    assert_eq!(stdout.matches("--- Account ").count(), 6);
+
+    let owner_list = fixture.path().with_extension("owners");
+    std::fs::write(&owner_list, format!("# system\n{}\n\n", system_program)).unwrap();
+    let owner_file = format!("@{}", owner_list.display());
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &[
+            "debug",
+            "--owner",
+            common::TOKEN_PROGRAM_ID,
+            "--owner",
+            &owner_file,
+            "--count",
+            "10",
+        ],
+    );
+    std::fs::remove_file(&owner_list).unwrap();
+    assert_eq!(stdout.matches("--- Account ").count(), 6);
+}
+
+#[test]
+fn histogram_buckets_data_lengths() {
+    let fixture = Fixture::new();
+    let csv_path = fixture.path().with_extension("csv");
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["histogram", "--csv", csv_path.to_str().unwrap()],
+    );
+    assert!(
+        stdout.contains("All accounts (6 accounts, 422)"),
+        "{}",
+        stdout
+    );
+
+    let csv = std::fs::read_to_string(&csv_path).unwrap();
+    std::fs::remove_file(&csv_path).unwrap();
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        rows[..5],
+        [
+            "owner,min_data_len,max_data_len,accounts,data_bytes",
+            "all,0,0,2,0",
+            "all,8,15,1,10",
+            "all,64,127,1,82",
+            "all,128,255,2,330",
+        ]
+    );
+    assert!(rows.contains(&format!("{},128,255,2,330", common::TOKEN_PROGRAM_ID).as_str()));
+}
+
+#[test]
+fn wealth_counts_latest_system_balances() {
+    let fixture = Fixture::new();
+    let json_path = fixture.path().with_extension("json");
+    let csv_path = fixture.path().with_extension("lorenz.csv");
+    run_ok(
+        &fixture,
+        &[],
+        &[
+            "wealth",
+            "--lorenz-points",
+            "2",
+            "--json",
+            json_path.to_str().unwrap(),
+            "--csv",
+            csv_path.to_str().unwrap(),
+        ],
+    );
+    let report: Value = serde_json::from_slice(&std::fs::read(&json_path).unwrap()).unwrap();
+    let csv = std::fs::read_to_string(&csv_path).unwrap();
+    std::fs::remove_file(&json_path).unwrap();
+    std::fs::remove_file(&csv_path).unwrap();
+
+    // Only the latest version of ALICE counts, besides ODD_ACCOUNT
+    assert_eq!(report["accounts"], 2);
+    assert_eq!(
+        report["total_lamports"],
+        common::ALICE_LAMPORTS + common::ODD_LAMPORTS
+    );
+    assert_eq!(report["percentiles"][2]["lamports"], common::ODD_LAMPORTS);
+    assert_eq!(report["percentiles"][4]["lamports"], common::ALICE_LAMPORTS);
+    assert!((report["gini"].as_f64().unwrap() - 0.5).abs() < 0.001);
+    assert_eq!(csv.lines().count(), 4);
+    assert!(csv.ends_with("1,1\n"));
+}
+
+#[test]
+fn richlist_ranks_latest_balances() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["richlist", "--top", "2"]);
+    let rows: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.trim_start().starts_with(|c: char| c.is_ascii_digit()))
+        .collect();
+
+    // ALICE ranks by its latest balance, not the larger stale one
+    assert_eq!(rows.len(), 2);
+    assert!(rows[0].contains(&common::ALICE.to_string()));
+    assert!(rows[0].contains(&common::ALICE_LAMPORTS.to_string()));
+    assert!(rows[0].ends_with("System Program"));
+    assert!(rows[1].contains(&common::TOKEN_ACCOUNT.to_string()));
+    assert!(rows[1].ends_with("Token Program"));
+    assert!(!stdout.contains(&common::ALICE_OLD_LAMPORTS.to_string()));
+}
+
+#[test]
+fn richlist_filters_latest_versions() {
+    // Hides the latest version of Alice but not the older one
+    let fixture = Fixture::new();
+    let stdout = run_ok(
+        &fixture,
+        &["--min-lamports", "1500000000"],
+        &["richlist", "--top", "2"],
+    );
+    assert!(!stdout.contains(&common::ALICE.to_string()), "{}", stdout);
+
+    // Alice is deleted by a later zero-lamport version
+    let mut storages = common::storages();
+    storages.insert(
+        (common::SLOT, 2),
+        vec![common::StoredAccount {
+            pubkey: common::ALICE,
+            write_version: 20,
+            lamports: 0,
+            owner: Pubkey::default(),
+            executable: false,
+            data: Vec::new(),
+        }],
+    );
+    let fixture = Fixture::with_storages(storages);
+    let stdout = run_ok(
+        &fixture,
+        &["--skip-zero-lamports"],
+        &["richlist", "--top", "2"],
+    );
+    assert!(!stdout.contains(&common::ALICE.to_string()), "{}", stdout);
+}
+
+#[test]
+fn largest_accounts_ranks_data_length() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["largest-accounts", "--top", "3"]);
+    let rows: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.trim_start().starts_with(|c: char| c.is_ascii_digit()))
+        .collect();
+
+    assert_eq!(rows.len(), 3);
+    assert!(rows[0].contains(&common::TOKEN_ACCOUNT.to_string()));
+    assert!(rows[0].contains(" 165 "));
+    assert!(rows[0].ends_with("Token Program"));
+    assert!(rows[1].contains(&common::DANGLING_TOKEN_ACCOUNT.to_string()));
+    assert!(rows[2].contains(&common::MINT.to_string()));
+    assert!(rows[2].contains(" 82 "));
+}
+
+#[test]
+fn rent_reports_reserve_and_underfunded_accounts() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["rent"]);
+    let value = |label: &str| {
+        stdout
+            .lines()
+            .find_map(|line| line.strip_prefix(label))
+            .unwrap()
+            .trim()
+            .to_string()
+    };
+
+    // With default rent, the token accounts and the mint hold exactly their minimum
+    // balance, ALICE more, and the 10 bytes of ODD_ACCOUNT are not covered
+    let odd_minimum = Rent::default().minimum_balance(10);
+    let reserve = Rent::default().minimum_balance(0)
+        + common::MINT_LAMPORTS
+        + 2 * common::TOKEN_ACCOUNT_LAMPORTS
+        + common::ODD_LAMPORTS;
+    assert_eq!(value("Accounts:"), "5");
+    assert_eq!(value("Rent-exempt reserve:"), reserve.to_string());
+    assert_eq!(value("Under-funded accounts:"), "1");
+    assert_eq!(
+        value("Under-funded shortfall:"),
+        (odd_minimum - common::ODD_LAMPORTS).to_string()
+    );
+    assert_eq!(value("Empty accounts:"), "0");
+    assert!(stdout
+        .lines()
+        .any(|line| line.starts_with("Token Program") && line.contains(" 2 ")));
+}
+
+#[test]
+fn schema_stats_groups_by_discriminator() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["schema-stats", "--owner", common::TOKEN_PROGRAM_ID],
+    );
+    assert_eq!(report_value(&stdout, "Accounts:"), "3 in 3 account types");
+    // The mint starts with its authority option tag and BOB, token accounts with their mint
+    let mint_row: Vec<&str> = stdout
+        .lines()
+        .find(|line| line.starts_with("0100000002020202"))
+        .expect("no mint row")
+        .split_whitespace()
+        .collect();
+    assert_eq!(mint_row[1..], ["1", "33.33%", "82", "82", "82"]);
+    assert!(stdout
+        .lines()
+        .any(|line| line.starts_with("0303030303030303")));
+    assert!(stdout
+        .lines()
+        .any(|line| line.starts_with("0606060606060606")));
+}
+
+#[test]
+fn schema_stats_skips_closed_accounts() {
+    // A token account whose latest version was closed and handed back to the system program
+    let closed = Pubkey::new_from_array([0x20; 32]);
+    let mut storages = common::storages();
+    storages
+        .get_mut(&(common::OLD_SLOT, 0))
+        .unwrap()
+        .push(common::StoredAccount {
+            pubkey: closed,
+            write_version: 2,
+            lamports: common::TOKEN_ACCOUNT_LAMPORTS,
+            owner: Pubkey::from_str(common::TOKEN_PROGRAM_ID).unwrap(),
+            executable: false,
+            data: vec![0x20; 165],
+        });
+    storages
+        .get_mut(&(common::SLOT, 1))
+        .unwrap()
+        .push(common::StoredAccount {
+            pubkey: closed,
+            write_version: 15,
+            lamports: 0,
+            owner: system_program::id(),
+            executable: false,
+            data: vec![],
+        });
+    let fixture = Fixture::with_storages(storages);
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["schema-stats", "--owner", common::TOKEN_PROGRAM_ID],
+    );
+    assert_eq!(report_value(&stdout, "Accounts:"), "3 in 3 account types");
+    assert!(!stdout
+        .lines()
+        .any(|line| line.starts_with("2020202020202020")));
+}
+
+#[test]
+fn sample_by_count_and_rate() {
+    let fixture = Fixture::new();
+    let stdout = run_ok(&fixture, &[], &["sample", "--count", "3", "--seed", "7"]);
+    let samples: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(samples.len(), 3);
+    assert!(samples.iter().all(|sample| sample["pubkey"].is_string()));
+
+    // Only the latest version of Alice
+    let stdout = run_ok(&fixture, &[], &["sample", "--rate", "1"]);
+    assert_eq!(stdout.lines().count(), 5);
+    assert!(stdout.contains(&format!("\"data\":\"{}\"", "aa".repeat(10))));
+    let alice: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .filter(|sample| sample["pubkey"] == common::ALICE.to_string())
+        .collect();
+    assert_eq!(alice.len(), 1);
+    assert_eq!(alice[0]["write_version"], 10);
+
+    // Closed accounts aren't sampled
+    let incremental = Fixture::incremental(incremental_storages(), common::SLOT);
+    let incremental_arg = format!("--incremental={}", incremental.path().display());
+    let stdout = run_ok(&fixture, &[&incremental_arg], &["sample", "--rate", "1"]);
+    assert_eq!(stdout.lines().count(), 4);
+    assert!(!stdout.contains(&common::ALICE.to_string()));
+
+    let stdout = run_ok(&fixture, &[], &["sample", "--rate", "0"]);
+    assert_eq!(stdout, "");
+}
+
+#[test]
+fn lookup_prints_all_versions() {
+    let fixture = Fixture::new();
+    let pubkeys = format!(
+        "{},{},{}",
+        common::ALICE,
+        common::ODD_ACCOUNT,
+        common::MISSING_MINT
+    );
+    let stdout = run_ok(&fixture, &[], &["lookup", &pubkeys]);
+
+    let alice = stdout
+        .split(&format!("--- Account {} ---", common::ALICE))
+        .nth(1)
+        .unwrap();
+    assert!(alice.contains("Versions found: 2"));
+    assert!(alice.contains(&format!(
+        "Version 1 of 2 (latest)\nSlot:          {}",
+        common::SLOT
+    )));
+    assert!(alice.contains(&format!(
+        "Version 2 of 2 (stale)\nSlot:          {}",
+        common::OLD_SLOT
+    )));
+    assert!(stdout.contains("  00000000  aa aa aa aa aa aa aa aa aa aa"));
+    assert!(stdout.contains(&format!(
+        "--- Account {} ---\nNot found in snapshot",
+        common::MISSING_MINT
+    )));
+    assert!(stdout.contains("1 of 3 pubkeys not found"));
+}
+
+#[test]
+fn index_serves_point_lookups() {
+    let fixture = Fixture::new();
+    let index = fixture.path().with_extension("index");
+    let index_arg = index.to_str().unwrap();
+    let stdout = run_ok(&fixture, &[], &["index", "build", index_arg]);
+    assert!(stdout.contains("Indexed 5 accounts"), "{}", stdout);
+
+    let pubkeys = format!("{},{}", common::ALICE, common::MISSING_MINT);
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["index", "get", "--index", index_arg, &pubkeys],
+    );
+    std::fs::remove_file(&index).unwrap();
+    assert!(stdout.contains(&format!("Slot:          {}", common::SLOT)));
+    assert!(stdout.contains("Write version: 10"));
+    assert!(stdout.contains(&format!(
+        "--- Account {} ---\nNot found in snapshot",
+        common::MISSING_MINT
+    )));
+}
+
+#[test]
+fn index_ignores_account_filters() {
+    let fixture = Fixture::new();
+    let index = fixture.path().with_extension("filtered.index");
+    let index_arg = index.to_str().unwrap();
+    // Hides the latest version of Alice but not the older one
+    run_ok(
+        &fixture,
+        &["--min-lamports", "1500000000"],
+        &["index", "build", index_arg],
+    );
+
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &[
+            "index",
+            "get",
+            "--index",
+            index_arg,
+            &common::ALICE.to_string(),
+        ],
+    );
+    std::fs::remove_file(&index).unwrap();
+    assert!(stdout.contains("Write version: 10"), "{}", stdout);
+}
+
+#[test]
+fn bloom_filter_contains_snapshot_pubkeys() {
+    let fixture = Fixture::new();
+    let output = fixture.path().with_extension("bloom");
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["bloom-filter", "--output", output.to_str().unwrap()],
+    );
+    // The two versions of Alice are inserted once
+    assert!(stdout.contains("Accounts inserted:   5"), "{}", stdout);
+
+    let file = std::fs::File::open(&output).unwrap();
+    let filter = PubkeyBloomFilter::read_from(file).unwrap();
+    std::fs::remove_file(&output).unwrap();
+    assert_eq!(filter.slot(), common::SLOT);
+    for pubkey in [
+        common::ALICE,
+        common::MINT,
+        common::TOKEN_ACCOUNT,
+        common::DANGLING_TOKEN_ACCOUNT,
+        common::ODD_ACCOUNT,
+    ] {
+        assert!(filter.contains(&pubkey), "{} missing", pubkey);
+    }
+    assert!(!filter.contains(&common::MISSING_MINT));
+}
+
+#[test]
+fn bloom_filter_skips_closed_accounts() {
+    let fixture = Fixture::new();
+    let incremental = Fixture::incremental(incremental_storages(), common::SLOT);
+    let incremental_arg = format!("--incremental={}", incremental.path().display());
+    let output = fixture.path().with_extension("closed.bloom");
+    let stdout = run_ok(
+        &fixture,
+        &[&incremental_arg],
+        &["bloom-filter", "--output", output.to_str().unwrap()],
+    );
+    assert!(stdout.contains("Accounts inserted:   4"), "{}", stdout);
+
+    let file = std::fs::File::open(&output).unwrap();
+    let filter = PubkeyBloomFilter::read_from(file).unwrap();
+    std::fs::remove_file(&output).unwrap();
+    assert!(!filter.contains(&common::ALICE));
+    assert!(filter.contains(&common::MINT));
+}
+
+#[test]
+fn bloom_filter_rejects_truncated_bits() {
+    let fixture = Fixture::new();
+    let output = fixture.path().with_extension("truncated.bloom");
+    run_ok(
+        &fixture,
+        &[],
+        &["bloom-filter", "--output", output.to_str().unwrap()],
+    );
+    let mut bytes = std::fs::read(&output).unwrap();
+    std::fs::remove_file(&output).unwrap();
+    bytes.pop();
+    assert!(PubkeyBloomFilter::read_from(bytes.as_slice()).is_err());
+
+    // A header claiming more bits than any file holds
+    let num_bits_offset = 8 + 8 + 32 + 4;
+    bytes[num_bits_offset..num_bits_offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(PubkeyBloomFilter::read_from(bytes.as_slice()).is_err());
+}
+
+#[test]
+fn compression_benchmark_runs() {
+    let fixture = Fixture::new();
+    run_ok(
+        &fixture,
+        &[],
+        &["compression-benchmark", "--owner", common::TOKEN_PROGRAM_ID],
+    );
+}
+
+#[test]
+fn custom_compress_writes_output() {
+    let fixture = Fixture::new();
+    let output = fixture.path().join("tokens.bin");
+    run_ok(
+        &fixture,
+        &[],
+        &["custom-compress", "--output", output.to_str().unwrap()],
+    );
+    assert!(output.is_file());
+}
+
+#[test]
+fn compressed_diff_reports_new_and_closed_token_accounts() {
+    let fixture = Fixture::new();
+    let partial = fixture.path().join("partial-tokens.bin");
+    let full = fixture.path().join("tokens.bin");
+    for (output, max_accounts) in [(&partial, "1"), (&full, "2")] {
+        run_ok(
+            &fixture,
+            &[],
+            &[
+                "custom-compress",
+                "--output",
+                output.to_str().unwrap(),
+                "--max-accounts",
+                max_accounts,
+            ],
+        );
+    }
+
+    let diff = |before: &Path, after: &Path| {
+        let output = run_source(before, &[], &["compressed-diff", after.to_str().unwrap()]);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let stdout = diff(&partial, &full);
+    assert_eq!(report_value(&stdout, "Token accounts before:"), "1");
+    assert_eq!(report_value(&stdout, "New accounts:"), "1");
+    assert_eq!(report_value(&stdout, "Closed accounts:"), "0");
+    assert_eq!(report_value(&stdout, "Balance changes:"), "0");
+    let stdout = diff(&full, &partial);
+    assert_eq!(report_value(&stdout, "New accounts:"), "0");
+    assert_eq!(report_value(&stdout, "Closed accounts:"), "1");
+}
+
+/// SBFv2 `noop.so` from the solana_rbpf test programs: 1768 bytes with a 96-byte `.text`.
+const NOOP_ELF: &[u8] = include_bytes!("fixtures/noop.so");
+
+#[test]
+fn elf_analysis_reports_versions_sections_and_duplicates() {
+    use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+
+    let program_id = Pubkey::new_from_array([90; 32]);
+    let upgradeable_id = Pubkey::new_from_array([91; 32]);
+    let programdata_address = Pubkey::new_from_array([92; 32]);
+    let v1_program_id = Pubkey::new_from_array([93; 32]);
+
+    // Same binary with the SBFv2 bit of e_flags cleared
+    let mut v1_elf = NOOP_ELF.to_vec();
+    v1_elf[48] &= !0x20;
+    // Deployed with room to grow, like the CLI does
+    let mut programdata = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+        slot: 5,
+        upgrade_authority_address: Some(common::ALICE),
+    })
+    .unwrap();
+    programdata.extend_from_slice(NOOP_ELF);
+    programdata.resize(programdata.len() + 512, 0);
+    let program = bincode::serialize(&UpgradeableLoaderState::Program {
+        programdata_address,
+    })
+    .unwrap();
+
+    let stored = |pubkey, write_version, owner, executable, data| common::StoredAccount {
+        pubkey,
+        write_version,
+        lamports: 1_141_440,
+        owner,
+        executable,
+        data,
+    };
+    let mut storages = common::storages();
+    // An older version of the same program isn't another deployment
+    storages.insert(
+        (common::OLD_SLOT, 1),
+        vec![stored(
+            program_id,
+            2,
+            solana_sdk::bpf_loader::id(),
+            true,
+            NOOP_ELF.to_vec(),
+        )],
+    );
+    storages.get_mut(&(common::SLOT, 1)).unwrap().extend([
+        stored(
+            program_id,
+            90,
+            solana_sdk::bpf_loader::id(),
+            true,
+            NOOP_ELF.to_vec(),
+        ),
+        stored(
+            upgradeable_id,
+            91,
+            bpf_loader_upgradeable::id(),
+            true,
+            program,
+        ),
+        stored(
+            programdata_address,
+            92,
+            bpf_loader_upgradeable::id(),
+            false,
+            programdata,
+        ),
+        stored(
+            v1_program_id,
+            93,
+            solana_sdk::bpf_loader::id(),
+            true,
+            v1_elf,
+        ),
+    ]);
+    let fixture = Fixture::with_storages(storages);
+
+    let stdout = run_ok(&fixture, &[], &["elf-analysis", "--sections"]);
+    assert_eq!(report_value(&stdout, "Programs parsed:"), "3");
+    assert_eq!(report_value(&stdout, "SBFv1:"), "1");
+    assert_eq!(report_value(&stdout, "SBFv2:"), "2");
+    assert_eq!(report_value(&stdout, "Unparseable:"), "0");
+    assert_eq!(report_value(&stdout, "Unique binaries:"), "2");
+    let section = |name: &str| -> Vec<&str> {
+        stdout
+            .lines()
+            .find(|line| line.split_whitespace().next() == Some(name))
+            .unwrap_or_else(|| panic!("no {} row", name))
+            .split_whitespace()
+            .skip(1)
+            .collect()
+    };
+    assert_eq!(section(".text"), ["3", "288"]);
+    assert_eq!(section(".rodata"), ["3", "33"]);
+
+    // The padding after the upgradeable program's ELF isn't part of the binary
+    let (_, duplicates) = stdout
+        .split_once("--- Duplicate Deployments (1 binaries, 1768 wasted bytes) ---")
+        .expect("no duplicate deployments section");
+    let mut listed: Vec<&str> = duplicates
+        .lines()
+        .filter_map(|line| line.strip_prefix("    "))
+        .collect();
+    listed.sort();
+    let mut expected = vec![program_id.to_string(), upgradeable_id.to_string()];
+    expected.sort();
+    assert_eq!(listed, expected);
+    assert!(duplicates.contains("copies=2 size=1768 wasted=1768"));
+}
+
+/// The fixture plus an upgradeable program, whose ProgramData account holds 100 bytes of
+/// bytecode.
+fn upgradeable_program_storages(
+    program_id: Pubkey,
+    programdata_address: Pubkey,
+) -> std::collections::BTreeMap<(u64, u64), Vec<common::StoredAccount>> {
+    use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+
+    let mut programdata = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+        slot: 5,
+        upgrade_authority_address: Some(common::ALICE),
+    })
+    .unwrap();
+    programdata.extend_from_slice(&[1; 100]);
+    let program = bincode::serialize(&UpgradeableLoaderState::Program {
+        programdata_address,
+    })
+    .unwrap();
+    let mut storages = common::storages();
+    let accounts = storages.get_mut(&(common::SLOT, 1)).unwrap();
+    for (pubkey, write_version, executable, data) in [
+        (program_id, 96, true, program),
+        (programdata_address, 97, false, programdata),
+    ] {
+        accounts.push(common::StoredAccount {
+            pubkey,
+            write_version,
+            lamports: 1_141_440,
+            owner: bpf_loader_upgradeable::id(),
+            executable,
+            data,
+        });
+    }
+    storages
+}
+
+#[test]
+fn programs_pairs_programdata() {
+    let program_id = Pubkey::new_from_array([96; 32]);
+    let programdata_address = Pubkey::new_from_array([97; 32]);
+    let fixture = Fixture::with_storages(upgradeable_program_storages(
+        program_id,
+        programdata_address,
+    ));
+    let csv = fixture.path().join("programs.csv");
+
+    let stdout = run_ok(&fixture, &[], &["programs", "--csv", csv.to_str().unwrap()]);
+    assert_eq!(report_value(&stdout, "Deployed programs:"), "1");
+    assert_eq!(report_value(&stdout, "  Upgradeable:"), "1");
+    assert_eq!(report_value(&stdout, "Missing ProgramData:"), "0");
+    assert_eq!(report_value(&stdout, "Binary size:"), "100");
+
+    let csv = std::fs::read_to_string(&csv).unwrap();
+    assert_eq!(
+        csv.lines().nth(1).unwrap(),
+        format!(
+            "{},{},{},5,100",
+            program_id,
+            programdata_address,
+            common::ALICE
+        )
+    );
+}
+
+#[test]
+fn dump_stakes_writes_delegations() {
+    use solana_sdk::stake::state::{Authorized, Delegation, Lockup, Meta, Stake, StakeState};
+
+    let voter = Pubkey::new_from_array([80; 32]);
+    let meta = Meta {
+        rent_exempt_reserve: 2_282_880,
+        authorized: Authorized {
+            staker: common::ALICE,
+            withdrawer: common::BOB,
+        },
+        lockup: Lockup::default(),
+    };
+    let delegated = StakeState::Stake(
+        meta,
+        Stake {
+            delegation: Delegation::new(&voter, 1_000_000_000, 1),
+            credits_observed: 0,
+        },
+    );
+    let mut storages = common::storages();
+    let stored = storages.get_mut(&(common::SLOT, 1)).unwrap();
+    for (i, state) in [delegated, StakeState::Initialized(meta)]
+        .into_iter()
+        .enumerate()
+    {
+        let mut data = bincode::serialize(&state).unwrap();
+        data.resize(200, 0);
+        stored.push(common::StoredAccount {
+            pubkey: Pubkey::new_from_array([81 + i as u8; 32]),
+            write_version: 80 + i as u64,
+            lamports: 1_002_282_880,
+            owner: solana_sdk::stake::program::id(),
+            executable: false,
+            data,
+        });
+    }
+    let fixture = Fixture::with_storages(storages);
+    let db = fixture.path().join("stakes.duckdb");
+
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["dump-stakes", "--db", db.to_str().unwrap()],
+    );
+    assert_eq!(report_value(&stdout, "Stake accounts:"), "2");
+    assert_eq!(report_value(&stdout, "Vote accounts:"), "1");
+    assert_eq!(query_count(&db, "stake_accounts"), 2);
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT voter || ':' || stake_accounts || ':' || delegated_stake FROM stake_delegations"
+        ),
+        [format!("{}:1:1000000000", voter)]
+    );
+}
+
+#[test]
+fn dump_sysvars_prints_json() {
+    use solana_sdk::clock::Clock;
+    use solana_sdk::rent::Rent;
+    use solana_sdk::sysvar;
+
+    let clock = Clock {
+        slot: common::SLOT,
+        epoch: 3,
+        unix_timestamp: 1_700_000_000,
+        ..Clock::default()
+    };
+    let mut storages = common::storages();
+    let accounts = storages.get_mut(&(common::SLOT, 1)).unwrap();
+    for (pubkey, write_version, data) in [
+        (sysvar::clock::id(), 92, bincode::serialize(&clock).unwrap()),
+        (
+            sysvar::rent::id(),
+            93,
+            bincode::serialize(&Rent::default()).unwrap(),
+        ),
+    ] {
+        accounts.push(common::StoredAccount {
+            pubkey,
+            write_version,
+            lamports: 1_169_280,
+            owner: sysvar::id(),
+            executable: false,
+            data,
+        });
+    }
+    let fixture = Fixture::with_storages(storages);
+
+    let stdout = run_ok(&fixture, &[], &["dump-sysvars"]);
+    let sysvars: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(sysvars["clock"]["epoch"], 3);
+    assert_eq!(sysvars["clock"]["unix_timestamp"], 1_700_000_000);
+    assert_eq!(sysvars["rent"]["lamports_per_byte_year"], 3480);
+    assert!(sysvars["epoch_schedule"].is_null());
+}
+
+#[test]
+fn dump_votes_writes_epoch_credits() {
+    use solana_sdk::clock::Clock;
+    use solana_sdk::vote::state::{VoteInit, VoteState, VoteStateVersions};
+
+    let vote_account = Pubkey::new_from_array([90; 32]);
+    let mut vote_state = VoteState::new(
+        &VoteInit {
+            node_pubkey: common::ALICE,
+            authorized_voter: common::ALICE,
+            authorized_withdrawer: common::BOB,
+            commission: 7,
+        },
+        &Clock::default(),
+    );
+    vote_state.epoch_credits = vec![(1, 10, 0), (2, 25, 10)];
+    let mut data = bincode::serialize(&VoteStateVersions::new_current(vote_state)).unwrap();
+    data.resize(VoteState::size_of(), 0);
+    let mut storages = common::storages();
+    storages
+        .get_mut(&(common::SLOT, 1))
+        .unwrap()
+        .push(common::StoredAccount {
+            pubkey: vote_account,
+            write_version: 90,
+            lamports: 27_074_400,
+            owner: solana_sdk::vote::program::id(),
+            executable: false,
+            data,
+        });
+    let fixture = Fixture::with_storages(storages);
+    let db = fixture.path().join("votes.duckdb");
+    run_ok(&fixture, &[], &["dump-votes", "--db", db.to_str().unwrap()]);
+
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT node_pubkey || ':' || commission || ':' || credits FROM vote_accounts"
+        ),
+        [format!("{}:7:25", common::ALICE)]
+    );
+    assert_eq!(
+        query_strings(
+            &db,
+            "SELECT epoch || ':' || credits FROM vote_epoch_credits ORDER BY epoch"
+        ),
+        ["1:10", "2:25"]
+    );
+}
+
+#[test]
+fn nonce_accounts_reports_authorities() {
+    use solana_sdk::hash::Hash;
+    use solana_sdk::nonce::state::{Data, DurableNonce, State, Versions};
+
+    let nonce_account = Pubkey::new_from_array([91; 32]);
+    let blockhash = Hash::new_from_array([7; 32]);
+    let state = State::Initialized(Data::new(
+        common::ALICE,
+        DurableNonce::from_blockhash(&blockhash),
+        5000,
+    ));
+    let mut storages = common::storages();
+    storages
+        .get_mut(&(common::SLOT, 1))
+        .unwrap()
+        .push(common::StoredAccount {
+            pubkey: nonce_account,
+            write_version: 91,
+            lamports: 1_447_680,
+            owner: solana_sdk::system_program::id(),
+            executable: false,
+            data: bincode::serialize(&Versions::new(state)).unwrap(),
+        });
+    // A nonce account of BOB's that has since been withdrawn and closed
+    let closed_state = State::Initialized(Data::new(
+        common::BOB,
+        DurableNonce::from_blockhash(&blockhash),
+        5000,
+    ));
+    let closed_account = Pubkey::new_from_array([92; 32]);
+    storages
+        .get_mut(&(common::OLD_SLOT, 0))
+        .unwrap()
+        .push(common::StoredAccount {
+            pubkey: closed_account,
+            write_version: 2,
+            lamports: 1_447_680,
+            owner: solana_sdk::system_program::id(),
+            executable: false,
+            data: bincode::serialize(&Versions::new(closed_state)).unwrap(),
+        });
+    storages
+        .get_mut(&(common::SLOT, 1))
+        .unwrap()
+        .push(common::StoredAccount {
+            pubkey: closed_account,
+            write_version: 92,
+            lamports: 0,
+            owner: solana_sdk::system_program::id(),
+            executable: false,
+            data: vec![],
+        });
+    let fixture = Fixture::with_storages(storages);
+    let csv = fixture.path().join("nonces.csv");
+
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["nonce-accounts", "--csv", csv.to_str().unwrap()],
+    );
+    assert_eq!(report_value(&stdout, "Nonce accounts:"), "1");
+    assert_eq!(report_value(&stdout, "Distinct authorities:"), "1");
+    assert_eq!(report_value(&stdout, "Locked lamports:"), "1447680");
+
+    let csv = std::fs::read_to_string(&csv).unwrap();
+    assert_eq!(
+        csv.lines().nth(1).unwrap(),
+        format!(
+            "{},1447680,{},{},5000",
+            nonce_account,
+            common::ALICE,
+            DurableNonce::from_blockhash(&blockhash).as_hash()
+        )
+    );
+}
+
+#[test]
+fn features_diffs_expected() {
+    use solana_sdk::feature::{self, Feature};
+
+    let enabled = solana_sdk::feature_set::secp256k1_program_enabled::id();
+    let pending = Pubkey::new_from_array([94; 32]);
+    let mut storages = common::storages();
+    let accounts = storages.get_mut(&(common::SLOT, 1)).unwrap();
+    for (pubkey, write_version, activated_at) in [(enabled, 94, Some(0)), (pending, 95, None)] {
+        accounts.push(common::StoredAccount {
+            pubkey,
+            write_version,
+            lamports: 953_520,
+            owner: feature::id(),
+            executable: false,
+            data: bincode::serialize(&Feature { activated_at }).unwrap(),
+        });
+    }
+    let fixture = Fixture::with_storages(storages);
+
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["features", "--expected", &pending.to_string()],
+    );
+    assert_eq!(report_value(&stdout, "Feature accounts:"), "2");
+    assert_eq!(report_value(&stdout, "Active:"), "1");
+    assert_eq!(report_value(&stdout, "Pending activation:"), "1");
+    assert_eq!(report_value(&stdout, "Expected but inactive:"), "1");
+    assert_eq!(report_value(&stdout, "Active but not expected:"), "1");
+}
+
+/// Clock sysvar account at `epoch`.
+fn clock_account(epoch: u64, write_version: u64) -> common::StoredAccount {
+    use solana_sdk::clock::Clock;
+    use solana_sdk::sysvar;
+
+    let clock = Clock {
+        epoch,
+        ..Clock::default()
+    };
+    common::StoredAccount {
+        pubkey: sysvar::clock::id(),
+        write_version,
+        lamports: 1_169_280,
+        owner: sysvar::id(),
+        executable: false,
+        data: bincode::serialize(&clock).unwrap(),
+    }
+}
+
+#[test]
+fn stake_hygiene_finds_no_stake() {
+    let mut storages = common::storages();
+    storages
+        .get_mut(&(common::SLOT, 1))
+        .unwrap()
+        .push(clock_account(common::EPOCH, 15));
+    let fixture = Fixture::with_storages(storages);
+    let stdout = run_ok(&fixture, &[], &["stake-hygiene"]);
+    assert_eq!(report_value(&stdout, "Stake accounts:"), "0");
+}
+
+#[test]
+fn stake_hygiene_reads_latest_clock() {
+    use solana_sdk::stake::state::{Authorized, Delegation, Lockup, Meta, Stake, StakeState};
+
+    let mut delegation = Delegation::new(&Pubkey::new_from_array([80; 32]), 1_000_000_000, 1);
+    delegation.deactivation_epoch = 3;
+    let state = StakeState::Stake(
+        Meta {
+            rent_exempt_reserve: 2_282_880,
+            authorized: Authorized {
+                staker: common::ALICE,
+                withdrawer: common::BOB,
+            },
+            lockup: Lockup::default(),
+        },
+        Stake {
+            delegation,
+            credits_observed: 0,
+        },
+    );
+    let mut data = bincode::serialize(&state).unwrap();
+    data.resize(200, 0);
+    let mut storages = common::storages();
+    // The stale Clock predates the deactivation
+    storages
+        .get_mut(&(common::OLD_SLOT, 0))
+        .unwrap()
+        .push(clock_account(1, 2));
+    let accounts = storages.get_mut(&(common::SLOT, 1)).unwrap();
+    accounts.push(clock_account(5, 15));
+    accounts.push(common::StoredAccount {
+        pubkey: Pubkey::new_from_array([81; 32]),
+        write_version: 16,
+        lamports: 1_002_282_880,
+        owner: solana_sdk::stake::program::id(),
+        executable: false,
+        data,
+    });
+    let fixture = Fixture::with_storages(storages);
+
+    let stdout = run_ok(&fixture, &[], &["stake-hygiene"]);
+    assert!(stdout.contains("Stake Hygiene Report (epoch 5)"));
+    assert_eq!(report_value(&stdout, "Stake accounts:"), "1");
+    assert_eq!(report_value(&stdout, "Deactivated with balance:"), "1");
+    assert_eq!(report_value(&stdout, "Delegated to missing vote:"), "0");
+}
+
+#[test]
+fn sample_bundle_writes_index() {
+    let fixture = Fixture::new();
+    let output = fixture.path().join("bundle");
+    run_ok(
+        &fixture,
+        &[],
+        &["sample-bundle", "--output", output.to_str().unwrap()],
+    );
+
+    let index: Value =
+        serde_json::from_slice(&std::fs::read(output.join("index.json")).unwrap()).unwrap();
+    let owners = index.as_array().unwrap();
+    assert_eq!(owners.len(), 2);
+    assert_eq!(owners[0]["owner"], common::TOKEN_PROGRAM_ID);
+    assert_eq!(owners[0]["samples"].as_array().unwrap().len(), 3);
+
+    // Both versions of Alice are kept, next to the odd account
+    assert_eq!(owners[1]["owner"], system_program::id().to_string());
+    let owner_dir = output.join(format!("002_{}", system_program::id()));
+    for version in [(common::OLD_SLOT, 1), (common::SLOT, 10)] {
+        let name = format!("{}_{}_{}", common::ALICE, version.0, version.1);
+        assert!(
+            owner_dir.join(format!("{}.json", name)).is_file(),
+            "{}",
+            name
+        );
+        assert!(
+            owner_dir.join(format!("{}.bin", name)).is_file(),
+            "{}",
+            name
+        );
+    }
+    assert_eq!(std::fs::read_dir(&owner_dir).unwrap().count(), 6);
+}
+
+/// Writes the LZ4 end mark when dropped, like the other encoders do.
+struct Lz4Writer(Option<lz4::Encoder<std::fs::File>>);
+
+impl Write for Lz4Writer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.as_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.as_mut().unwrap().flush()
+    }
+}
+
+impl Drop for Lz4Writer {
+    fn drop(&mut self) {
+        let (_, result) = self.0.take().unwrap().finish();
+        result.unwrap();
+    }
+}
+
+#[test]
+fn compressed_archives_are_detected() {
+    let fixture = Fixture::new();
+    let archives = [
+        fixture.archive("snapshot.tar.zst", |file| {
+            zstd::Encoder::new(file, 3).unwrap().auto_finish()
+        }),
+        fixture.archive("snapshot.tar.gz", |file| {
+            flate2::write::GzEncoder::new(file, flate2::Compression::default())
+        }),
+        fixture.archive("snapshot.tar.bz2", |file| {
+            bzip2::write::BzEncoder::new(file, bzip2::Compression::default())
+        }),
+        fixture.archive("snapshot.tar.lz4", |file| {
+            Lz4Writer(Some(lz4::EncoderBuilder::new().build(file).unwrap()))
+        }),
+    ];
+    for archive in archives {
+        let output = run_source(&archive, &[], &["summary", "--format", "json"]);
+        assert!(
+            output.status.success(),
+            "{:?} failed: {}",
+            archive,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(summary["total_accounts"], 6, "{:?}", archive);
+    }
+}
+
+#[test]
+fn hot_storage_is_read_like_append_vecs() {
+    let fixture = Fixture::with_hot_storage();
+    let summary = summary_json(&fixture, &[]);
+    assert_eq!(summary["total_accounts"], 6);
+    assert_eq!(summary["total_data_bytes"], 82 + 165 + 165 + 10);
+
+    let stdout = run_ok(&fixture, &[], &["dangling-mints"]);
+    assert_eq!(report_value(&stdout, "Token accounts checked:"), "2");
+    assert_eq!(report_value(&stdout, "Missing mints:"), "1");
+}
+
+#[test]
+fn expected_hash_is_checked() {
+    let fixture = Fixture::new();
+    let archive = fixture.archive("snapshot.tar.gz", |file| {
+        flate2::write::GzEncoder::new(file, flate2::Compression::default())
+    });
+    let digest = solana_sdk::hash::hash(&std::fs::read(&archive).unwrap());
+    let hex: String = digest
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    let output = run_source(&archive, &["--expected-hash", &hex], &["verify"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let wrong = "00".repeat(32);
+    let output = run_source(&archive, &["--expected-hash", &wrong], &["verify"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("SHA-256 mismatch"));
+}
+
+#[test]
+fn expected_hash_is_checked_by_commands_that_stop_early() {
+    let fixture = Fixture::new();
+    let archive = fixture.archive("snapshot.tar.gz", |file| {
+        flate2::write::GzEncoder::new(file, flate2::Compression::default())
+    });
+    let digest = solana_sdk::hash::hash(&std::fs::read(&archive).unwrap());
+    let hex: String = digest
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    // info only reads the manifest
+    let output = run_source(&archive, &["--expected-hash", &hex], &["info"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let wrong = "00".repeat(32);
+    let output = run_source(&archive, &["--expected-hash", &wrong], &["info"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("SHA-256 mismatch"));
+}
+
+/// Accepts a single HTTP request on a local port and returns its body.
+fn receive_one_post() -> (String, std::thread::JoinHandle<Value>) {
+    use std::io::{BufRead, BufReader, Read};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    });
+    (url, handle)
+}
+
+#[test]
+fn notify_url_receives_run_summary() {
+    let fixture = Fixture::new();
+
+    let (url, handle) = receive_one_post();
+    run_ok(&fixture, &["--notify-url", &url], &["verify"]);
+    let notification = handle.join().unwrap();
+    assert_eq!(notification["command"], "verify");
+    assert_eq!(notification["status"], "succeeded");
+    assert_eq!(notification["slot"], common::SLOT);
+    assert_eq!(notification["counts"]["accs"], 6);
+    assert!(notification["error"].is_null());
+
+    let (url, handle) = receive_one_post();
+    let fixture = Fixture::with_truncated_storage();
+    let output = run(&fixture, &["--notify-url", &url], &["verify"]);
+    assert!(!output.status.success());
+    let notification = handle.join().unwrap();
+    assert_eq!(notification["status"], "failed");
+    assert!(notification["error"].is_string());
+}
+
+#[test]
+fn config_file_provides_defaults() {
+    let fixture = Fixture::new();
+    let config = fixture.path().with_extension("toml");
+    std::fs::write(
+        &config,
+        format!(
+            "source = {:?}\nmin-lamports = 1000000000\n\n[summary]\nformat = \"json\"\n",
+            fixture.path()
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_solana-snapshot-etl"))
+        .args(["--no-cache", "--raw", "--config"])
+        .arg(&config)
+        .arg("summary")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["total_accounts"], 2);
+
+    // Command-line options take precedence over the file
+    let config_arg = format!("--config={}", config.display());
+    let summary = summary_json(&fixture, &[&config_arg, "--min-lamports", "0"]);
+    assert_eq!(summary["total_accounts"], 6);
+    std::fs::remove_file(&config).unwrap();
+}
+
+#[test]
+fn raw_overrides_human_from_config_file() {
+    let fixture = Fixture::new();
+    let config = fixture.path().with_extension("human.toml");
+    std::fs::write(&config, "human = true\n").unwrap();
+    let config_arg = format!("--config={}", config.display());
+    let summary = |global_args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_solana-snapshot-etl"))
+            .arg("--no-cache")
+            .arg(&config_arg)
+            .args(global_args)
+            .arg(fixture.path())
+            .arg("summary")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let human = summary(&[]);
+    let raw = summary(&["--raw"]);
+    std::fs::remove_file(&config).unwrap();
+    assert!(human.contains(" SOL"), "{}", human);
+    assert!(!raw.contains(" SOL"), "{}", raw);
+    assert!(raw.contains(&common::CAPITALIZATION.to_string()), "{}", raw);
+}
+
+/// Incremental snapshot closing Alice's account.
+#[cfg(feature = "scripting")]
+#[test]
+fn script_emits_rows_and_metrics() {
+    let fixture = Fixture::new();
+    let script = fixture.path().with_extension("rhai");
+    let rows = fixture.path().with_extension("script.jsonl");
+    std::fs::write(
+        &script,
+        format!(
+            r#"
+fn process(account) {{
+    metric("accounts", 1);
+    if account.pubkey == "{}" {{
+        metric("alice_lamports", account.lamports);
+        emit(#{{ slot: account.slot, write_version: account.write_version }});
+    }}
+}}
+"#,
+            common::ALICE
+        ),
+    )
+    .unwrap();
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &[
+            "script",
+            "--script",
+            script.to_str().unwrap(),
+            "--output",
+            rows.to_str().unwrap(),
+        ],
+    );
+    let mut emitted: Vec<Value> = std::fs::read_to_string(&rows)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    std::fs::remove_file(&script).unwrap();
+    std::fs::remove_file(&rows).unwrap();
+    emitted.sort_by_key(|row| row["write_version"].as_u64());
+    assert_eq!(
+        emitted,
+        [
+            serde_json::json!({ "slot": common::OLD_SLOT, "write_version": 1 }),
+            serde_json::json!({ "slot": common::SLOT, "write_version": 10 }),
+        ]
+    );
+    let alice_lamports = stdout
+        .lines()
+        .find(|line| line.trim_start().starts_with("alice_lamports"))
+        .unwrap();
+    assert!(alice_lamports
+        .ends_with(&(common::ALICE_OLD_LAMPORTS + common::ALICE_LAMPORTS).to_string()));
+}
+
+/// Writes a plugin exporting `memory` and `alloc`, with `on_account` as given.
+#[cfg(feature = "wasm")]
+fn write_plugin(fixture: &Fixture, on_account: &str) -> std::path::PathBuf {
+    let plugin = fixture.path().with_extension("wat");
+    std::fs::write(
+        &plugin,
+        format!(
+            r#"
+(module
+  (import "solana_snapshot_etl" "emit" (func $emit (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "alice")
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "on_account") (param $ptr i32) (param $len i32) {}))
+"#,
+            on_account
+        ),
+    )
+    .unwrap();
+    plugin
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn plugin_emits_lines() {
+    let fixture = Fixture::new();
+    // Emits "alice" for the account holding ALICE_LAMPORTS, read at offset 64 of the record
+    let plugin = write_plugin(
+        &fixture,
+        &format!(
+            "(if (i64.eq (i64.load offset=64 (local.get $ptr)) (i64.const {})) \
+             (then (call $emit (i32.const 0) (i32.const 5))))",
+            common::ALICE_LAMPORTS
+        ),
+    );
+    let stdout = run_ok(
+        &fixture,
+        &[],
+        &["plugin", "--plugin", plugin.to_str().unwrap()],
+    );
+    std::fs::remove_file(&plugin).unwrap();
+    assert_eq!(stdout.lines().filter(|line| *line == "alice").count(), 1);
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn plugin_cannot_emit_outside_its_memory() {
+    let fixture = Fixture::new();
+    let plugin = write_plugin(&fixture, "(call $emit (i32.const 65000) (i32.const 1000))");
+    let output = run(
+        &fixture,
+        &[],
+        &["plugin", "--plugin", plugin.to_str().unwrap()],
+    );
+    std::fs::remove_file(&plugin).unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("outside of the plugin memory"));
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn plugin_stuck_in_a_loop_runs_out_of_fuel() {
+    let fixture = Fixture::new();
+    let plugin = write_plugin(&fixture, "(loop $forever (br $forever))");
+    let output = run(
+        &fixture,
+        &[],
+        &["plugin", "--plugin", plugin.to_str().unwrap()],
+    );
+    std::fs::remove_file(&plugin).unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Plugin failed on account"));
+}
+
+fn incremental_storages() -> std::collections::BTreeMap<(u64, u64), Vec<common::StoredAccount>> {
+    let mut storages = std::collections::BTreeMap::new();
+    storages.insert(
+        (common::INCREMENTAL_SLOT, 3),
+        vec![common::StoredAccount {
+            pubkey: common::ALICE,
+            write_version: 20,
+            lamports: 0,
+            owner: system_program::id(),
+            executable: false,
+            data: vec![],
+        }],
+    );
+    storages
+}
+
+#[test]
+fn incremental_layers_over_its_full_snapshot() {
+    let fixture = Fixture::new();
+    let incremental = Fixture::incremental(incremental_storages(), common::SLOT);
+    let incremental_arg = format!("--incremental={}", incremental.path().display());
+
+    let summary = summary_json(&fixture, &[&incremental_arg]);
+    assert_eq!(summary["slot"], common::INCREMENTAL_SLOT);
+    // Both full versions of Alice are shadowed by the closed one
+    assert_eq!(summary["total_accounts"], 5);
+}
+
+#[test]
+fn incremental_rejects_another_base_slot() {
+    let fixture = Fixture::new();
+    let incremental = Fixture::incremental(incremental_storages(), common::OLD_SLOT);
+    let incremental_arg = format!("--incremental={}", incremental.path().display());
+
+    let output = run(&fixture, &[&incremental_arg], &["summary"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains(&format!(
+        "is based on slot {}, not on the full snapshot at slot {}",
+        common::OLD_SLOT,
+        common::SLOT
+    )));
+}
+
+#[test]
+fn lenient_fails_on_truncated_incremental_storage() {
+    let fixture = Fixture::new();
+    let incremental = Fixture::truncated_incremental(incremental_storages(), common::SLOT);
+    let incremental_arg = format!("--incremental={}", incremental.path().display());
+
+    let output = run(&fixture, &[&incremental_arg, "--lenient"], &["stats"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains(&format!("Append vec {}.2", common::INCREMENTAL_SLOT)));
+}
+
+#[test]
+fn from_rpc_rejects_a_newer_archive_than_reported() {
+    let url = common::serve(
+        |request| match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/") => common::http_response(
+                "200 OK",
+                &[],
+                br#"{"jsonrpc":"2.0","id":1,"result":{"full":100,"incremental":null}}"#,
+            ),
+            (_, "/snapshot.tar.bz2") => common::http_response(
+                "302 Found",
+                &[(
+                    "Location",
+                    "/snapshot-101-11111111111111111111111111111111.tar.zst".to_string(),
+                )],
+                b"",
+            ),
+            _ => common::http_response("200 OK", &[], b""),
+        },
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_solana-snapshot-etl"))
+        .args(["--no-cache", "--raw", "--from-rpc", &url, "summary"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("instead of the snapshot-100-* archive it reported"));
+}
+
+/// Serves the fixture as a gzipped archive behind a redirect from `/snapshot.tar.gz`.
+/// The first download is cut off halfway, and `resume` answers range requests for the
+/// rest, given the byte they ask for. Returns the URL and the requests received.
+fn serve_interrupted_archive(
+    resume: impl Fn(&common::Request, &[u8], u64) -> Vec<u8> + Send + Sync + 'static,
+) -> (
+    String,
+    std::sync::Arc<std::sync::Mutex<Vec<common::Request>>>,
+) {
+    let fixture = Fixture::new();
+    let archive = std::fs::read(fixture.archive("snapshot.tar.gz", |file| {
+        flate2::write::GzEncoder::new(file, flate2::Compression::default())
+    }))
+    .unwrap();
+    let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = std::sync::Arc::clone(&requests);
+    let url = common::serve(move |request| {
+        let response = match (request.path.as_str(), request.header("range")) {
+            ("/snapshot.tar.gz", _) => common::http_response(
+                "302 Found",
+                &[("Location", "/files/snapshot-100.tar.gz".to_string())],
+                b"",
+            ),
+            ("/files/snapshot-100.tar.gz", None) => {
+                let mut response = common::http_response(
+                    "200 OK",
+                    &[
+                        ("ETag", "\"v1\"".to_string()),
+                        ("Connection", "close".to_string()),
+                    ],
+                    &archive,
+                );
+                response.truncate(response.len() - archive.len() / 2);
+                response
+            }
+            ("/files/snapshot-100.tar.gz", Some(range)) => {
+                let start = range
+                    .strip_prefix("bytes=")
+                    .and_then(|range| range.split('-').next())
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                resume(request, &archive, start)
+            }
+            _ => common::http_response("404 Not Found", &[], b""),
+        };
+        log.lock().unwrap().push(common::Request {
+            method: request.method.clone(),
+            path: request.path.clone(),
+            headers: request.headers.clone(),
+        });
+        response
+    });
+    (format!("{}/snapshot.tar.gz", url), requests)
+}
+
+fn partial_content(archive: &[u8], start: u64) -> Vec<u8> {
+    common::http_response(
+        "206 Partial Content",
+        &[(
+            "Content-Range",
+            format!("bytes {}-{}/{}", start, archive.len() - 1, archive.len()),
+        )],
+        &archive[start as usize..],
+    )
+}
+
+#[test]
+fn http_download_resumes_from_the_redirected_archive() {
+    let (url, requests) = serve_interrupted_archive(|request, archive, start| {
+        if request.header("if-range") == Some("\"v1\"") {
+            partial_content(archive, start)
+        } else {
+            common::http_response("200 OK", &[], archive)
+        }
+    });
+    let output = run_source(
+        Path::new(&url),
+        &["--http-retry-backoff", "0"],
+        &["summary", "--format", "json"],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["total_accounts"], 6);
+
+    let requests = requests.lock().unwrap();
+    let resumed = requests
+        .iter()
+        .find(|request| request.header("range").is_some())
+        .expect("download was not resumed");
+    assert_eq!(resumed.path, "/files/snapshot-100.tar.gz");
+}
+
+#[test]
+fn http_download_rejects_misplaced_or_changed_resumes() {
+    // A range starting elsewhere than requested
+    let (url, _) = serve_interrupted_archive(|_, archive, _| partial_content(archive, 0));
+    let output = run_source(
+        Path::new(&url),
+        &["--http-retry-backoff", "0"],
+        &["summary"],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("from byte 0"));
+
+    // The whole archive again, as sent when it changed since the ETag in If-Range
+    let (url, _) = serve_interrupted_archive(|_, archive, _| {
+        common::http_response("200 OK", &[("ETag", "\"v2\"".to_string())], archive)
+    });
+    let output = run_source(
+        Path::new(&url),
+        &["--http-retry-backoff", "0"],
+        &["summary"],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("the archive changed"));
+}
+
+/// Serves the fixture as a gzipped archive, answering the first requests with `failures`
+/// in turn. Returns the URL and the number of requests received.
+fn serve_flaky_archive(
+    failures: Vec<Vec<u8>>,
+) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let fixture = Fixture::new();
+    let archive = std::fs::read(fixture.archive("snapshot.tar.gz", |file| {
+        flate2::write::GzEncoder::new(file, flate2::Compression::default())
+    }))
+    .unwrap();
+    let requests = std::sync::Arc::new(AtomicUsize::new(0));
+    let count = std::sync::Arc::clone(&requests);
+    let url = common::serve(
+        move |_| match failures.get(count.fetch_add(1, Ordering::SeqCst)) {
+            Some(failure) => failure.clone(),
+            None => common::http_response("200 OK", &[], &archive),
+        },
+    );
+    (format!("{}/snapshot.tar.gz", url), requests)
+}
+
+#[test]
+fn http_requests_retry_transient_failures_with_backoff() {
+    use std::sync::atomic::Ordering;
+    use std::time::{Duration, Instant};
+
+    // A connection closed before the status line fails without a status, like a reset
+    let dropped = b"HTTP/1.1 200 OK\r\nConnection: close\r\n".to_vec();
+    let (url, requests) = serve_flaky_archive(vec![
+        common::http_response("503 Service Unavailable", &[], b""),
+        common::http_response("429 Too Many Requests", &[], b""),
+        dropped,
+    ]);
+    let started = Instant::now();
+    let output = run_source(
+        Path::new(&url),
+        &["--http-retry-backoff", "0.1"],
+        &["summary", "--format", "json"],
+    );
+    let elapsed = started.elapsed();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["total_accounts"], 6);
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+    // Waits of 0.1, 0.2 and 0.4 seconds
+    assert!(elapsed >= Duration::from_millis(700), "took {:?}", elapsed);
+
+    // Retries run out
+    let (url, requests) =
+        serve_flaky_archive(vec![common::http_response("502 Bad Gateway", &[], b""); 3]);
+    let output = run_source(
+        Path::new(&url),
+        &["--http-retries", "2", "--http-retry-backoff", "0"],
+        &["summary"],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("502"));
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn http_requests_do_not_retry_client_errors() {
+    use std::sync::atomic::Ordering;
+
+    let (url, requests) =
+        serve_flaky_archive(vec![common::http_response("404 Not Found", &[], b"")]);
+    let output = run_source(
+        Path::new(&url),
+        &["--http-retry-backoff", "0"],
+        &["summary"],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("404"));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+/// Serves the fixture as a gzipped archive behind a redirect from `/snapshot.tar.gz`,
+/// answering HEAD requests with `head_status` and range requests with `range`, given the
+/// first byte they ask for. Returns the URL and the paths of the range requests received
+/// with their If-Range header.
+fn serve_ranged_archive(
+    head_status: &'static str,
+    range: impl Fn(&[u8], u64) -> Vec<u8> + Send + Sync + 'static,
+) -> (
+    String,
+    std::sync::Arc<std::sync::Mutex<Vec<(String, Option<String>)>>>,
+) {
+    let fixture = Fixture::new();
+    let archive = std::fs::read(fixture.archive("snapshot.tar.gz", |file| {
+        flate2::write::GzEncoder::new(file, flate2::Compression::default())
+    }))
+    .unwrap();
+    let range_requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = std::sync::Arc::clone(&range_requests);
+    let url = common::serve(move |request| {
+        if request.path == "/snapshot.tar.gz" {
+            return common::http_response(
+                "302 Found",
+                &[("Location", "/files/snapshot-100.tar.gz".to_string())],
+                b"",
+            );
+        }
+        if request.method == "HEAD" {
+            let mut response = common::http_response(
+                head_status,
+                &[
+                    ("Accept-Ranges", "bytes".to_string()),
+                    ("ETag", "\"v1\"".to_string()),
+                ],
+                &archive,
+            );
+            // Only the headers are sent, so the body may stand in for the archive
+            response.truncate(response.len() - archive.len());
+            return response;
+        }
+        match request.header("range") {
+            Some(bytes) => {
+                log.lock().unwrap().push((
+                    request.path.clone(),
+                    request.header("if-range").map(str::to_string),
+                ));
+                let start = bytes
+                    .strip_prefix("bytes=")
+                    .and_then(|bytes| bytes.split('-').next())
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                range(&archive, start)
+            }
+            None => common::http_response("200 OK", &[], &archive),
+        }
+    });
+    (format!("{}/snapshot.tar.gz", url), range_requests)
+}
+
+#[test]
+fn segmented_download_fetches_ranges_of_the_redirected_archive() {
+    let (url, range_requests) = serve_ranged_archive("200 OK", partial_content);
+    let output = run_source(
+        Path::new(&url),
+        &["--download-threads", "4", "--http-retry-backoff", "0"],
+        &["summary", "--format", "json"],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["total_accounts"], 6);
+
+    let range_requests = range_requests.lock().unwrap();
+    assert!(!range_requests.is_empty());
+    for (path, if_range) in range_requests.iter() {
+        assert_eq!(path, "/files/snapshot-100.tar.gz");
+        assert_eq!(if_range.as_deref(), Some("\"v1\""));
+    }
+}
+
+#[test]
+fn segmented_download_falls_back_to_a_single_stream() {
+    let (url, range_requests) = serve_ranged_archive("405 Method Not Allowed", partial_content);
+    let output = run_source(
+        Path::new(&url),
+        &["--download-threads", "4", "--http-retry-backoff", "0"],
+        &["summary", "--format", "json"],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["total_accounts"], 6);
+    assert!(range_requests.lock().unwrap().is_empty());
+}
+
+#[test]
+fn segmented_download_rejects_misplaced_ranges() {
+    let (url, _) = serve_ranged_archive("200 OK", |archive, _| {
+        common::http_response(
+            "206 Partial Content",
+            &[(
+                "Content-Range",
+                format!("bytes 1-{}/{}", archive.len() - 1, archive.len()),
+            )],
+            &archive[1..],
+        )
+    });
+    let output = run_source(
+        Path::new(&url),
+        &[
+            "--download-threads",
+            "4",
+            "--http-retries",
+            "0",
+            "--http-retry-backoff",
+            "0",
+        ],
+        &["summary"],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("from byte 1"));
+}
+
+#[test]
+fn throttled_download_does_not_burst_after_a_stall() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    // The archive takes two seconds at the rate limit, and its range is only answered
+    // after a stall of two seconds. Only a second's worth of that stall may be made up
+    let rate = Arc::new(AtomicU64::new(0));
+    let stall = Duration::from_secs(2);
+    let (url, _) = serve_ranged_archive("200 OK", {
+        let rate = Arc::clone(&rate);
+        move |archive, start| {
+            rate.store(archive.len() as u64 / 2, Ordering::SeqCst);
+            std::thread::sleep(stall);
+            partial_content(archive, start)
+        }
+    });
+    // Learn the archive size from a first, unthrottled download
+    let output = run_source(Path::new(&url), &["--download-threads", "2"], &["summary"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let rate_arg = format!("--max-download-rate={}", rate.load(Ordering::SeqCst));
+
+    let started = Instant::now();
+    let output = run_source(
+        Path::new(&url),
+        &["--download-threads", "2", &rate_arg],
+        &["summary"],
+    );
+    let elapsed = started.elapsed();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        elapsed >= stall + Duration::from_millis(900),
+        "took {:?}",
+        elapsed
+    );
+}
+
+/// Serves the fixture as a gzipped archive. Returns the URL and the archive's length.
+fn serve_archive() -> (String, u64) {
+    let fixture = Fixture::new();
+    let archive = std::fs::read(fixture.archive("snapshot.tar.gz", |file| {
+        flate2::write::GzEncoder::new(file, flate2::Compression::default())
+    }))
+    .unwrap();
+    let len = archive.len() as u64;
+    let url = common::serve(move |_| common::http_response("200 OK", &[], &archive));
+    (format!("{}/snapshot.tar.gz", url), len)
+}
+
+/// Lines of `stderr` that are `--progress json` reports.
+fn progress_reports(stderr: &[u8]) -> Vec<Value> {
+    String::from_utf8_lossy(stderr)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|report| report.get("progress").is_some())
+        .collect()
+}
+
+#[test]
+fn json_progress_reports_bars_unless_quiet() {
+    // Throttled to take about two seconds, for several reports to be printed
+    let (url, len) = serve_archive();
+    let rate_arg = format!("--max-download-rate={}", len / 2);
+    let global_args = [
+        rate_arg.as_str(),
+        "--progress",
+        "json",
+        "--progress-interval",
+        "0.1",
+    ];
+    let output = run_source(Path::new(&url), &global_args, &["summary"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let reports = progress_reports(&output.stderr);
+    let elapsed: Vec<f64> = reports
+        .iter()
+        .map(|report| report["elapsed_secs"].as_f64().unwrap())
+        .collect();
+    assert!(elapsed.windows(2).all(|pair| pair[0] < pair[1]));
+    // The archive bar is only added once the response arrives
+    let archive_reports: Vec<&Value> = reports
+        .iter()
+        .filter_map(|report| report["progress"].get("archive"))
+        .collect();
+    assert!(
+        archive_reports.len() >= 5,
+        "{} reports",
+        archive_reports.len()
+    );
+    for archive in archive_reports {
+        assert_eq!(archive["length"], len);
+        assert!(archive["position"].as_u64().unwrap() <= len);
+    }
+
+    // --quiet drops the reports along with the bars
+    let quiet_args = [&["--quiet"][..], &global_args].concat();
+    let output = run_source(Path::new(&url), &quiet_args, &["summary"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(progress_reports(&output.stderr).is_empty());
+}
+
+#[test]
+fn archive_progress_estimates_the_remaining_time() {
+    // Throttled to take about two seconds
+    let (url, len) = serve_archive();
+    let rate_arg = format!("--max-download-rate={}", len / 2);
+    let output = run_source(
+        Path::new(&url),
+        &[
+            &rate_arg,
+            "--progress",
+            "json",
+            "--progress-interval",
+            "0.1",
+        ],
+        &["summary"],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let reports = progress_reports(&output.stderr);
+    let etas: Vec<f64> = reports
+        .iter()
+        .filter_map(|report| report["progress"].get("archive"))
+        .filter(|archive| archive["position"].as_u64().unwrap() < len)
+        .map(|archive| archive["eta_secs"].as_f64().unwrap())
+        .collect();
+    assert!(!etas.is_empty());
+    assert!(etas.iter().all(|eta| *eta < 10.0), "{:?}", etas);
+    assert!(etas.iter().any(|eta| *eta > 0.0), "{:?}", etas);
+    // Accounts are counted without knowing how many there are
+    for report in &reports {
+        if let Some(accs) = report["progress"].get("accs") {
+            assert!(accs["eta_secs"].is_null());
+        }
+    }
+}
+
+#[test]
+fn cached_downloads_are_keyed_on_the_served_archive() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let fixture = Fixture::new();
+    let archive = std::fs::read(fixture.archive("snapshot.tar.gz", |file| {
+        flate2::write::GzEncoder::new(file, flate2::Compression::default())
+    }))
+    .unwrap();
+    let version = Arc::new(AtomicUsize::new(1));
+    let downloads = Arc::new(AtomicUsize::new(0));
+    let url = {
+        let version = Arc::clone(&version);
+        let downloads = Arc::clone(&downloads);
+        common::serve(move |request| {
+            if request.path == "/snapshot.tar.gz" {
+                return common::http_response(
+                    "302 Found",
+                    &[("Location", "/files/snapshot.tar.gz".to_string())],
+                    b"",
+                );
+            }
+            if request.method == "GET" {
+                downloads.fetch_add(1, Ordering::SeqCst);
+            }
+            let etag = format!("\"v{}\"", version.load(Ordering::SeqCst));
+            common::http_response("200 OK", &[("ETag", etag)], &archive)
+        })
+    };
+    let url = format!("{}/snapshot.tar.gz", url);
+    let cache_dir = fixture.path().join("downloads");
+    let cache_dir_arg = format!("--cache-dir={}", cache_dir.display());
+    let summarize = || {
+        let output = run_source(Path::new(&url), &[&cache_dir_arg], &["summary"]);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    };
+
+    summarize();
+    assert_eq!(downloads.load(Ordering::SeqCst), 1);
+    // The same archive is read from the cache
+    summarize();
+    assert_eq!(downloads.load(Ordering::SeqCst), 1);
+    // Once the URL serves another archive, it is downloaded again
+    version.store(2, Ordering::SeqCst);
+    summarize();
+    assert_eq!(downloads.load(Ordering::SeqCst), 2);
+    assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 2);
+}
+
+#[test]
+fn cached_downloads_need_an_etag_or_modification_time() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let fixture = Fixture::new();
+    let archive = std::fs::read(fixture.archive("snapshot.tar.gz", |file| {
+        flate2::write::GzEncoder::new(file, flate2::Compression::default())
+    }))
+    .unwrap();
+    let last_modified = Arc::new(std::sync::Mutex::new(None::<String>));
+    let downloads = Arc::new(AtomicUsize::new(0));
+    let url = {
+        let last_modified = Arc::clone(&last_modified);
+        let downloads = Arc::clone(&downloads);
+        common::serve(move |request| {
+            if request.method == "GET" {
+                downloads.fetch_add(1, Ordering::SeqCst);
+            }
+            let headers: Vec<_> = last_modified
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|time| ("Last-Modified", time.clone()))
+                .collect();
+            common::http_response("200 OK", &headers, &archive)
+        })
+    };
+    let url = format!("{}/snapshot.tar.gz", url);
+    let cache_dir = fixture.path().join("downloads");
+    let cache_dir_arg = format!("--cache-dir={}", cache_dir.display());
+    let summarize = || {
+        let output = run_source(Path::new(&url), &[&cache_dir_arg], &["summary"]);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    };
+
+    // A length alone doesn't identify the archive, so nothing is cached
+    summarize();
+    summarize();
+    assert_eq!(downloads.load(Ordering::SeqCst), 2);
+    assert!(!cache_dir.exists());
+
+    // A modification time stands in for the ETag
+    *last_modified.lock().unwrap() = Some("Wed, 01 Jan 2025 00:00:00 GMT".to_string());
+    summarize();
+    summarize();
+    assert_eq!(downloads.load(Ordering::SeqCst), 3);
+    *last_modified.lock().unwrap() = Some("Thu, 02 Jan 2025 00:00:00 GMT".to_string());
+    summarize();
+    assert_eq!(downloads.load(Ordering::SeqCst), 4);
+}
+
+/// Counts the accounts handed to its consumers.
+struct CountingFactory(std::sync::Arc<std::sync::atomic::AtomicU64>);
+
+struct CountingConsumer(std::sync::Arc<std::sync::atomic::AtomicU64>);
+
+impl solana_snapshot_etl::parallel::AppendVecConsumerFactory for CountingFactory {
+    type Consumer = CountingConsumer;
+
+    fn new_consumer(&mut self) -> solana_snapshot_etl::parallel::GenericResult<CountingConsumer> {
+        Ok(CountingConsumer(std::sync::Arc::clone(&self.0)))
+    }
+}
+
+impl solana_snapshot_etl::parallel::AppendVecConsumer for CountingConsumer {
+    fn on_append_vec(
+        &mut self,
+        append_vec: &solana_snapshot_etl::append_vec::AppendVec,
+    ) -> solana_snapshot_etl::parallel::GenericResult<()> {
+        let accounts = solana_snapshot_etl::append_vec_iter(append_vec).count() as u64;
+        self.0
+            .fetch_add(accounts, std::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+struct NoProgress;
+
+impl solana_snapshot_etl::ReadProgressTracking for NoProgress {
+    fn new_read_progress_tracker(
+        &self,
+        _: &Path,
+        rd: Box<dyn std::io::Read>,
+        _: u64,
+    ) -> Box<dyn std::io::Read> {
+        rd
+    }
+}
+
+/// Consumer failing on its first append vec.
+struct FailingFactory;
+
+struct FailingConsumer;
+
+impl solana_snapshot_etl::parallel::AppendVecConsumerFactory for FailingFactory {
+    type Consumer = FailingConsumer;
+
+    fn new_consumer(&mut self) -> solana_snapshot_etl::parallel::GenericResult<FailingConsumer> {
+        Ok(FailingConsumer)
+    }
+}
+
+impl solana_snapshot_etl::parallel::AppendVecConsumer for FailingConsumer {
+    fn on_append_vec(
+        &mut self,
+        _: &solana_snapshot_etl::append_vec::AppendVec,
+    ) -> solana_snapshot_etl::parallel::GenericResult<()> {
+        Err("consumer failed".into())
+    }
+}
+
+#[test]
+fn consumer_registry_fans_out_one_scan() {
+    use solana_snapshot_etl::parallel::{par_iter_append_vecs, ConsumerFactoryRegistry};
+    use solana_snapshot_etl::SnapshotExtractor;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    let fixture = Fixture::new();
+    let mut extractor = solana_snapshot_etl::unpacked::UnpackedSnapshotExtractor::open(
+        fixture.path(),
+        Box::new(NoProgress),
+    )
+    .unwrap();
+    let first = Arc::new(AtomicU64::new(0));
+    let second = Arc::new(AtomicU64::new(0));
+    let mut registry = ConsumerFactoryRegistry::new();
+    registry
+        .register(CountingFactory(Arc::clone(&first)))
+        .register(CountingFactory(Arc::clone(&second)));
+    assert_eq!(registry.len(), 2);
+
+    par_iter_append_vecs(extractor.iter(), &mut registry, 2).unwrap();
+
+    let stored: usize = common::storages().values().map(Vec::len).sum();
+    assert_eq!(first.load(Ordering::Relaxed), stored as u64);
+    assert_eq!(second.load(Ordering::Relaxed), stored as u64);
+}
+
+#[test]
+fn par_iter_returns_consumer_errors() {
+    use solana_snapshot_etl::parallel::{par_iter_append_vecs, ConsumerFactoryRegistry};
+    use solana_snapshot_etl::SnapshotExtractor;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::Arc;
+
+    let fixture = Fixture::new();
+    let mut extractor = solana_snapshot_etl::unpacked::UnpackedSnapshotExtractor::open(
+        fixture.path(),
+        Box::new(NoProgress),
+    )
+    .unwrap();
+    let mut registry = ConsumerFactoryRegistry::new();
+    registry
+        .register(CountingFactory(Arc::new(AtomicU64::new(0))))
+        .register(FailingFactory);
+
+    let error = par_iter_append_vecs(extractor.iter(), &mut registry, 2).unwrap_err();
+    assert_eq!(error.to_string(), "consumer failed");
+}
+
+/// Maps an append vec to its slot, slower for lower slots so that outputs complete out of
+/// order, and fails on `fail_slot` and panics on `panic_slot`.
+struct SlotMapper {
+    fail_slot: Option<u64>,
+    panic_slot: Option<u64>,
+}
+
+impl solana_snapshot_etl::parallel::AppendVecMapperFactory for SlotMapper {
+    type Mapper = SlotMapper;
+
+    fn new_mapper(&mut self) -> solana_snapshot_etl::parallel::GenericResult<SlotMapper> {
+        Ok(SlotMapper {
+            fail_slot: self.fail_slot,
+            panic_slot: self.panic_slot,
+        })
+    }
+}
+
+impl solana_snapshot_etl::parallel::AppendVecMapper for SlotMapper {
+    type Output = u64;
+
+    fn map_append_vec(
+        &mut self,
+        append_vec: solana_snapshot_etl::append_vec::AppendVec,
+    ) -> solana_snapshot_etl::parallel::GenericResult<u64> {
+        let slot = append_vec.get_slot();
+        if Some(slot) == self.fail_slot {
+            return Err(format!("failed on slot {}", slot).into());
+        }
+        if Some(slot) == self.panic_slot {
+            panic!("mapper panicked on slot {}", slot);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20 - slot % 20));
+        Ok(slot)
+    }
+}
+
+impl solana_snapshot_etl::parallel::AppendVecCollector<u64> for Vec<u64> {
+    fn collect(
+        &mut self,
+        slot: u64,
+        output: u64,
+    ) -> solana_snapshot_etl::parallel::GenericResult<()> {
+        assert_eq!(slot, output);
+        self.push(slot);
+        Ok(())
+    }
+}
+
+#[test]
+fn ordered_par_map_delivers_in_extractor_order() {
+    use solana_snapshot_etl::parallel::{par_map_append_vecs, ParallelOptions};
+    use solana_snapshot_etl::SnapshotExtractor;
+
+    let mut storages = common::storages();
+    for slot in 0..20 {
+        storages.insert(
+            (slot, 100 + slot),
+            vec![common::StoredAccount {
+                pubkey: Pubkey::new_unique(),
+                write_version: slot,
+                lamports: 1,
+                owner: system_program::id(),
+                executable: false,
+                data: vec![],
+            }],
+        );
+    }
+    let fixture = Fixture::with_storages(storages);
+    let open = || {
+        solana_snapshot_etl::unpacked::UnpackedSnapshotExtractor::open(
+            fixture.path(),
+            Box::new(NoProgress),
+        )
+        .unwrap()
+    };
+    let expected: Vec<u64> = open()
+        .iter()
+        .map(|append_vec| append_vec.unwrap().get_slot())
+        .collect();
+    let options = ParallelOptions {
+        num_threads: 4,
+        ordered: true,
+        reorder_window: 4,
+    };
+
+    let mut slots = Vec::new();
+    par_map_append_vecs(
+        open().iter(),
+        &mut SlotMapper {
+            fail_slot: None,
+            panic_slot: None,
+        },
+        &mut slots,
+        options,
+    )
+    .unwrap();
+    assert_eq!(slots, expected);
+
+    // A failing mapper is reported instead of panicking
+    let mut slots = Vec::new();
+    let error = par_map_append_vecs(
+        open().iter(),
+        &mut SlotMapper {
+            fail_slot: Some(common::SLOT),
+            panic_slot: None,
+        },
+        &mut slots,
+        options,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("failed on slot {}", common::SLOT)
+    );
+
+    // A mapper panicking on the first append vec holds back every later output, which
+    // fills the window while the other mappers wait for more
+    let mut slots = Vec::new();
+    let error = par_map_append_vecs(
+        open().iter(),
+        &mut SlotMapper {
+            fail_slot: None,
+            panic_slot: Some(expected[0]),
+        },
+        &mut slots,
+        options,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("Mapper thread panicked on slot {}", expected[0])
+    );
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_extractor_streams_an_http_archive() {
+    use futures::TryStreamExt;
+    use solana_snapshot_etl::async_archived::AsyncArchiveSnapshotExtractor;
+
+    let fixture = Fixture::new();
+    let archive = std::fs::read(fixture.archive("snapshot.tar.zst", |file| {
+        zstd::Encoder::new(file, 3).unwrap().auto_finish()
+    }))
+    .unwrap();
+    let url = common::serve(move |request| match request.path.as_str() {
+        "/snapshot.tar.zst" => common::http_response("200 OK", &[], &archive),
+        _ => common::http_response("404 Not Found", &[], b""),
+    });
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let extractor =
+            AsyncArchiveSnapshotExtractor::from_url(&format!("{}/snapshot.tar.zst", url))
+                .await
+                .unwrap();
+        assert_eq!(extractor.slot(), common::SLOT);
+        assert_eq!(extractor.bank_hash(), common::bank_hash());
+        let append_vecs: Vec<_> = extractor.try_collect().await.unwrap();
+        let mut slots: Vec<u64> = append_vecs
+            .iter()
+            .map(|append_vec| append_vec.get_slot())
+            .collect();
+        slots.sort();
+        assert_eq!(slots, [common::OLD_SLOT, common::SLOT]);
+        let accounts: usize = append_vecs
+            .iter()
+            .map(|append_vec| solana_snapshot_etl::append_vec_iter(append_vec).count())
+            .sum();
+        assert_eq!(accounts, 6);
+
+        // Error statuses fail the extractor before any append vec is read
+        assert!(
+            AsyncArchiveSnapshotExtractor::from_url(&format!("{}/missing.tar.zst", url))
+                .await
+                .is_err()
+        );
+    });
+}
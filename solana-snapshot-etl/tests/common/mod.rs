@@ -0,0 +1,662 @@
+//! Synthesized unpacked snapshot used by the integration tests.
+//!
+//! The fixture mirrors the on-disk layout written by the validator: a bincode manifest
+//! under `snapshots/<slot>/<slot>`, a status cache, and append vecs under `accounts/`.
+
+#![allow(dead_code)]
+
+use serde::Serialize;
+use solana_runtime::accounts_hash::{AccountsHasher, MERKLE_FANOUT};
+use solana_runtime::blockhash_queue::BlockhashQueue;
+use solana_runtime::rent_collector::RentCollector;
+use solana_sdk::epoch_schedule::EpochSchedule;
+use solana_sdk::fee_calculator::{FeeCalculator, FeeRateGovernor};
+use solana_sdk::hard_forks::HardForks;
+use solana_sdk::hash::{hashv, Hash};
+use solana_sdk::inflation::Inflation;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+use solana_snapshot_etl::solana::{BankHashInfo, BankIncrementalSnapshotPersistence, ExtraFields};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const SLOT: u64 = 100;
+pub const OLD_SLOT: u64 = 90;
+/// Slot of incremental snapshots layered over the fixture
+pub const INCREMENTAL_SLOT: u64 = 110;
+pub const EPOCH: u64 = 2;
+pub const BLOCK_HEIGHT: u64 = 95;
+
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+
+pub const ALICE: Pubkey = Pubkey::new_from_array([1; 32]);
+pub const BOB: Pubkey = Pubkey::new_from_array([2; 32]);
+pub const MINT: Pubkey = Pubkey::new_from_array([3; 32]);
+pub const TOKEN_ACCOUNT: Pubkey = Pubkey::new_from_array([4; 32]);
+pub const DANGLING_TOKEN_ACCOUNT: Pubkey = Pubkey::new_from_array([5; 32]);
+pub const MISSING_MINT: Pubkey = Pubkey::new_from_array([6; 32]);
+/// System-owned account holding data, violating a chain invariant
+pub const ODD_ACCOUNT: Pubkey = Pubkey::new_from_array([7; 32]);
+
+pub const ALICE_OLD_LAMPORTS: u64 = 2_000_000_000;
+pub const ALICE_LAMPORTS: u64 = 1_000_000_000;
+pub const MINT_LAMPORTS: u64 = 1_461_600;
+pub const TOKEN_ACCOUNT_LAMPORTS: u64 = 2_039_280;
+pub const ODD_LAMPORTS: u64 = 5_000;
+pub const TOKEN_AMOUNT: u64 = 500;
+pub const METADATA_ACCOUNT_LEN: usize = 679;
+
+/// Lamports of the latest version of every account.
+pub const CAPITALIZATION: u64 =
+    ALICE_LAMPORTS + MINT_LAMPORTS + 2 * TOKEN_ACCOUNT_LAMPORTS + ODD_LAMPORTS;
+
+pub struct StoredAccount {
+    pub pubkey: Pubkey,
+    pub write_version: u64,
+    pub lamports: u64,
+    pub owner: Pubkey,
+    pub executable: bool,
+    pub data: Vec<u8>,
+}
+
+impl StoredAccount {
+    fn hash(&self) -> Hash {
+        hashv(&[self.pubkey.as_ref(), &self.write_version.to_le_bytes()])
+    }
+}
+
+fn align_to_u64(buf: &mut Vec<u8>) {
+    buf.resize((buf.len() + 7) & !7, 0);
+}
+
+/// Serializes accounts in the append vec entry layout: `StoredMeta`, `AccountMeta`,
+/// the account hash and the data, each aligned to 8 bytes.
+pub fn append_vec_bytes(accounts: &[StoredAccount]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for account in accounts {
+        buf.extend_from_slice(&account.write_version.to_le_bytes());
+        buf.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        buf.extend_from_slice(account.pubkey.as_ref());
+        align_to_u64(&mut buf);
+
+        buf.extend_from_slice(&account.lamports.to_le_bytes());
+        buf.extend_from_slice(&0u64.to_le_bytes()); // rent_epoch
+        buf.extend_from_slice(account.owner.as_ref());
+        buf.push(account.executable as u8);
+        align_to_u64(&mut buf);
+
+        buf.extend_from_slice(account.hash().as_ref());
+        buf.extend_from_slice(&account.data);
+        align_to_u64(&mut buf);
+    }
+    buf
+}
+
+/// Serializes accounts as a tiered hot storage file: account blocks, index block, owners
+/// block and footer.
+pub fn hot_storage_bytes(accounts: &[StoredAccount]) -> Vec<u8> {
+    let mut owners: Vec<Pubkey> = Vec::new();
+    let mut offsets = Vec::new();
+    let mut buf = Vec::new();
+    for account in accounts {
+        let owner_index = match owners.iter().position(|owner| *owner == account.owner) {
+            Some(index) => index,
+            None => {
+                owners.push(account.owner);
+                owners.len() - 1
+            }
+        };
+        let padding = (8 - account.data.len() % 8) % 8;
+        let flags: u32 = 1 | if account.executable { 2 } else { 0 }; // has_rent_epoch
+
+        offsets.push((buf.len() / 8) as u32);
+        buf.extend_from_slice(&account.lamports.to_le_bytes());
+        buf.extend_from_slice(&(padding as u32 | (owner_index as u32) << 3).to_le_bytes());
+        buf.extend_from_slice(&flags.to_le_bytes());
+        buf.extend_from_slice(&account.data);
+        buf.resize(buf.len() + padding, 0);
+        buf.extend_from_slice(&0u64.to_le_bytes()); // rent_epoch
+    }
+
+    let account_block_size = buf.len() as u64;
+    let index_block_offset = buf.len() as u64;
+    for account in accounts {
+        buf.extend_from_slice(account.pubkey.as_ref());
+    }
+    for offset in offsets {
+        buf.extend_from_slice(&offset.to_le_bytes());
+    }
+    let owners_block_offset = buf.len() as u64;
+    for owner in &owners {
+        buf.extend_from_slice(owner.as_ref());
+    }
+
+    // Formats: hot account metas, addresses-only owners, addresses-then-offsets index,
+    // aligned raw account blocks
+    buf.extend_from_slice(&[0u8; 8]);
+    buf.extend_from_slice(&(accounts.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&16u32.to_le_bytes()); // account_meta_entry_size
+    buf.extend_from_slice(&account_block_size.to_le_bytes());
+    buf.extend_from_slice(&(owners.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&32u32.to_le_bytes()); // owner_entry_size
+    buf.extend_from_slice(&index_block_offset.to_le_bytes());
+    buf.extend_from_slice(&owners_block_offset.to_le_bytes());
+    buf.extend_from_slice(&[0u8; 96]); // min/max account address, hash
+    buf.extend_from_slice(&1u64.to_le_bytes()); // format_version
+    buf.extend_from_slice(&168u64.to_le_bytes()); // footer_size
+    buf.extend_from_slice(b"AnzaTech");
+    buf
+}
+
+pub fn token_account_data(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0u8; 165];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[64..72].copy_from_slice(&amount.to_le_bytes());
+    data[108] = 1; // AccountState::Initialized
+    data
+}
+
+/// Metaplex metadata account of `mint`, with an optional `(key, verified)` collection.
+pub fn metadata_data(mint: &Pubkey, collection: Option<(Pubkey, bool)>) -> Vec<u8> {
+    let mut data = vec![4u8]; // AccountKey::MetadataV1
+    data.extend_from_slice(BOB.as_ref()); // update_authority
+    data.extend_from_slice(mint.as_ref());
+    for field in ["Item", "ITEM", "https://example.com/item.json"] {
+        data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        data.extend_from_slice(field.as_bytes());
+    }
+    data.extend_from_slice(&500u16.to_le_bytes()); // seller_fee_basis_points
+    data.extend_from_slice(&[0, 0, 1]); // no creators, primary_sale_happened, is_mutable
+    data.extend_from_slice(&[1, 255]); // edition_nonce
+    data.extend_from_slice(&[1, 0]); // token_standard: NonFungible
+    match collection {
+        Some((key, verified)) => {
+            data.extend_from_slice(&[1, verified as u8]);
+            data.extend_from_slice(key.as_ref());
+        }
+        None => data.push(0),
+    }
+    data.push(0); // no uses
+                  // Metadata accounts are allocated at their maximum size
+    data.resize(METADATA_ACCOUNT_LEN, 0);
+    data
+}
+
+fn mint_data(authority: &Pubkey, supply: u64, decimals: u8) -> Vec<u8> {
+    let mut data = vec![0u8; 82];
+    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    data[4..36].copy_from_slice(authority.as_ref());
+    data[36..44].copy_from_slice(&supply.to_le_bytes());
+    data[44] = decimals;
+    data[45] = 1; // is_initialized
+    data
+}
+
+fn incremental_fields(full_slot: u64) -> ExtraFields {
+    ExtraFields {
+        incremental_snapshot_persistence: Some(BankIncrementalSnapshotPersistence {
+            full_slot,
+            ..BankIncrementalSnapshotPersistence::default()
+        }),
+        ..ExtraFields::default()
+    }
+}
+
+/// Append vecs of the fixture, keyed by (slot, id).
+pub fn storages() -> BTreeMap<(u64, u64), Vec<StoredAccount>> {
+    let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
+    let mut storages = BTreeMap::new();
+    storages.insert(
+        (OLD_SLOT, 0),
+        vec![StoredAccount {
+            pubkey: ALICE,
+            write_version: 1,
+            lamports: ALICE_OLD_LAMPORTS,
+            owner: system_program::id(),
+            executable: false,
+            data: vec![],
+        }],
+    );
+    storages.insert(
+        (SLOT, 1),
+        vec![
+            StoredAccount {
+                pubkey: ALICE,
+                write_version: 10,
+                lamports: ALICE_LAMPORTS,
+                owner: system_program::id(),
+                executable: false,
+                data: vec![],
+            },
+            StoredAccount {
+                pubkey: MINT,
+                write_version: 11,
+                lamports: MINT_LAMPORTS,
+                owner: token_program,
+                executable: false,
+                data: mint_data(&BOB, 1_000, 6),
+            },
+            StoredAccount {
+                pubkey: TOKEN_ACCOUNT,
+                write_version: 12,
+                lamports: TOKEN_ACCOUNT_LAMPORTS,
+                owner: token_program,
+                executable: false,
+                data: token_account_data(&MINT, &BOB, TOKEN_AMOUNT),
+            },
+            StoredAccount {
+                pubkey: DANGLING_TOKEN_ACCOUNT,
+                write_version: 13,
+                lamports: TOKEN_ACCOUNT_LAMPORTS,
+                owner: token_program,
+                executable: false,
+                data: token_account_data(&MISSING_MINT, &BOB, 7),
+            },
+            StoredAccount {
+                pubkey: ODD_ACCOUNT,
+                write_version: 14,
+                lamports: ODD_LAMPORTS,
+                owner: system_program::id(),
+                executable: false,
+                data: vec![0xaa; 10],
+            },
+        ],
+    );
+    storages
+}
+
+/// Accounts hash over the latest version of every live account, computed with the
+/// validator's implementation.
+pub fn accounts_hash(storages: &BTreeMap<(u64, u64), Vec<StoredAccount>>) -> Hash {
+    let mut latest: BTreeMap<Pubkey, (u64, u64, &StoredAccount)> = BTreeMap::new();
+    for ((slot, _), accounts) in storages {
+        for account in accounts {
+            let version = (*slot, account.write_version);
+            match latest.get(&account.pubkey) {
+                Some((s, w, _)) if (*s, *w) >= version => {}
+                _ => {
+                    latest.insert(account.pubkey, (version.0, version.1, account));
+                }
+            }
+        }
+    }
+    let hashes: Vec<(Pubkey, Hash)> = latest
+        .iter()
+        .filter(|(_, (_, _, account))| account.lamports != 0)
+        .map(|(pubkey, (_, _, account))| (*pubkey, account.hash()))
+        .collect();
+    AccountsHasher::compute_merkle_root(hashes, MERKLE_FANOUT)
+}
+
+/// Bincode-compatible stand-in for the bank fields of the manifest.
+#[derive(Serialize)]
+struct SerializableBank {
+    blockhash_queue: BlockhashQueue,
+    ancestors: HashMap<u64, usize>,
+    hash: Hash,
+    parent_hash: Hash,
+    parent_slot: u64,
+    hard_forks: HardForks,
+    transaction_count: u64,
+    tick_height: u64,
+    signature_count: u64,
+    capitalization: u64,
+    max_tick_height: u64,
+    hashes_per_tick: Option<u64>,
+    ticks_per_slot: u64,
+    ns_per_slot: u128,
+    genesis_creation_time: i64,
+    slots_per_year: f64,
+    accounts_data_len: u64,
+    slot: u64,
+    epoch: u64,
+    block_height: u64,
+    collector_id: Pubkey,
+    collector_fees: u64,
+    fee_calculator: FeeCalculator,
+    fee_rate_governor: FeeRateGovernor,
+    collected_rent: u64,
+    rent_collector: RentCollector,
+    epoch_schedule: EpochSchedule,
+    inflation: Inflation,
+    stakes: EmptyStakes,
+    unused_accounts: (Vec<Pubkey>, Vec<Pubkey>, HashMap<Pubkey, u64>),
+    epoch_stakes: HashMap<u64, u64>,
+    is_delta: bool,
+}
+
+/// Serializes like an empty `Stakes<Delegation>`.
+#[derive(Serialize, Default)]
+struct EmptyStakes {
+    vote_accounts: HashMap<Pubkey, u64>,
+    stake_delegations: HashMap<Pubkey, u64>,
+    unused: u64,
+    epoch: u64,
+    stake_history: Vec<u64>,
+}
+
+#[derive(Serialize)]
+struct SerializableStorageEntry {
+    id: usize,
+    accounts_current_len: usize,
+}
+
+#[derive(Serialize)]
+struct SerializableAccountsDbFields(
+    HashMap<u64, Vec<SerializableStorageEntry>>,
+    u64,
+    u64,
+    BankHashInfo,
+    Vec<u64>,
+    Vec<(u64, Hash)>,
+);
+
+pub fn bank_hash() -> Hash {
+    Hash::new_from_array([9; 32])
+}
+
+/// An unpacked snapshot written to a temporary directory, removed on drop.
+pub struct Fixture {
+    pub root: PathBuf,
+    pub slot: u64,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        Self::build(storages(), false, false)
+    }
+
+    /// Writes a snapshot holding `storages` instead of the default ones.
+    pub fn with_storages(storages: BTreeMap<(u64, u64), Vec<StoredAccount>>) -> Self {
+        Self::build(storages, false, false)
+    }
+
+    /// Writes the fixture, plus a storage listed in the manifest with `current_len`
+    /// bytes but backed by a much shorter file.
+    pub fn with_truncated_storage() -> Self {
+        Self::build(storages(), true, false)
+    }
+
+    /// Writes the append vec of the latest slot in the tiered hot storage format.
+    pub fn with_hot_storage() -> Self {
+        Self::build(storages(), false, true)
+    }
+
+    /// Writes an incremental snapshot at `INCREMENTAL_SLOT` holding `storages`, taken on
+    /// top of a full snapshot at `full_slot`.
+    pub fn incremental(storages: BTreeMap<(u64, u64), Vec<StoredAccount>>, full_slot: u64) -> Self {
+        Self::write(
+            storages,
+            INCREMENTAL_SLOT,
+            Some(incremental_fields(full_slot)),
+            false,
+            false,
+        )
+    }
+
+    /// Writes an incremental snapshot like [`Fixture::incremental`], plus a truncated
+    /// storage.
+    pub fn truncated_incremental(
+        storages: BTreeMap<(u64, u64), Vec<StoredAccount>>,
+        full_slot: u64,
+    ) -> Self {
+        Self::write(
+            storages,
+            INCREMENTAL_SLOT,
+            Some(incremental_fields(full_slot)),
+            true,
+            false,
+        )
+    }
+
+    fn build(
+        storages: BTreeMap<(u64, u64), Vec<StoredAccount>>,
+        truncated: bool,
+        hot_storage: bool,
+    ) -> Self {
+        Self::write(storages, SLOT, None, truncated, hot_storage)
+    }
+
+    fn write(
+        storages: BTreeMap<(u64, u64), Vec<StoredAccount>>,
+        slot: u64,
+        extra_fields: Option<ExtraFields>,
+        truncated: bool,
+        hot_storage: bool,
+    ) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let root = std::env::temp_dir().join(format!(
+            "solana-snapshot-etl-fixture-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&root);
+
+        let accounts_dir = root.join("accounts");
+        fs::create_dir_all(&accounts_dir).unwrap();
+        let mut entries: HashMap<u64, Vec<SerializableStorageEntry>> = HashMap::new();
+        for ((slot, id), accounts) in &storages {
+            let bytes = if hot_storage && *slot == SLOT {
+                hot_storage_bytes(accounts)
+            } else {
+                append_vec_bytes(accounts)
+            };
+            fs::write(accounts_dir.join(format!("{}.{}", slot, id)), &bytes).unwrap();
+            entries
+                .entry(*slot)
+                .or_default()
+                .push(SerializableStorageEntry {
+                    id: *id as usize,
+                    accounts_current_len: bytes.len(),
+                });
+        }
+        if truncated {
+            fs::write(accounts_dir.join(format!("{}.2", slot)), [0u8; 64]).unwrap();
+            entries
+                .entry(slot)
+                .or_default()
+                .push(SerializableStorageEntry {
+                    id: 2,
+                    accounts_current_len: 4096,
+                });
+        }
+
+        let bank = SerializableBank {
+            blockhash_queue: BlockhashQueue::default(),
+            ancestors: HashMap::new(),
+            hash: bank_hash(),
+            parent_hash: Hash::default(),
+            parent_slot: slot - 1,
+            hard_forks: HardForks::default(),
+            transaction_count: 0,
+            tick_height: 0,
+            signature_count: 0,
+            capitalization: CAPITALIZATION,
+            max_tick_height: 0,
+            hashes_per_tick: None,
+            ticks_per_slot: 64,
+            ns_per_slot: 400_000_000,
+            genesis_creation_time: 0,
+            slots_per_year: 0.0,
+            accounts_data_len: 0,
+            slot,
+            epoch: EPOCH,
+            block_height: BLOCK_HEIGHT,
+            collector_id: Pubkey::default(),
+            collector_fees: 0,
+            fee_calculator: FeeCalculator::default(),
+            fee_rate_governor: FeeRateGovernor::default(),
+            collected_rent: 0,
+            rent_collector: RentCollector::default(),
+            epoch_schedule: EpochSchedule::default(),
+            inflation: Inflation::default(),
+            stakes: EmptyStakes::default(),
+            unused_accounts: (vec![], vec![], HashMap::new()),
+            epoch_stakes: HashMap::new(),
+            is_delta: false,
+        };
+        let accounts_db_fields = SerializableAccountsDbFields(
+            entries,
+            14,
+            slot,
+            BankHashInfo {
+                accounts_hash: accounts_hash(&storages),
+                ..BankHashInfo::default()
+            },
+            vec![],
+            vec![],
+        );
+
+        let manifest_dir = root.join("snapshots").join(slot.to_string());
+        fs::create_dir_all(&manifest_dir).unwrap();
+        let mut manifest = bincode::serialize(&bank).unwrap();
+        manifest.extend(bincode::serialize(&accounts_db_fields).unwrap());
+        if let Some(extra_fields) = extra_fields {
+            manifest.extend(bincode::serialize(&extra_fields).unwrap());
+        }
+        fs::write(manifest_dir.join(slot.to_string()), manifest).unwrap();
+        fs::write(root.join("snapshots").join("status_cache"), []).unwrap();
+
+        Self { root, slot }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Packs the snapshot into `name` next to it, manifest first like the validator does.
+    pub fn archive<W: Write>(&self, name: &str, encoder: impl FnOnce(File) -> W) -> PathBuf {
+        let archive_path = self.root.join(name);
+        let mut builder = tar::Builder::new(encoder(File::create(&archive_path).unwrap()));
+        let manifest = Path::new("snapshots")
+            .join(self.slot.to_string())
+            .join(self.slot.to_string());
+        for path in [manifest, Path::new("snapshots").join("status_cache")] {
+            builder
+                .append_path_with_name(self.root.join(&path), &path)
+                .unwrap();
+        }
+        let mut accounts: Vec<_> = fs::read_dir(self.root.join("accounts"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        accounts.sort();
+        for name in accounts {
+            let path = Path::new("accounts").join(name);
+            builder
+                .append_path_with_name(self.root.join(&path), &path)
+                .unwrap();
+        }
+        builder.into_inner().unwrap();
+        archive_path
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// A request received by [`serve`].
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+}
+
+impl Request {
+    /// Value of the header `name`, given in lower case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+/// Serves HTTP/1.1 on a local port until the test process exits, answering every request
+/// with the raw response returned by `handler`, without its body for HEAD requests.
+/// Connections are closed after responses with a `Connection: close` header, which may
+/// cut their body short. Returns the base URL of the server.
+pub fn serve(handler: impl Fn(&Request) -> Vec<u8> + Send + Sync + 'static) -> String {
+    use std::io::{BufRead, BufReader, Read};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handler = std::sync::Arc::new(handler);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let handler = std::sync::Arc::clone(&handler);
+            std::thread::spawn(move || {
+                let mut reader = BufReader::new(stream);
+                loop {
+                    let mut request_line = String::new();
+                    if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                        return;
+                    }
+                    let mut parts = request_line.split_whitespace();
+                    let method = parts.next().unwrap_or_default().to_string();
+                    let path = parts.next().unwrap_or_default().to_string();
+                    let mut headers = HashMap::new();
+                    loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+                        }
+                    }
+                    let content_length = headers
+                        .get("content-length")
+                        .and_then(|value| value.parse().ok())
+                        .unwrap_or(0);
+                    let mut body = vec![0; content_length];
+                    if reader.read_exact(&mut body).is_err() {
+                        return;
+                    }
+                    let head = method == "HEAD";
+                    let mut response = handler(&Request {
+                        method,
+                        path,
+                        headers,
+                    });
+                    // Responses to HEAD keep their Content-Length but drop the body
+                    if head {
+                        if let Some(end) = response.windows(4).position(|w| w == b"\r\n\r\n") {
+                            response.truncate(end + 4);
+                        }
+                    }
+                    let close = response
+                        .windows(19)
+                        .any(|w| w.eq_ignore_ascii_case(b"Connection: close\r\n"));
+                    if reader.get_mut().write_all(&response).is_err() || close {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    url
+}
+
+/// A raw HTTP response with `status`, extra `headers` and `body`.
+pub fn http_response(status: &str, headers: &[(&str, String)], body: &[u8]) -> Vec<u8> {
+    let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", status, body.len());
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    let mut response = response.into_bytes();
+    response.extend_from_slice(body);
+    response
+}